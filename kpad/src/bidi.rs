@@ -0,0 +1,81 @@
+//! Bi-directional text support: maps logical char order to display (visual) order.
+//!
+//! The buffer always stores text in logical order (the order it was typed). Lines that contain
+//! right-to-left scripts (Arabic, Hebrew, ...) must be reordered before they are drawn, otherwise
+//! RTL words show up mirrored. We use the Unicode Bidirectional Algorithm from `unicode-bidi`.
+
+use unicode_bidi::BidiInfo; // unicode bidirectional algorithm
+
+/// Compute the visual order of the chars in `line`.
+///
+/// Returns `None` for lines without any RTL content (the common case), so callers can keep
+/// their fast identity path. Otherwise returns a vector where entry `i` is the **logical char
+/// index** displayed at visual position `i`.
+pub fn visual_order(line: &str) -> Option<Vec<usize>> {
+    let info = BidiInfo::new(line, None);
+    if !info.has_rtl() {
+        return None;
+    }
+
+    // Map byte offsets to char indices so we can translate the byte-based runs.
+    let mut byte_to_char = vec![0; line.len() + 1];
+    for (char_i, (byte_i, _)) in line.char_indices().enumerate() {
+        byte_to_char[byte_i] = char_i;
+    }
+
+    let mut order = Vec::with_capacity(line.chars().count());
+    for para in &info.paragraphs {
+        let (levels, runs) = info.visual_runs(para, para.range.clone());
+        for run in runs {
+            let chars: Vec<usize> = line[run.clone()]
+                .char_indices()
+                .map(|(off, _)| byte_to_char[run.start + off])
+                .collect();
+            if levels[run.start].is_rtl() {
+                order.extend(chars.into_iter().rev());
+            } else {
+                order.extend(chars);
+            }
+        }
+    }
+    Some(order)
+}
+
+/// Display order for `line`: the bidi visual order, or the identity order for pure LTR text.
+pub fn display_order(line: &str) -> Vec<usize> {
+    visual_order(line).unwrap_or_else(|| (0..line.chars().count()).collect())
+}
+
+/// Find the visual position of logical char index `x`.
+///
+/// A cursor past the last char (`x == len`) is placed after the last visual cell.
+pub fn logical_to_visual(order: &[usize], x: usize) -> usize {
+    order.iter().position(|&i| i == x).unwrap_or(order.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ltr_line_has_no_reordering() {
+        assert_eq!(visual_order("hello world"), None);
+        assert_eq!(visual_order(""), None);
+    }
+
+    #[test]
+    fn rtl_run_is_reversed() {
+        // "ab " followed by three Hebrew letters (alef, bet, gimel)
+        let order = visual_order("ab \u{5d0}\u{5d1}\u{5d2}").unwrap();
+        assert_eq!(order, vec![0, 1, 2, 5, 4, 3]);
+    }
+
+    #[test]
+    fn logical_to_visual_maps_positions() {
+        let order = vec![0, 1, 2, 5, 4, 3];
+        assert_eq!(logical_to_visual(&order, 0), 0);
+        assert_eq!(logical_to_visual(&order, 3), 5);
+        assert_eq!(logical_to_visual(&order, 5), 3);
+        assert_eq!(logical_to_visual(&order, 6), 6);
+    }
+}
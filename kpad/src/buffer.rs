@@ -1,9 +1,14 @@
 //! The document buffer: stores text using a Rope for O(log n) operations on large files.
 
 use crate::types::{LineEnding, Pos};
-use ropey::Rope;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use ropey::{Rope, RopeBuilder};
 use std::borrow::Cow;
-use std::io::{self, Write};
+use std::io::{self, Read, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Bytes `Buffer::read_utf8` reads at a time.
+const READ_CHUNK: usize = 1 << 20;
 
 /// The document buffer using a Rope data structure.
 ///
@@ -14,6 +19,32 @@ pub struct Buffer {
     pub text: Rope,
     /// Line ending style for this buffer.
     pub line_ending: LineEnding,
+    /// Encoding of the file on disk (`--encoding`); the text is UTF-8 in memory.
+    pub encoding: &'static Encoding,
+    /// Changes with every edit; unique across buffers (see `revision`).
+    revision: u64,
+}
+
+/// A revision number no buffer state had before.
+fn next_revision() -> u64 {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    NEXT.fetch_add(1, Ordering::Relaxed)
+}
+
+/// `text` in `encoding`; fails for characters the encoding cannot represent.
+fn encode(text: &str, encoding: &'static Encoding) -> io::Result<Vec<u8>> {
+    // encoding_rs only decodes UTF-16. Windows tools expect its byte order mark.
+    if encoding == UTF_16LE {
+        return Ok([0xFEFF].into_iter().chain(text.encode_utf16()).flat_map(u16::to_le_bytes).collect());
+    }
+    if encoding == UTF_16BE {
+        return Ok([0xFEFF].into_iter().chain(text.encode_utf16()).flat_map(u16::to_be_bytes).collect());
+    }
+    let (bytes, _, unmappable) = encoding.encode(text);
+    if unmappable {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("the text has characters {} cannot represent", encoding.name())));
+    }
+    Ok(bytes.into_owned())
 }
 
 impl Buffer {
@@ -22,6 +53,8 @@ impl Buffer {
         Self {
             text: Rope::new(),
             line_ending: LineEnding::LF,
+            encoding: UTF_8,
+            revision: next_revision(),
         }
     }
 
@@ -38,7 +71,54 @@ impl Buffer {
         let normalized = s.replace("\r\n", "\n");
         let text = Rope::from_str(&normalized);
 
-        Self { text, line_ending }
+        Self { text, line_ending, encoding: UTF_8, revision: next_revision() }
+    }
+
+    /// Build a buffer from file bytes in `encoding`, dropping a byte order mark; saving
+    /// writes the same encoding.
+    pub fn decode(bytes: &[u8], encoding: &'static Encoding) -> Self {
+        let (text, _) = encoding.decode_with_bom_removal(bytes);
+        Self { encoding, ..Self::from_string(&text) }
+    }
+
+    /// Build a buffer from UTF-8 read in chunks, so a huge file never exists as one `String`;
+    /// `progress` gets the number of bytes read after every chunk.
+    pub fn read_utf8(mut reader: impl Read, mut progress: impl FnMut(usize)) -> io::Result<Self> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "stream did not contain valid UTF-8");
+        let mut builder = RopeBuilder::new();
+        let mut line_ending = LineEnding::LF;
+        let mut chunk = vec![0; READ_CHUNK];
+        // Read bytes not appended yet: a char, or a "\r\n", cut by the chunk end.
+        let mut pending = Vec::new();
+        let mut read = 0;
+        loop {
+            let n = match reader.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            read += n;
+            pending.extend_from_slice(&chunk[..n]);
+            let valid = match std::str::from_utf8(&pending) {
+                Ok(s) => s.len(),
+                Err(e) if e.error_len().is_none() => e.valid_up_to(),
+                Err(_) => return Err(invalid()),
+            };
+            let text = std::str::from_utf8(&pending[..valid]).map_err(|_| invalid())?;
+            let text = text.strip_suffix('\r').unwrap_or(text);
+            if text.contains("\r\n") {
+                line_ending = LineEnding::CRLF;
+                builder.append(&text.replace("\r\n", "\n"));
+            } else {
+                builder.append(text);
+            }
+            let used = text.len();
+            pending.drain(..used);
+            progress(read);
+        }
+        builder.append(std::str::from_utf8(&pending).map_err(|_| invalid())?);
+        Ok(Self { text: builder.finish(), line_ending, encoding: UTF_8, revision: next_revision() })
     }
 
     /// Serialize the buffer for saving to disk, using the detected line ending.
@@ -54,7 +134,9 @@ impl Buffer {
     /// Stream the buffer to a writer, avoiding full String allocation.
     /// This is more efficient for large files.
     pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
-        if self.line_ending == LineEnding::CRLF {
+        if self.encoding != UTF_8 {
+            writer.write_all(&encode(&self.to_string(), self.encoding)?)?;
+        } else if self.line_ending == LineEnding::CRLF {
             // Need to convert LF to CRLF while streaming
             for chunk in self.text.chunks() {
                 let converted = chunk.replace('\n', "\r\n");
@@ -110,6 +192,21 @@ impl Buffer {
         None
     }
 
+    /// Search backwards for a query string that starts before a char index.
+    /// Returns the char index of the last such match, or None if not found.
+    pub fn search_backward(&self, query: &str, before_char_idx: usize) -> Option<usize> {
+        let query_len = query.chars().count();
+        if query_len == 0 || before_char_idx == 0 {
+            return None;
+        }
+
+        // A match starting at `before - 1` ends at `before - 1 + query_len`; nothing later counts.
+        let end = (before_char_idx - 1 + query_len).min(self.text.len_chars());
+        let haystack = self.text.slice(..end).to_string();
+        let byte_idx = haystack.rfind(query)?;
+        Some(haystack[..byte_idx].chars().count())
+    }
+
     /// Convert a char index to a Pos (line, column).
     pub fn char_idx_to_pos_public(&self, char_idx: usize) -> Pos {
         self.char_idx_to_pos(char_idx)
@@ -120,6 +217,11 @@ impl Buffer {
         self.pos_to_char_idx(p)
     }
 
+    /// Size of the text in bytes (UTF-8, LF line endings).
+    pub fn len_bytes(&self) -> usize {
+        self.text.len_bytes()
+    }
+
     /// Number of lines in the buffer.
     pub fn line_count(&self) -> usize {
         // Rope counts trailing newline as an extra line, adjust for consistency
@@ -162,10 +264,10 @@ impl Buffer {
         let old_len = self.line_len_chars(y);
         // Remove old content (but not the newline if it exists)
         if old_len > 0 {
-            self.text.remove(start..start + old_len);
+            self.remove(start..start + old_len);
         }
         // Insert new content
-        self.text.insert(start, content);
+        self.insert(start, content);
     }
 
     /// Clamp a position to a valid line and a valid column within that line.
@@ -201,7 +303,7 @@ impl Buffer {
     /// Insert a single character at a position, returning the new cursor position.
     pub fn insert_char(&mut self, p: Pos, ch: char) -> Pos {
         let idx = self.pos_to_char_idx(p);
-        self.text.insert_char(idx, ch);
+        self.insert(idx, ch.encode_utf8(&mut [0; 4]));
         if ch == '\n' {
             Pos { y: p.y + 1, x: 0 }
         } else {
@@ -230,10 +332,10 @@ impl Buffer {
             // Merging with previous line - calculate new cursor pos before removal
             let new_y = p.y.saturating_sub(1);
             let new_x = self.line_len_chars(new_y);
-            self.text.remove(idx - 1..idx);
+            self.remove(idx - 1..idx);
             Pos { y: new_y, x: new_x }
         } else {
-            self.text.remove(idx - 1..idx);
+            self.remove(idx - 1..idx);
             Pos { y: p.y, x: p.x - 1 }
         }
     }
@@ -247,7 +349,7 @@ impl Buffer {
             return p;
         }
 
-        self.text.remove(idx..idx + 1);
+        self.remove(idx..idx + 1);
         p
     }
 
@@ -274,7 +376,7 @@ impl Buffer {
         let start_idx = self.pos_to_char_idx(a);
         let end_idx = self.pos_to_char_idx(b);
 
-        self.text.remove(start_idx..end_idx);
+        self.remove(start_idx..end_idx);
         a
     }
 
@@ -283,12 +385,28 @@ impl Buffer {
     pub fn insert_str(&mut self, p: Pos, text: &str) -> Pos {
         let normalized = text.replace("\r\n", "\n");
         let idx = self.pos_to_char_idx(p);
-        self.text.insert(idx, &normalized);
+        self.insert(idx, &normalized);
 
         // Calculate new position
         self.char_idx_to_pos(idx + normalized.chars().count())
     }
 
+    /// Identifies the current text: equal revisions mean the same buffer with no edit since.
+    /// Caches derived from the text (search match counts) compare it.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    fn insert(&mut self, char_idx: usize, text: &str) {
+        self.text.insert(char_idx, text);
+        self.revision = next_revision();
+    }
+
+    fn remove(&mut self, range: std::ops::Range<usize>) {
+        self.text.remove(range);
+        self.revision = next_revision();
+    }
+
     /// Calculate the end position if `text` was inserted at `p`.
     pub fn calc_end_pos(&self, p: Pos, text: &str) -> Pos {
         let normalized = text.replace("\r\n", "\n");
@@ -357,6 +475,48 @@ mod tests {
         assert_eq!(buf_crlf.to_string(), "a\r\nb");
     }
 
+    #[test]
+    fn round_trips_other_encodings() {
+        let latin1 = Encoding::for_label(b"latin1").unwrap();
+        let buf = Buffer::decode(b"caf\xE9\r\n", latin1);
+        assert_eq!(buf.line(0).as_ref(), "caf\u{e9}");
+        let mut out = Vec::new();
+        buf.write_to(&mut out).unwrap();
+        assert_eq!(out, b"caf\xE9\r\n");
+
+        let utf16 = Buffer::decode(b"\xFF\xFEh\x00i\x00", UTF_16LE);
+        assert_eq!(utf16.line(0).as_ref(), "hi");
+        let mut out = Vec::new();
+        utf16.write_to(&mut out).unwrap();
+        assert_eq!(out, b"\xFF\xFEh\x00i\x00");
+
+        let mut greek = Buffer::decode(b"", latin1);
+        greek.insert_str(Pos { y: 0, x: 0 }, "\u{3b1}");
+        assert!(greek.write_to(&mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn reads_utf8_in_pieces() {
+        /// Hands out two bytes per read, cutting chars and CRLFs.
+        struct Trickle<'a>(&'a [u8]);
+        impl Read for Trickle<'_> {
+            fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+                let n = self.0.len().min(out.len()).min(2);
+                out[..n].copy_from_slice(&self.0[..n]);
+                self.0 = &self.0[n..];
+                Ok(n)
+            }
+        }
+        let text = "h\u{e9}llo\r\n\u{4e16}\u{1f600}\r\nend\r";
+        let mut reads = 0;
+        let buf = Buffer::read_utf8(Trickle(text.as_bytes()), |_| reads += 1).unwrap();
+        assert_eq!(buf.text.to_string(), Buffer::from_string(text).text.to_string());
+        assert_eq!(buf.line_ending, LineEnding::CRLF);
+        assert_eq!(reads, text.len().div_ceil(2));
+        assert!(Buffer::read_utf8(Trickle(b"ok\xff"), |_| ()).is_err());
+        assert!(Buffer::read_utf8(Trickle(b"cut\xe4\xb8"), |_| ()).is_err());
+    }
+
     // ==================== Insert tests ====================
 
     #[test]
@@ -504,6 +664,19 @@ mod tests {
         assert_eq!(buf.line(0).as_ref(), "日本語");
     }
 
+    // ==================== Search tests ====================
+
+    #[test]
+    fn search_backward_finds_last_match_before() {
+        let buf = Buffer::from_string("foo bar\nfoo bär foo");
+        assert_eq!(buf.search_backward("foo", 20), Some(16));
+        assert_eq!(buf.search_backward("foo", 16), Some(8));
+        assert_eq!(buf.search_backward("foo", 1), Some(0));
+        assert_eq!(buf.search_backward("foo", 0), None);
+        assert_eq!(buf.search_backward("bär", 19), Some(12));
+        assert_eq!(buf.search_backward("baz", 20), None);
+    }
+
     #[test]
     fn clamp_pos_works() {
         let buf = Buffer::from_string("short\nlonger line");
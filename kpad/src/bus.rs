@@ -0,0 +1,91 @@
+//! Message bus: the single channel the main loop waits on.
+//!
+//! Terminal input is read on a background thread and forwarded here, so the main loop can block
+//! on one channel for *all* wake-up sources (keys, mouse, resize, and any background work that
+//! posts a message) instead of polling the terminal on a fixed interval.
+
+use crate::collab::Op; // pair editing edits
+use crate::editor::GrepHit; // find-in-files results
+use crate::lsp::Diagnostic; // language server diagnostics
+use anyhow::{anyhow, Result}; // anyhow error handling
+use crossterm::event::{self, Event}; // terminal events
+use std::path::PathBuf; // file path handling
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender}; // channel primitives
+use std::thread; // background input reader
+use std::time::Duration; // wait timeouts
+
+/// A message delivered to the main loop.
+pub enum Message {
+    /// A terminal event (key, mouse, resize, ...).
+    Input(Event),
+    /// Reading terminal input failed; the editor cannot continue.
+    InputError(std::io::Error),
+    /// Another `kpad --reuse` invocation asked us to open a file; the result goes to `reply`.
+    OpenFile { path: PathBuf, reply: Sender<Result<(), String>> },
+    /// A file in a plugin directory changed (auto-reload).
+    PluginsChanged,
+    /// Hits of a running `grep`; `done` once it finished.
+    GrepHits { search: u64, hits: Vec<GrepHit>, done: bool },
+    /// An edit from the pair editing host, which had seen `seen` of ours.
+    PairEdit { op: Op, seen: u64 },
+    /// The connection to the pair editing host closed; `out_of_step` if the host dropped it
+    /// because an edit from here did not fit its text.
+    PairClosed { out_of_step: bool },
+    /// A language server's diagnostics for the document `uri`, replacing earlier ones.
+    Diagnostics { uri: String, diagnostics: Vec<Diagnostic> },
+    /// The language server for this `[lsp]` extension exited.
+    LspStopped(String),
+    /// A `build` finished; its status line report.
+    BuildDone(String),
+}
+
+/// Owns the channel; hand out senders to producers with `sender()`.
+pub struct Bus {
+    tx: Sender<Message>,
+    rx: Receiver<Message>,
+}
+
+impl Bus {
+    /// Create an empty bus.
+    pub fn new() -> Self {
+        let (tx, rx) = mpsc::channel();
+        Self { tx, rx }
+    }
+
+    /// A handle that background producers use to post messages.
+    pub fn sender(&self) -> Sender<Message> {
+        self.tx.clone()
+    }
+
+    /// Start the background thread that forwards terminal events onto the bus.
+    pub fn spawn_input_reader(&self) {
+        let tx = self.sender();
+        thread::spawn(move || loop {
+            let msg = match event::read() {
+                Ok(ev) => Message::Input(ev),
+                Err(e) => {
+                    let _ = tx.send(Message::InputError(e));
+                    break;
+                }
+            };
+            if tx.send(msg).is_err() {
+                break;
+            }
+        });
+    }
+
+    /// Wait for the next message.
+    ///
+    /// - `Some(timeout)`: returns `Ok(None)` if nothing arrived in time (time to `tick`).
+    /// - `None`: blocks until a message arrives (low-power idle).
+    pub fn wait(&self, timeout: Option<Duration>) -> Result<Option<Message>> {
+        match timeout {
+            Some(t) => match self.rx.recv_timeout(t) {
+                Ok(msg) => Ok(Some(msg)),
+                Err(RecvTimeoutError::Timeout) => Ok(None),
+                Err(RecvTimeoutError::Disconnected) => Err(anyhow!("message bus disconnected")),
+            },
+            None => self.rx.recv().map(Some).map_err(|_| anyhow!("message bus disconnected")),
+        }
+    }
+}
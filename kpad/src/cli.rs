@@ -0,0 +1,299 @@
+//! Command line interface: the flags (parsed with clap), `--help`, and the shell completion
+//! scripts `--completions` prints, generated from the same definition.
+
+use clap::{Arg, ArgAction, ArgGroup, ArgMatches, Args, CommandFactory, FromArgMatches, Parser}; // argument parsing
+use clap::error::ErrorKind; // usage errors found after parsing
+use clap_complete::Shell; // completion scripts
+use crate::remote::is_remote; // remote locations keep their colons
+use crate::utils::parse_location; // LINE:COL
+use encoding_rs::Encoding; // --encoding
+use std::io::{self, IsTerminal}; // completion script output, piped stdin
+use std::path::PathBuf; // file paths
+
+/// Shown after the flags in `--help`.
+const KEYBINDINGS_HELP: &str = "\
+Keybindings:
+  Ctrl+P         Command Palette (Discovery)
+  Ctrl+S         Save
+  Ctrl+O         Open file prompt
+  Ctrl+Q         Quit
+  Alt+W          Toggle Word Wrap
+  Home / End     Start / End of line
+  Ctrl+Home/End  Top / Bottom of document";
+
+/// kpad — A simple TUI text editor
+#[derive(Parser)]
+#[command(name = "kpad", version, after_help = KEYBINDINGS_HELP)]
+pub struct Cli {
+    #[arg(value_name = "FILE", help = "Files to open, one buffer each (created on save if missing). Also \
+        scp://[USER@]HOST[:PORT]/PATH to edit over SSH, or https://URL to view read-only. \
+        FILE:LINE[:COL] or +LINE before FILE starts at that line; - reads piped input")]
+    pub files: Vec<PathBuf>,
+
+    /// Where to start in each of `files`, taken from `FILE:LINE:COL` and `+LINE` arguments
+    /// (see `split_locations`).
+    #[arg(skip)]
+    pub locations: Vec<Option<(isize, Option<usize>)>>,
+
+    /// Open the files read-only
+    #[arg(long)]
+    pub readonly: bool,
+
+    /// Start on line N of the first file
+    #[arg(short, long, value_name = "N")]
+    pub line: Option<isize>,
+
+    /// Read the files in ENCODING (e.g. latin1, windows-1252, utf-16le) instead of
+    /// UTF-8; saving writes it back
+    #[arg(long, value_name = "ENCODING", value_parser = parse_encoding)]
+    pub encoding: Option<&'static Encoding>,
+
+    /// Use this config file instead of kpad.toml in the config directory
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Draw with ASCII separators and 16 colors (for consoles without box drawing or
+    /// 256 colors; detected automatically where possible)
+    #[arg(long)]
+    pub ascii: bool,
+
+    /// Start without plugins, the user config and saved state (plugin stores, word goals), to
+    /// find out whether one of them causes trouble or to fix a broken kpad.toml
+    #[arg(long, conflicts_with = "reuse")]
+    pub safe: bool,
+
+    /// `--reuse`, `--check`, `--init-config`, `--host` or `--attach`.
+    #[command(flatten)]
+    pub mode: Mode,
+
+    /// Run `;`-separated commands on each file without the UI, then exit
+    #[arg(short, long, value_name = "COMMANDS", conflicts_with = "reuse")]
+    pub execute: Option<String>,
+
+    /// Run a Rhai script against the plugin API (`api`) on each file and save the files it
+    /// changed, then exit (exit code 1 on a script error); without files, filter stdin to stdout
+    #[arg(long, value_name = "SCRIPT", conflicts_with_all = ["reuse", "execute", "check"])]
+    pub script: Option<PathBuf>,
+
+    /// Print a shell completion script and exit
+    #[arg(long, value_name = "SHELL", exclusive = true)]
+    pub completions: Option<Shell>,
+}
+
+/// What kpad runs as, chosen by one of the mutually exclusive mode flags.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Mode {
+    /// Edit the files in the UI.
+    #[default]
+    Edit,
+    /// `--reuse`: open the files in an already running kpad, or edit them in a new one that
+    /// later `--reuse` calls open files in.
+    Reuse,
+    /// `--check`: validate the files and exit.
+    Check,
+    /// `--init-config`: write a default kpad.toml and exit.
+    InitConfig,
+    /// `--host`: share the first file for pair editing, without a UI.
+    Host,
+    /// `--attach`: edit the file shared by `--host`.
+    Attach,
+}
+
+/// Argument ids of the mode flags.
+const MODE_IDS: [(&str, Mode); 5] = [
+    ("reuse", Mode::Reuse),
+    ("check", Mode::Check),
+    ("init_config", Mode::InitConfig),
+    ("host", Mode::Host),
+    ("attach", Mode::Attach),
+];
+
+impl FromArgMatches for Mode {
+    fn from_arg_matches(matches: &ArgMatches) -> Result<Self, clap::Error> {
+        Ok(MODE_IDS.iter().find(|(id, _)| matches.get_flag(id)).map_or(Mode::Edit, |&(_, mode)| mode))
+    }
+
+    fn update_from_arg_matches(&mut self, matches: &ArgMatches) -> Result<(), clap::Error> {
+        *self = Self::from_arg_matches(matches)?;
+        Ok(())
+    }
+}
+
+impl Args for Mode {
+    fn augment_args(cmd: clap::Command) -> clap::Command {
+        let flag = |id: &'static str, long: &'static str| Arg::new(id).long(long).action(ArgAction::SetTrue);
+        cmd.arg(flag("reuse", "reuse").short('r')
+                .help("Open the files in an already running kpad (single-instance)"))
+            .arg(flag("check", "check").requires("files").conflicts_with("execute")
+                .help("Validate the files (JSON, TOML, [check] in kpad.toml); exit code 1 on errors"))
+            .arg(flag("init_config", "init-config").conflicts_with_all(["files", "execute", "script"])
+                .help("Write a commented default kpad.toml and exit"))
+            .arg(flag("host", "host").requires("files").conflicts_with_all(["execute", "script"])
+                .help("Experimental: share the first FILE for pair editing on this machine; runs without a UI \
+                    until stopped, while `kpad --attach` editors come and go"))
+            .arg(flag("attach", "attach").conflicts_with_all(["files", "execute", "script"])
+                .help("Experimental: edit the file shared by a running `kpad --host` together with others"))
+            .group(ArgGroup::new("mode").args(MODE_IDS.map(|(id, _)| id)))
+    }
+
+    fn augment_args_for_update(cmd: clap::Command) -> clap::Command {
+        Self::augment_args(cmd)
+    }
+}
+
+/// An encoding by its WHATWG label.
+fn parse_encoding(label: &str) -> Result<&'static Encoding, String> {
+    Encoding::for_label(label.as_bytes()).ok_or_else(|| format!("unknown encoding '{label}'"))
+}
+
+/// `arg` without a `:LINE` or `:LINE:COL` suffix (as in compiler messages), and that place.
+fn split_location(arg: &str) -> Option<(&str, (isize, Option<usize>))> {
+    let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    let (rest, last) = arg.rsplit_once(':').filter(|(_, last)| digits(last))?;
+    let (path, place) = match rest.rsplit_once(':') {
+        Some((path, line)) if digits(line) => (path, format!("{line}:{last}")),
+        _ => (rest, last.to_string()),
+    };
+    Some((path, parse_location(&place)?)).filter(|(path, _)| !path.is_empty())
+}
+
+/// The clap command; `-v` prints the version like before clap.
+fn command() -> clap::Command {
+    Cli::command().disable_version_flag(true).arg(
+        Arg::new("version").short('v').long("version").action(ArgAction::Version).help("Print version"),
+    )
+}
+
+impl Cli {
+    /// Parse the process arguments; usage errors print a message and exit.
+    pub fn parse_args() -> Self {
+        let mut cli = Self::from_arg_matches(&command().get_matches()).unwrap_or_else(|e| e.exit());
+        cli.split_locations();
+        cli.check_files().unwrap_or_else(|e| e.exit());
+        cli
+    }
+
+    /// `--check` and `--host` need a file; clap sees one in `kpad --host +5`, but
+    /// `split_locations` takes the `+5` out.
+    fn check_files(&self) -> Result<(), clap::Error> {
+        let flag = match self.mode {
+            Mode::Check => "--check",
+            Mode::Host => "--host",
+            _ => return Ok(()),
+        };
+        if self.files.is_empty() {
+            return Err(command().error(ErrorKind::MissingRequiredArgument, format!("{flag} needs a FILE")));
+        }
+        Ok(())
+    }
+
+    /// Whether to read stdin into a buffer: `-` was given (it is taken out of `files`), or no
+    /// files were and input is piped in.
+    pub fn take_stdin(&mut self) -> bool {
+        let dash = self.files.iter().position(|f| f.as_os_str() == "-");
+        if let Some(i) = dash {
+            self.files.remove(i);
+            self.locations.remove(i);
+        }
+        dash.is_some() || (self.files.is_empty() && !io::stdin().is_terminal())
+    }
+
+    /// Move start places out of `files` into `locations`: `+LINE` applies to the file after it,
+    /// and a `:LINE[:COL]` suffix is split off paths that do not exist as written. `--line`
+    /// overrides the first file's place; without files it stays in `line`, as does `+LINE`.
+    fn split_locations(&mut self) {
+        let mut files = Vec::new();
+        let mut plus = None;
+        for file in std::mem::take(&mut self.files) {
+            let arg = file.to_string_lossy().into_owned();
+            if let Some(line) = arg.strip_prefix('+').and_then(|n| n.parse().ok()) {
+                plus = Some((line, None));
+                continue;
+            }
+            let suffix = if file.exists() || is_remote(&arg) { None } else { split_location(&arg) };
+            let (file, at) = match suffix {
+                Some((path, at)) => (PathBuf::from(path), Some(at)),
+                None => (file, None),
+            };
+            files.push(file);
+            self.locations.push(plus.take().or(at));
+        }
+        if let Some(first) = self.locations.first_mut() {
+            if let Some(n) = self.line.take() {
+                *first = Some((n, None));
+            }
+        } else if let Some((line, _)) = plus {
+            self.line = Some(line);
+        }
+        self.files = files;
+    }
+}
+
+/// Print the completion script for `shell` to stdout.
+pub fn print_completions(shell: Shell) {
+    clap_complete::generate(shell, &mut command(), "kpad", &mut io::stdout());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Cli, clap::Error> {
+        command().try_get_matches_from(args).and_then(|m| Cli::from_arg_matches(&m))
+    }
+
+    #[test]
+    fn parses_flag_combinations() {
+        let cli = parse(&["kpad", "a.txt", "--readonly", "-l", "12", "b.txt", "--encoding", "latin1"]).unwrap();
+        assert_eq!(cli.files, [PathBuf::from("a.txt"), PathBuf::from("b.txt")]);
+        assert!(cli.readonly && !cli.ascii);
+        assert_eq!(cli.line, Some(12));
+        assert_eq!(cli.encoding.map(Encoding::name), Some("windows-1252"));
+
+        assert!(parse(&["kpad", "--encoding", "klingon"]).is_err());
+        assert!(parse(&["kpad", "--check"]).is_err());
+        assert!(parse(&["kpad", "--safe", "--reuse"]).is_err());
+        assert!(parse(&["kpad", "--script", "t.rhai", "-e", "save"]).is_err());
+        assert!(parse(&["kpad", "--host"]).is_err() && parse(&["kpad", "--attach", "a.txt"]).is_err());
+        assert!(parse(&["kpad", "--check", "--host", "a.txt"]).is_err());
+        assert_eq!(parse(&["kpad", "--host", "a.txt"]).unwrap().mode, Mode::Host);
+        assert_eq!(parse(&["kpad", "a.txt"]).unwrap().mode, Mode::Edit);
+        assert!(parse(&["kpad", "--completions", "bash", "a.txt"]).is_err());
+        assert_eq!(parse(&["kpad", "-v"]).err().map(|e| e.kind()), Some(clap::error::ErrorKind::DisplayVersion));
+    }
+
+    #[test]
+    fn splits_start_places_off_files() {
+        assert_eq!(split_location("src/main.rs:12:5"), Some(("src/main.rs", (12, Some(5)))));
+        assert_eq!(split_location("a.txt:7"), Some(("a.txt", (7, None))));
+        assert_eq!(split_location(r"C:\a.txt"), None);
+        assert_eq!(split_location(":3"), None);
+        let mut cli = parse(&["kpad", "missing.txt:4:2", "+3", "b.txt", "c.txt"]).unwrap();
+        cli.split_locations();
+        assert_eq!(cli.files, [PathBuf::from("missing.txt"), PathBuf::from("b.txt"), PathBuf::from("c.txt")]);
+        assert_eq!(cli.locations, [Some((4, Some(2))), Some((3, None)), None]);
+        let mut cli = parse(&["kpad", "-l", "9", "a.txt:2"]).unwrap();
+        cli.split_locations();
+        assert_eq!((cli.locations[0], cli.line), (Some((9, None)), None));
+        let mut cli = parse(&["kpad", "+5"]).unwrap();
+        cli.split_locations();
+        assert!(cli.files.is_empty() && cli.line == Some(5));
+        let mut cli = parse(&["kpad", "--host", "+5"]).unwrap();
+        cli.split_locations();
+        assert!(cli.check_files().is_err());
+    }
+
+    #[test]
+    fn scripts_list_every_flag() {
+        for shell in [Shell::Bash, Shell::Zsh, Shell::PowerShell] {
+            let mut script = Vec::new();
+            clap_complete::generate(shell, &mut command(), "kpad", &mut script);
+            let script = String::from_utf8(script).unwrap();
+            for arg in command().get_arguments() {
+                if let Some(long) = arg.get_long() {
+                    assert!(script.contains(&format!("--{long}")), "{shell}: --{long}");
+                }
+            }
+        }
+    }
+}
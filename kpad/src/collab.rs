@@ -0,0 +1,379 @@
+//! Experimental pair editing on one machine: `kpad --host FILE` serves the text of FILE over a
+//! local socket without a UI, and `kpad --attach` edits it; editors can attach and detach while
+//! the host keeps running. The host holds the text for editors that attach later; the editors
+//! save the file as usual.
+//!
+//! Edits travel as operations (`Op`: retain, insert and delete runs over the whole text).
+//! Concurrent edits are merged by operational transformation in the Jupiter scheme: each
+//! editor and its connection on the host form a pair of `Channel`s, and each side transforms an
+//! incoming operation past its own operations the other side had not seen when it sent it, so
+//! every copy ends up with the same text. The host's side wins ties (both inserting at one
+//! spot), which orders them the same way everywhere.
+//!
+//! The protocol is one JSON `Packet` per line.
+
+use crate::buffer::Buffer; // the hosted text
+use crate::bus::Message; // remote edits for the editor
+use crate::instance::socket_name; // per-user socket names
+use anyhow::{bail, Context, Result}; // anyhow error handling
+use interprocess::local_socket::{prelude::*, ListenerOptions, SendHalf, Stream}; // local sockets
+use ropey::Rope; // text the operations apply to
+use serde::{Deserialize, Serialize}; // wire format
+use std::collections::HashMap; // attached editors
+use std::fs; // reading the hosted file
+use std::io::{BufRead, BufReader, ErrorKind, Write}; // line-based protocol
+use std::path::{Path, PathBuf}; // file paths
+use std::sync::mpsc::{self, Sender}; // connection events
+use std::thread; // connection threads
+
+/// Local socket the host listens on.
+const SERVICE: &str = "kpad-pair";
+
+/// A run of an operation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Part {
+    /// Keep this many chars.
+    Retain(usize),
+    /// Insert text.
+    Insert(String),
+    /// Delete this many chars.
+    Delete(usize),
+}
+
+impl Part {
+    /// Chars of the old text the run covers.
+    fn span(&self) -> usize {
+        match self {
+            Self::Retain(n) | Self::Delete(n) => *n,
+            Self::Insert(_) => 0,
+        }
+    }
+
+    /// What is left of a retain or delete run after its first `n` chars.
+    fn rest(self, n: usize) -> Option<Self> {
+        match self {
+            Self::Retain(m) if m > n => Some(Self::Retain(m - n)),
+            Self::Delete(m) if m > n => Some(Self::Delete(m - n)),
+            _ => None,
+        }
+    }
+}
+
+/// An edit of a whole text: its runs cover every char of it in order.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Op(Vec<Part>);
+
+impl Op {
+    /// Append a run, merging it into the last one of the same kind.
+    fn push(&mut self, part: Part) {
+        match (self.0.last_mut(), part) {
+            (_, Part::Retain(0) | Part::Delete(0)) => {}
+            (_, Part::Insert(s)) if s.is_empty() => {}
+            (Some(Part::Retain(n)), Part::Retain(m)) | (Some(Part::Delete(n)), Part::Delete(m)) => *n += m,
+            (Some(Part::Insert(s)), Part::Insert(t)) => s.push_str(&t),
+            (_, part) => self.0.push(part),
+        }
+    }
+
+    /// Replace `delete` chars at char `at` of a `len`-char text with `insert`.
+    pub fn replace(len: usize, at: usize, delete: usize, insert: &str) -> Self {
+        let mut op = Self::default();
+        op.push(Part::Retain(at));
+        op.push(Part::Delete(delete));
+        op.push(Part::Insert(insert.to_string()));
+        op.push(Part::Retain(len.saturating_sub(at + delete)));
+        op
+    }
+
+    /// `text` with the operation applied; `None` if it was made for a text of another length.
+    pub fn apply(&self, text: &Rope) -> Option<Rope> {
+        let mut out = text.clone();
+        let mut at = 0;
+        for part in &self.0 {
+            if at + part.span() > out.len_chars() { return None; }
+            match part {
+                Part::Retain(n) => at += n,
+                Part::Insert(s) => {
+                    out.insert(at, s);
+                    at += s.chars().count();
+                }
+                Part::Delete(n) => out.remove(at..at + n),
+            }
+        }
+        (at == out.len_chars()).then_some(out)
+    }
+}
+
+/// Transform concurrent operations `a` and `b` on one text into `(a', b')` such that `b` then
+/// `a'` and `a` then `b'` give the same text. `a` inserts first where both insert at one spot.
+/// `None` if they were made for texts of different lengths.
+pub fn transform(a: &Op, b: &Op) -> Option<(Op, Op)> {
+    let (mut a2, mut b2) = (Op::default(), Op::default());
+    let (mut ia, mut ib) = (a.0.iter().cloned(), b.0.iter().cloned());
+    let (mut pa, mut pb) = (ia.next(), ib.next());
+    loop {
+        match (pa.take(), pb.take()) {
+            (None, None) => return Some((a2, b2)),
+            (Some(Part::Insert(s)), other) => {
+                b2.push(Part::Retain(s.chars().count()));
+                a2.push(Part::Insert(s));
+                (pa, pb) = (ia.next(), other);
+            }
+            (other, Some(Part::Insert(s))) => {
+                a2.push(Part::Retain(s.chars().count()));
+                b2.push(Part::Insert(s));
+                (pa, pb) = (other, ib.next());
+            }
+            (Some(part_a), Some(part_b)) => {
+                let len = part_a.span().min(part_b.span());
+                match (&part_a, &part_b) {
+                    (Part::Retain(_), Part::Retain(_)) => {
+                        a2.push(Part::Retain(len));
+                        b2.push(Part::Retain(len));
+                    }
+                    (Part::Delete(_), Part::Retain(_)) => a2.push(Part::Delete(len)),
+                    (Part::Retain(_), Part::Delete(_)) => b2.push(Part::Delete(len)),
+                    // Both deleted these chars.
+                    _ => {}
+                }
+                pa = part_a.rest(len).or_else(|| ia.next());
+                pb = part_b.rest(len).or_else(|| ib.next());
+            }
+            _ => return None,
+        }
+    }
+}
+
+/// The smallest single replacement turning `old` into `new`: char offset, chars deleted and
+/// text inserted. `None` if they are equal.
+pub fn diff(old: &Rope, new: &Rope) -> Option<(usize, usize, String)> {
+    let (old_len, new_len) = (old.len_chars(), new.len_chars());
+    let prefix = old.chars().zip(new.chars()).take_while(|(a, b)| a == b).count();
+    if prefix == old_len && prefix == new_len { return None; }
+    let suffix = old.chars_at(old_len).reversed().zip(new.chars_at(new_len).reversed())
+        .take(old_len.min(new_len) - prefix)
+        .take_while(|(a, b)| a == b)
+        .count();
+    Some((prefix, old_len - prefix - suffix, new.slice(prefix..new_len - suffix).to_string()))
+}
+
+/// A protocol message.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Packet {
+    /// Host to a newly attached editor: the hosted file and its text.
+    Hello { path: PathBuf, text: String },
+    /// An edit, with the sender's count of edits it sent before it and received so far.
+    Edit { op: Op, sent: u64, seen: u64 },
+    /// Host to an editor whose edit did not fit the host's text; the host drops the connection.
+    OutOfStep,
+}
+
+/// One side of an editor-host connection.
+pub struct Channel {
+    /// Edits sent.
+    sent: u64,
+    /// Edits received.
+    received: u64,
+    /// Edits sent that the other side may not have seen yet, with their number.
+    pending: Vec<(u64, Op)>,
+    /// Whether this side's inserts go first at a shared spot (the host's).
+    first: bool,
+}
+
+impl Channel {
+    /// A fresh channel; `first` on the host side.
+    pub fn new(first: bool) -> Self {
+        Self { sent: 0, received: 0, pending: Vec::new(), first }
+    }
+
+    /// The packet that sends an edit made on this side.
+    pub fn local(&mut self, op: Op) -> Packet {
+        let packet = Packet::Edit { op: op.clone(), sent: self.sent, seen: self.received };
+        self.pending.push((self.sent, op));
+        self.sent += 1;
+        packet
+    }
+
+    /// An edit from the other side, which had received `seen` of ours, transformed to apply
+    /// here. `None` if it does not fit (the copies went out of step).
+    pub fn remote(&mut self, op: Op, seen: u64) -> Option<Op> {
+        self.pending.retain(|(n, _)| *n >= seen);
+        let mut op = op;
+        for (_, mine) in &mut self.pending {
+            let (theirs, ours) = if self.first {
+                let (ours, theirs) = transform(mine, &op)?;
+                (theirs, ours)
+            } else {
+                transform(&op, mine)?
+            };
+            op = theirs;
+            *mine = ours;
+        }
+        self.received += 1;
+        Some(op)
+    }
+}
+
+/// Write `packet` as one line.
+pub fn send(out: &mut impl Write, packet: &Packet) -> std::io::Result<()> {
+    let mut line = serde_json::to_string(packet).map_err(std::io::Error::other)?;
+    line.push('\n');
+    out.write_all(line.as_bytes())
+}
+
+/// Pass every packet read from `input` to `on` until the connection ends or `on` returns
+/// `false`. Lines that are not packets are skipped.
+fn read_packets(input: impl BufRead, mut on: impl FnMut(Packet) -> bool) {
+    for line in input.lines() {
+        let Ok(line) = line else { break; };
+        if let Ok(packet) = serde_json::from_str(&line) {
+            if !on(packet) { break; }
+        }
+    }
+}
+
+/// What the connection threads tell the host.
+enum Event {
+    Joined(usize, SendHalf),
+    Packet(usize, Packet),
+    Left(usize),
+}
+
+/// Host `path` for `kpad --attach` until the process is stopped (`--host`).
+pub fn host(path: &Path) -> Result<()> {
+    let path = std::path::absolute(path)?;
+    let mut doc = match fs::read_to_string(&path) {
+        Ok(text) => Buffer::from_string(&text),
+        Err(e) if e.kind() == ErrorKind::NotFound => Buffer::new(),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    let listener = ListenerOptions::new().name(socket_name(SERVICE)?).try_overwrite(true).create_sync()?;
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for (id, conn) in listener.incoming().filter_map(std::result::Result::ok).enumerate() {
+            let (input, out) = conn.split();
+            if tx.send(Event::Joined(id, out)).is_err() { break; }
+            let tx = tx.clone();
+            thread::spawn(move || {
+                read_packets(BufReader::new(input), |p| tx.send(Event::Packet(id, p)).is_ok());
+                let _ = tx.send(Event::Left(id));
+            });
+        }
+    });
+    eprintln!("Hosting {} for `kpad --attach`; Ctrl+C stops.", path.display());
+
+    let mut editors: HashMap<usize, (SendHalf, Channel)> = HashMap::new();
+    for event in rx {
+        match event {
+            Event::Joined(id, mut out) => {
+                let hello = Packet::Hello { path: path.clone(), text: doc.to_string() };
+                if send(&mut out, &hello).is_ok() {
+                    editors.insert(id, (out, Channel::new(true)));
+                    eprintln!("Editor {id} attached ({} attached).", editors.len());
+                }
+            }
+            Event::Packet(id, Packet::Edit { op, seen, .. }) => {
+                let Some((_, channel)) = editors.get_mut(&id) else { continue; };
+                let merged = channel.remote(op, seen).and_then(|op| Some((op.apply(&doc.text)?, op)));
+                let Some((text, op)) = merged else {
+                    // Its copy no longer matches ours; relaying more of its edits would only spread
+                    // the damage, so it detaches.
+                    if let Some((mut out, _)) = editors.remove(&id) {
+                        let _ = send(&mut out, &Packet::OutOfStep);
+                    }
+                    eprintln!("Editor {id} went out of step and was detached ({} attached).", editors.len());
+                    continue;
+                };
+                doc.text = text;
+                for (_, (out, channel)) in editors.iter_mut().filter(|(other, _)| **other != id) {
+                    // A failed write means the editor left; its thread reports that.
+                    let _ = send(out, &channel.local(op.clone()));
+                }
+            }
+            Event::Packet(..) => {}
+            Event::Left(id) => {
+                if editors.remove(&id).is_some() {
+                    eprintln!("Editor {id} detached ({} attached).", editors.len());
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Attach to the running host (`--attach`): the hosted file, its text, and where to send
+/// edits. Edits from the host are posted to the bus as `Message::PairEdit`.
+pub fn attach(tx: Sender<Message>) -> Result<(PathBuf, String, SendHalf)> {
+    let conn = Stream::connect(socket_name(SERVICE)?).context("No kpad --host is running")?;
+    let (input, out) = conn.split();
+    let mut input = BufReader::new(input);
+    let mut line = String::new();
+    input.read_line(&mut line)?;
+    let Ok(Packet::Hello { path, text }) = serde_json::from_str(&line) else { bail!("The host did not send its text"); };
+    thread::spawn(move || {
+        let mut out_of_step = false;
+        read_packets(input, |packet| match packet {
+            Packet::Edit { op, seen, .. } => tx.send(Message::PairEdit { op, seen }).is_ok(),
+            Packet::Hello { .. } => true,
+            Packet::OutOfStep => {
+                out_of_step = true;
+                false
+            }
+        });
+        let _ = tx.send(Message::PairClosed { out_of_step });
+    });
+    Ok((path, text, out))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A pseudo-random edit of a `len`-char text.
+    fn random_op(len: usize, seed: &mut u64) -> Op {
+        let mut next = |n: usize| {
+            *seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+            usize::try_from(*seed >> 33).unwrap_or(0) % n.max(1)
+        };
+        let at = next(len + 1);
+        let delete = next(len - at + 1);
+        let insert = ["", "x", "yz", "\n"][next(4)];
+        Op::replace(len, at, delete, insert)
+    }
+
+    #[test]
+    fn concurrent_edits_converge() {
+        let text = Rope::from_str("hello wide\nworld");
+        let mut seed = 7;
+        for _ in 0..500 {
+            let a = random_op(text.len_chars(), &mut seed);
+            let b = random_op(text.len_chars(), &mut seed);
+            let (a2, b2) = transform(&a, &b).unwrap();
+            let ab = b2.apply(&a.apply(&text).unwrap()).unwrap();
+            let ba = a2.apply(&b.apply(&text).unwrap()).unwrap();
+            assert_eq!(ab, ba, "{a:?} / {b:?}");
+        }
+        let first = Op::replace(5, 2, 0, "A");
+        let second = Op::replace(5, 2, 0, "B");
+        let (a2, _) = transform(&first, &second).unwrap();
+        assert_eq!(a2.apply(&second.apply(&Rope::from_str("abcde")).unwrap()).unwrap(), "abABcde");
+    }
+
+    #[test]
+    fn channels_merge_edits_in_flight() {
+        let (mut host, mut editor) = (Channel::new(true), Channel::new(false));
+        let start = Rope::from_str("abc");
+        // Both edit before seeing the other's edit.
+        let Packet::Edit { op: from_host, seen: host_seen, .. } = host.local(Op::replace(3, 0, 1, "X")) else { unreachable!() };
+        let Packet::Edit { op: from_editor, seen: editor_seen, .. } = editor.local(Op::replace(3, 3, 0, "!")) else { unreachable!() };
+        let on_host = host.remote(from_editor.clone(), editor_seen).unwrap().apply(&from_host.apply(&start).unwrap()).unwrap();
+        let on_editor = editor.remote(from_host.clone(), host_seen).unwrap().apply(&from_editor.apply(&start).unwrap()).unwrap();
+        assert_eq!(on_host, "Xbc!");
+        assert_eq!(on_editor, on_host);
+        assert_eq!(diff(&start, &on_host), Some((0, 3, "Xbc!".to_string())));
+        assert_eq!(diff(&Rope::from_str("abca"), &Rope::from_str("aXca")), Some((1, 1, "X".to_string())));
+        assert_eq!(diff(&start, &start), None);
+    }
+}
@@ -2,18 +2,27 @@
 
 use crate::editor::Editor; // main editor state
 use crate::utils::levenshtein_distance; // string distance for suggestions
-use anyhow::Result; // anyhow error handling
-use std::collections::HashMap; // dictionary data structure
+use anyhow::{bail, Result}; // anyhow error handling
+use std::collections::{HashMap, HashSet}; // dictionary data structure, remapped commands
 
 /// Where a command comes from: built-in Rust function or plugin function.
 #[derive(Clone)]
 pub enum CommandSource {
     /// A built-in command implemented as a Rust function.
     Builtin(fn(&mut Editor) -> Result<()>),
-    /// A plugin-provided command (plugin_id, function_name).
+    /// A built-in command that takes arguments (`goto_line 120`, `set wrap on`).
+    BuiltinArgs(fn(&mut Editor, &[String]) -> Result<()>),
+    /// A plugin-provided command (plugin_id, function_name). A function with a second parameter
+    /// gets the arguments as an array of strings.
     Plugin { plugin_id: String, func: String },
+    /// A keyboard macro, as keys in macro notation (see `editor::macros`).
+    Macro(String),
 }
 
+/// Candidates for an argument of a command in the command prompt, given the registry and the
+/// arguments before it.
+pub type ArgCompleter = fn(&CommandRegistry, &[&str]) -> Vec<String>;
+
 /// A user-invokable action.
 ///
 /// Commands can be invoked either by keybinding (`key`) or via the command palette prompt.
@@ -29,7 +38,10 @@ pub struct Command {
 pub struct CommandRegistry {
     commands: Vec<Command>,
     by_name: HashMap<String, usize>,
-    keymap: HashMap<String, String>, // key -> command_name
+    keymap: HashMap<String, String>, // key -> command_name (effective bindings)
+    user_keys: HashMap<String, String>, // key -> command_name from the config file ("" unbinds)
+    completers: HashMap<String, ArgCompleter>, // command_name (lowercase) -> argument candidates
+    aliases: HashMap<String, String>, // alias (lowercase) -> the command line it stands for
 }
 
 impl CommandRegistry {
@@ -39,17 +51,23 @@ impl CommandRegistry {
             commands: vec![],
             by_name: HashMap::new(),
             keymap: HashMap::new(),
+            user_keys: HashMap::new(),
+            completers: HashMap::new(),
+            aliases: HashMap::new(),
         }
     }
 
     /// Add or replace a command.
     ///
     /// - Names are case-insensitive.
-    /// - If a keybinding is present, we also add it to `keymap` so key presses can resolve fast.
+    /// - If a keybinding is present, we also add it to `keymap` so key presses can resolve fast,
+    ///   unless the user's config binds that key or remaps the command.
     pub fn register(&mut self, cmd: Command) {
         let name_key = cmd.name.to_lowercase();
         if let Some(k) = cmd.key.as_ref() {
-            self.keymap.insert(k.clone(), cmd.name.clone());
+            if !self.user_keys.contains_key(k) && !self.is_remapped(&name_key) {
+                self.keymap.insert(k.clone(), cmd.name.clone());
+            }
         }
 
         if let Some(&idx) = self.by_name.get(&name_key) {
@@ -61,26 +79,134 @@ impl CommandRegistry {
         }
     }
 
+    /// Drop all plugin commands (and their keybindings) before plugins are reloaded.
+    pub fn remove_plugin_commands(&mut self) {
+        let old = std::mem::take(&mut self.commands);
+        self.by_name.clear();
+        for cmd in old.into_iter().filter(|c| !matches!(c.source, CommandSource::Plugin { .. })) {
+            self.register(cmd);
+        }
+        self.rebuild_keymap();
+    }
+
     /// Lookup a command by name (case-insensitive).
     pub fn get(&self, name: &str) -> Option<&Command> {
         let idx = *self.by_name.get(&name.to_lowercase())?;
         self.commands.get(idx)
     }
 
+    /// Let `alias` stand for the command line `expansion` (`w` for `save`, `wrapon` for
+    /// `set wrap on`).
+    pub fn set_alias(&mut self, alias: &str, expansion: &str) {
+        self.aliases.insert(alias.to_lowercase(), expansion.trim().to_string());
+    }
+
+    /// `line` with a leading alias replaced by the command line it stands for, keeping the
+    /// arguments after it (`g 12` -> `goto_line 12`). The expansion is not expanded again.
+    pub fn expand_alias(&self, line: &str) -> String {
+        let line = line.trim();
+        let (word, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        match self.aliases.get(&word.to_lowercase()) {
+            Some(expansion) if rest.is_empty() => expansion.clone(),
+            Some(expansion) => format!("{expansion} {rest}"),
+            None => line.to_string(),
+        }
+    }
+
+    /// The command a command line runs, after alias expansion.
+    pub fn command_for(&self, line: &str) -> Option<&Command> {
+        self.get(self.expand_alias(line).split_whitespace().next()?)
+    }
+
     /// List commands (sorted) for help/auto-complete UI.
-    #[allow(dead_code)]
     pub fn list_names(&self) -> Vec<String> {
         let mut v: Vec<_> = self.commands.iter().map(|c| c.name.clone()).collect();
         v.sort();
         v
     }
 
+    /// Let Tab in the command prompt complete the arguments of command `name` from `f`.
+    pub fn set_completer(&mut self, name: &str, f: ArgCompleter) {
+        self.completers.insert(name.to_lowercase(), f);
+    }
+
+    /// Tab completions of a command prompt line like `set wr`: the line with its last word
+    /// replaced by each candidate of the command's completer that starts with it. Aliases
+    /// complete like the command they stand for.
+    pub fn complete_args(&self, line: &str) -> Vec<String> {
+        let Some((head, partial)) = line.rsplit_once(' ') else { return Vec::new(); };
+        let expanded = self.expand_alias(head.trim_start().trim_start_matches(':'));
+        let mut words = expanded.split_whitespace();
+        let Some(name) = words.next() else { return Vec::new(); };
+        let Some(complete) = self.completers.get(&name.to_lowercase()) else { return Vec::new(); };
+        let args: Vec<&str> = words.collect();
+        let mut found: Vec<String> = complete(self, &args).into_iter()
+            .filter(|c| c.starts_with(partial))
+            .map(|c| format!("{head} {c}"))
+            .collect();
+        found.sort();
+        found.dedup();
+        found
+    }
+
     /// Resolve a key chord like `"Ctrl+S"` to a command name.
     pub fn resolve_key(&self, key: &str) -> Option<String> {
         self.keymap.get(key).cloned()
     }
 
-    /// Fuzzy-ish search over commands by name/description.
+    /// Bind a key chord to a command from the user's config (an empty command unbinds the key).
+    /// The command loses its default key, and the binding survives plugin reloads.
+    pub fn bind_user_key(&mut self, key: &str, command: &str) {
+        self.user_keys.insert(normalize_key_string(key), command.to_string());
+        self.rebuild_keymap();
+    }
+
+    /// Whether the user's config binds command `name_key` (lowercase) to a key of its own.
+    fn is_remapped(&self, name_key: &str) -> bool {
+        self.user_keys.values().any(|c| c.to_lowercase() == name_key)
+    }
+
+    /// Recompute `keymap`: the commands' default keys, then the user's bindings on top.
+    fn rebuild_keymap(&mut self) {
+        let remapped: HashSet<String> = self.user_keys.values().map(|c| c.to_lowercase()).collect();
+        self.keymap.clear();
+        for cmd in &self.commands {
+            if let Some(k) = &cmd.key {
+                if !remapped.contains(&cmd.name.to_lowercase()) {
+                    self.keymap.insert(k.clone(), cmd.name.clone());
+                }
+            }
+        }
+        for (k, name) in &self.user_keys {
+            if name.is_empty() {
+                self.keymap.remove(k);
+            } else {
+                self.keymap.insert(k.clone(), name.clone());
+            }
+        }
+    }
+
+    /// Effective bindings as (key, command, from the user's config), sorted by command name.
+    pub fn bindings(&self) -> Vec<(&str, &str, bool)> {
+        let mut v: Vec<_> = self.keymap.iter()
+            .map(|(k, c)| (k.as_str(), c.as_str(), self.user_keys.contains_key(k)))
+            .collect();
+        v.sort_by(|a, b| (a.1.to_lowercase(), a.0).cmp(&(b.1.to_lowercase(), b.0)));
+        v
+    }
+
+    /// Keys bound to command `name`, for display.
+    pub fn keys_for(&self, name: &str) -> Vec<&str> {
+        let mut keys: Vec<&str> = self.keymap.iter()
+            .filter(|(_, c)| c.eq_ignore_ascii_case(name))
+            .map(|(k, _)| k.as_str())
+            .collect();
+        keys.sort_unstable();
+        keys
+    }
+
+    /// Fuzzy-ish search over commands by name/description: names starting with `query` first,
+    /// then names containing it, then matching descriptions.
     pub fn search(&self, query: &str, limit: usize) -> Vec<&Command> {
         let q = query.to_lowercase();
         let mut items: Vec<&Command> = self
@@ -90,7 +216,10 @@ impl CommandRegistry {
                 c.name.to_lowercase().contains(&q) || c.description.to_lowercase().contains(&q)
             })
             .collect();
-        items.sort_by_key(|c| c.name.to_lowercase());
+        items.sort_by_key(|c| {
+            let name = c.name.to_lowercase();
+            (!name.starts_with(&q), !name.contains(&q), name)
+        });
         items.truncate(limit);
         items
     }
@@ -164,4 +293,150 @@ pub fn canonical_key_string(key: &crossterm::event::KeyEvent) -> String {
     }
 }
 
+/// Split a command line into its words: the command name and its arguments. Whitespace separates
+/// words; single or double quotes keep spaces in one (`open "my notes.txt"`). Backslashes are
+/// plain characters, so Windows paths need no escaping.
+pub fn split_args(line: &str) -> Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quote = None;
+    for c in line.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                word.get_or_insert_with(String::new);
+            }
+            None if c.is_whitespace() => words.extend(word.take()),
+            _ => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if quote.is_some() { bail!("Unterminated quote in '{}'", line.trim()); }
+    words.extend(word);
+    Ok(words)
+}
+
+/// Normalize a user-provided keybinding string into canonical form.
+pub fn normalize_key_string(s: &str) -> String {
+    let mut ctrl = false;
+    let mut alt = false;
+    let mut shift = false;
+    let mut key = None::<String>;
 
+    for part in s.split('+').map(str::trim).filter(|p| !p.is_empty()) {
+        let p = part.to_lowercase();
+        match p.as_str() {
+            "ctrl" | "control" => ctrl = true,
+            "alt" => alt = true,
+            "shift" => shift = true,
+            _ => {
+                key = Some(match p.as_str() {
+                    "enter" => "Enter".to_string(),
+                    "esc" | "escape" => "Esc".to_string(),
+                    "backspace" => "Backspace".to_string(),
+                    "delete" | "del" => "Delete".to_string(),
+                    "tab" => "Tab".to_string(),
+                    "left" => "Left".to_string(),
+                    "right" => "Right".to_string(),
+                    "up" => "Up".to_string(),
+                    "down" => "Down".to_string(),
+                    "home" => "Home".to_string(),
+                    "end" => "End".to_string(),
+                    "pageup" => "PageUp".to_string(),
+                    "pagedown" => "PageDown".to_string(),
+                    other => {
+                        if other.len() == 1 {
+                            other.chars().next().unwrap().to_ascii_uppercase().to_string()
+                        } else if other.starts_with('f')
+                            && other[1..].chars().all(|c| c.is_ascii_digit())
+                        {
+                            format!("F{}", &other[1..])
+                        } else {
+                            part.to_string()
+                        }
+                    }
+                });
+            }
+        }
+    }
+
+    let mut key = key.unwrap_or_else(|| "?".to_string());
+    // Terminals report Shift+Tab as BackTab (with Shift).
+    if shift && key == "Tab" { key = "BackTab".to_string(); }
+    let mut parts = Vec::new();
+    if ctrl { parts.push("Ctrl".to_string()); }
+    if alt { parts.push("Alt".to_string()); }
+    if shift { parts.push("Shift".to_string()); }
+    parts.push(key);
+    parts.join("+")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    #[test]
+    fn enhanced_chords_match_bindings() {
+        let ctrl_shift = KeyModifiers::CONTROL | KeyModifiers::SHIFT;
+        let chords = [
+            (KeyEvent::new(KeyCode::Char('D'), ctrl_shift), "ctrl+shift+d"),
+            (KeyEvent::new(KeyCode::Enter, KeyModifiers::CONTROL), "Ctrl+Enter"),
+            (KeyEvent::new(KeyCode::Tab, KeyModifiers::CONTROL), "Ctrl+Tab"),
+            (KeyEvent::new(KeyCode::BackTab, ctrl_shift), "Ctrl+Shift+Tab"),
+        ];
+        for (event, binding) in chords {
+            assert_eq!(canonical_key_string(&event), normalize_key_string(binding));
+        }
+        assert_ne!(normalize_key_string("Ctrl+Shift+D"), normalize_key_string("Ctrl+D"));
+    }
+
+    #[test]
+    fn completes_the_last_argument() {
+        let mut reg = CommandRegistry::new();
+        reg.set_completer("set", |_, args| match args {
+            [] => vec!["wrap".to_string(), "tab_width".to_string()],
+            ["wrap"] => vec!["on".to_string(), "off".to_string()],
+            _ => Vec::new(),
+        });
+        assert_eq!(reg.complete_args("set "), ["set tab_width", "set wrap"]);
+        assert_eq!(reg.complete_args(":set w"), [":set wrap"]);
+        assert_eq!(reg.complete_args("set wrap o"), ["set wrap off", "set wrap on"]);
+        assert!(reg.complete_args("set").is_empty());
+        assert!(reg.complete_args("save ").is_empty());
+    }
+
+    #[test]
+    fn search_lists_name_matches_first() {
+        let mut reg = CommandRegistry::new();
+        for (name, description) in [("alternate_buffer", "Switch to the previous buffer"), ("reverse_lines", "Reverse lines"), ("prev", "")] {
+            reg.register(Command { name: name.to_string(), description: description.to_string(), key: None, source: CommandSource::Builtin(|_| Ok(())) });
+        }
+        let names: Vec<&str> = reg.search("rev", 10).iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["reverse_lines", "prev", "alternate_buffer"]);
+    }
+
+    #[test]
+    fn expands_aliases_before_lookup() {
+        let mut reg = CommandRegistry::new();
+        reg.register(Command { name: "goto_line".to_string(), description: String::new(), key: None, source: CommandSource::Builtin(|_| Ok(())) });
+        reg.set_alias("G", "goto_line");
+        reg.set_alias("wrapon", "set wrap on");
+        reg.set_completer("set", |_, _| vec!["wrap".to_string()]);
+        assert_eq!(reg.expand_alias(" g 12 "), "goto_line 12");
+        assert_eq!(reg.expand_alias("wrapon"), "set wrap on");
+        assert_eq!(reg.expand_alias("gg 12"), "gg 12");
+        assert_eq!(reg.command_for("g 3").map(|c| c.name.as_str()), Some("goto_line"));
+        reg.set_alias("s", "set");
+        assert_eq!(reg.complete_args("s w"), ["s wrap"]);
+    }
+
+    #[test]
+    fn splits_command_lines_into_words() {
+        assert_eq!(split_args("  set  wrap on ").unwrap(), ["set", "wrap", "on"]);
+        assert_eq!(split_args(r#"open "my notes.txt""#).unwrap(), ["open", "my notes.txt"]);
+        assert_eq!(split_args(r"open C:\x\'a b'.txt ''").unwrap(), ["open", r"C:\x\a b.txt", ""]);
+        assert!(split_args("").unwrap().is_empty());
+        assert!(split_args("find 'abc").is_err());
+    }
+}
@@ -0,0 +1,478 @@
+//! User configuration: `kpad.toml`, read once at startup.
+//!
+//! The file lives in `~/.config/kpad/kpad.toml` (`%APPDATA%\kpad\kpad.toml` on Windows), or
+//! wherever `$KPAD_CONFIG` points. Every setting is optional; a missing file means defaults.
+//!
+//! ```toml
+//! tab_width = 2
+//! word_wrap = true
+//! wrap_indent = "same"
+//! ruler = 100
+//! theme = "light"
+//! plugin_dirs = ["~/kpad-plugins"]
+//! auto_close = true
+//! bell = "visual"
+//! middle_click = "clipboard"
+//! keymap = "vim"
+//! sort = "plain"
+//! scrolloff = 3
+//! scroll_lines = 5
+//! low_power = true
+//!
+//! [keybindings]
+//! "Alt+R" = "rename_word"
+//! "Ctrl+N" = ""
+//!
+//! [alias]
+//! fmt = "json_validate"
+//! wrapon = "set wrap on"
+//!
+//! [check]
+//! json = ["json_validate", "json_schema"]
+//!
+//! [lsp]
+//! rs = ["rust-analyzer"]
+//!
+//! [auto_close_pairs]
+//! md = "()[]**"
+//! ```
+
+use crate::commands::CommandRegistry; // user keybindings
+use crate::utils::safe_mode; // --safe skips the config
+use anyhow::{bail, Context, Result}; // anyhow error handling
+use crossterm::style::Color; // theme colors
+use serde::Deserialize; // toml deserialization
+use std::collections::HashMap; // keybinding table
+use std::fs; // reading the config file
+use std::io::ErrorKind; // missing config file
+use std::path::{Path, PathBuf}; // config and plugin paths
+
+/// Largest accepted `tab_width`.
+pub(crate) const MAX_TAB_WIDTH: usize = 16;
+
+/// Settings from `kpad.toml`.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Spaces inserted by Tab.
+    pub tab_width: usize,
+    /// Whether word wrap starts on.
+    pub word_wrap: bool,
+    /// How continuation rows of wrapped lines are indented.
+    pub wrap_indent: WrapIndent,
+    /// Line length marked by a guide column right after it; 0 shows none.
+    pub ruler: usize,
+    /// Color theme name (see `Theme::named`).
+    pub theme: String,
+    /// Key chords mapped to command names, e.g. `"Alt+R" = "rename_word"`; a remapped command
+    /// loses its default key, and `""` unbinds a key. Also accepted as `bindings`.
+    #[serde(alias = "bindings")]
+    pub keybindings: HashMap<String, String>,
+    /// Plugin directories searched after the default ones; relative paths are relative to the
+    /// config file and `~/` is the home directory.
+    pub plugin_dirs: Vec<PathBuf>,
+    /// Validation commands `kpad --check` runs, by file extension, e.g.
+    /// `json = ["json_validate"]`; replaces the built-in JSON/TOML validators for that extension.
+    pub check: HashMap<String, Vec<String>>,
+    /// Language server command lines (program, then arguments) by file extension, e.g.
+    /// `rs = ["rust-analyzer"]`; their diagnostics are shown in the text.
+    pub lsp: HashMap<String, Vec<String>>,
+    /// Whether typing an opening bracket or quote also inserts its closing one.
+    pub auto_close: bool,
+    /// Pairs auto-closed by file extension, as open/close characters in a row, e.g.
+    /// `md = "()[]**"`; `""` turns auto-closing off for that extension.
+    pub auto_close_pairs: HashMap<String, String>,
+    /// Files of this many megabytes and more open without syntax highlighting and word wrap;
+    /// 0 never does.
+    pub large_file_mb: u64,
+    /// Whether a tab bar lists the open buffers once there are two or more.
+    pub tab_bar: bool,
+    /// Feedback on errors such as "No matches" or a failed save.
+    pub bell: Bell,
+    /// What a middle click pastes at the pointer.
+    pub middle_click: MiddleClick,
+    /// Modeless (the default) or vim-style modal editing.
+    pub keymap: Keymap,
+    /// Undo steps kept per buffer; 0 keeps all.
+    pub undo_steps: usize,
+    /// Megabytes of text the undo history of a buffer may hold; 0 has no limit. The newest
+    /// step is always kept.
+    pub undo_mb: usize,
+    /// Keyboard macros by name, in macro notation (`"fix" = "<Home># <Down>"`); each becomes a
+    /// command that `[keybindings]` can bind.
+    pub macros: HashMap<String, String>,
+    /// How `sort_lines` compares lines.
+    pub sort: Sort,
+    /// Lines of context kept above and below the cursor when scrolling.
+    pub scrolloff: usize,
+    /// Lines the view scrolls per mouse wheel step.
+    pub scroll_lines: usize,
+    /// Milliseconds between wake-ups while something animates.
+    pub poll_ms: u64,
+    /// Milliseconds between wake-ups when nothing is pending.
+    pub idle_poll_ms: u64,
+    /// Whether an idle editor sleeps until input instead of waking every `idle_poll_ms`.
+    pub low_power: bool,
+    /// Command aliases, e.g. `fmt = "format_json"` or `wrapon = "set wrap on"`; arguments typed
+    /// after an alias follow its expansion. Also accepted as `aliases`.
+    #[serde(alias = "aliases")]
+    pub alias: HashMap<String, String>,
+}
+
+/// How kpad gets attention on errors (`bell` in kpad.toml).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Bell {
+    /// Only the status message.
+    #[default]
+    Off,
+    /// The status bar flashes.
+    Visual,
+    /// The terminal bell (BEL) rings.
+    Audible,
+    /// Both.
+    Both,
+}
+
+impl Bell {
+    /// Whether the status bar flashes.
+    pub fn visual(self) -> bool {
+        matches!(self, Self::Visual | Self::Both)
+    }
+
+    /// Whether the terminal bell rings.
+    pub fn audible(self) -> bool {
+        matches!(self, Self::Audible | Self::Both)
+    }
+}
+
+/// What a middle click pastes (`middle_click` in kpad.toml), as in Linux terminals.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MiddleClick {
+    /// Nothing.
+    Off,
+    /// The selected text, or the clipboard when nothing is selected.
+    #[default]
+    Selection,
+    /// The clipboard.
+    Clipboard,
+}
+
+/// Editing style (`keymap` in kpad.toml).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Keymap {
+    /// Keys type text; shortcuts use Ctrl and Alt.
+    #[default]
+    Standard,
+    /// Modal editing: Normal, Insert and Visual modes (see `editor::modal`).
+    Vim,
+}
+
+/// Indentation of the continuation rows of a wrapped line (`wrap_indent` in kpad.toml).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WrapIndent {
+    /// Continuation rows start at the left edge.
+    #[default]
+    None,
+    /// As far as the line's own indentation.
+    Same,
+    /// One `tab_width` deeper than the line.
+    Indent,
+}
+
+/// How `sort_lines` compares lines (`sort` in kpad.toml).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Sort {
+    /// Runs of digits by value, so `file9` comes before `file10`.
+    #[default]
+    Natural,
+    /// Char by char.
+    Plain,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            tab_width: 4,
+            word_wrap: false,
+            wrap_indent: WrapIndent::None,
+            ruler: 80,
+            theme: "dark".to_string(),
+            keybindings: HashMap::new(),
+            plugin_dirs: Vec::new(),
+            check: HashMap::new(),
+            lsp: HashMap::new(),
+            auto_close: true,
+            auto_close_pairs: HashMap::new(),
+            large_file_mb: 50,
+            tab_bar: true,
+            bell: Bell::Off,
+            middle_click: MiddleClick::Selection,
+            keymap: Keymap::Standard,
+            undo_steps: 1000,
+            undo_mb: 64,
+            macros: HashMap::new(),
+            sort: Sort::Natural,
+            scrolloff: 0,
+            scroll_lines: 3,
+            poll_ms: 50,
+            idle_poll_ms: 1000,
+            low_power: false,
+            alias: HashMap::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Load the user's config file; defaults if there is none.
+    pub fn load() -> Result<Self> {
+        if safe_mode() { return Ok(Self::default()); }
+        let Some(path) = config_path() else { return Ok(Self::default()); };
+        match fs::read_to_string(&path) {
+            Ok(text) => Self::parse(&text, path.parent().unwrap_or(Path::new(".")))
+                .with_context(|| format!("{}", path.display())),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        }
+    }
+
+    /// Parse config text; relative plugin directories are resolved against `dir`.
+    fn parse(text: &str, dir: &Path) -> Result<Self> {
+        let mut config: Self = toml::from_str(text)?;
+        if config.tab_width == 0 || config.tab_width > MAX_TAB_WIDTH {
+            bail!("tab_width must be between 1 and {MAX_TAB_WIDTH}");
+        }
+        if config.scroll_lines == 0 {
+            bail!("scroll_lines must be at least 1");
+        }
+        if config.poll_ms == 0 || config.idle_poll_ms == 0 {
+            bail!("poll_ms and idle_poll_ms must be at least 1");
+        }
+        if Theme::named(&config.theme).is_none() {
+            bail!("Unknown theme '{}' (available: {})", config.theme, Theme::NAMES.join(", "));
+        }
+        if let Some((ext, _)) = config.auto_close_pairs.iter().find(|(_, pairs)| pairs.chars().count() % 2 == 1) {
+            bail!("auto_close_pairs.{ext} must list open and close characters in pairs");
+        }
+        if let Some((ext, _)) = config.lsp.iter().find(|(_, command)| command.is_empty()) {
+            bail!("lsp.{ext} needs a server command");
+        }
+        for p in &mut config.plugin_dirs {
+            *p = resolve_dir(p, dir);
+        }
+        Ok(config)
+    }
+
+    /// `large_file_mb` in bytes.
+    pub fn large_file_bytes(&self) -> u64 {
+        if self.large_file_mb == 0 { u64::MAX } else { self.large_file_mb.saturating_mul(1 << 20) }
+    }
+
+    /// `undo_steps`, with 0 as no limit.
+    pub fn undo_step_limit(&self) -> usize {
+        if self.undo_steps == 0 { usize::MAX } else { self.undo_steps }
+    }
+
+    /// `undo_mb` in bytes, with 0 as no limit.
+    pub fn undo_byte_limit(&self) -> usize {
+        if self.undo_mb == 0 { usize::MAX } else { self.undo_mb.saturating_mul(1 << 20) }
+    }
+
+    /// Commands `kpad --check` runs on a file with extension `ext`.
+    pub fn check_commands(&self, ext: &str) -> Vec<String> {
+        if let Some(commands) = self.check.get(&ext.to_lowercase()) {
+            return commands.clone();
+        }
+        match ext.to_lowercase().as_str() {
+            "json" => vec!["json_validate".to_string()],
+            "toml" => vec!["toml_validate".to_string()],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Add the `[alias]` entries to `commands`; returns a status message listing bad entries.
+    /// An alias cannot shadow a command, and its expansion must start with a command name.
+    pub fn add_aliases(&self, commands: &mut CommandRegistry) -> Option<String> {
+        let mut problems = Vec::new();
+        for (alias, line) in &self.alias {
+            if commands.get(alias).is_some() {
+                problems.push(format!("'{alias}' is already a command"));
+            } else if line.split_whitespace().next().and_then(|name| commands.get(name)).is_none() {
+                problems.push(format!("unknown command '{line}' for {alias}"));
+            } else {
+                commands.set_alias(alias, line);
+            }
+        }
+        problems.sort();
+        (!problems.is_empty()).then(|| format!("Config aliases: {}", problems.join("; ")))
+    }
+
+    /// Add the `[keybindings]` to `commands`; returns a status message listing bad entries.
+    pub fn bind_keys(&self, commands: &mut CommandRegistry) -> Option<String> {
+        let mut problems = Vec::new();
+        for (key, name) in &self.keybindings {
+            if name.is_empty() || commands.command_for(name).is_some() {
+                commands.bind_user_key(key, name);
+            } else {
+                problems.push(format!("unknown command '{name}' for {key}"));
+            }
+        }
+        problems.sort();
+        (!problems.is_empty()).then(|| format!("Config keybindings: {}", problems.join("; ")))
+    }
+}
+
+/// Location of the config file: `$KPAD_CONFIG`, else `kpad/kpad.toml` in `~/.config` (the
+/// roaming app data directory on Windows).
+pub fn config_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("KPAD_CONFIG") {
+        return Some(path.into());
+    }
+    let base = if cfg!(windows) { dirs::config_dir() } else { dirs::home_dir().map(|h| h.join(".config")) };
+    base.map(|d| d.join("kpad").join("kpad.toml"))
+}
+
+/// Plugins of this user: `plugins/` next to the config file (also where `plugin_new` writes).
+pub fn user_plugin_dir() -> Option<PathBuf> {
+    config_path()?.parent().map(|d| d.join("plugins"))
+}
+
+/// Expand a leading `~/` and make relative paths relative to `base`.
+fn resolve_dir(p: &Path, base: &Path) -> PathBuf {
+    if let (Ok(rest), Some(home)) = (p.strip_prefix("~"), dirs::home_dir()) {
+        return home.join(rest);
+    }
+    base.join(p)
+}
+
+/// Colors of the editor chrome (syntax colors come from highlight rules).
+#[derive(Clone, Copy)]
+pub struct Theme {
+    /// Background of the cursor line.
+    pub current_line: Color,
+    /// Line numbers and `~` filler.
+    pub gutter: Color,
+    /// The `↪` in the gutter of wrapped lines' continuation rows.
+    pub wrap_marker: Color,
+    /// Background of the `ruler` guide.
+    pub ruler: Color,
+    /// Language server diagnostics (underline and gutter sign): errors, warnings, the rest.
+    pub diagnostic_error: Color,
+    pub diagnostic_warning: Color,
+    pub diagnostic_info: Color,
+    pub selection_fg: Color,
+    pub selection_bg: Color,
+    pub status_fg: Color,
+    pub status_bg: Color,
+}
+
+impl Theme {
+    /// Names accepted by `named`.
+    pub const NAMES: [&'static str; 2] = ["dark", "light"];
+
+    /// A built-in theme by name.
+    pub fn named(name: &str) -> Option<Self> {
+        match name {
+            "dark" => Some(Self {
+                current_line: Color::DarkBlue,
+                gutter: Color::DarkGrey,
+                wrap_marker: Color::AnsiValue(66),
+                ruler: Color::AnsiValue(236),
+                diagnostic_error: Color::Red,
+                diagnostic_warning: Color::Yellow,
+                diagnostic_info: Color::Cyan,
+                selection_fg: Color::Black,
+                selection_bg: Color::Grey,
+                status_fg: Color::Black,
+                status_bg: Color::White,
+            }),
+            "light" => Some(Self {
+                current_line: Color::AnsiValue(254),
+                gutter: Color::AnsiValue(245),
+                wrap_marker: Color::AnsiValue(109),
+                ruler: Color::AnsiValue(252),
+                diagnostic_error: Color::DarkRed,
+                diagnostic_warning: Color::AnsiValue(130),
+                diagnostic_info: Color::DarkCyan,
+                selection_fg: Color::White,
+                selection_bg: Color::DarkBlue,
+                status_fg: Color::White,
+                status_bg: Color::DarkGrey,
+            }),
+            _ => None,
+        }
+    }
+
+    /// The theme with every color passed through `f`.
+    pub fn map(self, f: impl Fn(Color) -> Color) -> Self {
+        Self {
+            current_line: f(self.current_line),
+            gutter: f(self.gutter),
+            wrap_marker: f(self.wrap_marker),
+            ruler: f(self.ruler),
+            diagnostic_error: f(self.diagnostic_error),
+            diagnostic_warning: f(self.diagnostic_warning),
+            diagnostic_info: f(self.diagnostic_info),
+            selection_fg: f(self.selection_fg),
+            selection_bg: f(self.selection_bg),
+            status_fg: f(self.status_fg),
+            status_bg: f(self.status_bg),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::{Command, CommandSource};
+
+    #[test]
+    fn parses_settings_and_rejects_bad_values() {
+        let dir = Path::new("/etc/kpad");
+        let config = Config::parse("tab_width = 2\ntheme = \"light\"\nplugin_dirs = [\"extra\"]\n\n[keybindings]\n\"Alt+R\" = \"rename_word\"\n", dir).unwrap();
+        assert_eq!(config.tab_width, 2);
+        assert!(!config.word_wrap);
+        assert_eq!(config.plugin_dirs, [dir.join("extra")]);
+        assert_eq!(config.keybindings["Alt+R"], "rename_word");
+
+        assert_eq!(Config::parse("", dir).unwrap().tab_width, 4);
+        assert!(Config::parse("tab_width = 0", dir).is_err());
+        assert!(Config::parse("theme = \"neon\"", dir).is_err());
+        assert!(Config::parse("tabwidth = 2", dir).is_err());
+        assert!(Config::parse("[auto_close_pairs]\nmd = \"()*\"", dir).is_err());
+        assert_eq!(Config::parse("[lsp]\nrs = [\"rust-analyzer\"]", dir).unwrap().lsp["rs"], ["rust-analyzer"]);
+        assert!(Config::parse("[lsp]\nrs = []", dir).is_err());
+        assert_eq!(Config::parse("bindings = { \"F5\" = \"save\" }", dir).unwrap().keybindings["F5"], "save");
+        assert_eq!(Config::parse("bell = \"both\"", dir).unwrap().bell, Bell::Both);
+        assert!(Config::parse("bell = \"loud\"", dir).is_err());
+        assert_eq!(Config::parse("", dir).unwrap().middle_click, MiddleClick::Selection);
+        assert_eq!(Config::parse("middle_click = \"off\"", dir).unwrap().middle_click, MiddleClick::Off);
+        assert_eq!(Config::parse("keymap = \"vim\"", dir).unwrap().keymap, Keymap::Vim);
+        assert_eq!(Config::parse("sort = \"plain\"", dir).unwrap().sort, Sort::Plain);
+        assert_eq!(Config::parse("scrolloff = 5", dir).unwrap().scrolloff, 5);
+        assert_eq!(Config::parse("", dir).unwrap().ruler, 80);
+        assert_eq!(Config::parse("wrap_indent = \"same\"", dir).unwrap().wrap_indent, WrapIndent::Same);
+        assert_eq!(Config::parse("", dir).unwrap().scroll_lines, 3);
+        assert!(Config::parse("scroll_lines = 0", dir).is_err());
+        assert_eq!(Config::parse("idle_poll_ms = 250", dir).unwrap().idle_poll_ms, 250);
+        assert!(Config::parse("poll_ms = 0", dir).is_err());
+        let undo = Config::parse("undo_steps = 0\nundo_mb = 2", dir).unwrap();
+        assert_eq!((undo.undo_step_limit(), undo.undo_byte_limit()), (usize::MAX, 2 << 20));
+    }
+
+    #[test]
+    fn adds_aliases_of_known_commands() {
+        let dir = Path::new(".");
+        assert_eq!(Config::parse("aliases = { f = \"save\" }", dir).unwrap().alias["f"], "save");
+        let config = Config::parse("alias.s = \"save now\"\nalias.save = \"quit\"\nalias.zz = \"nope\"\n", dir).unwrap();
+        let mut commands = CommandRegistry::new();
+        commands.register(Command { name: "save".to_string(), description: String::new(), key: None, source: CommandSource::Builtin(|_| Ok(())) });
+        let problems = config.add_aliases(&mut commands);
+        assert_eq!(problems.as_deref(), Some("Config aliases: 'save' is already a command; unknown command 'nope' for zz"));
+        assert_eq!(commands.expand_alias("s"), "save now");
+    }
+}
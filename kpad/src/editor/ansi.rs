@@ -0,0 +1,60 @@
+//! `strip_ansi`: remove terminal escape sequences (colors, cursor movement, window titles) from
+//! the selection or the whole buffer, e.g. after pasting terminal output, as one undo step.
+
+use super::Editor; // editor state
+use crate::types::{Pos, SelectionKind}; // positions, stream selection
+use anyhow::Result; // anyhow error handling
+use regex::Regex; // escape sequence matching
+use std::time::Duration; // timing for status messages
+
+/// CSI (`ESC [ ... m`), OSC (`ESC ] ... BEL` or `ESC ] ... ESC \`), character-set
+/// (`ESC ( B`) and other two-byte escape sequences.
+const ESCAPE: &str = r"\x1b\[[0-?]*[ -/]*[@-~]|\x1b\][^\x07\x1b]*(?:\x07|\x1b\\)|\x1b[()][0-9A-Za-z]|\x1b[@-Z\\-_]";
+
+/// `text` without escape sequences, and how many were removed.
+fn strip_escapes(text: &str) -> (String, usize) {
+    let re = Regex::new(ESCAPE).expect("valid escape pattern");
+    let count = re.find_iter(text).count();
+    (re.replace_all(text, "").into_owned(), count)
+}
+
+impl Editor {
+    /// Remove escape sequences from the selection (which then covers the cleaned text) or the
+    /// whole buffer.
+    pub fn strip_ansi(&mut self) -> Result<()> {
+        if !self.ensure_writable() { return Ok(()); }
+        let selection = self.selection_range();
+        let (start, end) = selection.unwrap_or_else(|| {
+            let last = self.doc.buf.line_count() - 1;
+            (Pos { y: 0, x: 0 }, Pos { y: last, x: self.doc.buf.line_len_chars(last) })
+        });
+        let (clean, count) = strip_escapes(&self.doc.buf.get_range(start, end));
+        if count == 0 {
+            self.set_status("No escape sequences found.", Duration::from_secs(2));
+            return Ok(());
+        }
+        self.replace_range(start, end, &clean);
+        if selection.is_some() {
+            self.doc.anchor = Some(start);
+            self.selection_kind = SelectionKind::Stream;
+            self.doc.cursor = self.doc.buf.calc_end_pos(start, &clean);
+        } else {
+            self.doc.cursor = self.doc.buf.clamp_pos(self.doc.cursor);
+        }
+        let plural = if count == 1 { "" } else { "s" };
+        self.set_status(format!("Removed {count} escape sequence{plural}."), Duration::from_secs(2));
+        self.ensure_visible()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_color_and_title_sequences() {
+        let text = "\x1b[1;31merror\x1b[0m: bad\n\x1b]0;title\x07$ ls \x1b(Bok\x1b[K";
+        assert_eq!(strip_escapes(text), ("error: bad\n$ ls ok".to_string(), 5));
+        assert_eq!(strip_escapes("plain [1m"), ("plain [1m".to_string(), 0));
+    }
+}
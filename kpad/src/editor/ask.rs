@@ -0,0 +1,64 @@
+//! Questions from plugins (`api.prompt`, `api.confirm`): the prompt line opens and the plugin
+//! call waits for Enter or Esc, reading keys from the message bus like the main loop does.
+
+use super::Editor; // editor state
+use crate::bus::{Bus, Message}; // keys and held-back messages
+use crate::types::{Prompt, PromptKind}; // the question's prompt
+use anyhow::Result; // anyhow error handling
+use crossterm::event::{Event, KeyCode, KeyEventKind}; // answer keys
+use std::io; // drawing while waiting
+
+impl Editor {
+    /// Ask a plugin's question: `kind` is `PluginInput` (starting as `initial`) or
+    /// `PluginConfirm`, shown after `label`. Returns the text entered, or `None` when it was
+    /// cancelled with Esc or cannot be asked (no terminal, or another prompt is open). Messages
+    /// other than keys that arrive meanwhile are posted again afterwards.
+    pub(crate) fn ask_user(&mut self, kind: PromptKind, label: String, initial: &str) -> Result<Option<String>> {
+        let Some(events) = self.events.clone() else { return Ok(None); };
+        if self.headless || self.prompt.is_some() {
+            return Ok(None);
+        }
+        self.plugin_prompt = Some(label);
+        self.prompt = Some(Prompt::new(kind, initial));
+        self.mark_redraw();
+        let mut held = Vec::new();
+        let answer = self.wait_for_answer(&events, &mut held);
+        self.prompt = None;
+        self.plugin_prompt = None;
+        self.mark_redraw();
+        if let Some(tx) = &self.bus {
+            for msg in held {
+                let _ = tx.send(msg);
+            }
+        }
+        answer
+    }
+
+    /// Draw and handle keys until the open prompt is answered (`Some`) or cancelled.
+    fn wait_for_answer(&mut self, events: &Bus, held: &mut Vec<Message>) -> Result<Option<String>> {
+        let mut stdout = io::stdout();
+        loop {
+            self.render(&mut stdout)?;
+            match events.wait(None)? {
+                Some(Message::Input(Event::Key(key))) if key.kind == KeyEventKind::Press => match key.code {
+                    KeyCode::Enter => return Ok(self.prompt.take().map(|p| p.input)),
+                    KeyCode::Esc => return Ok(None),
+                    _ => {
+                        self.handle_prompt_key(key)?;
+                    }
+                },
+                Some(Message::Input(Event::Paste(text))) => self.handle_paste(&text)?,
+                Some(Message::Input(Event::Resize(..))) => self.on_resize()?,
+                Some(Message::Input(_)) | None => {}
+                Some(msg @ Message::InputError(_)) => {
+                    held.push(msg);
+                    return Ok(None);
+                }
+                Some(msg) => held.push(msg),
+            }
+            if self.prompt.is_none() {
+                return Ok(None);
+            }
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! File attributes: the read-only attribute (`toggle_readonly`, and saving over a read-only
+//! file), and the Mark of the Web (`remove_zone_identifier`).
+//!
+//! On Unix "read-only" means no write permission at all; making a file writable adds write
+//! permission for its owner only.
+//!
+//! Windows marks downloaded files with a `Zone.Identifier` alternate data stream, which makes
+//! scripts ask before running. Outside Windows the same path names the `name:Zone.Identifier`
+//! file that copying from Windows (WSL, network shares) leaves next to the file.
+
+use super::Editor; // editor state
+use crate::types::{Prompt, PromptKind}; // confirmation prompts
+use anyhow::Result; // anyhow error handling
+use std::fs; // file metadata and permissions
+use std::io; // permission errors
+use std::path::{Path, PathBuf}; // file paths
+use std::time::Duration; // timing for status messages
+
+/// Whether the file at `path` has the read-only attribute.
+fn is_readonly(path: &Path) -> bool {
+    fs::metadata(path).is_ok_and(|m| m.permissions().readonly())
+}
+
+/// Set or clear the read-only attribute of `path`.
+fn set_readonly(path: &Path, readonly: bool) -> io::Result<()> {
+    let mut perms = fs::metadata(path)?.permissions();
+    #[cfg(unix)]
+    if !readonly {
+        use std::os::unix::fs::PermissionsExt;
+        perms.set_mode(perms.mode() | 0o200);
+        return fs::set_permissions(path, perms);
+    }
+    perms.set_readonly(readonly);
+    fs::set_permissions(path, perms)
+}
+
+/// The Mark-of-the-Web stream (or sidecar file) of `path`.
+fn zone_identifier_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(":Zone.Identifier");
+    PathBuf::from(name)
+}
+
+/// Status note for a file with the Mark of the Web, naming where it was downloaded from.
+pub(crate) fn zone_note(path: &Path) -> Option<String> {
+    let zone = fs::read_to_string(zone_identifier_path(path)).ok()?;
+    let from = zone.lines()
+        .find_map(|l| l.strip_prefix("HostUrl="))
+        .map(|url| format!(" from {}", url.trim()))
+        .unwrap_or_default();
+    let name = path.file_name().map_or_else(|| path.display().to_string(), |n| n.to_string_lossy().into_owned());
+    Some(format!("{name} was downloaded{from} (Mark of the Web); remove_zone_identifier unblocks it."))
+}
+
+impl Editor {
+    /// The current file when it is a local file on disk.
+    fn local_file(&self) -> Option<PathBuf> {
+        self.doc.file_path.clone().filter(|p| self.doc.remote.is_none() && p.is_file())
+    }
+
+    /// Delete the current file's `Zone.Identifier` (`remove_zone_identifier`).
+    pub fn remove_zone_identifier(&mut self) {
+        let Some(path) = self.local_file() else {
+            self.set_status("No local file on disk.", Duration::from_secs(2));
+            return;
+        };
+        let zone = zone_identifier_path(&path);
+        if !zone.exists() {
+            self.set_status("This file has no Zone.Identifier.", Duration::from_secs(2));
+            return;
+        }
+        match fs::remove_file(&zone) {
+            Ok(()) => self.set_status(format!("Removed the Zone.Identifier of {}.", path.display()), Duration::from_secs(3)),
+            Err(e) => self.set_status(format!("Cannot remove the Zone.Identifier: {e}"), Duration::from_secs(4)),
+        }
+    }
+
+    /// Ask before setting or clearing the current file's read-only attribute (`toggle_readonly`).
+    pub fn prompt_toggle_readonly(&mut self) {
+        let Some(path) = self.local_file() else {
+            self.set_status("No local file on disk.", Duration::from_secs(2));
+            return;
+        };
+        let kind = if is_readonly(&path) { PromptKind::MakeWritable } else { PromptKind::MakeReadOnly };
+        self.prompt = Some(Prompt::new(kind, ""));
+        self.mark_redraw();
+    }
+
+    /// Save; a read-only current file asks whether to clear the attribute first.
+    pub(crate) fn save_checking_readonly(&mut self, path: PathBuf) -> Result<()> {
+        if self.local_file().as_ref() == Some(&path) && is_readonly(&path) {
+            self.prompt = Some(Prompt::new(PromptKind::SaveReadOnly, ""));
+            self.mark_redraw();
+            return Ok(());
+        }
+        self.save_to_path(path)
+    }
+
+    /// Answer to a read-only prompt of `kind`: `y` changes the attribute (and saves for
+    /// `SaveReadOnly`).
+    pub(crate) fn confirm_readonly(&mut self, kind: PromptKind, answer: &str) -> Result<()> {
+        let Some(path) = self.local_file() else { return Ok(()); };
+        if !matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+            self.set_status("Read-only attribute unchanged.", Duration::from_secs(2));
+            return Ok(());
+        }
+        let readonly = kind == PromptKind::MakeReadOnly;
+        if let Err(e) = set_readonly(&path, readonly) {
+            self.set_status(format!("Cannot change {}: {e}", path.display()), Duration::from_secs(4));
+            return Ok(());
+        }
+        match kind {
+            PromptKind::SaveReadOnly => self.save_to_path(path)?,
+            _ if readonly => self.set_status(format!("{} is now read-only.", path.display()), Duration::from_secs(2)),
+            _ => self.set_status(format!("{} is now writable.", path.display()), Duration::from_secs(2)),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sets_and_clears_readonly() {
+        let path = std::env::temp_dir().join(format!("kpad-readonly-{}", std::process::id()));
+        fs::write(&path, "x").unwrap();
+        set_readonly(&path, true).unwrap();
+        assert!(is_readonly(&path));
+        set_readonly(&path, false).unwrap();
+        assert!(!is_readonly(&path));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn notes_the_mark_of_the_web() {
+        let path = std::env::temp_dir().join(format!("kpad-zone-{}.ps1", std::process::id()));
+        fs::write(&path, "x").unwrap();
+        assert_eq!(zone_note(&path), None);
+        let zone = zone_identifier_path(&path);
+        fs::write(&zone, "[ZoneTransfer]\r\nZoneId=3\r\nHostUrl=https://example.com/a.ps1\r\n").unwrap();
+        let note = zone_note(&path).unwrap();
+        assert!(note.contains("downloaded from https://example.com/a.ps1 (Mark"), "{note}");
+        fs::remove_file(&zone).unwrap();
+        fs::remove_file(&path).unwrap();
+    }
+}
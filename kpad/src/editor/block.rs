@@ -0,0 +1,182 @@
+//! Column (block) selection: Alt+Shift+Arrow selects a rectangle of text, the same char columns
+//! on every line between the anchor and the cursor. Typing, Tab, Backspace and Delete then edit
+//! every line of it at once (the block shrinks to an empty column that keeps taking input),
+//! copy and cut take its lines, and a paste fills it row by row. Text copied from a block pastes
+//! back as a column at the cursor. Each of these is one undo step.
+
+use super::Editor; // editor state
+use crate::types::{Pos, SelectionKind}; // positions, block selection
+use anyhow::Result; // anyhow error handling
+use crossterm::event::{KeyCode, KeyEvent}; // terminal events
+use std::time::Duration; // timing for status messages
+
+/// Lines and char columns covered by a block selection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct BlockRange {
+    pub(crate) top: usize,
+    pub(crate) bottom: usize,
+    pub(crate) left: usize,
+    /// Exclusive; equal to `left` for an empty column.
+    pub(crate) right: usize,
+}
+
+/// Byte offset of char column `col` in `line`, clamped to its length.
+fn byte_at(line: &str, col: usize) -> usize {
+    line.char_indices().nth(col).map_or(line.len(), |(i, _)| i)
+}
+
+/// The text in columns `left..right` of each line, one row per line.
+fn block_text(lines: &[String], left: usize, right: usize) -> String {
+    lines.iter().map(|l| &l[byte_at(l, left)..byte_at(l, right)]).collect::<Vec<_>>().join("\n")
+}
+
+/// `lines` with columns `left..right` replaced by `texts`, one per line (repeated when there
+/// are fewer). Lines too short to reach `left` are padded with spaces before text goes there.
+fn replace_cols(lines: &[String], left: usize, right: usize, texts: &[&str]) -> Vec<String> {
+    lines.iter().zip(texts.iter().cycle()).map(|(line, text)| {
+        let (from, to) = (byte_at(line, left), byte_at(line, right));
+        let pad = if text.is_empty() { 0 } else { left.saturating_sub(line.chars().count()) };
+        format!("{}{}{text}{}", &line[..from], " ".repeat(pad), &line[to..])
+    }).collect()
+}
+
+impl Editor {
+    /// The block selection, if one is active.
+    pub(crate) fn block_range(&self) -> Option<BlockRange> {
+        let SelectionKind::Block { col } = self.selection_kind else { return None; };
+        let anchor = self.doc.anchor?;
+        Some(BlockRange {
+            top: anchor.y.min(self.doc.cursor.y),
+            bottom: anchor.y.max(self.doc.cursor.y),
+            left: anchor.x.min(col),
+            right: anchor.x.max(col),
+        })
+    }
+
+    /// Grow or shrink the block selection with Alt+Shift+Arrow, starting one at the cursor.
+    pub(crate) fn extend_block(&mut self, key: KeyEvent) -> Result<()> {
+        let col = match self.selection_kind {
+            SelectionKind::Block { col } if self.doc.anchor.is_some() => col,
+            _ => {
+                self.doc.anchor = Some(self.doc.cursor);
+                self.doc.cursor.x
+            }
+        };
+        let last = self.doc.buf.line_count() - 1;
+        let (y, col) = match key.code {
+            KeyCode::Left => (self.doc.cursor.y, col.saturating_sub(1)),
+            KeyCode::Right => (self.doc.cursor.y, col + 1),
+            KeyCode::Up => (self.doc.cursor.y.saturating_sub(1), col),
+            KeyCode::Down => ((self.doc.cursor.y + 1).min(last), col),
+            _ => (self.doc.cursor.y, col),
+        };
+        self.doc.cursor = Pos { y, x: col.min(self.doc.buf.line_len_chars(y)) };
+        self.selection_kind = SelectionKind::Block { col };
+        self.mark_redraw();
+        self.ensure_visible()
+    }
+
+    /// Lines `top..=bottom` of the buffer.
+    fn block_lines(&self, top: usize, bottom: usize) -> Vec<String> {
+        (top..=bottom).map(|y| self.doc.buf.line(y).to_string()).collect()
+    }
+
+    /// The block's text, one row per line.
+    pub(crate) fn block_selected_text(&self) -> Option<String> {
+        let b = self.block_range()?;
+        Some(block_text(&self.block_lines(b.top, b.bottom), b.left, b.right))
+    }
+
+    /// Replace the block's columns with `texts` (see `replace_cols`) as one undo step. The
+    /// block becomes an empty column after the first text, ready for more typing.
+    fn replace_block(&mut self, b: BlockRange, texts: &[&str]) {
+        let (anchor_y, cursor_y) = (self.doc.anchor.map_or(b.top, |a| a.y), self.doc.cursor.y);
+        let lines = replace_cols(&self.block_lines(b.top, b.bottom), b.left, b.right, texts);
+        self.replace_lines(b.top, b.bottom, &lines.join("\n"));
+        let col = b.left + texts[0].chars().count();
+        self.doc.anchor = Some(self.doc.buf.clamp_pos(Pos { y: anchor_y, x: col }));
+        self.doc.cursor = self.doc.buf.clamp_pos(Pos { y: cursor_y, x: col });
+        self.selection_kind = SelectionKind::Block { col };
+    }
+
+    /// Type `text` on every line of the block, replacing its columns. `false` without a block.
+    pub(crate) fn block_type(&mut self, text: &str) -> bool {
+        let Some(b) = self.block_range() else { return false; };
+        self.replace_block(b, &[text]);
+        true
+    }
+
+    /// Backspace/Delete (`forward`) on the block: clear its columns, or when it is an empty
+    /// column, delete the char before/after it on every line. `false` without a block.
+    pub(crate) fn block_delete(&mut self, forward: bool) -> bool {
+        let Some(mut b) = self.block_range() else { return false; };
+        if b.left == b.right {
+            if forward {
+                b.right += 1;
+            } else if b.left > 0 {
+                b.left -= 1;
+            } else {
+                return true;
+            }
+        }
+        self.replace_block(b, &[""]);
+        true
+    }
+
+    /// Copy the block's text; with `cut`, clear its columns too.
+    pub(crate) fn copy_block(&mut self, cut: bool) -> bool {
+        let Some(text) = self.block_selected_text() else { return false; };
+        if cut && !self.ensure_writable() { return true; }
+        self.remember_clip(&text);
+        match self.clipboard.as_mut().map(|cb| cb.set_text(text.clone())) {
+            Some(Ok(())) => {
+                self.block_copy = Some(text);
+                if cut { self.block_delete(false); }
+                let what = if cut { "Cut" } else { "Copied" };
+                self.set_status(format!("{what} block."), Duration::from_secs(2));
+            }
+            _ => self.set_error("Clipboard unavailable.", Duration::from_secs(2)),
+        }
+        true
+    }
+
+    /// Paste `text` into the block row by row, or, when it was copied from a block, as a column
+    /// at the cursor. `false` when neither applies.
+    pub(crate) fn paste_block(&mut self, text: &str) -> bool {
+        let rows: Vec<&str> = text.split('\n').map(|r| r.strip_suffix('\r').unwrap_or(r)).collect();
+        if let Some(b) = self.block_range() {
+            self.replace_block(b, &rows);
+            return true;
+        }
+        if self.block_copy.as_deref() != Some(text) { return false; }
+        let at = self.doc.cursor;
+        let last = self.doc.buf.line_count() - 1;
+        let bottom = (at.y + rows.len() - 1).min(last);
+        // Rows past the end of the buffer go on new lines.
+        let mut lines = self.block_lines(at.y, bottom);
+        lines.resize(rows.len(), String::new());
+        let lines = replace_cols(&lines, at.x, at.x, &rows);
+        self.clear_selection();
+        self.replace_lines(at.y, bottom, &lines.join("\n"));
+        self.doc.cursor = self.doc.buf.clamp_pos(at);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.split('\n').map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn takes_and_replaces_columns() {
+        let l = lines("name  age\nbob   42\nx\nélise 7");
+        assert_eq!(block_text(&l, 6, 9), "age\n42\n\n7");
+        assert_eq!(replace_cols(&l, 0, 4, &["NAME"]), lines("NAME  age\nNAME  42\nNAME\nNAMEe 7"));
+        assert_eq!(replace_cols(&l, 6, 6, &["a", "b"]), lines("name  aage\nbob   b42\nx     a\nélise b7"));
+        assert_eq!(replace_cols(&l, 3, 6, &[""]), lines("namage\nbob42\nx\néli7"));
+    }
+}
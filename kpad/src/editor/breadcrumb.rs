@@ -0,0 +1,156 @@
+//! Breadcrumb line: the scope around the cursor (Markdown headings, or enclosing indentation
+//! blocks in code), drawn above the text area.
+
+use super::Editor; // editor state
+use anyhow::Result; // anyhow error handling
+use crossterm::{ // terminal manipulation
+    cursor,
+    style::{self, Color},
+    terminal::{self, ClearType},
+    QueueableCommand,
+};
+use std::io::Stdout; // output handling
+
+/// Longest scope entry shown; longer headers are cut with an ellipsis.
+const MAX_CRUMB_CHARS: usize = 40;
+
+/// Separator between breadcrumb entries.
+const SEPARATOR: &str = " › ";
+
+/// Headings enclosing line `y` of a Markdown document (outermost first). Fenced code blocks
+/// are skipped so `# comments` in shell snippets do not count.
+fn markdown_scope(lines: impl Iterator<Item = String>, y: usize) -> Vec<String> {
+    let mut stack: Vec<(usize, String)> = Vec::new();
+    let mut in_fence = false;
+    for line in lines.take(y + 1) {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence { continue; }
+        let level = trimmed.chars().take_while(|&c| c == '#').count();
+        let rest = &trimmed[level..];
+        if (1..=6).contains(&level) && (rest.is_empty() || rest.starts_with(' ')) {
+            stack.retain(|(l, _)| *l < level);
+            stack.push((level, rest.trim().trim_end_matches('#').trim().to_string()));
+        }
+    }
+    stack.into_iter().map(|(_, title)| title).collect()
+}
+
+/// Headers of the indentation blocks enclosing line `y` (outermost first): each less-indented
+/// non-blank line above the cursor opens a block.
+fn code_scope(line_at: impl Fn(usize) -> String, y: usize) -> Vec<String> {
+    let indent = |l: &str| l.chars().take_while(|c| c.is_whitespace()).count();
+    let current = line_at(y);
+    let mut level = if current.trim().is_empty() { usize::MAX } else { indent(&current) };
+    let mut scope = Vec::new();
+    for i in (0..y).rev() {
+        if level == 0 { break; }
+        let line = line_at(i);
+        let trimmed = line.trim();
+        // Braces on their own line belong to the header above them.
+        if trimmed.is_empty() || trimmed.starts_with('{') || trimmed.starts_with('}') { continue; }
+        let ind = indent(&line);
+        if ind < level {
+            scope.push(trimmed.trim_end_matches(['{', ':']).trim_end().to_string());
+            level = ind;
+        }
+    }
+    scope.reverse();
+    scope
+}
+
+fn shorten(s: &str) -> String {
+    if s.chars().count() <= MAX_CRUMB_CHARS {
+        s.to_string()
+    } else {
+        let cut: String = s.chars().take(MAX_CRUMB_CHARS - 1).collect();
+        format!("{cut}…")
+    }
+}
+
+impl Editor {
+    /// Rows above the pinned lines: the tab bar and the breadcrumb line, when shown.
+    pub(crate) fn header_rows(&self) -> usize {
+        usize::from(self.tab_bar_shown()) + usize::from(self.breadcrumb)
+    }
+
+    /// Rows above the scrolled text area (tab bar, breadcrumb line, then pinned lines).
+    pub(crate) fn text_top(&self) -> usize {
+        self.header_rows() + self.pinned_rows()
+    }
+
+    /// Rows available for scrolled text on a terminal `height` rows high (minus status bar,
+    /// prompt, breadcrumb and pinned lines).
+    pub(crate) fn text_rows(&self, height: usize) -> usize {
+        height.saturating_sub(1 + usize::from(self.prompt.is_some()) + self.text_top())
+    }
+
+    /// Toggle the breadcrumb line.
+    pub fn toggle_breadcrumb(&mut self) -> Result<()> {
+        self.breadcrumb = !self.breadcrumb;
+        self.ensure_visible()?;
+        self.mark_redraw();
+        Ok(())
+    }
+
+    /// Scope of the cursor: file name, then enclosing headings or blocks.
+    fn breadcrumb_entries(&self) -> Vec<String> {
+        let name = self.doc.file_path.as_ref()
+            .and_then(|p| p.file_name())
+            .map_or_else(|| "<new file>".to_string(), |n| n.to_string_lossy().into_owned());
+        let ext = self.doc.file_path.as_ref()
+            .and_then(|p| p.extension())
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let y = self.doc.cursor.y;
+        let scope = if matches!(ext.as_str(), "md" | "markdown") {
+            markdown_scope((0..self.doc.buf.line_count()).map(|i| self.doc.buf.line(i).into_owned()), y)
+        } else {
+            code_scope(|i| self.doc.buf.line(i).into_owned(), y)
+        };
+        std::iter::once(name).chain(scope.iter().map(|s| shorten(s))).collect()
+    }
+
+    /// Draw the breadcrumb line at the top of the screen, below the tab bar.
+    pub(crate) fn render_breadcrumb(&self, stdout: &mut Stdout, width: usize) -> Result<()> {
+        let text = format!(" {}", self.breadcrumb_entries().join(SEPARATOR));
+        let mut shown: String = text.chars().take(width).collect();
+        shown.push_str(&" ".repeat(width.saturating_sub(shown.chars().count())));
+        stdout.queue(cursor::MoveTo(0, u16::from(self.tab_bar_shown())))?;
+        stdout.queue(terminal::Clear(ClearType::CurrentLine))?;
+        stdout.queue(style::SetBackgroundColor(self.caps.color(Color::AnsiValue(236))))?;
+        stdout.queue(style::SetForegroundColor(Color::Grey))?;
+        stdout.queue(style::Print(shown))?;
+        stdout.queue(style::ResetColor)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(s: &str) -> Vec<String> {
+        s.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn markdown_heading_stack() {
+        let doc = lines("# Guide\n## Install\ntext\n```sh\n# not a heading\n```\n### Linux\nmore\n## Usage\nx");
+        assert_eq!(markdown_scope(doc.clone().into_iter(), 7), ["Guide", "Install", "Linux"]);
+        assert_eq!(markdown_scope(doc.clone().into_iter(), 4), ["Guide", "Install"]);
+        assert_eq!(markdown_scope(doc.into_iter(), 9), ["Guide", "Usage"]);
+    }
+
+    #[test]
+    fn code_indent_blocks() {
+        let src = lines("impl Editor {\n    fn render(&self)\n    {\n        let x = 1;\n\n        draw(x);\n    }\n}");
+        let at = |i: usize| src[i].clone();
+        assert_eq!(code_scope(at, 5), ["impl Editor", "fn render(&self)"]);
+        assert_eq!(code_scope(at, 1), ["impl Editor"]);
+        assert!(code_scope(at, 0).is_empty());
+    }
+}
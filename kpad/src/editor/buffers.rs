@@ -0,0 +1,353 @@
+//! Multiple buffers: every open file keeps its own text, cursor, scroll, dirty flag and undo
+//! history.
+//!
+//! The active buffer lives in `Editor::doc` so all editing code works on it directly; the others
+//! wait in `Editor::buffers`. Switching swaps the two.
+
+use crate::buffer::Buffer; // document model
+use crate::remote::RemotePath; // remote origin of a buffer
+use crate::types::{Pos, SelectionKind, UndoEntry}; // core types
+use super::goal::WordGoal; // word-count goal
+use super::split::Pane; // split view state
+use super::Editor; // editor state
+use anyhow::Result; // anyhow error handling
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers}; // buffer list keys
+use std::mem; // swapping buffer state
+use std::path::{Path, PathBuf}; // file path handling
+use std::time::{Duration, Instant}; // timing for status messages and close confirmation
+
+/// Everything that belongs to one open buffer (`Editor::doc` for the active one).
+pub(crate) struct BufferState {
+    /// The editable document (lines of text).
+    pub(crate) buf: Buffer,
+    /// Cursor position in the buffer.
+    pub(crate) cursor: Pos,
+    /// Selection anchor.
+    pub(crate) anchor: Option<Pos>,
+    /// Viewport scroll position.
+    pub(crate) scroll_y: usize,
+    pub(crate) scroll_x: usize,
+    /// Path we'll save to.
+    pub(crate) file_path: Option<PathBuf>,
+    /// Remote origin of the file; `file_path` is then its local cache file.
+    pub(crate) remote: Option<RemotePath>,
+    /// URL a fetched buffer came from (such buffers have no `file_path`).
+    pub(crate) url: Option<String>,
+    /// Read-only buffers reject edits until saved under a new name.
+    pub(crate) read_only: bool,
+    /// "Dirty" means there are unsaved changes.
+    pub(crate) dirty: bool,
+    /// Number of leading lines kept visible above the scrolled text (`pin_lines`).
+    pub(crate) pinned_lines: usize,
+    /// Word-count goal (`goal`).
+    pub(crate) word_goal: Option<WordGoal>,
+    /// Undo and redo stacks.
+    pub(crate) undo: Vec<UndoEntry>,
+    pub(crate) redo: Vec<UndoEntry>,
+    /// The unfocused pane when the view is split (`split` command).
+    pub(crate) split: Option<Pane>,
+    /// Whether the focused pane of a split view is the right one.
+    pub(crate) split_focus_right: bool,
+}
+
+/// State of the buffer list overlay.
+pub(crate) struct BufferList {
+    /// Typed filter; only buffers whose name or path contains it are listed.
+    pub(crate) filter: String,
+    /// Selected row (index into the filtered list).
+    pub(crate) selected: usize,
+    /// Buffer with unsaved changes that the next close key will discard.
+    pub(crate) confirm_close: Option<usize>,
+}
+
+impl BufferState {
+    /// An empty, unnamed buffer.
+    pub(crate) fn empty() -> Self {
+        Self {
+            buf: Buffer::new(),
+            cursor: Pos { y: 0, x: 0 },
+            anchor: None,
+            scroll_y: 0,
+            scroll_x: 0,
+            file_path: None,
+            remote: None,
+            url: None,
+            read_only: false,
+            dirty: false,
+            pinned_lines: 0,
+            word_goal: None,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            split: None,
+            split_focus_right: false,
+        }
+    }
+
+    /// Short name for lists and the status bar.
+    pub(crate) fn name(&self) -> String {
+        display_name(self.file_path.as_deref(), self.remote.as_ref(), self.url.as_deref())
+    }
+}
+
+/// File name of a buffer, its remote URL, or `<new file>`.
+fn display_name(file_path: Option<&Path>, remote: Option<&RemotePath>, url: Option<&str>) -> String {
+    match (remote, file_path, url) {
+        (Some(r), _, _) => r.url(),
+        (None, Some(p), _) => p.file_name().map_or_else(|| p.display().to_string(), |n| n.to_string_lossy().into_owned()),
+        (None, None, Some(u)) => u.to_string(),
+        (None, None, None) => "<new file>".to_string(),
+    }
+}
+
+/// Whether `a` and `b` name the same file (falls back to comparing the paths as given).
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+impl Editor {
+    /// Number of open buffers.
+    pub(crate) fn buffer_count(&self) -> usize {
+        self.buffers.len()
+    }
+
+    /// State of buffer `i` (the active one lives in `doc`).
+    fn buffer_state(&self, i: usize) -> &BufferState {
+        if i == self.active { &self.doc } else { &self.buffers[i] }
+    }
+
+    /// Name of buffer `i`.
+    pub(crate) fn buffer_name(&self, i: usize) -> String {
+        self.buffer_state(i).name()
+    }
+
+    /// Whether buffer `i` has unsaved changes.
+    pub(crate) fn buffer_dirty(&self, i: usize) -> bool {
+        self.buffer_state(i).dirty
+    }
+
+    /// Number of buffers with unsaved changes.
+    pub(crate) fn dirty_buffer_count(&self) -> usize {
+        (0..self.buffers.len()).filter(|&i| self.buffer_dirty(i)).count()
+    }
+
+    /// Exchange the active buffer's state with `st`.
+    fn swap_active(&mut self, st: &mut BufferState) {
+        mem::swap(&mut self.doc, st);
+        self.selection_kind = SelectionKind::Stream;
+    }
+
+    /// Make buffer `i` the active one.
+    pub fn switch_buffer(&mut self, i: usize) -> Result<()> {
+        if i == self.active || i >= self.buffers.len() { return Ok(()); }
+        let mut st = mem::replace(&mut self.buffers[i], BufferState::empty());
+        self.swap_active(&mut st);
+        self.buffers[self.active] = st;
+        self.alternate = Some(self.active);
+        self.active = i;
+
+        let name = self.buffer_name(i);
+        self.detect_file_type();
+        self.large_file = self.is_large_file();
+        self.scroll_target = None;
+        self.last_close_hint = None;
+        self.typing_at = None;
+        self.set_status(format!("Buffer {}/{}: {name}", i + 1, self.buffers.len()), Duration::from_secs(2));
+        self.mark_redraw();
+        self.ensure_visible()
+    }
+
+    /// Switch to the next buffer (wrapping around).
+    pub fn next_buffer(&mut self) -> Result<()> {
+        if self.buffers.len() < 2 {
+            self.set_status("Only one buffer open.", Duration::from_secs(2));
+            return Ok(());
+        }
+        self.switch_buffer((self.active + 1) % self.buffers.len())
+    }
+
+    /// Switch to the previous buffer (wrapping around).
+    pub fn prev_buffer(&mut self) -> Result<()> {
+        if self.buffers.len() < 2 {
+            self.set_status("Only one buffer open.", Duration::from_secs(2));
+            return Ok(());
+        }
+        self.switch_buffer((self.active + self.buffers.len() - 1) % self.buffers.len())
+    }
+
+    /// Switch back to the previously active buffer.
+    pub fn alternate_buffer(&mut self) -> Result<()> {
+        match self.alternate.filter(|&i| i < self.buffers.len() && i != self.active) {
+            Some(i) => self.switch_buffer(i),
+            None => {
+                self.set_status("No alternate buffer.", Duration::from_secs(2));
+                Ok(())
+            }
+        }
+    }
+
+    /// Index of the buffer editing `path`, if it is open.
+    pub(crate) fn find_buffer(&self, path: &Path) -> Option<usize> {
+        (0..self.buffers.len()).find(|&i| {
+            self.buffer_state(i).file_path.as_deref().is_some_and(|p| same_file(p, path))
+        })
+    }
+
+    /// Add an empty `<new file>` buffer and switch to it.
+    pub fn new_buffer(&mut self) -> Result<()> {
+        self.buffers.push(BufferState::empty());
+        self.switch_buffer(self.buffers.len() - 1)
+    }
+
+    /// Get an empty buffer ready for a file being opened: the active one if it is an untouched
+    /// `<new file>`, otherwise a new buffer after the last one.
+    pub(crate) fn prepare_buffer_for_open(&mut self) -> Result<()> {
+        let untouched = self.doc.file_path.is_none() && self.doc.url.is_none() && !self.doc.dirty
+            && self.doc.buf.line_count() == 1 && self.doc.buf.line_len_chars(0) == 0;
+        if untouched { Ok(()) } else { self.new_buffer() }
+    }
+
+    /// Close the active buffer. With unsaved changes the command must be repeated within two
+    /// seconds. Closing the last buffer leaves an empty one.
+    pub fn close_buffer(&mut self) -> Result<()> {
+        if self.doc.dirty {
+            let now = Instant::now();
+            if self.last_close_hint.is_none_or(|t| now.duration_since(t) > Duration::from_secs(2)) {
+                self.last_close_hint = Some(now);
+                self.set_status("Unsaved changes! Close again to discard them.", Duration::from_secs(2));
+                return Ok(());
+            }
+        }
+        self.last_close_hint = None;
+        self.remove_buffer(self.active)?;
+        self.set_status(format!("Closed buffer ({} open).", self.buffers.len()), Duration::from_secs(2));
+        Ok(())
+    }
+
+    /// Drop buffer `i` without asking about unsaved changes.
+    fn remove_buffer(&mut self, i: usize) -> Result<()> {
+        let path = self.buffer_state(i).file_path.clone();
+        self.drop_buffer(i)?;
+        // The language server keeps the file open while another buffer still shows it.
+        if let Some(path) = path.filter(|p| (0..self.buffers.len()).all(|j| self.buffer_state(j).file_path.as_ref() != Some(p))) {
+            self.lsp_close(&path);
+        }
+        Ok(())
+    }
+
+    /// Take buffer `i` out of the buffer list, switching away from it if it is active.
+    fn drop_buffer(&mut self, i: usize) -> Result<()> {
+        if i != self.active {
+            self.buffers.remove(i);
+            if self.active > i { self.active -= 1; }
+            self.alternate = self.alternate.filter(|&a| a != i).map(|a| if a > i { a - 1 } else { a });
+            return Ok(());
+        }
+        let closed = self.active;
+        if self.buffers.len() == 1 {
+            let mut st = BufferState::empty();
+            self.swap_active(&mut st);
+            self.large_file = false;
+            self.highlighter.set_file_extension("");
+            self.highlighter.invalidate_all();
+            self.mark_redraw();
+        } else {
+            // Go to the alternate buffer if there is one, like after a `bdelete` in Vim.
+            let next = self.alternate.filter(|&i| i != closed && i < self.buffers.len())
+                .unwrap_or(if closed == 0 { 1 } else { closed - 1 });
+            let before = self.alternate.filter(|&i| i != closed);
+            self.switch_buffer(next)?;
+            self.buffers.remove(closed);
+            if self.active > closed { self.active -= 1; }
+            // The closed buffer cannot be the alternate; keep the one from before the close.
+            self.alternate = before.filter(|&i| i != next).map(|i| if i > closed { i - 1 } else { i });
+        }
+        Ok(())
+    }
+
+    /// Buffers whose name or path contains `query` (case-insensitive), in order.
+    pub(crate) fn buffer_matches(&self, query: &str) -> Vec<usize> {
+        let query = query.trim().to_lowercase();
+        (0..self.buffers.len())
+            .filter(|&i| {
+                let path = self.buffer_path(i).unwrap_or_default();
+                self.buffer_name(i).to_lowercase().contains(&query) || path.to_lowercase().contains(&query)
+            })
+            .collect()
+    }
+
+    /// Full path or URL of buffer `i`, if it has one.
+    pub(crate) fn buffer_path(&self, i: usize) -> Option<String> {
+        let st = self.buffer_state(i);
+        match (st.remote.as_ref(), st.file_path.as_ref(), st.url.as_ref()) {
+            (Some(r), _, _) => Some(r.url()),
+            (None, Some(p), _) => Some(p.display().to_string()),
+            (None, None, u) => u.cloned(),
+        }
+    }
+
+    /// Number of lines in buffer `i`.
+    pub(crate) fn buffer_lines(&self, i: usize) -> usize {
+        self.buffer_state(i).buf.line_count()
+    }
+
+    /// Open the buffer list overlay with the active buffer selected.
+    pub fn open_buffer_list(&mut self) {
+        self.buffer_list = Some(BufferList { filter: String::new(), selected: self.active, confirm_close: None });
+        self.mark_redraw();
+    }
+
+    /// Keys in the buffer list: Up/Down select, Enter switches, `d` (with an empty filter),
+    /// Delete or Ctrl+D close the selected buffer, other characters filter, Esc leaves.
+    pub(crate) fn handle_buffer_list_key(&mut self, key: KeyEvent) -> Result<()> {
+        let Some(mut list) = self.buffer_list.take() else { return Ok(()); };
+        let confirm = list.confirm_close.take();
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let hits = self.buffer_matches(&list.filter);
+        let picked = hits.get(list.selected).copied();
+        let close = key.code == KeyCode::Delete
+            || (key.code == KeyCode::Char('d') && (ctrl || list.filter.is_empty()));
+        match key.code {
+            KeyCode::Esc => {
+                self.mark_redraw();
+                return Ok(());
+            }
+            KeyCode::Enter => {
+                self.mark_redraw();
+                return picked.map_or(Ok(()), |i| self.switch_buffer(i));
+            }
+            _ if close => match picked {
+                // Unsaved changes: the list asks, and a second `d` on the same buffer closes it.
+                Some(i) if self.buffer_dirty(i) && confirm != Some(i) => list.confirm_close = Some(i),
+                Some(i) => {
+                    self.remove_buffer(i)?;
+                    let left = self.buffer_matches(&list.filter).len();
+                    list.selected = list.selected.min(left.saturating_sub(1));
+                    self.set_status(format!("Closed buffer ({} open).", self.buffers.len()), Duration::from_secs(2));
+                }
+                None => {}
+            },
+            KeyCode::Up => list.selected = list.selected.saturating_sub(1),
+            KeyCode::Down => list.selected = (list.selected + 1).min(hits.len().saturating_sub(1)),
+            KeyCode::Backspace => {
+                list.filter.pop();
+                list.selected = 0;
+            }
+            KeyCode::Char(c) if !ctrl => {
+                list.filter.push(c);
+                list.selected = 0;
+            }
+            _ => {}
+        }
+        self.reopen_buffer_list(list);
+        Ok(())
+    }
+
+    fn reopen_buffer_list(&mut self, list: BufferList) {
+        self.buffer_list = Some(list);
+        self.mark_redraw();
+    }
+
+}
@@ -1,10 +1,53 @@
 //! Built-in command registration.
 
 use crate::commands::{Command, CommandRegistry, CommandSource}; // command system
+use crate::config::Theme; // theme names
 use crate::types::{Prompt, PromptKind}; // prompt types
+use super::Editor; // editor state
+use super::sort::LineOrder; // sort_lines and friends
+use crate::utils::parse_location; // goto_line LINE:COL
+use anyhow::{anyhow, bail}; // argument errors
+use std::path::PathBuf; // open FILE
+
+/// Settings `set` changes, with the values Tab offers for each (none for numbers).
+pub(crate) const SETTINGS: [(&str, &[&str]); 9] = [
+    ("wrap", &["on", "off"]),
+    ("wrap_indent", &["none", "same", "indent"]),
+    ("tab_width", &[]),
+    ("ruler", &[]),
+    ("scrolloff", &[]),
+    ("auto_close", &["on", "off"]),
+    ("bell", &["off", "visual", "audible", "both"]),
+    ("middle_click", &["off", "selection", "clipboard"]),
+    ("keymap", &["standard", "vim"]),
+];
+
+/// Tab completion of command arguments in the command prompt.
+fn register_completers(reg: &mut CommandRegistry) {
+    reg.set_completer("theme", |_, args| {
+        if args.is_empty() { Theme::NAMES.map(String::from).to_vec() } else { Vec::new() }
+    });
+    reg.set_completer("set", |_, args| match args {
+        [] => SETTINGS.iter().map(|(name, _)| (*name).to_string()).collect(),
+        [name] => SETTINGS.iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, values)| values.iter().map(|v| (*v).to_string()).collect())
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    });
+}
+
+/// Built-in short forms of commands; `[alias]` in kpad.toml adds more.
+fn register_aliases(reg: &mut CommandRegistry) {
+    for (alias, command) in [("w", "save"), ("q", "quit"), ("wq", "save_and_quit"), ("x", "save_and_quit"), ("e", "open"), ("goto", "goto_line")] {
+        reg.set_alias(alias, command);
+    }
+}
 
 /// Register all built-in editor commands.
 pub fn register_builtin_commands(reg: &mut CommandRegistry) {
+    register_completers(reg);
+    register_aliases(reg);
     reg.register(Command {
         name: "save".to_string(),
         description: "Save file (Ctrl+S)".to_string(),
@@ -14,11 +57,15 @@ pub fn register_builtin_commands(reg: &mut CommandRegistry) {
 
     reg.register(Command {
         name: "open".to_string(),
-        description: "Open file (Ctrl+O)".to_string(),
+        description: "Open file (Ctrl+O); open FILE opens it directly".to_string(),
         key: Some("Ctrl+O".to_string()),
-        source: CommandSource::Builtin(|ed| {
-            ed.prompt = Some(Prompt::new(PromptKind::Open, ""));
-            Ok(())
+        source: CommandSource::BuiltinArgs(|ed, args| match args {
+            [] => {
+                ed.prompt = Some(Prompt::new(PromptKind::Open, ""));
+                Ok(())
+            }
+            [path] => ed.open_path(PathBuf::from(path)),
+            _ => bail!("Usage: open [FILE]"),
         }),
     });
 
@@ -27,7 +74,7 @@ pub fn register_builtin_commands(reg: &mut CommandRegistry) {
         description: "Find (Ctrl+F)".to_string(),
         key: Some("Ctrl+F".to_string()),
         source: CommandSource::Builtin(|ed| {
-            ed.prompt = Some(Prompt::new(PromptKind::Find, ed.last_find.clone().unwrap_or_default()));
+            ed.open_find();
             Ok(())
         }),
     });
@@ -77,15 +124,36 @@ pub fn register_builtin_commands(reg: &mut CommandRegistry) {
 
     reg.register(Command {
         name: "goto_line".to_string(),
-        description: "Go to line (Ctrl+G)".to_string(),
+        description: "Go to line (Ctrl+G); goto_line LINE[:COL] goes directly".to_string(),
         key: Some("Ctrl+G".to_string()),
-        source: CommandSource::Builtin(|ed| {
-            ed.prompt = Some(Prompt::new(PromptKind::GotoLine, ""));
-            ed.mark_redraw();
-            Ok(())
+        source: CommandSource::BuiltinArgs(|ed, args| match args {
+            [] => {
+                ed.prompt = Some(Prompt::new(PromptKind::GotoLine, ""));
+                ed.mark_redraw();
+                Ok(())
+            }
+            [location] => {
+                let (n, col) = parse_location(location).ok_or_else(|| anyhow!("Not a line number: '{location}'"))?;
+                ed.goto_line(n, col)
+            }
+            _ => bail!("Usage: goto_line [LINE[:COL]]"),
         }),
     });
 
+    reg.register(Command {
+        name: "jump_back".to_string(),
+        description: "Go back to where the cursor was before the last jump (Alt+Left)".to_string(),
+        key: Some("Alt+Left".to_string()),
+        source: CommandSource::Builtin(Editor::jump_back),
+    });
+
+    reg.register(Command {
+        name: "jump_forward".to_string(),
+        description: "Go forward again after jump_back (Alt+Right)".to_string(),
+        key: Some("Alt+Right".to_string()),
+        source: CommandSource::Builtin(Editor::jump_forward),
+    });
+
     reg.register(Command {
         name: "undo".to_string(),
         description: "Undo (Ctrl+Z)".to_string(),
@@ -128,6 +196,53 @@ pub fn register_builtin_commands(reg: &mut CommandRegistry) {
         source: CommandSource::Builtin(|ed| ed.paste()),
     });
 
+    reg.register(Command {
+        name: "paste_history".to_string(),
+        description: "Paste from the clipboard history (Ctrl+Shift+V)".to_string(),
+        key: Some("Ctrl+Shift+V".to_string()),
+        source: CommandSource::Builtin(|ed| { ed.open_paste_history(); Ok(()) }),
+    });
+
+    reg.register(Command {
+        name: "copy_path".to_string(),
+        description: "Copy the file's absolute path".to_string(),
+        key: None,
+        source: CommandSource::Builtin(|ed| {
+            ed.copy_path(false);
+            Ok(())
+        }),
+    });
+
+    reg.register(Command {
+        name: "copy_reference".to_string(),
+        description: "Copy path:line:col of the cursor".to_string(),
+        key: None,
+        source: CommandSource::Builtin(|ed| {
+            ed.copy_path(true);
+            Ok(())
+        }),
+    });
+
+    reg.register(Command {
+        name: "copy_highlighted".to_string(),
+        description: "Copy the selection or buffer with ANSI colors".to_string(),
+        key: None,
+        source: CommandSource::Builtin(|ed| {
+            ed.copy_highlighted(false);
+            Ok(())
+        }),
+    });
+
+    reg.register(Command {
+        name: "copy_highlighted_html".to_string(),
+        description: "Copy the selection or buffer as colored HTML".to_string(),
+        key: None,
+        source: CommandSource::Builtin(|ed| {
+            ed.copy_highlighted(true);
+            Ok(())
+        }),
+    });
+
     reg.register(Command {
         name: "select_all".to_string(),
         description: "Select entire buffer (Ctrl+A)".to_string(),
@@ -139,6 +254,20 @@ pub fn register_builtin_commands(reg: &mut CommandRegistry) {
         }),
     });
 
+    reg.register(Command {
+        name: "expand_selection".to_string(),
+        description: "Grow the selection: word, string or bracket contents, line, paragraph, buffer (Alt+=)".to_string(),
+        key: Some("Alt+=".to_string()),
+        source: CommandSource::Builtin(Editor::expand_selection),
+    });
+
+    reg.register(Command {
+        name: "shrink_selection".to_string(),
+        description: "Undo the last expand_selection (Alt+-)".to_string(),
+        key: Some("Alt+-".to_string()),
+        source: CommandSource::Builtin(Editor::shrink_selection),
+    });
+
     reg.register(Command {
         name: "wrap".to_string(),
         description: "Toggle word wrapping".to_string(),
@@ -148,4 +277,487 @@ pub fn register_builtin_commands(reg: &mut CommandRegistry) {
             Ok(())
         }),
     });
+
+    reg.register(Command {
+        name: "set".to_string(),
+        description: "Change a setting for this session: set NAME VALUE (set wrap on)".to_string(),
+        key: None,
+        source: CommandSource::BuiltinArgs(|ed, args| match args {
+            [name, value] => ed.set_option(name, value),
+            _ => bail!("Usage: set NAME VALUE"),
+        }),
+    });
+
+    reg.register(Command {
+        name: "theme".to_string(),
+        description: "Switch the color theme for this session: theme NAME".to_string(),
+        key: None,
+        source: CommandSource::BuiltinArgs(|ed, args| match args {
+            [name] => ed.set_theme(name),
+            _ => bail!("Usage: theme NAME ({})", Theme::NAMES.join(", ")),
+        }),
+    });
+
+    reg.register(Command {
+        name: "bidi_cursor".to_string(),
+        description: "Toggle visual/logical cursor movement on RTL lines".to_string(),
+        key: None,
+        source: CommandSource::Builtin(|ed| {
+            ed.toggle_bidi_visual_cursor();
+            Ok(())
+        }),
+    });
+
+    reg.register(Command {
+        name: "ambiguous_width".to_string(),
+        description: "Toggle ambiguous-width chars as wide (CJK terminals)".to_string(),
+        key: None,
+        source: CommandSource::Builtin(Editor::toggle_ambiguous_wide),
+    });
+
+    reg.register(Command {
+        name: "scrolloff".to_string(),
+        description: "Cycle scroll margin (0/3/5/10 lines of context)".to_string(),
+        key: None,
+        source: CommandSource::Builtin(Editor::cycle_scrolloff),
+    });
+
+    reg.register(Command {
+        name: "smooth_scroll".to_string(),
+        description: "Toggle animated scrolling for large jumps".to_string(),
+        key: None,
+        source: CommandSource::Builtin(|ed| {
+            ed.toggle_smooth_scroll();
+            Ok(())
+        }),
+    });
+
+    reg.register(Command {
+        name: "low_power".to_string(),
+        description: "Toggle low-power mode (no idle wake-ups)".to_string(),
+        key: None,
+        source: CommandSource::Builtin(|ed| {
+            ed.toggle_low_power();
+            Ok(())
+        }),
+    });
+
+    reg.register(Command {
+        name: "plugins".to_string(),
+        description: "Show loaded plugins, versions and load errors".to_string(),
+        key: None,
+        source: CommandSource::Builtin(|ed| {
+            ed.show_plugins = true;
+            ed.mark_redraw();
+            Ok(())
+        }),
+    });
+
+    reg.register(Command {
+        name: "plugins_reload".to_string(),
+        description: "Reload plugins from disk (also automatic on change)".to_string(),
+        key: None,
+        source: CommandSource::Builtin(Editor::reload_plugins),
+    });
+
+    reg.register(Command {
+        name: "toggle_comment".to_string(),
+        description: "Comment/uncomment selected lines (plugin file types)".to_string(),
+        key: None,
+        source: CommandSource::Builtin(Editor::toggle_comment),
+    });
+
+    reg.register(Command {
+        name: "build".to_string(),
+        description: "Save and run the file type's build command".to_string(),
+        key: None,
+        source: CommandSource::Builtin(Editor::run_build),
+    });
+
+    reg.register(Command {
+        name: "color_picker".to_string(),
+        description: "Adjust the #RRGGBB color under the cursor".to_string(),
+        key: None,
+        source: CommandSource::Builtin(|ed| {
+            ed.open_color_picker();
+            Ok(())
+        }),
+    });
+
+    reg.register(Command {
+        name: "toggle_tab_bar".to_string(),
+        description: "Show/hide the tab bar of open buffers".to_string(),
+        key: None,
+        source: CommandSource::Builtin(Editor::toggle_tab_bar),
+    });
+
+    reg.register(Command {
+        name: "toggle_breadcrumb".to_string(),
+        description: "Show/hide the scope breadcrumb above the text".to_string(),
+        key: None,
+        source: CommandSource::Builtin(Editor::toggle_breadcrumb),
+    });
+
+    reg.register(Command {
+        name: "toggle_modal".to_string(),
+        description: "Turn vim-style modal editing (Normal/Insert/Visual modes) on or off".to_string(),
+        key: None,
+        source: CommandSource::Builtin(|ed| { ed.toggle_modal(); Ok(()) }),
+    });
+
+    reg.register(Command {
+        name: "record_macro".to_string(),
+        description: "Start recording keys as a macro, or stop recording".to_string(),
+        key: Some("F7".to_string()),
+        source: CommandSource::Builtin(|ed| { ed.toggle_macro_recording(); Ok(()) }),
+    });
+
+    reg.register(Command {
+        name: "play_macro".to_string(),
+        description: "Replay the last recorded macro".to_string(),
+        key: Some("F8".to_string()),
+        source: CommandSource::Builtin(Editor::play_macro),
+    });
+
+    reg.register(Command {
+        name: "save_macro".to_string(),
+        description: "Save the last recorded macro as a named command, optionally bound to a key".to_string(),
+        key: None,
+        source: CommandSource::Builtin(|ed| { ed.prompt_save_macro(); Ok(()) }),
+    });
+
+    reg.register(Command {
+        name: "goal".to_string(),
+        description: "Set a word-count goal for this file (progress in the status bar)".to_string(),
+        key: None,
+        source: CommandSource::Builtin(|ed| {
+            ed.prompt_word_goal();
+            Ok(())
+        }),
+    });
+
+    reg.register(Command {
+        name: "pomodoro".to_string(),
+        description: "Start a timed focus session with a countdown in the status bar".to_string(),
+        key: None,
+        source: CommandSource::Builtin(|ed| {
+            ed.prompt_pomodoro();
+            Ok(())
+        }),
+    });
+
+    reg.register(Command {
+        name: "pin_lines".to_string(),
+        description: "Keep the first N lines visible at the top while scrolling".to_string(),
+        key: None,
+        source: CommandSource::Builtin(|ed| {
+            ed.prompt_pin_lines();
+            Ok(())
+        }),
+    });
+
+    reg.register(Command {
+        name: "buffers".to_string(),
+        description: "List open buffers: switch, close, filter".to_string(),
+        key: None,
+        source: CommandSource::Builtin(|ed| {
+            ed.open_buffer_list();
+            Ok(())
+        }),
+    });
+
+    reg.register(Command {
+        name: "buffer_next".to_string(),
+        description: "Switch to the next buffer".to_string(),
+        key: Some("Ctrl+Tab".to_string()),
+        source: CommandSource::Builtin(Editor::next_buffer),
+    });
+
+    reg.register(Command {
+        name: "buffer_prev".to_string(),
+        description: "Switch to the previous buffer".to_string(),
+        key: Some("Ctrl+Shift+BackTab".to_string()),
+        source: CommandSource::Builtin(Editor::prev_buffer),
+    });
+
+    reg.register(Command {
+        name: "buffer_new".to_string(),
+        description: "Open an empty buffer".to_string(),
+        key: None,
+        source: CommandSource::Builtin(Editor::new_buffer),
+    });
+
+    reg.register(Command {
+        name: "buffer_close".to_string(),
+        description: "Close the current buffer".to_string(),
+        key: None,
+        source: CommandSource::Builtin(Editor::close_buffer),
+    });
+
+    reg.register(Command {
+        name: "split".to_string(),
+        description: "Split the view side by side on the same buffer (again to unsplit)".to_string(),
+        key: Some("Alt+V".to_string()),
+        source: CommandSource::Builtin(Editor::toggle_split),
+    });
+
+    reg.register(Command {
+        name: "split_focus".to_string(),
+        description: "Move focus to the other pane of a split view".to_string(),
+        key: Some("Alt+O".to_string()),
+        source: CommandSource::Builtin(|ed| {
+            ed.focus_other_pane();
+            Ok(())
+        }),
+    });
+
+    reg.register(Command {
+        name: "scrollbind".to_string(),
+        description: "Toggle scrolling both panes of a split view together".to_string(),
+        key: None,
+        source: CommandSource::Builtin(|ed| {
+            ed.toggle_scrollbind();
+            Ok(())
+        }),
+    });
+
+    reg.register(Command {
+        name: "alternate_buffer".to_string(),
+        description: "Switch to the previously active buffer (Ctrl+^)".to_string(),
+        key: Some("Ctrl+6".to_string()),
+        source: CommandSource::Builtin(Editor::alternate_buffer),
+    });
+
+    reg.register(Command {
+        name: "find_next".to_string(),
+        description: "Find the next match of the last search (F3)".to_string(),
+        key: Some("F3".to_string()),
+        source: CommandSource::Builtin(|ed| ed.repeat_find(true)),
+    });
+
+    reg.register(Command {
+        name: "find_prev".to_string(),
+        description: "Find the previous match of the last search (Shift+F3)".to_string(),
+        key: Some("Shift+F3".to_string()),
+        source: CommandSource::Builtin(|ed| ed.repeat_find(false)),
+    });
+
+    reg.register(Command {
+        name: "rename_word".to_string(),
+        description: "Rename the identifier under the cursor (whole words, buffer or selection)".to_string(),
+        key: None,
+        source: CommandSource::Builtin(|ed| {
+            ed.prompt_rename_word();
+            Ok(())
+        }),
+    });
+
+    reg.register(Command {
+        name: "find_duplicates".to_string(),
+        description: "Mark lines that occur more than once".to_string(),
+        key: None,
+        source: CommandSource::Builtin(|ed| {
+            ed.find_duplicates();
+            Ok(())
+        }),
+    });
+
+    reg.register(Command {
+        name: "next_duplicate".to_string(),
+        description: "Go to the next repeated line".to_string(),
+        key: None,
+        source: CommandSource::Builtin(|ed| ed.jump_duplicate(true)),
+    });
+
+    reg.register(Command {
+        name: "prev_duplicate".to_string(),
+        description: "Go to the previous repeated line".to_string(),
+        key: None,
+        source: CommandSource::Builtin(|ed| ed.jump_duplicate(false)),
+    });
+
+    reg.register(Command {
+        name: "delete_duplicates".to_string(),
+        description: "Delete repeated lines, keeping the first of each".to_string(),
+        key: None,
+        source: CommandSource::Builtin(Editor::delete_duplicates),
+    });
+
+    reg.register(Command {
+        name: "sort_lines".to_string(),
+        description: "Sort the selected lines (or all lines)".to_string(),
+        key: None,
+        source: CommandSource::Builtin(|ed| ed.reorder_lines(LineOrder::Ascending)),
+    });
+
+    reg.register(Command {
+        name: "sort_lines_desc".to_string(),
+        description: "Sort the selected lines (or all lines), largest first".to_string(),
+        key: None,
+        source: CommandSource::Builtin(|ed| ed.reorder_lines(LineOrder::Descending)),
+    });
+
+    reg.register(Command {
+        name: "unique_lines".to_string(),
+        description: "Remove lines seen before in the selection (or buffer)".to_string(),
+        key: None,
+        source: CommandSource::Builtin(|ed| ed.reorder_lines(LineOrder::Unique)),
+    });
+
+    reg.register(Command {
+        name: "reverse_lines".to_string(),
+        description: "Reverse the order of the selected lines (or all lines)".to_string(),
+        key: None,
+        source: CommandSource::Builtin(|ed| ed.reorder_lines(LineOrder::Reverse)),
+    });
+
+    reg.register(Command {
+        name: "strip_ansi".to_string(),
+        description: "Remove ANSI escape sequences from the selection or buffer".to_string(),
+        key: None,
+        source: CommandSource::Builtin(Editor::strip_ansi),
+    });
+
+    reg.register(Command {
+        name: "complete_word".to_string(),
+        description: "Complete the word before the cursor from open buffers and the file type's dictionary".to_string(),
+        key: Some("Ctrl+N".to_string()),
+        source: CommandSource::Builtin(Editor::complete_word),
+    });
+
+    reg.register(Command {
+        name: "keys".to_string(),
+        description: "List the effective key bindings (defaults and kpad.toml)".to_string(),
+        key: None,
+        source: CommandSource::Builtin(|ed| {
+            ed.show_keys = true;
+            ed.mark_redraw();
+            Ok(())
+        }),
+    });
+
+    reg.register(Command {
+        name: "delete_file".to_string(),
+        description: "Move the current file to the trash (the buffer stays open)".to_string(),
+        key: None,
+        source: CommandSource::Builtin(|ed| {
+            ed.prompt_delete_file();
+            Ok(())
+        }),
+    });
+
+    reg.register(Command {
+        name: "grep".to_string(),
+        description: "Find a regex in all files under the working directory".to_string(),
+        key: None,
+        source: CommandSource::Builtin(|ed| {
+            ed.prompt_grep();
+            Ok(())
+        }),
+    });
+
+    reg.register(Command {
+        name: "diagnostics".to_string(),
+        description: "List the language server's diagnostics for this file".to_string(),
+        key: None,
+        source: CommandSource::Builtin(|ed| {
+            ed.show_diagnostics();
+            Ok(())
+        }),
+    });
+
+    reg.register(Command {
+        name: "diff".to_string(),
+        description: "Diff the saved file against the buffer (unsaved changes)".to_string(),
+        key: None,
+        source: CommandSource::Builtin(|ed| {
+            ed.diff_saved();
+            Ok(())
+        }),
+    });
+
+    reg.register(Command {
+        name: "diff_clipboard".to_string(),
+        description: "Diff the selection against the clipboard".to_string(),
+        key: None,
+        source: CommandSource::Builtin(|ed| {
+            ed.diff_clipboard();
+            Ok(())
+        }),
+    });
+
+    reg.register(Command {
+        name: "file_info".to_string(),
+        description: "Show file path, size, modified time, permissions, checksum".to_string(),
+        key: None,
+        source: CommandSource::Builtin(|ed| {
+            ed.show_file_info();
+            Ok(())
+        }),
+    });
+
+    reg.register(Command {
+        name: "toggle_readonly".to_string(),
+        description: "Set or clear the file's read-only attribute".to_string(),
+        key: None,
+        source: CommandSource::Builtin(|ed| {
+            ed.prompt_toggle_readonly();
+            Ok(())
+        }),
+    });
+
+    reg.register(Command {
+        name: "remove_zone_identifier".to_string(),
+        description: "Unblock a downloaded file (remove its Mark of the Web)".to_string(),
+        key: None,
+        source: CommandSource::Builtin(|ed| {
+            ed.remove_zone_identifier();
+            Ok(())
+        }),
+    });
+
+    reg.register(Command {
+        name: "json_validate".to_string(),
+        description: "Check the buffer as JSON and go to the first error".to_string(),
+        key: None,
+        source: CommandSource::Builtin(Editor::json_validate),
+    });
+
+    reg.register(Command {
+        name: "toml_validate".to_string(),
+        description: "Check the buffer as TOML and go to the first error".to_string(),
+        key: None,
+        source: CommandSource::Builtin(Editor::toml_validate),
+    });
+
+    reg.register(Command {
+        name: "plugin_new".to_string(),
+        description: "Create a plugin skeleton in the user plugin folder and open it".to_string(),
+        key: None,
+        source: CommandSource::Builtin(|ed| {
+            ed.prompt = Some(Prompt::new(PromptKind::PluginName, ""));
+            ed.mark_redraw();
+            Ok(())
+        }),
+    });
+
+    reg.register(Command {
+        name: "toggle_auto_close".to_string(),
+        description: "Toggle auto-closing of brackets and quotes".to_string(),
+        key: None,
+        source: CommandSource::Builtin(|ed| {
+            ed.toggle_auto_close();
+            Ok(())
+        }),
+    });
+
+    reg.register(Command {
+        name: "show_whitespace".to_string(),
+        description: "Toggle drawing tabs (→), trailing spaces (·) and non-breaking spaces (␣)".to_string(),
+        key: None,
+        source: CommandSource::Builtin(|ed| {
+            ed.toggle_show_whitespace();
+            Ok(())
+        }),
+    });
 }
@@ -0,0 +1,147 @@
+//! Mouse clicks in the text area: a click moves the cursor (Shift+click extends the selection),
+//! dragging selects, a double click selects the word under the pointer and a triple click the
+//! whole line.
+
+use super::render::text_area_width; // text columns of a pane
+use super::Editor; // editor state
+use crate::bidi; // visual order of right-to-left text
+use crate::types::{Pos, SelectionKind}; // document positions, stream selection
+use crate::utils::digits; // gutter width
+use anyhow::Result; // anyhow error handling
+use std::cmp::max; // gutter width
+use std::time::{Duration, Instant}; // multi-click timing
+
+/// Longest pause between the clicks of a double or triple click.
+const MULTI_CLICK_TIME: Duration = Duration::from_millis(400);
+
+/// The previous click, to count double and triple clicks.
+pub(crate) struct LastClick {
+    at: Instant,
+    pos: Pos,
+    count: u8,
+}
+
+impl Editor {
+    /// Index into `chars` of the char drawn at text column `col` when the first `skip` chars in
+    /// display order are scrolled out; past the end gives `chars.len()`.
+    fn x_at_column(&self, chars: &[char], skip: usize, col: usize) -> usize {
+        let text: String = chars.iter().collect();
+        let mut used = 0;
+        for &i in bidi::display_order(&text).iter().skip(skip) {
+            used += self.char_width(chars[i]);
+            if col < used { return i; }
+        }
+        chars.len()
+    }
+
+    /// Buffer position under screen cell (`col`, `row`) of the focused pane; clicks in the
+    /// gutter go to the line start and clicks below the text to the end of the buffer.
+    pub(crate) fn pos_at(&self, col: usize, row: usize) -> Option<Pos> {
+        let (w, h) = self.screen_size().ok()?;
+        let (left, pane_w) = self.pane_columns(usize::from(w));
+        let row = row.checked_sub(self.header_rows())?;
+        if col < left || col >= left + pane_w || row >= self.header_rows() + self.pinned_rows() + self.text_rows(usize::from(h)) {
+            return None;
+        }
+        let gutter = max(2, digits(self.doc.buf.line_count())) + 2;
+        let text_col = (col - left).saturating_sub(gutter);
+        let last = Pos { y: self.doc.buf.line_count() - 1, x: self.doc.buf.line_len_chars(self.doc.buf.line_count() - 1) };
+
+        // Pinned lines are drawn unwrapped on the rows above the scrolled text.
+        let Some(text_row) = row.checked_sub(self.pinned_rows()) else {
+            let chars: Vec<char> = self.doc.buf.line(row).chars().collect();
+            return Some(Pos { y: row, x: self.x_at_column(&chars, self.doc.scroll_x, text_col) });
+        };
+        if !self.wrapping() {
+            let y = self.doc.scroll_y + text_row;
+            if y >= self.doc.buf.line_count() { return Some(last); }
+            let chars: Vec<char> = self.doc.buf.line(y).chars().collect();
+            return Some(Pos { y, x: self.x_at_column(&chars, self.doc.scroll_x, text_col) });
+        }
+
+        // Wrapped: find the segment drawn on this row.
+        let avail = text_area_width(pane_w, gutter);
+        let target = self.doc.scroll_y + text_row;
+        let mut screen_row = 0;
+        for y in 0..self.doc.buf.line_count() {
+            let line = self.doc.buf.line(y);
+            let segments = self.calculate_wrap_segments(&line, avail);
+            if target < screen_row + segments.len() {
+                let chars: Vec<char> = line.chars().collect();
+                let seg = target - screen_row;
+                let start = segments[seg];
+                let end = segments.get(seg + 1).copied().unwrap_or(chars.len());
+                let pad = if seg == 0 { 0 } else { self.wrap_pad(&line, avail) };
+                let x = start + self.x_at_column(&chars[start..end], 0, text_col.saturating_sub(pad));
+                // The end of a non-final row is the first char of the next one.
+                let x = if x == end && seg + 1 < segments.len() { end - 1 } else { x };
+                return Some(Pos { y, x });
+            }
+            screen_row += segments.len();
+        }
+        Some(last)
+    }
+
+    /// First and last buffer line shown in the scrolled text area (below any pinned lines).
+    pub(crate) fn visible_lines(&self) -> (usize, usize) {
+        let (left, height) = self.screen_size().map_or((0, 24), |(w, h)| (self.pane_columns(usize::from(w)).0, usize::from(h)));
+        let top = self.text_top();
+        let line_on = |row| self.pos_at(left, row).map_or(self.doc.scroll_y, |p| p.y);
+        (line_on(top), line_on(top + self.text_rows(height).max(1) - 1))
+    }
+
+    /// Left button pressed at (`col`, `row`).
+    pub(crate) fn handle_click(&mut self, col: u16, row: u16, shift: bool) -> Result<()> {
+        if row == 0 && self.tab_bar_shown() {
+            return self.click_tab(col);
+        }
+        let Some(pos) = self.pos_at(usize::from(col), usize::from(row)) else { return Ok(()); };
+        self.completion = None;
+        let now = Instant::now();
+        let count = match &self.last_click {
+            Some(c) if c.pos == pos && now.duration_since(c.at) <= MULTI_CLICK_TIME && !shift => c.count % 3 + 1,
+            _ => 1,
+        };
+        self.last_click = Some(LastClick { at: now, pos, count });
+        self.selection_kind = SelectionKind::Stream;
+
+        match count {
+            1 if shift => {
+                if self.doc.anchor.is_none() { self.doc.anchor = Some(self.doc.cursor); }
+                self.doc.cursor = pos;
+            }
+            1 => {
+                self.doc.anchor = None;
+                self.doc.cursor = pos;
+            }
+            2 => {
+                let (start, end) = self.word_range_at(pos);
+                self.doc.anchor = Some(Pos { y: pos.y, x: start });
+                self.doc.cursor = Pos { y: pos.y, x: end };
+            }
+            _ => {
+                self.doc.anchor = Some(Pos { y: pos.y, x: 0 });
+                self.doc.cursor = if pos.y + 1 < self.doc.buf.line_count() {
+                    Pos { y: pos.y + 1, x: 0 }
+                } else {
+                    Pos { y: pos.y, x: self.doc.buf.line_len_chars(pos.y) }
+                };
+            }
+        }
+        self.ensure_visible()?;
+        self.mark_redraw();
+        Ok(())
+    }
+
+    /// Mouse moved to (`col`, `row`) with the left button down: extend the selection.
+    pub(crate) fn handle_drag(&mut self, col: u16, row: u16) -> Result<()> {
+        let Some(pos) = self.pos_at(usize::from(col), usize::from(row)) else { return Ok(()); };
+        if pos == self.doc.cursor { return Ok(()); }
+        if self.doc.anchor.is_none() { self.doc.anchor = Some(self.doc.cursor); }
+        self.selection_kind = SelectionKind::Stream;
+        self.doc.cursor = pos;
+        self.ensure_visible()?;
+        self.mark_redraw();
+        Ok(())
+    }
+}
@@ -1,18 +1,46 @@
 //! Clipboard operations: copy, cut, paste.
+//!
+//! A middle click pastes at the pointer like in Linux terminals: the selected text (kpad's
+//! stand-in for the X primary selection) or the clipboard, per `middle_click` in kpad.toml.
+//!
+//! Text pasted into the terminal arrives as one bracketed paste (`handle_paste`) rather than a
+//! key per character, so it is inserted as a single undo step with one redraw.
+//!
+//! `copy_path` and `copy_reference` copy where the buffer lives (`path:line:col` for the
+//! latter), for sharing code locations.
 
-use crate::types::EditOperation; // document edit operations
+use crate::config::MiddleClick; // middle-click setting
+use crate::types::{EditOperation, Pos}; // document edit operations, cursor position
 use super::Editor; // main editor logic
 use anyhow::Result; // anyhow error handling
+use std::path::{self, Path}; // absolute file paths
 use std::time::Duration; // timing for status messages
 
+/// `path:line:col` of `pos`, 1-based like the status bar.
+fn reference(path: &str, pos: Pos) -> String {
+    format!("{path}:{}:{}", pos.y + 1, pos.x + 1)
+}
+
+/// Pasted terminal text with its line breaks as `\n`: terminals send Enter (`\r`) for them.
+fn paste_text(text: &str) -> String {
+    text.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+/// `path` made absolute without resolving symlinks (the name the user opened).
+fn absolute(path: &Path) -> String {
+    path::absolute(path).unwrap_or_else(|_| path.to_path_buf()).display().to_string()
+}
+
 impl Editor {
     /// Copy to clipboard.
     pub fn copy(&mut self) -> Result<()> {
+        if self.copy_block(false) { return Ok(()); }
         let text = self.selected_text();
         if text.is_empty() {
             self.set_status("Nothing selected to copy.", Duration::from_secs(2));
             return Ok(());
         }
+        self.remember_clip(&text);
         if let Some(cb) = &mut self.clipboard {
             cb.set_text(text).ok();
             self.set_status("Copied selection.", Duration::from_secs(2));
@@ -22,13 +50,35 @@ impl Editor {
         Ok(())
     }
 
+    /// Copy the location of the buffer: its absolute path, or `path:line:col` of the cursor with
+    /// `with_cursor`. Remote and fetched buffers give their URL.
+    pub fn copy_path(&mut self, with_cursor: bool) {
+        let location = match (&self.doc.remote, &self.doc.url, &self.doc.file_path) {
+            (Some(r), _, _) => r.url(),
+            (None, Some(url), _) => url.clone(),
+            (None, None, Some(path)) => absolute(path),
+            (None, None, None) => {
+                self.set_error("Buffer has no file.", Duration::from_secs(2));
+                return;
+            }
+        };
+        let text = if with_cursor { reference(&location, self.doc.cursor) } else { location };
+        match self.clipboard.as_mut().map(|cb| cb.set_text(text.clone())) {
+            Some(Ok(())) => self.set_status(format!("Copied: {text}"), Duration::from_secs(2)),
+            _ => self.set_error("Clipboard unavailable.", Duration::from_secs(2)),
+        }
+    }
+
     /// Cut to clipboard.
     pub fn cut(&mut self) -> Result<()> {
+        if self.copy_block(true) { return self.ensure_visible(); }
+        if !self.ensure_writable() { return Ok(()); }
         let text = self.selected_text();
         if text.is_empty() {
             self.set_status("Nothing selected to cut.", Duration::from_secs(2));
             return Ok(());
         }
+        self.remember_clip(&text);
 
         let (a, b) = self.selection_range().unwrap();
         let op = EditOperation::Delete { start: a, _end: b, deleted_text: text.clone() };
@@ -45,17 +95,15 @@ impl Editor {
 
     /// Paste from clipboard.
     pub fn paste(&mut self) -> Result<()> {
+        if !self.ensure_writable() { return Ok(()); }
         if let Some(cb) = &mut self.clipboard {
             if let Ok(text) = cb.get_text() {
-                if let Some((a, b)) = self.selection_range() {
-                    let deleted_text = self.buf.get_range(a, b);
-                    let op = EditOperation::Delete { start: a, _end: b, deleted_text };
-                    self.record_edit(op);
+                if self.paste_block(&text) {
+                    self.ensure_visible()?;
+                    self.set_status("Pasted.", Duration::from_secs(2));
+                    return Ok(());
                 }
-
-                let op = EditOperation::Insert { pos: self.cursor, text: text.clone() };
-                self.record_edit(op);
-
+                self.record_insert(&text);
                 self.replace_selection_or_insert(&text);
                 self.ensure_visible()?;
                 self.set_status("Pasted.", Duration::from_secs(2));
@@ -65,4 +113,72 @@ impl Editor {
         self.set_status("Clipboard unavailable.", Duration::from_secs(2));
         Ok(())
     }
+
+    /// Text pasted into the terminal (bracketed paste): inserted in one go as one undo step,
+    /// or its first line into an open prompt. Ignored while a popup view is shown.
+    pub fn handle_paste(&mut self, text: &str) -> Result<()> {
+        self.finish_scroll_animation();
+        let text = paste_text(text);
+        if let Some(prompt) = &mut self.prompt {
+            let line = text.lines().next().unwrap_or_default();
+            let at = prompt.input.char_indices().nth(prompt.cursor).map_or(prompt.input.len(), |(i, _)| i);
+            prompt.input.insert_str(at, line);
+            prompt.cursor += line.chars().count();
+            self.mark_redraw();
+            return Ok(());
+        }
+        let popup = self.overlay_shown() || self.color_picker.is_some() || self.buffer_list.is_some()
+            || self.diff_view.is_some() || self.grep_view.is_some() || self.lsp.list.is_some() || self.paste_history.is_some();
+        if popup || text.is_empty() || !self.ensure_writable() { return Ok(()); }
+        self.context_menu = None;
+        self.completion = None;
+        self.auto_close.forget();
+        if !self.paste_block(&text) {
+            self.record_insert(&text);
+            self.replace_selection_or_insert(&text);
+        }
+        self.mark_redraw();
+        self.ensure_visible()
+    }
+
+    /// Middle button pressed at (`col`, `row`): paste there without touching the clipboard.
+    pub(crate) fn middle_click_paste(&mut self, col: u16, row: u16) -> Result<()> {
+        if self.middle_click == MiddleClick::Off { return Ok(()); }
+        let Some(pos) = self.pos_at(usize::from(col), usize::from(row)) else { return Ok(()); };
+        if !self.ensure_writable() { return Ok(()); }
+        let selected = Some(self.selected_text()).filter(|t| !t.is_empty() && self.middle_click == MiddleClick::Selection);
+        let text = match selected {
+            Some(t) => t,
+            None => match self.clipboard.as_mut().map(arboard::Clipboard::get_text) {
+                Some(Ok(t)) if !t.is_empty() => t,
+                _ => {
+                    self.set_status("Nothing to paste.", Duration::from_secs(2));
+                    return Ok(());
+                }
+            },
+        };
+        self.doc.anchor = None;
+        self.doc.cursor = pos;
+        self.record_insert(&text);
+        self.replace_selection_or_insert(&text);
+        self.ensure_visible()?;
+        self.mark_redraw();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn references_are_one_based() {
+        assert_eq!(reference("/src/main.rs", Pos { y: 0, x: 0 }), "/src/main.rs:1:1");
+        assert_eq!(reference("/src/main.rs", Pos { y: 41, x: 7 }), "/src/main.rs:42:8");
+    }
+
+    #[test]
+    fn pasted_line_breaks_become_newlines() {
+        assert_eq!(paste_text("a\rb\r\nc\n"), "a\nb\nc\n");
+    }
 }
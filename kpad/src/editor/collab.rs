@@ -0,0 +1,132 @@
+//! The editor side of pair editing (`kpad --attach`, see `crate::collab`): the hosted file opens
+//! in the first buffer, local edits are sent to the host after every key, and edits from the
+//! other editors are merged into the buffer (each is one undo step). When the host goes away
+//! the buffer stays as a local copy.
+
+use super::Editor; // editor state
+use crate::buffer::Buffer; // the hosted text
+use crate::bus::Message; // remote edits arrive on the bus
+use crate::collab::{self, Channel, Op}; // operations and their transformation
+use crate::plugins::Hook; // open hook
+use crate::types::Pos; // cursor positions
+use anyhow::Result; // anyhow error handling
+use interprocess::local_socket::SendHalf; // connection to the host
+use ropey::Rope; // text as last synced
+use std::path::PathBuf; // file path handling
+use std::sync::mpsc::Sender; // message bus sender
+use std::time::Duration; // timing for status messages
+
+/// An attached pair editing session.
+pub(crate) struct Session {
+    channel: Channel,
+    out: SendHalf,
+    /// The hosted file; only the buffer of this file takes part.
+    path: PathBuf,
+    /// The text as the host last knew it from us, plus remote edits merged since.
+    synced: Rope,
+    /// Remote edits not merged yet, with how many of ours the host had seen.
+    queue: Vec<(Op, u64)>,
+}
+
+/// Where char `idx` ends up after `delete` chars at `at` were replaced by `insert` chars.
+pub(super) fn shift(idx: usize, at: usize, delete: usize, insert: usize) -> usize {
+    if idx <= at {
+        idx
+    } else if idx >= at + delete {
+        idx - delete + insert
+    } else {
+        at + insert
+    }
+}
+
+impl Editor {
+    /// Attach to the running `kpad --host` and open the hosted file (`--attach`).
+    pub fn attach_pair(&mut self, tx: Sender<Message>) -> Result<()> {
+        let (path, text, out) = collab::attach(tx)?;
+        self.prepare_buffer_for_open()?;
+        self.doc.buf = Buffer::from_string(&text);
+        self.doc.cursor = Pos { y: 0, x: 0 };
+        self.doc.anchor = None;
+        self.doc.scroll_y = 0;
+        self.doc.scroll_x = 0;
+        self.doc.file_path = Some(path.clone());
+        self.doc.dirty = false;
+        self.doc.undo.clear();
+        self.doc.redo.clear();
+        self.collab = Some(Session {
+            channel: Channel::new(false),
+            out,
+            path: path.clone(),
+            synced: self.doc.buf.text.clone(),
+            queue: Vec::new(),
+        });
+        self.set_status(format!("Pair editing {}.", path.display()), Duration::from_secs(3));
+        self.detect_file_type();
+        self.run_hook(Hook::Open, Some(&path))
+    }
+
+    /// Queue an edit from the host; `sync_pair` merges it.
+    pub fn queue_pair_edit(&mut self, op: Op, seen: u64) {
+        if let Some(session) = &mut self.collab {
+            session.queue.push((op, seen));
+        }
+    }
+
+    /// The host went away, or dropped us because our copy went out of step.
+    pub fn pair_closed(&mut self, out_of_step: bool) {
+        if self.collab.take().is_none() {
+            return;
+        }
+        if out_of_step {
+            self.set_error("Pair editing went out of step; detached.", Duration::from_secs(5));
+        } else {
+            self.set_error("The pair editing host stopped; this is now a local copy.", Duration::from_secs(5));
+        }
+    }
+
+    /// Send local changes to the host and merge remote ones into the buffer, when the hosted
+    /// file is the active buffer. Called by the main loop before each render.
+    pub fn sync_pair(&mut self) {
+        let Some(mut session) = self.collab.take() else { return; };
+        if self.doc.file_path.as_ref() != Some(&session.path) {
+            self.collab = Some(session);
+            return;
+        }
+        if let Some((at, delete, insert)) = collab::diff(&session.synced, &self.doc.buf.text) {
+            let op = Op::replace(session.synced.len_chars(), at, delete, &insert);
+            if collab::send(&mut session.out, &session.channel.local(op)).is_err() {
+                self.set_error("The pair editing host stopped; this is now a local copy.", Duration::from_secs(5));
+                return;
+            }
+            session.synced = self.doc.buf.text.clone();
+        }
+        for (op, seen) in std::mem::take(&mut session.queue) {
+            let merged = session.channel.remote(op, seen).and_then(|op| op.apply(&session.synced));
+            let Some(text) = merged else {
+                self.set_error("Pair editing went out of step; detached.", Duration::from_secs(5));
+                return;
+            };
+            if let Some((at, delete, insert)) = collab::diff(&session.synced, &text) {
+                let start = self.doc.buf.char_idx_to_pos_public(at);
+                let end = self.doc.buf.char_idx_to_pos_public(at + delete);
+                self.replace_range_in_place(start, end, &insert);
+            }
+            session.synced = text;
+        }
+        self.collab = Some(session);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn positions_follow_remote_edits() {
+        // "abcdef" with "cd" replaced by "XYZ".
+        assert_eq!(shift(1, 2, 2, 3), 1);
+        assert_eq!(shift(2, 2, 2, 3), 2);
+        assert_eq!(shift(3, 2, 2, 3), 5);
+        assert_eq!(shift(5, 2, 2, 3), 6);
+    }
+}
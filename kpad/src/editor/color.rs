@@ -0,0 +1,174 @@
+//! Hex color literals: status-bar swatch for `#RRGGBB` under the cursor and the color picker.
+
+use crate::types::Pos; // cursor positions
+use super::Editor; // editor state
+use anyhow::Result; // anyhow error handling
+use crossterm::{ // terminal manipulation
+    cursor,
+    event::{KeyCode, KeyEvent, KeyModifiers},
+    style::{self, Color},
+    QueueableCommand,
+};
+use std::io::Stdout; // output handling
+use std::time::Duration; // timing for status messages
+
+/// Channel names shown in the picker, in `rgb` order.
+const CHANNELS: [&str; 3] = ["R", "G", "B"];
+
+/// Width of a channel bar in the picker, in cells.
+const BAR_WIDTH: usize = 16;
+
+/// The `#RRGGBB` literal covering char column `x` of `line` (the cursor may also sit right
+/// after it): its start column and color.
+pub(crate) fn hex_color_at(line: &str, x: usize) -> Option<(usize, [u8; 3])> {
+    let chars: Vec<char> = line.chars().collect();
+    let lo = x.saturating_sub(7);
+    let hi = x.min(chars.len().saturating_sub(7));
+    (lo..=hi).rev().find_map(|start| {
+        let lit = chars.get(start..start + 7)?;
+        if lit[0] != '#' || !lit[1..].iter().all(char::is_ascii_hexdigit) {
+            return None;
+        }
+        let hex: String = lit[1..].iter().collect();
+        let [_, rgb @ ..] = u32::from_str_radix(&hex, 16).ok()?.to_be_bytes();
+        Some((start, rgb))
+    })
+}
+
+/// Format `rgb` as `#rrggbb`, matching the letter case of `like` (an existing literal).
+fn format_hex(rgb: [u8; 3], like: &str) -> String {
+    let hex = format!("#{:02x}{:02x}{:02x}", rgb[0], rgb[1], rgb[2]);
+    if like.chars().any(|c| c.is_ascii_uppercase()) { hex.to_uppercase() } else { hex }
+}
+
+/// State of the open color picker.
+pub(crate) struct ColorPicker {
+    /// Position of the `#` of the literal being edited.
+    at: Pos,
+    /// The literal as it was in the buffer (kept for letter case).
+    original: String,
+    rgb: [u8; 3],
+    /// Selected channel (index into `rgb`).
+    channel: usize,
+}
+
+impl Editor {
+    /// The color of the `#RRGGBB` literal under the cursor, if any.
+    pub(crate) fn color_under_cursor(&self) -> Option<[u8; 3]> {
+        hex_color_at(&self.doc.buf.line(self.doc.cursor.y), self.doc.cursor.x).map(|(_, rgb)| rgb)
+    }
+
+    /// Open the color picker for the `#RRGGBB` literal under the cursor.
+    pub fn open_color_picker(&mut self) {
+        let line = self.doc.buf.line(self.doc.cursor.y).into_owned();
+        let Some((x, rgb)) = hex_color_at(&line, self.doc.cursor.x) else {
+            self.set_status("No #RRGGBB color under the cursor.", Duration::from_secs(2));
+            return;
+        };
+        if !self.ensure_writable() { return; }
+        let original = line.chars().skip(x).take(7).collect();
+        self.color_picker = Some(ColorPicker { at: Pos { y: self.doc.cursor.y, x }, original, rgb, channel: 0 });
+        self.mark_redraw();
+    }
+
+    /// Keys while the picker is open: Up/Down pick a channel, Left/Right adjust it (Shift or
+    /// PageUp/PageDown by 16), Enter writes the color back, Esc cancels.
+    pub(crate) fn handle_color_picker_key(&mut self, key: KeyEvent) {
+        let Some(picker) = self.color_picker.as_mut() else { return; };
+        let step = if key.modifiers.contains(KeyModifiers::SHIFT) { 16 } else { 1 };
+        let ch = picker.channel;
+        match key.code {
+            KeyCode::Up => picker.channel = (ch + 2) % 3,
+            KeyCode::Down | KeyCode::Tab => picker.channel = (ch + 1) % 3,
+            KeyCode::Left => picker.rgb[ch] = picker.rgb[ch].saturating_sub(step),
+            KeyCode::Right => picker.rgb[ch] = picker.rgb[ch].saturating_add(step),
+            KeyCode::PageDown => picker.rgb[ch] = picker.rgb[ch].saturating_sub(16),
+            KeyCode::PageUp => picker.rgb[ch] = picker.rgb[ch].saturating_add(16),
+            KeyCode::Home => picker.rgb[ch] = 0,
+            KeyCode::End => picker.rgb[ch] = 255,
+            KeyCode::Enter => {
+                let at = picker.at;
+                let new = format_hex(picker.rgb, &picker.original);
+                if new != picker.original {
+                    self.replace_range(at, Pos { y: at.y, x: at.x + 7 }, &new);
+                    self.set_status(format!("Color set to {new}"), Duration::from_secs(2));
+                }
+                self.color_picker = None;
+            }
+            KeyCode::Esc => self.color_picker = None,
+            _ => {}
+        }
+        self.mark_redraw();
+    }
+
+    /// Draw the picker box in the bottom-right corner of the text area.
+    pub(crate) fn render_color_picker(&self, stdout: &mut Stdout, width: usize, editor_h: usize) -> Result<()> {
+        let Some(picker) = &self.color_picker else { return Ok(()); };
+        let [r, g, b] = picker.rgb;
+        let swatch = self.caps.color(Color::Rgb { r, g, b });
+        let hex = format_hex(picker.rgb, &picker.original);
+
+        let box_w = BAR_WIDTH + 12;
+        let rows = 7;
+        if width < box_w + 2 || editor_h < rows + 1 { return Ok(()); }
+        let (Ok(x0), Ok(y0)) = (u16::try_from(width - box_w - 2), u16::try_from(editor_h - rows - 1)) else {
+            return Ok(());
+        };
+
+        let bg = self.caps.color(Color::AnsiValue(235));
+        let row = |stdout: &mut Stdout, dy: u16| -> Result<()> {
+            stdout.queue(cursor::MoveTo(x0, y0 + dy))?;
+            stdout.queue(style::SetBackgroundColor(bg))?;
+            stdout.queue(style::SetForegroundColor(Color::White))?;
+            Ok(())
+        };
+
+        row(stdout, 0)?;
+        stdout.queue(style::Print(format!(" {:<w$}", format!("Color {} -> {hex}", picker.original), w = box_w - 1)))?;
+        row(stdout, 1)?;
+        stdout.queue(style::Print(" "))?;
+        stdout.queue(style::SetBackgroundColor(swatch))?;
+        stdout.queue(style::Print(" ".repeat(box_w - 2)))?;
+        stdout.queue(style::SetBackgroundColor(bg))?;
+        stdout.queue(style::Print(" "))?;
+        for (i, (name, value)) in CHANNELS.iter().zip(picker.rgb).enumerate() {
+            row(stdout, 2 + u16::try_from(i).unwrap_or(0))?;
+            let selected = i == picker.channel;
+            let filled = usize::from(value) * BAR_WIDTH / 255;
+            let bar_color = match i { 0 => Color::Red, 1 => Color::Green, _ => Color::Blue };
+            stdout.queue(style::Print(format!(" {}{name} ", if selected { ">" } else { " " })))?;
+            stdout.queue(style::SetForegroundColor(bar_color))?;
+            stdout.queue(style::Print(format!("{}{}", self.caps.glyph("█", "#").repeat(filled), self.caps.glyph("░", "-").repeat(BAR_WIDTH - filled))))?;
+            stdout.queue(style::SetForegroundColor(Color::White))?;
+            stdout.queue(style::Print(format!(" {value:>3}    ")))?;
+        }
+        row(stdout, 5)?;
+        stdout.queue(style::Print(format!(" {:<w$}", "←/→ adjust  ↑/↓ channel", w = box_w - 1)))?;
+        row(stdout, 6)?;
+        stdout.queue(style::Print(format!(" {:<w$}", "Enter apply  Esc cancel", w = box_w - 1)))?;
+        stdout.queue(style::ResetColor)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_color_around_cursor() {
+        let line = "color: #1e90FF;";
+        assert_eq!(hex_color_at(line, 7), Some((7, [0x1e, 0x90, 0xff])));
+        assert_eq!(hex_color_at(line, 11), Some((7, [0x1e, 0x90, 0xff])));
+        assert_eq!(hex_color_at(line, 14), Some((7, [0x1e, 0x90, 0xff])));
+        assert_eq!(hex_color_at(line, 3), None);
+        assert_eq!(hex_color_at("#12345", 2), None);
+        assert_eq!(hex_color_at("#12345g", 2), None);
+    }
+
+    #[test]
+    fn keeps_letter_case() {
+        assert_eq!(format_hex([255, 0, 171], "#AABBCC"), "#FF00AB");
+        assert_eq!(format_hex([255, 0, 171], "#aabbcc"), "#ff00ab");
+    }
+}
@@ -0,0 +1,221 @@
+//! Word completion (`complete_word`, Ctrl+N): words from every open buffer and from the file
+//! type's dictionary, offered in a popup at the cursor.
+//!
+//! Candidates from the active buffer come first, nearest occurrence to the cursor first; then
+//! words seen only in other buffers, most frequent first; then dictionary words.
+
+use super::Editor; // editor state
+use crate::types::Pos; // cursor positions
+use anyhow::Result; // anyhow error handling
+use crossterm::{ // terminal manipulation
+    cursor,
+    event::{KeyCode, KeyEvent, KeyModifiers},
+    style::{self, Color},
+    QueueableCommand,
+};
+use std::cmp::Reverse; // descending sort keys
+use std::collections::HashMap; // word statistics
+use std::fs; // reading dictionary files
+use std::io::Stdout; // output handling
+use std::time::Duration; // timing for status messages
+
+/// Most candidates kept for the popup.
+const MAX_CANDIDATES: usize = 50;
+
+/// Rows shown in the popup at once.
+const POPUP_ROWS: usize = 8;
+
+/// Longest candidate shown in full; longer ones are cut.
+const POPUP_MAX_WIDTH: usize = 40;
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// How a candidate word was seen.
+#[derive(Default)]
+struct WordStats {
+    /// Line distance to the nearest occurrence in the active buffer.
+    distance: Option<usize>,
+    /// Occurrences in all buffers.
+    count: usize,
+}
+
+/// Candidate words starting with a prefix, gathered from several sources.
+struct Candidates<'a> {
+    prefix: &'a str,
+    lower_prefix: String,
+    words: HashMap<String, WordStats>,
+}
+
+impl<'a> Candidates<'a> {
+    fn new(prefix: &'a str) -> Self {
+        Self { prefix, lower_prefix: prefix.to_lowercase(), words: HashMap::new() }
+    }
+
+    fn matches(&self, word: &str) -> bool {
+        word != self.prefix && word.chars().count() > self.prefix.chars().count()
+            && word.to_lowercase().starts_with(&self.lower_prefix)
+    }
+
+    /// Count the words of `line`; `distance` is its line distance from the cursor when it is in
+    /// the active buffer.
+    fn add_line(&mut self, line: &str, distance: Option<usize>) {
+        for word in line.split(|c: char| !is_word_char(c)) {
+            if !self.matches(word) { continue; }
+            let stats = self.words.entry(word.to_string()).or_default();
+            stats.count += 1;
+            if let Some(d) = distance {
+                stats.distance = Some(stats.distance.map_or(d, |old| old.min(d)));
+            }
+        }
+    }
+
+    /// Offer a dictionary word (never counted as seen in a buffer).
+    fn add_dictionary_word(&mut self, word: &str) {
+        if self.matches(word) && !self.words.contains_key(word) {
+            self.words.insert(word.to_string(), WordStats::default());
+        }
+    }
+
+    /// The best candidates: nearest in the active buffer, then most frequent, then alphabetical.
+    fn ranked(self) -> Vec<String> {
+        let mut words: Vec<(String, WordStats)> = self.words.into_iter().collect();
+        words.sort_by(|(a, sa), (b, sb)| {
+            (sa.distance.is_none(), sa.distance, Reverse(sa.count), a)
+                .cmp(&(sb.distance.is_none(), sb.distance, Reverse(sb.count), b))
+        });
+        words.into_iter().take(MAX_CANDIDATES).map(|(w, _)| w).collect()
+    }
+}
+
+/// The open completion popup.
+pub(crate) struct Completion {
+    /// Where the completed word starts.
+    start: Pos,
+    items: Vec<String>,
+    selected: usize,
+}
+
+impl Editor {
+    /// Complete the word before the cursor: a single candidate is inserted right away, several
+    /// open the popup.
+    pub fn complete_word(&mut self) -> Result<()> {
+        if !self.ensure_writable() { return Ok(()); }
+        let line: Vec<char> = self.doc.buf.line(self.doc.cursor.y).chars().collect();
+        let x = self.doc.cursor.x.min(line.len());
+        let start_x = line[..x].iter().rposition(|&c| !is_word_char(c)).map_or(0, |i| i + 1);
+        let prefix: String = line[start_x..x].iter().collect();
+        if prefix.is_empty() {
+            self.set_status("Type the start of a word to complete.", Duration::from_secs(2));
+            return Ok(());
+        }
+
+        let mut candidates = Candidates::new(&prefix);
+        for y in 0..self.doc.buf.line_count() {
+            candidates.add_line(&self.doc.buf.line(y), Some(y.abs_diff(self.doc.cursor.y)));
+        }
+        for (i, st) in self.buffers.iter().enumerate() {
+            if i == self.active { continue; }
+            for y in 0..st.buf.line_count() {
+                candidates.add_line(&st.buf.line(y), None);
+            }
+        }
+        if let Some(path) = self.current_filetype().and_then(|ft| ft.dictionary.clone()) {
+            match fs::read_to_string(&path) {
+                Ok(words) => words.lines().for_each(|w| candidates.add_dictionary_word(w.trim())),
+                Err(e) => self.set_status(format!("Dictionary {}: {e}", path.display()), Duration::from_secs(3)),
+            }
+        }
+
+        let items = candidates.ranked();
+        let start = Pos { y: self.doc.cursor.y, x: start_x };
+        match items.len() {
+            0 => self.set_status(format!("No completions for '{prefix}'."), Duration::from_secs(2)),
+            1 => self.insert_completion(start, &items[0])?,
+            _ => self.completion = Some(Completion { start, items, selected: 0 }),
+        }
+        self.mark_redraw();
+        Ok(())
+    }
+
+    /// Replace the word from `start` to the cursor with `word`.
+    fn insert_completion(&mut self, start: Pos, word: &str) -> Result<()> {
+        let end = self.doc.cursor;
+        self.replace_range(start, end, word);
+        self.doc.cursor = Pos { y: start.y, x: start.x + word.chars().count() };
+        self.clear_selection();
+        self.ensure_visible()
+    }
+
+    /// Keys while the popup is open: Up/Down (or Ctrl+P/Ctrl+N) select, Enter/Tab insert, Esc
+    /// closes. Returns false for any other key, which closes the popup and is handled normally.
+    pub(crate) fn handle_completion_key(&mut self, key: KeyEvent) -> Result<bool> {
+        let Some(c) = self.completion.as_mut() else { return Ok(false); };
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let last = c.items.len() - 1;
+        match key.code {
+            KeyCode::Down => c.selected = if c.selected == last { 0 } else { c.selected + 1 },
+            KeyCode::Char('n') if ctrl => c.selected = if c.selected == last { 0 } else { c.selected + 1 },
+            KeyCode::Up => c.selected = c.selected.checked_sub(1).unwrap_or(last),
+            KeyCode::Char('p') if ctrl => c.selected = c.selected.checked_sub(1).unwrap_or(last),
+            KeyCode::Enter | KeyCode::Tab => {
+                let start = c.start;
+                let word = c.items[c.selected].clone();
+                self.completion = None;
+                self.insert_completion(start, &word)?;
+            }
+            KeyCode::Esc => self.completion = None,
+            _ => {
+                self.completion = None;
+                self.mark_redraw();
+                return Ok(false);
+            }
+        }
+        self.mark_redraw();
+        Ok(true)
+    }
+
+    /// Draw the popup below the cursor cell (`x`, `y`), or above it near the bottom of the text
+    /// area (which ends at row `bottom`).
+    pub(crate) fn render_completion(&self, stdout: &mut Stdout, x: usize, y: usize, width: usize, bottom: usize) -> Result<()> {
+        let Some(c) = &self.completion else { return Ok(()); };
+        let rows = c.items.len().min(POPUP_ROWS);
+        let item_w = c.items.iter().map(|w| w.chars().count()).max().unwrap_or(0).min(POPUP_MAX_WIDTH);
+        let box_w = (item_w + 2).min(width);
+        let top = if y + 1 + rows <= bottom { y + 1 } else { y.saturating_sub(rows) };
+        // Keep the word start aligned with the text when there is room.
+        let prefix_w = self.doc.cursor.x.saturating_sub(c.start.x);
+        let left = x.saturating_sub(prefix_w + 1).min(width.saturating_sub(box_w));
+        let first = (c.selected + 1).saturating_sub(rows);
+
+        for (row, (i, word)) in c.items.iter().enumerate().skip(first).take(rows).enumerate() {
+            let (Ok(col), Ok(line)) = (u16::try_from(left), u16::try_from(top + row)) else { break; };
+            let shown: String = word.chars().take(box_w.saturating_sub(2)).collect();
+            let selected = i == c.selected;
+            stdout.queue(cursor::MoveTo(col, line))?;
+            stdout.queue(style::SetBackgroundColor(if selected { Color::DarkBlue } else { self.caps.color(Color::AnsiValue(236)) }))?;
+            stdout.queue(style::SetForegroundColor(if selected { Color::White } else { Color::Grey }))?;
+            stdout.queue(style::Print(format!(" {shown:<w$} ", w = box_w.saturating_sub(2))))?;
+        }
+        stdout.queue(style::ResetColor)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranks_by_proximity_then_frequency() {
+        let mut c = Candidates::new("fo");
+        c.add_line("format fo foo", Some(3));
+        c.add_line("fold", Some(1));
+        c.add_line("forest forest foreign", None);
+        c.add_line("Foobar foreign", None);
+        c.add_dictionary_word("fortune");
+        c.add_dictionary_word("fold");
+        assert_eq!(c.ranked(), ["fold", "foo", "format", "foreign", "forest", "Foobar", "fortune"]);
+    }
+}
@@ -0,0 +1,163 @@
+//! Diff overlay: a unified line diff of two texts, scrollable full screen. `diff` compares the
+//! file on disk with the buffer, `diff_clipboard` the selection with the clipboard.
+
+use super::Editor; // editor state
+use crate::buffer::Buffer; // decoding the saved file
+use anyhow::Result; // anyhow error handling
+use crossterm::{ // terminal manipulation
+    cursor,
+    event::{KeyCode, KeyEvent},
+    style::{self, Color},
+    terminal::{self, ClearType},
+    QueueableCommand,
+};
+use similar::{ChangeTag, TextDiff}; // line diff
+use std::fs; // reading the saved file
+use std::io::{Stdout, Write}; // output handling
+use std::time::Duration; // timing for status messages
+
+/// Unchanged lines shown around each change.
+const CONTEXT_LINES: usize = 3;
+
+/// The open diff overlay.
+pub(crate) struct DiffView {
+    title: String,
+    /// Diff lines, each starting with its marker: `@` hunk header, `-`, `+` or ` `.
+    lines: Vec<String>,
+    /// First line shown.
+    scroll: usize,
+}
+
+/// Unified diff lines from `old` to `new` (line endings are ignored).
+fn diff_lines(old: &str, new: &str) -> Vec<String> {
+    let (old, new) = (old.replace("\r\n", "\n"), new.replace("\r\n", "\n"));
+    let diff = TextDiff::from_lines(&old, &new);
+    let mut lines = Vec::new();
+    for hunk in diff.unified_diff().context_radius(CONTEXT_LINES).iter_hunks() {
+        lines.push(hunk.header().to_string());
+        for change in hunk.iter_changes() {
+            let marker = match change.tag() {
+                ChangeTag::Delete => '-',
+                ChangeTag::Insert => '+',
+                ChangeTag::Equal => ' ',
+            };
+            lines.push(format!("{marker}{}", change.value().trim_end_matches('\n').replace('\t', "    ")));
+        }
+    }
+    lines
+}
+
+impl Editor {
+    /// Show the diff from `old` to `new` in the overlay; identical texts only get a status message.
+    pub(crate) fn show_diff(&mut self, title: &str, old: &str, new: &str) {
+        let lines = diff_lines(old, new);
+        if lines.is_empty() {
+            self.set_status("No differences.", Duration::from_secs(2));
+            return;
+        }
+        self.diff_view = Some(DiffView { title: title.to_string(), lines, scroll: 0 });
+        self.mark_redraw();
+    }
+
+    /// Diff the file on disk (old) against the buffer (new), to review unsaved changes (`diff`
+    /// command). The file is decoded with the buffer's encoding.
+    pub fn diff_saved(&mut self) {
+        let Some(path) = self.doc.file_path.clone() else {
+            self.set_status("The buffer has no file on disk.", Duration::from_secs(2));
+            return;
+        };
+        let saved = match fs::read(&path) {
+            Ok(bytes) => Buffer::decode(&bytes, self.doc.buf.encoding).to_string(),
+            Err(e) => {
+                self.set_status(format!("{}: {e}", path.display()), Duration::from_secs(3));
+                return;
+            }
+        };
+        let name = path.file_name().map_or_else(|| path.display().to_string(), |n| n.to_string_lossy().into_owned());
+        let text = self.doc.buf.to_string();
+        self.show_diff(&format!("{name} on disk -> buffer"), &saved, &text);
+    }
+
+    /// Diff the selection (old) against the clipboard (new) (`diff_clipboard` command).
+    pub fn diff_clipboard(&mut self) {
+        let selection = self.selected_text();
+        if selection.is_empty() {
+            self.set_status("Select the text to compare with the clipboard.", Duration::from_secs(2));
+            return;
+        }
+        let Some(clip) = self.clipboard.as_mut().and_then(|cb| cb.get_text().ok()) else {
+            self.set_status("Clipboard unavailable.", Duration::from_secs(2));
+            return;
+        };
+        self.show_diff("selection -> clipboard", &selection, &clip);
+    }
+
+    /// Keys in the diff overlay: arrows, PageUp/PageDown, Home/End scroll; Esc, q or Enter close.
+    pub(crate) fn handle_diff_key(&mut self, key: KeyEvent) {
+        let page = usize::from(self.screen_size().map_or(24, |(_, h)| h)).saturating_sub(3).max(1);
+        let Some(view) = self.diff_view.as_mut() else { return; };
+        let last = view.lines.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') => self.diff_view = None,
+            KeyCode::Up => view.scroll = view.scroll.saturating_sub(1),
+            KeyCode::Down => view.scroll = (view.scroll + 1).min(last),
+            KeyCode::PageUp => view.scroll = view.scroll.saturating_sub(page),
+            KeyCode::PageDown => view.scroll = (view.scroll + page).min(last),
+            KeyCode::Home => view.scroll = 0,
+            KeyCode::End => view.scroll = last.saturating_sub(page - 1),
+            _ => {}
+        }
+        self.mark_redraw();
+    }
+
+    /// Render the diff overlay: title row, diff lines, key hint row.
+    pub fn render_diff(&mut self, stdout: &mut Stdout) -> Result<()> {
+        let Some(view) = &self.diff_view else { return Ok(()); };
+        let (w, h) = terminal::size()?;
+        let width = usize::from(w);
+
+        stdout.queue(cursor::Hide)?;
+        stdout.queue(style::ResetColor)?;
+        stdout.queue(terminal::Clear(ClearType::All))?;
+
+        let title = format!(" DIFF: {} ({} lines)", view.title, view.lines.len());
+        stdout.queue(cursor::MoveTo(0, 0))?;
+        stdout.queue(style::SetBackgroundColor(self.theme.status_bg))?;
+        stdout.queue(style::SetForegroundColor(self.theme.status_fg))?;
+        stdout.queue(style::Print(format!("{:<width$}", title.chars().take(width).collect::<String>())))?;
+        stdout.queue(style::ResetColor)?;
+
+        for (row, line) in (1..h.saturating_sub(1)).zip(view.lines.iter().skip(view.scroll)) {
+            let color = match line.chars().next() {
+                Some('-') => Color::Red,
+                Some('+') => Color::Green,
+                Some('@') => Color::Cyan,
+                _ => Color::Grey,
+            };
+            stdout.queue(cursor::MoveTo(0, row))?;
+            stdout.queue(style::SetForegroundColor(color))?;
+            stdout.queue(style::Print(line.chars().take(width).collect::<String>()))?;
+        }
+        stdout.queue(style::ResetColor)?;
+
+        stdout.queue(cursor::MoveTo(0, h.saturating_sub(1)))?;
+        stdout.queue(style::SetForegroundColor(Color::DarkGrey))?;
+        stdout.queue(style::Print(" Up/Down PgUp/PgDn Home/End scroll   Esc close"))?;
+        stdout.queue(style::ResetColor)?;
+        stdout.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diffs_lines_with_context() {
+        let old = "a\nb\nc\r\nd\n";
+        let new = "a\nB\nc\nd\ne\n";
+        assert_eq!(diff_lines(old, new), ["@@ -1,4 +1,5 @@", " a", "-b", "+B", " c", " d", "+e"]);
+        assert!(diff_lines("same\r\n", "same\n").is_empty());
+    }
+}
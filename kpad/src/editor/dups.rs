@@ -0,0 +1,130 @@
+//! Repeated lines: `find_duplicates` marks the line numbers of every line that occurs more than
+//! once (until the text changes), `next_duplicate`/`prev_duplicate` jump between them and
+//! `delete_duplicates` keeps only the first occurrence of each, as one undo step. Blank lines
+//! never count as duplicates.
+
+use super::Editor; // editor state
+use crate::types::Pos; // positions
+use anyhow::Result; // anyhow error handling
+use std::collections::HashMap; // lines seen
+use std::time::Duration; // timing for status messages
+
+/// Repeated lines found by `find_duplicates`.
+pub(crate) struct Duplicates {
+    /// Buffer revision the lines were found in; any edit hides the marks.
+    revision: u64,
+    /// Groups of equal lines (line indices, ascending), in order of first occurrence.
+    groups: Vec<Vec<usize>>,
+}
+
+/// Groups of indices of equal non-blank lines occurring more than once.
+fn duplicate_groups(lines: &[String]) -> Vec<Vec<usize>> {
+    let mut by_text: HashMap<&str, usize> = HashMap::new();
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    for (y, line) in lines.iter().enumerate() {
+        if line.trim().is_empty() { continue; }
+        let g = *by_text.entry(line).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[g].push(y);
+    }
+    groups.retain(|g| g.len() > 1);
+    groups
+}
+
+impl Editor {
+    /// Lines of the buffer.
+    fn all_lines(&self) -> Vec<String> {
+        (0..self.doc.buf.line_count()).map(|y| self.doc.buf.line(y).to_string()).collect()
+    }
+
+    /// The duplicate groups of the current text, found again if the text changed.
+    fn current_duplicates(&mut self) -> &[Vec<usize>] {
+        let revision = self.doc.buf.revision();
+        if self.duplicates.as_ref().is_none_or(|d| d.revision != revision) {
+            let groups = duplicate_groups(&self.all_lines());
+            self.duplicates = Some(Duplicates { revision, groups });
+        }
+        self.duplicates.as_ref().map_or(&[], |d| &d.groups)
+    }
+
+    /// Whether line `y` carries a duplicate mark.
+    pub(crate) fn is_duplicate_line(&self, y: usize) -> bool {
+        self.duplicates.as_ref()
+            .filter(|d| d.revision == self.doc.buf.revision())
+            .is_some_and(|d| d.groups.iter().any(|g| g.binary_search(&y).is_ok()))
+    }
+
+    /// Mark the repeated lines and report how many there are (`find_duplicates`).
+    pub fn find_duplicates(&mut self) {
+        let groups = self.current_duplicates();
+        let (count, lines) = (groups.len(), groups.iter().map(Vec::len).sum::<usize>());
+        self.mark_redraw();
+        if count == 0 {
+            self.set_status("No duplicate lines.", Duration::from_secs(2));
+        } else {
+            let msg = format!("{lines} lines in {count} duplicate groups (next_duplicate, delete_duplicates)");
+            self.set_status(msg, Duration::from_secs(4));
+        }
+    }
+
+    /// Move to the next (`forward`) or previous repeated line, wrapping around.
+    pub fn jump_duplicate(&mut self, forward: bool) -> Result<()> {
+        let cursor_y = self.doc.cursor.y;
+        let groups = self.current_duplicates();
+        let mut marked: Vec<(usize, usize, usize)> = groups.iter()
+            .flat_map(|g| g.iter().enumerate().map(move |(i, &y)| (y, i + 1, g.len())))
+            .collect();
+        marked.sort_unstable();
+        let found = if forward {
+            marked.iter().find(|m| m.0 > cursor_y).or_else(|| marked.first())
+        } else {
+            marked.iter().rev().find(|m| m.0 < cursor_y).or_else(|| marked.last())
+        };
+        let Some(&(y, n, total)) = found else {
+            self.set_status("No duplicate lines.", Duration::from_secs(2));
+            return Ok(());
+        };
+        self.doc.cursor = Pos { y, x: 0 };
+        self.clear_selection();
+        self.mark_redraw();
+        self.set_status(format!("Occurrence {n} of {total}"), Duration::from_secs(2));
+        self.ensure_visible()
+    }
+
+    /// Delete every repeated line but its first occurrence, as one undo step.
+    pub fn delete_duplicates(&mut self) -> Result<()> {
+        if !self.ensure_writable() { return Ok(()); }
+        let lines = self.all_lines();
+        let mut removed: Vec<usize> = duplicate_groups(&lines).iter().flat_map(|g| g[1..].iter().copied()).collect();
+        if removed.is_empty() {
+            self.set_status("No duplicate lines.", Duration::from_secs(2));
+            return Ok(());
+        }
+        removed.sort_unstable();
+        let kept: Vec<&str> = lines.iter().enumerate()
+            .filter(|(y, _)| removed.binary_search(y).is_err())
+            .map(|(_, l)| l.as_str())
+            .collect();
+        let cursor_y = self.doc.cursor.y - removed.partition_point(|&y| y < self.doc.cursor.y);
+        let last = lines.len() - 1;
+        self.replace_lines(0, last, &kept.join("\n"));
+        self.doc.cursor = self.doc.buf.clamp_pos(Pos { y: cursor_y, x: self.doc.cursor.x });
+        self.clear_selection();
+        let plural = if removed.len() == 1 { "" } else { "s" };
+        self.set_status(format!("Removed {} duplicate line{plural}.", removed.len()), Duration::from_secs(3));
+        self.ensure_visible()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_repeated_lines() {
+        let lines: Vec<String> = ["b", "a", "", "b", "c", "", "a", "b"].iter().map(ToString::to_string).collect();
+        assert_eq!(duplicate_groups(&lines), [vec![0, 3, 7], vec![1, 6]]);
+    }
+}
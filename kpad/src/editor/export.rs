@@ -0,0 +1,136 @@
+//! Copy with colors: `copy_highlighted` puts the selection (or the whole buffer) on the
+//! clipboard with its syntax-highlight colors as ANSI escape sequences, for terminals and chat
+//! clients that render them; `copy_highlighted_html` copies it as a `<pre>` block instead.
+
+use super::highlight::highlight_to_crossterm; // the colors drawn on screen
+use super::Editor; // editor state
+use crate::types::Pos; // document positions
+use crossterm::style::Color; // terminal colors
+use std::time::Duration; // timing for status messages
+
+/// Text drawn in one color (`None`: the default foreground).
+type Run = (Option<Color>, String);
+
+/// SGR foreground code of a highlight color.
+fn sgr(color: Color) -> u8 {
+    match color {
+        Color::DarkRed => 31,
+        Color::DarkGreen => 32,
+        Color::DarkYellow => 33,
+        Color::DarkBlue => 34,
+        Color::DarkMagenta => 35,
+        Color::DarkCyan => 36,
+        Color::Grey => 37,
+        Color::Red => 91,
+        Color::Green => 92,
+        Color::Yellow => 93,
+        Color::Blue => 94,
+        Color::Magenta => 95,
+        Color::Cyan => 96,
+        Color::White => 97,
+        _ => 39,
+    }
+}
+
+/// CSS color of a highlight color (xterm's palette).
+fn css(color: Color) -> &'static str {
+    match color {
+        Color::DarkRed => "#cd0000",
+        Color::DarkGreen => "#00cd00",
+        Color::DarkYellow => "#cdcd00",
+        Color::DarkBlue => "#0000ee",
+        Color::DarkMagenta => "#cd00cd",
+        Color::DarkCyan => "#00cdcd",
+        Color::Grey => "#e5e5e5",
+        Color::Red => "#ff0000",
+        Color::Green => "#00ff00",
+        Color::Yellow => "#ffff00",
+        Color::Blue => "#5c5cff",
+        Color::Magenta => "#ff00ff",
+        Color::Cyan => "#00ffff",
+        _ => "#ffffff",
+    }
+}
+
+/// Lines of runs as ANSI-colored text; colors are reset at each line end.
+fn to_ansi(lines: &[Vec<Run>]) -> String {
+    let line = |runs: &Vec<Run>| {
+        runs.iter().map(|(color, text)| match color {
+            Some(c) => format!("\x1b[{}m{text}\x1b[39m", sgr(*c)),
+            None => text.clone(),
+        }).collect::<Vec<_>>().concat()
+    };
+    lines.iter().map(line).collect::<Vec<_>>().join("\n")
+}
+
+/// `text` with HTML special characters escaped.
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// Lines of runs as a `<pre>` block with colored `<span>`s.
+fn to_html(lines: &[Vec<Run>]) -> String {
+    let line = |runs: &Vec<Run>| {
+        runs.iter().map(|(color, text)| match color {
+            Some(c) => format!("<span style=\"color:{}\">{}</span>", css(*c), escape_html(text)),
+            None => escape_html(text),
+        }).collect::<Vec<_>>().concat()
+    };
+    format!("<pre>{}</pre>", lines.iter().map(line).collect::<Vec<_>>().join("\n"))
+}
+
+impl Editor {
+    /// The selection (or the whole buffer) as one list of color runs per line.
+    fn highlighted_runs(&mut self) -> Vec<Vec<Run>> {
+        let last = self.doc.buf.line_count() - 1;
+        let (start, end) = self.selection_range().unwrap_or((Pos { y: 0, x: 0 }, Pos { y: last, x: self.doc.buf.line_len_chars(last) }));
+        let mut lines = Vec::new();
+        for y in start.y..=end.y {
+            let line = self.doc.buf.line(y);
+            let spans = if self.large_file { Vec::new() } else { self.highlighter.get_highlights(y, &line) };
+            let from = if y == start.y { start.x } else { 0 };
+            let to = if y == end.y { end.x } else { usize::MAX };
+            let mut runs: Vec<Run> = Vec::new();
+            for (i, ch) in line.chars().enumerate().skip(from).take(to.saturating_sub(from)) {
+                let color = self.highlighter.color_at(&spans, i).map(highlight_to_crossterm);
+                match runs.last_mut() {
+                    Some((c, text)) if *c == color => text.push(ch),
+                    _ => runs.push((color, ch.to_string())),
+                }
+            }
+            lines.push(runs);
+        }
+        lines
+    }
+
+    /// Copy the selection (or the whole buffer) with its highlight colors, as HTML with `html`
+    /// and ANSI escape sequences otherwise.
+    pub fn copy_highlighted(&mut self, html: bool) {
+        let lines = self.highlighted_runs();
+        let text = if html { to_html(&lines) } else { to_ansi(&lines) };
+        let what = if self.selection_range().is_some() { "selection" } else { "buffer" };
+        let format = if html { "HTML" } else { "ANSI colors" };
+        match self.clipboard.as_mut().map(|cb| cb.set_text(text)) {
+            Some(Ok(())) => self.set_status(format!("Copied {what} as {format}."), Duration::from_secs(2)),
+            _ => self.set_error("Clipboard unavailable.", Duration::from_secs(2)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_color_runs() {
+        let lines = vec![
+            vec![(Some(Color::Blue), "fn".to_string()), (None, " a<b>".to_string())],
+            vec![(Some(Color::DarkGreen), "\"&\"".to_string())],
+        ];
+        assert_eq!(to_ansi(&lines), "\x1b[94mfn\x1b[39m a<b>\n\x1b[32m\"&\"\x1b[39m");
+        assert_eq!(
+            to_html(&lines),
+            "<pre><span style=\"color:#5c5cff\">fn</span> a&lt;b&gt;\n<span style=\"color:#00cd00\">\"&amp;\"</span></pre>"
+        );
+    }
+}
@@ -2,91 +2,284 @@
 
 use crate::buffer::Buffer; // document model
 use crate::plugins::Hook; // plugin lifecycle hooks
+use crate::remote::{fetch_url, is_http_url, url_file_name, RemotePath}; // remote locations
 use crate::types::{Pos, Prompt, PromptKind}; // core types
+use super::attrs::zone_note; // downloaded-file note
+use super::large::read_utf8_file; // chunked UTF-8 loading
 use super::Editor; // editor state
 use anyhow::{Context, Result}; // anyhow error handling
+use encoding_rs::{Encoding, UTF_8}; // --encoding
 use std::fs::{self, File}; // file system access and file handle
-use std::io::BufWriter; // buffered writing
-use std::mem; // memory manipulation
-use std::path::PathBuf; // file path handling
+use std::io::{self, BufWriter, Read}; // buffered writing, piped input
+use std::path::{Path, PathBuf}; // file path handling
 use std::time::Duration; // timing for status messages
 
+/// Read `path` as UTF-8, or in `encoding` when one is given (`--encoding`).
+pub(crate) fn read_buffer(path: &Path, encoding: Option<&'static Encoding>) -> Result<Buffer> {
+    if let Some(encoding) = encoding {
+        let bytes = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        return Ok(Buffer::decode(&bytes, encoding));
+    }
+    read_utf8_file(path)
+}
+
 impl Editor {
     /// Save the buffer.
     pub fn cmd_save(&mut self) -> Result<()> {
-        if self.file_path.is_none() {
-            self.prompt = Some(Prompt::new(PromptKind::SaveAs, ""));
+        if self.doc.file_path.is_none() {
+            let suggested = self.doc.url.as_deref().map(url_file_name).unwrap_or_default();
+            self.prompt = Some(Prompt::new(PromptKind::SaveAs, suggested));
             return Ok(());
         }
-        self.save_to_path(self.file_path.clone().unwrap())
+        self.save_checking_readonly(self.doc.file_path.clone().unwrap())
     }
 
     /// Save the buffer to a specific path.
     /// Uses streaming write to avoid allocating the entire file as a String.
+    ///
+    /// An `scp://` path (Save As) or the cache file of an opened remote file is written locally
+    /// and then uploaded. Upload errors are shown on the status line and leave the buffer dirty.
     pub fn save_to_path(&mut self, path: PathBuf) -> Result<()> {
+        let remote = match path.to_str().and_then(RemotePath::parse) {
+            Some(r) => Some(r),
+            None => self.doc.remote.clone().filter(|r| r.cache_path().is_ok_and(|p| p == path)),
+        };
+        let path = match &remote {
+            Some(r) => r.cache_path()?,
+            None => path,
+        };
+
+        // Fail before truncating the file when the text does not fit its encoding.
+        if self.doc.buf.encoding != UTF_8 {
+            self.doc.buf.write_to(io::sink()).with_context(|| format!("Cannot save {}", path.display()))?;
+        }
         let file = File::create(&path)
             .with_context(|| format!("Failed to create {}", path.display()))?;
         let writer = BufWriter::new(file);
-        self.buf.write_to(writer)
+        self.doc.buf.write_to(writer)
             .with_context(|| format!("Failed writing {}", path.display()))?;
-        self.file_path = Some(path.clone());
-        self.dirty = false;
-        self.set_status(format!("Saved: {}", path.display()), Duration::from_secs(2));
+        self.finish_save(&path, remote)
+    }
 
-        let mut plugins = mem::take(&mut self.plugins);
-        plugins.call_hook(self, Hook::OnSave, Some(&path))?;
-        self.plugins = plugins;
-        Ok(())
+    /// Bookkeeping after the buffer was written to `path` (the cache file of `remote`, if any):
+    /// adopt the path, upload remote files, clear the dirty flag and run the save hook.
+    pub(crate) fn finish_save(&mut self, path: &Path, remote: Option<RemotePath>) -> Result<()> {
+        let renamed = self.doc.file_path.as_deref() != Some(path);
+        self.doc.file_path = Some(path.to_path_buf());
+        self.doc.remote = remote;
+        self.doc.url = None;
+        if renamed { self.detect_file_type(); }
+        self.doc.read_only = false;
+        if let Some(r) = &self.doc.remote {
+            if let Err(e) = r.upload(path) {
+                self.doc.dirty = true;
+                self.set_error(format!("{e:#}"), Duration::from_secs(5));
+                return Ok(());
+            }
+        }
+        self.doc.dirty = false;
+        let shown = self.doc.remote.as_ref().map_or_else(|| path.display().to_string(), RemotePath::url);
+        self.set_status(format!("Saved: {shown}"), Duration::from_secs(2));
+
+        self.run_hook(Hook::Save, Some(&path.to_path_buf()))
     }
 
     /// Open a file.
     pub fn open_path(&mut self, path: PathBuf) -> Result<()> {
-        let s = fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
-        self.buf = Buffer::from_string(&s);
-        self.cursor = Pos { y: 0, x: 0 };
-        self.anchor = None;
-        self.scroll_y = 0;
-        self.scroll_x = 0;
-        self.file_path = Some(path.clone());
-        self.dirty = false;
-        self.undo.clear();
-        self.redo.clear();
-
-        // Update highlighter for new file extension
-        if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
-            self.highlighter.set_file_extension(ext);
-        } else {
-            self.highlighter.set_file_extension("");
+        self.push_jump();
+        if let Some(remote) = path.to_str().and_then(RemotePath::parse) {
+            return self.open_remote(remote);
         }
-
+        if let Some(url) = path.to_str().filter(|s| is_http_url(s)) {
+            return self.open_url(url.to_string());
+        }
+        if let Some(i) = self.find_buffer(&path) {
+            return self.switch_buffer(i);
+        }
+        let buf = read_buffer(&path, self.open_encoding)?;
+        self.prepare_buffer_for_open()?;
+        self.doc.buf = buf;
+        self.doc.cursor = Pos { y: 0, x: 0 };
+        self.doc.anchor = None;
+        self.doc.scroll_y = 0;
+        self.doc.scroll_x = 0;
+        self.doc.remote = None;
+        self.doc.url = None;
+        self.doc.read_only = false;
+        self.doc.dirty = false;
+        self.doc.undo.clear();
+        self.doc.redo.clear();
         self.ensure_visible()?;
 
-        let mut plugins = mem::take(&mut self.plugins);
-        plugins.call_hook(self, Hook::OnOpen, Some(&path))?;
-        self.plugins = plugins;
-        self.set_status(format!("Opened: {}", path.display()), Duration::from_secs(2));
+        match zone_note(&path) {
+            Some(note) => self.set_status(note, Duration::from_secs(6)),
+            None => self.set_status(format!("Opened: {}", path.display()), Duration::from_secs(2)),
+        }
+        self.detect_large_file();
+        self.doc.file_path = Some(path);
+        self.detect_file_type();
+        self.load_word_goal();
+        let path = self.doc.file_path.clone();
+        self.run_hook(Hook::Open, path.as_ref())
+    }
+
+    /// Download and open an `scp://` file. Connection errors are shown on the status line.
+    fn open_remote(&mut self, remote: RemotePath) -> Result<()> {
+        let local = match remote.download() {
+            Ok(p) => p,
+            Err(e) => {
+                self.set_error(format!("{e:#}"), Duration::from_secs(5));
+                return Ok(());
+            }
+        };
+        self.open_path(local)?;
+        self.set_status(format!("Opened: {}", remote.url()), Duration::from_secs(2));
+        self.doc.remote = Some(remote);
         Ok(())
     }
 
-    /// Find the next occurrence of query.
+    /// Fetch an HTTP(S) URL into a read-only buffer; Save As is prefilled with its file name.
+    fn open_url(&mut self, url: String) -> Result<()> {
+        let text = match fetch_url(&url) {
+            Ok(t) => t,
+            Err(e) => {
+                self.set_error(format!("{e:#}"), Duration::from_secs(5));
+                return Ok(());
+            }
+        };
+        self.prepare_buffer_for_open()?;
+        self.doc.buf = Buffer::from_string(&text);
+        self.doc.cursor = Pos { y: 0, x: 0 };
+        self.doc.anchor = None;
+        self.doc.scroll_y = 0;
+        self.doc.scroll_x = 0;
+        self.doc.file_path = None;
+        self.doc.remote = None;
+        self.doc.dirty = false;
+        self.doc.undo.clear();
+        self.doc.redo.clear();
+        self.doc.read_only = true;
+        self.set_status(format!("Fetched {url} (read-only)"), Duration::from_secs(3));
+        self.doc.url = Some(url);
+        self.detect_file_type();
+        self.ensure_visible()?;
+
+        self.run_hook(Hook::Open, None)
+    }
+
+    /// Read everything piped into stdin (`kpad -`, `cmd | kpad`) into an unnamed buffer, decoded
+    /// from `--encoding` or as UTF-8 with invalid bytes replaced. Keys are still read from the
+    /// terminal: crossterm opens it itself (`/dev/tty`, `CONIN$`) when stdin is not one.
+    pub fn open_stdin(&mut self) -> Result<()> {
+        let mut bytes = Vec::new();
+        io::stdin().lock().read_to_end(&mut bytes).context("Failed to read stdin")?;
+        self.prepare_buffer_for_open()?;
+        self.doc.buf = match self.open_encoding {
+            Some(encoding) => Buffer::decode(&bytes, encoding),
+            None => Buffer::from_string(&String::from_utf8_lossy(&bytes)),
+        };
+        self.doc.cursor = Pos { y: 0, x: 0 };
+        self.doc.anchor = None;
+        self.doc.scroll_y = 0;
+        self.doc.scroll_x = 0;
+        self.doc.file_path = None;
+        self.doc.remote = None;
+        self.doc.url = None;
+        self.doc.dirty = false;
+        self.doc.undo.clear();
+        self.doc.redo.clear();
+        let lines = self.doc.buf.line_count();
+        self.set_status(format!("Read {lines} lines from stdin."), Duration::from_secs(3));
+        self.detect_file_type();
+        self.ensure_visible()?;
+
+        self.run_hook(Hook::Open, None)
+    }
+
+    /// Open a file forwarded by another `kpad --reuse` invocation (or given as an extra
+    /// command-line argument) in its own buffer. Errors go to the status line and are returned.
+    pub fn open_from_instance(&mut self, path: PathBuf) -> Result<(), String> {
+        self.open_path(path).map_err(|e| {
+            let msg = format!("{e:#}");
+            self.set_error(msg.clone(), Duration::from_secs(4));
+            msg
+        })
+    }
+
+    /// Find the next occurrence of query (the first one in the selection it was started with).
     pub fn find_next(&mut self, query: &str) -> Result<()> {
         if query.is_empty() {
             return Ok(());
         }
         self.last_find = Some(query.to_string());
+        if self.find_scope_range().is_some() {
+            return self.find_in_scope(query, true, true);
+        }
 
-        let start_pos = self.cursor;
+        let start_pos = self.doc.cursor;
         if let Some(p) = self.search_forward(query, start_pos, true) {
-            self.cursor = p;
+            self.push_jump();
+            self.doc.cursor = p;
+            self.clear_selection();
+            self.ensure_visible()?;
+            let position = self.match_position(query);
+            self.set_status(position, Duration::from_secs(2));
+        } else {
+            self.set_error("No matches.", Duration::from_secs(2));
+        }
+        Ok(())
+    }
+
+    /// Jump to the next (`forward`) or previous match of the last search without opening the
+    /// Find prompt (F3 / Shift+F3).
+    pub fn repeat_find(&mut self, forward: bool) -> Result<()> {
+        let Some(query) = self.last_find.clone().filter(|q| !q.is_empty()) else {
+            self.set_status("No previous search. Press Ctrl+F to find.", Duration::from_secs(2));
+            return Ok(());
+        };
+        if self.find_scope_range().is_some() {
+            return self.find_in_scope(&query, forward, false);
+        }
+        let found = if forward {
+            // Start one char in so a cursor sitting on a match moves past it.
+            let from = self.doc.buf.pos_to_char_idx_public(self.doc.cursor) + 1;
+            if from >= self.doc.buf.text.len_chars() {
+                self.search_forward(&query, Pos { y: 0, x: 0 }, false)
+            } else {
+                self.search_forward(&query, self.doc.buf.char_idx_to_pos_public(from), true)
+            }
+        } else {
+            self.search_backward(&query, self.doc.cursor, true)
+        };
+        if let Some(p) = found {
+            self.push_jump();
+            self.doc.cursor = p;
             self.clear_selection();
             self.ensure_visible()?;
-            self.set_status("Match found.", Duration::from_secs(1));
+            let position = self.match_position(&query);
+            self.set_status(format!("{position}: {query}"), Duration::from_secs(2));
         } else {
-            self.set_status("No matches.", Duration::from_secs(2));
+            self.set_error(format!("No matches: {query}"), Duration::from_secs(2));
         }
         Ok(())
     }
 
+    /// Find the first match of `re` at or after `from` (no wrap-around).
+    pub fn find_regex(&self, re: &regex::Regex, from: Pos) -> Option<Pos> {
+        for y in from.y..self.doc.buf.line_count() {
+            let line = self.doc.buf.line(y);
+            let start_byte = if y == from.y {
+                line.char_indices().nth(from.x).map_or(line.len(), |(b, _)| b)
+            } else {
+                0
+            };
+            if let Some(m) = re.find_at(&line, start_byte) {
+                return Some(Pos { y, x: line[..m.start()].chars().count() });
+            }
+        }
+        None
+    }
+
     /// Search forward for query using optimized Rope traversal.
     /// Avoids line-by-line iteration by searching through the entire text.
     pub fn search_forward(&self, query: &str, from: Pos, wrap: bool) -> Option<Pos> {
@@ -95,19 +288,38 @@ impl Editor {
         }
 
         // Convert starting position to char index
-        let start_idx = self.buf.pos_to_char_idx_public(from);
+        let start_idx = self.doc.buf.pos_to_char_idx_public(from);
 
         // Search from cursor to end
-        if let Some(match_idx) = self.buf.search_from(query, start_idx) {
-            return Some(self.buf.char_idx_to_pos_public(match_idx));
+        if let Some(match_idx) = self.doc.buf.search_from(query, start_idx) {
+            return Some(self.doc.buf.char_idx_to_pos_public(match_idx));
         }
 
         // Wrap around: search from beginning to cursor
         if wrap && start_idx > 0 {
-            if let Some(match_idx) = self.buf.search_from(query, 0) {
+            if let Some(match_idx) = self.doc.buf.search_from(query, 0) {
                 // Only return if match is before original position
                 if match_idx < start_idx {
-                    return Some(self.buf.char_idx_to_pos_public(match_idx));
+                    return Some(self.doc.buf.char_idx_to_pos_public(match_idx));
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Search backward for a string, optionally wrapping around to the end.
+    pub fn search_backward(&self, query: &str, from: Pos, wrap: bool) -> Option<Pos> {
+        let start_idx = self.doc.buf.pos_to_char_idx_public(from);
+        if let Some(match_idx) = self.doc.buf.search_backward(query, start_idx) {
+            return Some(self.doc.buf.char_idx_to_pos_public(match_idx));
+        }
+
+        // Wrap around: the last match in the document, if it is after the original position
+        if wrap {
+            if let Some(match_idx) = self.doc.buf.search_backward(query, self.doc.buf.text.len_chars()) {
+                if match_idx > start_idx {
+                    return Some(self.doc.buf.char_idx_to_pos_public(match_idx));
                 }
             }
         }
@@ -0,0 +1,148 @@
+//! File information overlay (`file_info`): where the current file is, its size, modification
+//! time, permissions, encoding, line endings, SHA-256 checksum, and whether the buffer still
+//! matches it.
+
+use super::Editor; // editor state
+use anyhow::Result; // anyhow error handling
+use crossterm::{ // terminal manipulation
+    cursor,
+    style::{self, Color},
+    terminal::{self, ClearType},
+    QueueableCommand,
+};
+use sha2::{Digest, Sha256}; // file checksum
+use std::fs::{self, Metadata}; // file metadata
+use std::io::{Stdout, Write}; // output handling
+use std::path::Path; // file path
+use std::time::{Duration, UNIX_EPOCH}; // modification time, status messages
+
+/// `YYYY-MM-DD HH:MM:SS UTC` for `secs` seconds since the Unix epoch.
+fn format_utc(secs: u64) -> String {
+    let days = i64::try_from(secs / 86_400).unwrap_or(i64::MAX);
+    let rem = secs % 86_400;
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02} UTC", rem / 3600, rem / 60 % 60, rem % 60)
+}
+
+/// Permissions as shown by `ls -l` with the octal mode, e.g. `rw-r--r-- (644)`.
+#[cfg(unix)]
+fn permissions(meta: &Metadata) -> String {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = meta.permissions().mode() & 0o777;
+    let bits: String = (0..9).rev()
+        .map(|i| if mode & (1 << i) == 0 { '-' } else { ['x', 'w', 'r'][i % 3] })
+        .collect();
+    format!("{bits} ({mode:o})")
+}
+
+/// The read-only attribute (the only permission Windows keeps per file).
+#[cfg(not(unix))]
+fn permissions(meta: &Metadata) -> String {
+    if meta.permissions().readonly() { "read-only attribute set" } else { "writable" }.to_string()
+}
+
+/// Encoding of the file's bytes.
+fn encoding(bytes: &[u8]) -> &'static str {
+    match bytes.strip_prefix(b"\xEF\xBB\xBF") {
+        Some(_) => "UTF-8 with BOM",
+        None if std::str::from_utf8(bytes).is_ok() => "UTF-8",
+        None => "not valid UTF-8",
+    }
+}
+
+impl Editor {
+    /// Open the overlay for the current file (`file_info` command).
+    pub fn show_file_info(&mut self) {
+        let Some(path) = self.doc.file_path.clone() else {
+            self.set_status("The buffer has no file on disk.", Duration::from_secs(2));
+            return;
+        };
+        match self.file_info_lines(&path) {
+            Ok(lines) => {
+                self.file_info = Some(lines);
+                self.mark_redraw();
+            }
+            Err(e) => self.set_status(format!("{}: {e}", path.display()), Duration::from_secs(3)),
+        }
+    }
+
+    /// The overlay text; read once when it opens.
+    fn file_info_lines(&self, path: &Path) -> std::io::Result<Vec<String>> {
+        let meta = fs::metadata(path)?;
+        let bytes = fs::read(path)?;
+        let mut buffer = Vec::new();
+        self.doc.buf.write_to(&mut buffer)?;
+
+        let modified = meta.modified().ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map_or_else(|| "unknown".to_string(), |d| format_utc(d.as_secs()));
+        let full = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let state = if buffer == bytes { "same as on disk" } else { "differs from disk" };
+        let remote = self.doc.remote.as_ref().map(|r| format!("  Remote:      {}", r.url()));
+
+        let mut lines = vec![
+            " FILE INFO ".to_string(),
+            "===========".to_string(),
+            String::new(),
+            format!("  Path:        {}", full.display()),
+        ];
+        lines.extend(remote);
+        lines.extend([
+            format!("  Size:        {} bytes on disk", meta.len()),
+            format!("  Modified:    {modified}"),
+            format!("  Permissions: {}", permissions(&meta)),
+            format!("  Read-only:   {}", if meta.permissions().readonly() { "yes" } else { "no" }),
+            format!("  Encoding:    {}", encoding(&bytes)),
+            format!("  End of Line: {}", self.doc.buf.line_ending.name()),
+            format!("  SHA-256:     {:x}", Sha256::digest(&bytes)),
+            format!("  Buffer:      {state}{}", if self.doc.dirty { " (unsaved changes)" } else { "" }),
+            String::new(),
+            " Press any key to close... ".to_string(),
+        ]);
+        Ok(lines)
+    }
+
+    /// Render the file info overlay.
+    pub fn render_file_info(&mut self, stdout: &mut Stdout) -> Result<()> {
+        let Some(lines) = &self.file_info else { return Ok(()); };
+        let (w, h) = terminal::size()?;
+
+        stdout.queue(cursor::Hide)?;
+        stdout.queue(style::SetBackgroundColor(Color::DarkMagenta))?;
+        stdout.queue(style::SetForegroundColor(Color::White))?;
+        stdout.queue(terminal::Clear(ClearType::All))?;
+
+        let start_y = usize::from(h).saturating_sub(lines.len()) / 2;
+        for (i, line) in lines.iter().enumerate() {
+            let Ok(y) = u16::try_from(start_y + i) else { break; };
+            stdout.queue(cursor::MoveTo(1, y))?;
+            stdout.queue(style::Print(line.chars().take(usize::from(w).saturating_sub(1)).collect::<String>()))?;
+        }
+
+        stdout.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_times_and_encodings() {
+        assert_eq!(format_utc(0), "1970-01-01 00:00:00 UTC");
+        assert_eq!(format_utc(951_782_400 + 3_723), "2000-02-29 01:02:03 UTC");
+        assert_eq!(encoding(b"\xEF\xBB\xBFhi"), "UTF-8 with BOM");
+        assert_eq!(encoding(b"caf\xC3\xA9"), "UTF-8");
+        assert_eq!(encoding(b"caf\xE9"), "not valid UTF-8");
+    }
+}
@@ -0,0 +1,183 @@
+//! File-type support contributed by plugin `[[filetypes]]` bundles: highlight rules, comment
+//! toggling and the build command.
+//!
+//! The file type follows the file name: it is detected again whenever the name changes (open,
+//! Save As, buffer switch), so saving an unnamed buffer as `notes.md` highlights it as Markdown.
+
+use crate::bus::Message; // finished builds
+use crate::plugins::FileType; // plugin file-type bundle
+use crate::remote::url_file_name; // file name of a fetched URL
+use super::langs::language_name; // built-in language names
+use super::Editor; // editor state
+use anyhow::Result; // anyhow error handling
+use std::io; // build command errors
+use std::path::Path; // file extensions
+use std::process::{Command, Output}; // running build commands
+use std::thread; // background builds
+use std::time::Duration; // timing for status messages
+
+/// `path` quoted for the build shell, so spaces and shell characters in a file name stay part
+/// of the name.
+fn shell_quote(path: &str) -> String {
+    if cfg!(windows) {
+        // Windows file names cannot contain `"`.
+        format!("\"{path}\"")
+    } else {
+        format!("'{}'", path.replace('\'', r"'\''"))
+    }
+}
+
+/// Run a build command line in the platform shell and wait for it.
+fn run_shell(cmdline: &str) -> io::Result<Output> {
+    if cfg!(windows) {
+        Command::new("cmd").args(["/C", cmdline]).output()
+    } else {
+        Command::new("sh").args(["-c", cmdline]).output()
+    }
+}
+
+/// Status line report of a finished build.
+fn build_report(cmdline: &str, output: io::Result<Output>) -> String {
+    match output {
+        Ok(out) if out.status.success() => format!("Build succeeded: {cmdline}"),
+        Ok(out) => {
+            let stderr = String::from_utf8_lossy(&out.stderr);
+            let stdout = String::from_utf8_lossy(&out.stdout);
+            let last_line = |s: &str| s.lines().rfind(|l| !l.trim().is_empty()).map(str::to_string);
+            let last = last_line(&stderr).or_else(|| last_line(&stdout)).unwrap_or_default();
+            format!("Build failed ({}): {}", out.status, last.trim())
+        }
+        Err(e) => format!("Build could not start: {e}"),
+    }
+}
+
+impl Editor {
+    /// Register the highlight rules of every plugin file type.
+    ///
+    /// Rules are keyed by extension, so they apply automatically to any file opened later.
+    pub(crate) fn apply_plugin_filetypes(&mut self) {
+        for ft in self.plugins.filetypes() {
+            for ext in &ft.extensions {
+                for rule in &ft.highlights {
+                    self.highlighter.register_rule(ext, rule.clone());
+                }
+            }
+        }
+    }
+
+    /// Lowercase extension of the file name, or of the name in a fetched URL.
+    pub(crate) fn file_extension(&self) -> String {
+        let name = self.doc.file_path.clone().or_else(|| self.doc.url.as_deref().map(|u| url_file_name(u).into()));
+        name.as_deref().and_then(Path::extension).map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default()
+    }
+
+    /// Pick the highlight rules for the current file name and recolor the buffer.
+    pub(crate) fn detect_file_type(&mut self) {
+        let ext = self.file_extension();
+        self.highlighter.set_file_extension(&ext);
+        self.highlighter.invalidate_all();
+        self.mark_redraw();
+    }
+
+    /// Language shown in the status bar: a built-in language's name, or the extension of a
+    /// plugin file type.
+    pub(crate) fn language_label(&self) -> Option<String> {
+        let ext = self.file_extension();
+        match language_name(&ext) {
+            Some(name) => Some(name.to_string()),
+            None => self.plugins.filetype_for(&ext).map(|_| ext.to_uppercase()),
+        }
+    }
+
+    /// The plugin file type of the current file, if any.
+    pub(crate) fn current_filetype(&self) -> Option<&FileType> {
+        let ext = self.doc.file_path.as_ref()?.extension()?.to_str()?;
+        self.plugins.filetype_for(ext)
+    }
+
+    /// Comment or uncomment the selected lines (or the cursor line) with the file type's prefix.
+    ///
+    /// If every non-blank line is already commented the prefix is removed, otherwise it is added.
+    pub fn toggle_comment(&mut self) -> Result<()> {
+        if !self.ensure_writable() { return Ok(()); }
+        let Some(prefix) = self.current_filetype().and_then(|ft| ft.comment.clone()) else {
+            self.set_status("No comment prefix for this file type.", Duration::from_secs(2));
+            return Ok(());
+        };
+        let (first, last) = match self.selection_range() {
+            // A selection ending at column 0 does not include that line.
+            Some((a, b)) if b.x == 0 && b.y > a.y => (a.y, b.y - 1),
+            Some((a, b)) => (a.y, b.y),
+            None => (self.doc.cursor.y, self.doc.cursor.y),
+        };
+
+        let lines: Vec<String> = (first..=last).map(|y| self.doc.buf.line(y).into_owned()).collect();
+        let commented = |l: &str| l.trim_start().starts_with(prefix.as_str());
+        let uncomment = lines.iter().filter(|l| !l.trim().is_empty()).all(|l| commented(l));
+
+        let new_lines: Vec<String> = lines.iter().map(|l| {
+            let indent = l.len() - l.trim_start().len();
+            let (lead, rest) = l.split_at(indent);
+            if rest.is_empty() {
+                l.clone()
+            } else if uncomment {
+                let rest = &rest[prefix.len()..];
+                format!("{lead}{}", rest.strip_prefix(' ').unwrap_or(rest))
+            } else {
+                format!("{lead}{prefix} {rest}")
+            }
+        }).collect();
+
+        self.replace_lines(first, last, &new_lines.join("\n"));
+        self.doc.cursor.x = self.doc.cursor.x.min(self.doc.buf.line_len_chars(self.doc.cursor.y));
+        if let Some(a) = self.doc.anchor.as_mut() {
+            a.x = a.x.min(self.doc.buf.line_len_chars(a.y));
+        }
+        self.ensure_visible()
+    }
+
+    /// Save, then run the file type's build command and report the result on the status line.
+    /// The build runs in the background (`build_done` reports it); headless it runs to the end
+    /// right away.
+    pub fn run_build(&mut self) -> Result<()> {
+        let Some(build) = self.current_filetype().and_then(|ft| ft.build.clone()) else {
+            self.set_status("No build command for this file type.", Duration::from_secs(2));
+            return Ok(());
+        };
+        if self.doc.dirty {
+            self.cmd_save()?;
+        }
+        let Some(path) = self.doc.file_path.clone() else { return Ok(()); };
+        let cmdline = build.replace("{file}", &shell_quote(&path.display().to_string()));
+
+        let Some(tx) = self.bus.clone() else {
+            self.build_done(build_report(&cmdline, run_shell(&cmdline)));
+            return Ok(());
+        };
+        self.set_status(format!("Building: {cmdline}"), Duration::from_secs(10));
+        thread::spawn(move || {
+            let report = build_report(&cmdline, run_shell(&cmdline));
+            let _ = tx.send(Message::BuildDone(report));
+        });
+        Ok(())
+    }
+
+    /// Show the report of a finished build.
+    pub fn build_done(&mut self, report: String) {
+        self.set_status(report, Duration::from_secs(5));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn quoted_paths_stay_one_argument() {
+        for name in ["my file.rs", "x;echo hacked|sh;.rs", "it's $HOME.rs"] {
+            let out = run_shell(&format!("printf %s {}", shell_quote(name))).unwrap();
+            assert_eq!(String::from_utf8_lossy(&out.stdout), name);
+        }
+    }
+}
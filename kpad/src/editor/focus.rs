@@ -0,0 +1,97 @@
+//! Timed writing sessions: `pomodoro` asks for a number of minutes (25 by default, 0 stops the
+//! timer) and the status bar counts down. When time is up the status bar flashes, and the
+//! terminal bell rings if `bell` in kpad.toml is audible. The countdown is driven by `tick`,
+//! like status-message expiry.
+
+use crate::types::{Prompt, PromptKind}; // minutes prompt
+use super::Editor; // editor state
+use std::time::{Duration, Instant}; // countdown
+
+/// Minutes offered by the prompt.
+const DEFAULT_MINUTES: u64 = 25;
+
+/// A running focus session.
+pub(crate) struct FocusTimer {
+    ends: Instant,
+    minutes: u64,
+    /// Whole seconds left when the status bar was last drawn.
+    shown: u64,
+}
+
+/// `MM:SS` (or `H:MM:SS`) for `secs` seconds.
+fn clock(secs: u64) -> String {
+    let (h, m, s) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if h > 0 { format!("{h}:{m:02}:{s:02}") } else { format!("{m:02}:{s:02}") }
+}
+
+/// Whole seconds left until `ends`, rounded up so the clock reaches 00:00 at the end.
+fn secs_left(ends: Instant, now: Instant) -> u64 {
+    let left = ends.saturating_duration_since(now);
+    left.as_secs() + u64::from(left.subsec_nanos() > 0)
+}
+
+impl Editor {
+    /// Ask how long the focus session runs (`pomodoro`).
+    pub fn prompt_pomodoro(&mut self) {
+        let minutes = self.focus.as_ref().map_or(DEFAULT_MINUTES, |f| f.minutes);
+        self.prompt = Some(Prompt::new(PromptKind::Pomodoro, minutes.to_string()));
+        self.mark_redraw();
+    }
+
+    /// Start a focus session of `minutes`, replacing a running one; 0 stops the timer.
+    pub fn start_pomodoro(&mut self, minutes: u64) {
+        if minutes == 0 {
+            let msg = if self.focus.take().is_some() { "Focus session stopped." } else { "No focus session running." };
+            self.set_status(msg, Duration::from_secs(2));
+            return;
+        }
+        let ends = Instant::now() + Duration::from_secs(minutes.saturating_mul(60));
+        self.focus = Some(FocusTimer { ends, minutes, shown: minutes.saturating_mul(60) });
+        self.set_status(format!("Focus session: {minutes} min"), Duration::from_secs(2));
+    }
+
+    /// Count down the focus session; called from `tick`.
+    pub(crate) fn advance_focus_timer(&mut self) {
+        let Some(focus) = &mut self.focus else { return; };
+        let left = secs_left(focus.ends, Instant::now());
+        if left == focus.shown { return; }
+        focus.shown = left;
+        let minutes = focus.minutes;
+        self.mark_redraw();
+        if left == 0 {
+            self.focus = None;
+            self.set_alert(format!("Time's up! {minutes} min focus session done."), Duration::from_secs(10));
+        }
+    }
+
+    /// When the countdown next changes, for `next_wakeup`.
+    pub(crate) fn focus_deadline(&self) -> Option<Instant> {
+        let focus = self.focus.as_ref()?;
+        let left = focus.ends.saturating_duration_since(Instant::now());
+        Some(Instant::now() + Duration::from_nanos(u64::from(left.subsec_nanos())))
+    }
+
+    /// Time left for the status bar, if a session is running.
+    pub(crate) fn focus_countdown(&self) -> Option<String> {
+        let focus = self.focus.as_ref()?;
+        Some(format!("Focus {}", clock(secs_left(focus.ends, Instant::now()))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_down_in_whole_seconds() {
+        assert_eq!(clock(25 * 60), "25:00");
+        assert_eq!(clock(61), "01:01");
+        assert_eq!(clock(0), "00:00");
+        assert_eq!(clock(90 * 60 + 5), "1:30:05");
+
+        let now = Instant::now();
+        assert_eq!(secs_left(now + Duration::from_millis(1500), now), 2);
+        assert_eq!(secs_left(now + Duration::from_secs(3), now), 3);
+        assert_eq!(secs_left(now, now + Duration::from_secs(1)), 0);
+    }
+}
@@ -0,0 +1,96 @@
+//! Word-count goal for writing sessions: `goal` asks for a target number of words (0 clears
+//! it), and the status bar then shows the words written since the file was opened and how much
+//! of the goal that is. Goals are remembered per file in `<state dir>/goals.json`.
+
+use crate::plugins::PluginStore; // goals.json in the state directory
+use crate::types::{Prompt, PromptKind}; // goal prompt
+use super::Editor; // editor state
+use std::path::Path; // file paths
+use std::time::Duration; // timing for status messages
+
+/// Word-count goal of a buffer.
+pub(crate) struct WordGoal {
+    /// Words to write this session.
+    target: usize,
+    /// Word count when the session started (file opened or goal first set).
+    start: usize,
+}
+
+/// Status-bar text for `written` words of a `target`-word goal.
+fn progress_text(written: usize, target: usize) -> String {
+    format!("Goal {written}/{target} ({}%)", written * 100 / target.max(1))
+}
+
+/// Key of `path` in the goal store.
+fn goal_key(path: &Path) -> String {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf()).display().to_string()
+}
+
+impl Editor {
+    /// Words in the active buffer (whitespace-separated, like the stats screen).
+    fn word_count(&self) -> usize {
+        (0..self.doc.buf.line_count()).map(|y| self.doc.buf.line(y).split_whitespace().count()).sum()
+    }
+
+    /// Ask for the word goal of the active buffer (`goal`).
+    pub fn prompt_word_goal(&mut self) {
+        let current = self.doc.word_goal.as_ref().map_or(0, |g| g.target);
+        self.prompt = Some(Prompt::new(PromptKind::WordGoal, current.to_string()));
+        self.mark_redraw();
+    }
+
+    /// Set the word goal of the active buffer and remember it for the file; 0 clears it.
+    /// Words already written this session keep counting.
+    pub fn set_word_goal(&mut self, target: usize) {
+        if target == 0 {
+            self.doc.word_goal = None;
+        } else {
+            let start = self.doc.word_goal.as_ref().map_or_else(|| self.word_count(), |g| g.start);
+            self.doc.word_goal = Some(WordGoal { target, start });
+        }
+        let msg = if target == 0 { "Word goal cleared.".to_string() } else { format!("Word goal: {target} words") };
+        let saved = match &self.doc.file_path {
+            Some(path) => PluginStore::named("goals").and_then(|store| {
+                store.set(&goal_key(path), (target > 0).then(|| target.into()))
+            }),
+            None => Ok(()),
+        };
+        match saved {
+            Ok(()) => self.set_status(msg, Duration::from_secs(2)),
+            Err(e) => self.set_status(format!("{msg} (not remembered: {e:#})"), Duration::from_secs(4)),
+        }
+        self.mark_redraw();
+    }
+
+    /// Pick up the remembered goal of a newly opened file; the session starts now.
+    pub(crate) fn load_word_goal(&mut self) {
+        let Some(path) = &self.doc.file_path else { return; };
+        // A missing or unreadable store just means no goal.
+        let target = PluginStore::named("goals")
+            .and_then(|store| store.get(&goal_key(path)))
+            .ok()
+            .flatten()
+            .and_then(|v| v.as_u64())
+            .and_then(|n| usize::try_from(n).ok())
+            .filter(|&n| n > 0);
+        self.doc.word_goal = target.map(|target| WordGoal { target, start: self.word_count() });
+    }
+
+    /// Goal progress for the status bar, if the buffer has a goal.
+    pub(crate) fn word_goal_progress(&self) -> Option<String> {
+        let goal = self.doc.word_goal.as_ref()?;
+        Some(progress_text(self.word_count().saturating_sub(goal.start), goal.target))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shows_progress_toward_the_goal() {
+        assert_eq!(progress_text(0, 1000), "Goal 0/1000 (0%)");
+        assert_eq!(progress_text(250, 1000), "Goal 250/1000 (25%)");
+        assert_eq!(progress_text(1500, 1000), "Goal 1500/1000 (150%)");
+    }
+}
@@ -0,0 +1,252 @@
+//! Find in files: `grep` asks for a regex and searches every file under the working directory
+//! on a background thread. Hits stream into a full-screen list as they are found (posted to the
+//! main loop as `Message::GrepHits`); Enter opens the file at the match. Hidden directories,
+//! `target` and `node_modules`, binary files and files over 4 MB are skipped.
+
+use crate::bus::Message; // results posted to the main loop
+use crate::types::{Pos, Prompt, PromptKind}; // match positions, pattern prompt
+use super::Editor; // editor state
+use anyhow::Result; // anyhow error handling
+use crossterm::{ // terminal manipulation
+    cursor,
+    event::{KeyCode, KeyEvent},
+    style::{self, Color},
+    terminal::{self, ClearType},
+    QueueableCommand,
+};
+use regex::Regex; // search pattern
+use std::fs; // reading files
+use std::io::{Stdout, Write}; // output handling
+use std::path::{Path, PathBuf}; // file paths
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering}; // cancellation, search ids
+use std::sync::Arc; // flag shared with the search thread
+use std::thread; // background search
+use std::time::Duration; // timing for status messages
+
+/// Directories never searched, besides hidden ones.
+const SKIP_DIRS: [&str; 2] = ["target", "node_modules"];
+
+/// Larger files are skipped.
+const MAX_FILE_BYTES: u64 = 4 * 1024 * 1024;
+
+/// The search stops after this many hits.
+const MAX_HITS: usize = 10_000;
+
+/// Id of the next search; hits of an older search are dropped.
+static NEXT_SEARCH: AtomicU64 = AtomicU64::new(1);
+
+/// One matching line.
+pub struct GrepHit {
+    /// Path relative to the searched directory.
+    path: PathBuf,
+    /// 1-based line number.
+    line: usize,
+    /// Char column where the match starts.
+    col: usize,
+    /// The line's text.
+    text: String,
+}
+
+/// The results list of a search.
+pub(crate) struct GrepView {
+    search: u64,
+    pattern: String,
+    root: PathBuf,
+    hits: Vec<GrepHit>,
+    selected: usize,
+    done: bool,
+    /// Tells the search thread to stop; set when the list goes away.
+    cancel: Arc<AtomicBool>,
+}
+
+impl Drop for GrepView {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Hits of `re` in `text`, the contents of `path`.
+fn grep_text(path: &Path, text: &str, re: &Regex) -> Vec<GrepHit> {
+    text.lines().enumerate().filter_map(|(i, line)| {
+        let m = re.find(line)?;
+        Some(GrepHit { path: path.to_path_buf(), line: i + 1, col: line[..m.start()].chars().count(), text: line.to_string() })
+    }).collect()
+}
+
+/// Search the files under `dir` (shown relative to `root`) in name order, handing each file's
+/// hits to `found`. Stops, returning `false`, once `found` does.
+fn walk(root: &Path, dir: &Path, re: &Regex, found: &mut dyn FnMut(Vec<GrepHit>) -> bool) -> bool {
+    let Ok(entries) = fs::read_dir(dir) else { return true; };
+    let mut entries: Vec<_> = entries.filter_map(std::result::Result::ok).collect();
+    entries.sort_by_key(std::fs::DirEntry::file_name);
+    for entry in entries {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        let Ok(kind) = entry.file_type() else { continue; };
+        let path = entry.path();
+        if kind.is_dir() {
+            if name.starts_with('.') || SKIP_DIRS.contains(&name.as_ref()) { continue; }
+            if !walk(root, &path, re, found) { return false; }
+        } else if kind.is_file() && entry.metadata().is_ok_and(|m| m.len() <= MAX_FILE_BYTES) {
+            // Unreadable and binary (NUL or invalid UTF-8) files are skipped.
+            let Ok(bytes) = fs::read(&path) else { continue; };
+            if bytes.contains(&0) { continue; }
+            let Ok(text) = String::from_utf8(bytes) else { continue; };
+            let hits = grep_text(path.strip_prefix(root).unwrap_or(&path), &text, re);
+            if !hits.is_empty() && !found(hits) { return false; }
+        }
+    }
+    true
+}
+
+impl Editor {
+    /// Ask for the pattern to search the working directory for (`grep`).
+    pub fn prompt_grep(&mut self) {
+        let word = self.selected_text();
+        let initial = if word.contains('\n') { String::new() } else { regex::escape(&word) };
+        self.prompt = Some(Prompt::new(PromptKind::Grep, initial));
+        self.mark_redraw();
+    }
+
+    /// Start searching the working directory for `pattern` and show the results list. Without
+    /// a message bus (headless) the search runs to the end right away.
+    pub fn start_grep(&mut self, pattern: &str) {
+        if pattern.is_empty() { return; }
+        let re = match Regex::new(pattern) {
+            Ok(re) => re,
+            Err(e) => {
+                self.set_error(format!("Invalid pattern: {e}"), Duration::from_secs(4));
+                return;
+            }
+        };
+        let root = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        let search = NEXT_SEARCH.fetch_add(1, Ordering::Relaxed);
+        let cancel = Arc::new(AtomicBool::new(false));
+        let mut view = GrepView { search, pattern: pattern.to_string(), root: root.clone(), hits: Vec::new(), selected: 0, done: false, cancel: Arc::clone(&cancel) };
+        if let Some(tx) = self.bus.clone() {
+            thread::spawn(move || {
+                let mut total = 0;
+                walk(&root, &root, &re, &mut |hits| {
+                    total += hits.len();
+                    !cancel.load(Ordering::Relaxed)
+                        && tx.send(Message::GrepHits { search, hits, done: false }).is_ok()
+                        && total < MAX_HITS
+                });
+                let _ = tx.send(Message::GrepHits { search, hits: Vec::new(), done: true });
+            });
+        } else {
+            walk(&root, &root, &re, &mut |hits| {
+                view.hits.extend(hits);
+                view.hits.len() < MAX_HITS
+            });
+            view.done = true;
+        }
+        self.grep_view = Some(view);
+        self.mark_redraw();
+    }
+
+    /// Hits found by search `search`; `done` when it finished.
+    pub fn add_grep_hits(&mut self, search: u64, hits: Vec<GrepHit>, done: bool) {
+        let Some(view) = self.grep_view.as_mut().filter(|v| v.search == search) else { return; };
+        view.hits.extend(hits);
+        view.done |= done;
+        self.mark_redraw();
+    }
+
+    /// Keys in the results list: Up/Down, PageUp/PageDown, Home/End select; Enter opens the
+    /// file at the match; Esc or q close the list (stopping the search).
+    pub(crate) fn handle_grep_key(&mut self, key: KeyEvent) -> Result<()> {
+        let page = usize::from(self.screen_size().map_or(24, |(_, h)| h)).saturating_sub(3).max(1);
+        let Some(view) = self.grep_view.as_mut() else { return Ok(()); };
+        let last = view.hits.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.grep_view = None,
+            KeyCode::Up => view.selected = view.selected.saturating_sub(1),
+            KeyCode::Down => view.selected = (view.selected + 1).min(last),
+            KeyCode::PageUp => view.selected = view.selected.saturating_sub(page),
+            KeyCode::PageDown => view.selected = (view.selected + page).min(last),
+            KeyCode::Home => view.selected = 0,
+            KeyCode::End => view.selected = last,
+            KeyCode::Enter => {
+                let Some(hit) = view.hits.get(view.selected) else { return Ok(()); };
+                let (path, pos) = (view.root.join(&hit.path), Pos { y: hit.line - 1, x: hit.col });
+                self.grep_view = None;
+                self.mark_redraw();
+                if let Err(e) = self.open_path(path) {
+                    self.set_error(format!("{e:#}"), Duration::from_secs(4));
+                    return Ok(());
+                }
+                self.doc.cursor = self.doc.buf.clamp_pos(pos);
+                self.clear_selection();
+                return self.ensure_visible();
+            }
+            _ => {}
+        }
+        self.mark_redraw();
+        Ok(())
+    }
+
+    /// Render the results list: title row, one row per hit, key hint row.
+    pub fn render_grep(&mut self, stdout: &mut Stdout) -> Result<()> {
+        let Some(view) = &self.grep_view else { return Ok(()); };
+        let (w, h) = terminal::size()?;
+        let width = usize::from(w);
+
+        stdout.queue(cursor::Hide)?;
+        stdout.queue(style::ResetColor)?;
+        stdout.queue(terminal::Clear(ClearType::All))?;
+
+        let state = if view.done { "" } else { ", searching..." };
+        let title = format!(" GREP: {} ({} matches{state})", view.pattern, view.hits.len());
+        stdout.queue(cursor::MoveTo(0, 0))?;
+        stdout.queue(style::SetBackgroundColor(self.theme.status_bg))?;
+        stdout.queue(style::SetForegroundColor(self.theme.status_fg))?;
+        stdout.queue(style::Print(format!("{:<width$}", title.chars().take(width).collect::<String>())))?;
+        stdout.queue(style::ResetColor)?;
+
+        // The list scrolls to keep the selected hit on screen.
+        let list_h = usize::from(h).saturating_sub(2).max(1);
+        let skip = (view.selected + 1).saturating_sub(list_h);
+        for (row, (i, hit)) in (1..h.saturating_sub(1)).zip(view.hits.iter().enumerate().skip(skip)) {
+            let location = format!(" {}:{}: ", hit.path.display(), hit.line);
+            let text: String = location.chars().chain(hit.text.trim_end().chars().map(|c| if c == '\t' { ' ' } else { c })).take(width).collect();
+            stdout.queue(cursor::MoveTo(0, row))?;
+            if i == view.selected {
+                stdout.queue(style::SetBackgroundColor(Color::White))?;
+                stdout.queue(style::SetForegroundColor(Color::Black))?;
+                stdout.queue(style::Print(format!("{text:<width$}")))?;
+                stdout.queue(style::ResetColor)?;
+            } else {
+                stdout.queue(style::SetForegroundColor(Color::Cyan))?;
+                stdout.queue(style::Print(&location))?;
+                stdout.queue(style::ResetColor)?;
+                stdout.queue(style::Print(text.chars().skip(location.chars().count()).collect::<String>()))?;
+            }
+        }
+        if view.hits.is_empty() && view.done {
+            stdout.queue(cursor::MoveTo(0, 1))?;
+            stdout.queue(style::Print("   (no matches)"))?;
+        }
+
+        stdout.queue(cursor::MoveTo(0, h.saturating_sub(1)))?;
+        stdout.queue(style::SetForegroundColor(Color::DarkGrey))?;
+        stdout.queue(style::Print(" Up/Down PgUp/PgDn Home/End select   Enter open   Esc close"))?;
+        stdout.queue(style::ResetColor)?;
+        stdout.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_matching_lines() {
+        let re = Regex::new("fo+").unwrap();
+        let hits = grep_text(Path::new("src/a.rs"), "bar\nlet é = foo;\n\nfoo", &re);
+        let found: Vec<_> = hits.iter().map(|h| (h.line, h.col, h.text.as_str())).collect();
+        assert_eq!(found, [(2, 8, "let é = foo;"), (4, 0, "foo")]);
+        assert_eq!(hits[0].path, Path::new("src/a.rs"));
+    }
+}
@@ -1,4 +1,5 @@
-//! Syntax highlighting system for plugin-registered rules.
+//! Syntax highlighting system for plugin-registered rules, with the built-in language packs
+//! (`langs.rs`) as fallback.
 
 use crate::types::{HighlightColor, HighlightRule, HighlightSpan}; // syntax highlighting types
 use crossterm::style::Color; // terminal colors
@@ -33,12 +34,31 @@ struct CompiledRule {
     group: usize,
 }
 
+/// Compile a rule; invalid patterns are reported on stderr and skipped.
+fn compile_rule(rule: HighlightRule) -> Option<CompiledRule> {
+    let regex = match Regex::new(&rule.pattern) {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("Invalid highlight pattern '{}': {}", rule.pattern, e);
+            return None;
+        }
+    };
+    Some(CompiledRule {
+        regex,
+        color: rule.color,
+        priority: rule.priority,
+        group: rule.group,
+    })
+}
+
 /// Manages syntax highlighting rules registered by plugins.
 #[derive(Default)]
 pub struct Highlighter {
     /// Rules grouped by file extension (e.g., "md", "rs").
     /// Empty string key "" means applies to all files.
     rules_by_ext: HashMap<String, Vec<CompiledRule>>,
+    /// Built-in rules by extension, used when no plugin registered rules for it.
+    builtin_by_ext: HashMap<String, Vec<CompiledRule>>,
     /// Cache of computed highlights per line (cleared on edit).
     cache: HashMap<usize, Vec<HighlightSpan>>,
     /// Current file extension being edited.
@@ -55,22 +75,7 @@ impl Highlighter {
     /// Use "" for rules that apply to all files.
     pub fn register_rule(&mut self, extension: &str, rule: HighlightRule) {
         let ext = extension.to_lowercase();
-
-        // Try to compile the regex
-        let regex = match Regex::new(&rule.pattern) {
-            Ok(r) => r,
-            Err(e) => {
-                eprintln!("Invalid highlight pattern '{}': {}", rule.pattern, e);
-                return;
-            }
-        };
-
-        let compiled = CompiledRule {
-            regex,
-            color: rule.color,
-            priority: rule.priority,
-            group: rule.group,
-        };
+        let Some(compiled) = compile_rule(rule) else { return; };
 
         self.rules_by_ext
             .entry(ext)
@@ -81,6 +86,13 @@ impl Highlighter {
         self.cache.clear();
     }
 
+    /// Register a built-in fallback rule (kept across `clear_all_rules`).
+    pub(crate) fn register_builtin_rule(&mut self, extension: &str, rule: HighlightRule) {
+        let Some(compiled) = compile_rule(rule) else { return; };
+        self.builtin_by_ext.entry(extension.to_lowercase()).or_default().push(compiled);
+        self.cache.clear();
+    }
+
     /// Clear all rules for a specific extension.
     pub fn clear_rules(&mut self, extension: &str) {
         let ext = extension.to_lowercase();
@@ -139,8 +151,9 @@ impl Highlighter {
     fn compute_highlights(&self, text: &str) -> Vec<HighlightSpan> {
         let mut spans = Vec::new();
 
-        // Get rules for current extension + global rules
-        let ext_rules = self.rules_by_ext.get(&self.current_ext);
+        // Get rules for current extension (plugin rules, else built-in ones) + global rules
+        let ext_rules = self.rules_by_ext.get(&self.current_ext)
+            .or_else(|| self.builtin_by_ext.get(&self.current_ext));
         let global_rules = self.rules_by_ext.get("");
 
         let rules: Vec<&CompiledRule> = ext_rules
@@ -0,0 +1,120 @@
+//! Prompt history: what was entered in the Open/Save As, Find/Find in files, command and Goto
+//! line prompts is remembered per kind (the last `HISTORY_SIZE`, without repeats) in
+//! `<state dir>/history.json`. Up and Down in a prompt step through its history; Down past the
+//! newest entry brings back what was typed before.
+
+use super::Editor; // editor state
+use crate::plugins::PluginStore; // history.json in the state directory
+use crate::types::PromptKind; // prompt kinds
+use serde_json::{json, Value}; // stored history
+use std::collections::HashMap; // entries by kind
+
+/// Entries kept per kind.
+const HISTORY_SIZE: usize = 100;
+
+/// Prompt history by kind, oldest entry first.
+#[derive(Default)]
+pub(crate) struct History(HashMap<String, Vec<String>>);
+
+/// The history `kind` shares, if it keeps one.
+fn history_key(kind: PromptKind) -> Option<&'static str> {
+    match kind {
+        PromptKind::Open | PromptKind::SaveAs => Some("file"),
+        PromptKind::Find | PromptKind::Grep => Some("find"),
+        PromptKind::Command => Some("command"),
+        PromptKind::GotoLine => Some("goto"),
+        _ => None,
+    }
+}
+
+/// Add `text` as the newest entry, dropping an earlier copy and the oldest past `HISTORY_SIZE`.
+fn push(entries: &mut Vec<String>, text: &str) {
+    if text.trim().is_empty() { return; }
+    entries.retain(|e| e != text);
+    entries.push(text.to_string());
+    let excess = entries.len().saturating_sub(HISTORY_SIZE);
+    entries.drain(..excess);
+}
+
+/// The entry Up (`back`) or Down shows next among `len` entries when `at` is shown (`None`: the
+/// typed input). `None` from Down past the newest entry; Up stops at the oldest.
+fn step(len: usize, at: Option<usize>, back: bool) -> Option<usize> {
+    match (at, back) {
+        (None, true) => len.checked_sub(1),
+        (Some(i), true) => Some(i.saturating_sub(1)),
+        (Some(i), false) if i + 1 < len => Some(i + 1),
+        _ => None,
+    }
+}
+
+impl History {
+    /// The history saved by earlier sessions; empty when there is none (or in safe mode).
+    pub(crate) fn load() -> Self {
+        let saved = PluginStore::named("history").and_then(|store| store.entries()).unwrap_or_default();
+        let entries = saved.into_iter()
+            .filter_map(|(key, value)| {
+                let list = value.as_array()?.iter().filter_map(Value::as_str).map(ToString::to_string).collect();
+                Some((key, list))
+            })
+            .collect();
+        Self(entries)
+    }
+}
+
+impl Editor {
+    /// Remember `input` entered in a `kind` prompt.
+    pub(crate) fn remember_prompt_input(&mut self, kind: PromptKind, input: &str) {
+        let Some(key) = history_key(kind) else { return; };
+        let entries = self.history.0.entry(key.to_string()).or_default();
+        push(entries, input);
+        // History is a convenience: without a state directory it lasts for the session.
+        let _ = PluginStore::named("history").and_then(|store| store.set(key, Some(json!(entries))));
+    }
+
+    /// Up (`back`) or Down in a prompt: show the previous or next history entry.
+    pub(crate) fn browse_prompt_history(&mut self, back: bool) {
+        let Some(prompt) = &mut self.prompt else { return; };
+        let Some(key) = history_key(prompt.kind) else { return; };
+        let entries = self.history.0.get(key).map_or(&[][..], Vec::as_slice);
+        let at = prompt.history.as_ref().map(|(i, _)| *i);
+        let typed = prompt.history.take().map_or_else(|| prompt.input.clone(), |(_, typed)| typed);
+        match step(entries.len(), at, back) {
+            Some(i) => {
+                prompt.input = entries[i].clone();
+                prompt.history = Some((i, typed));
+            }
+            None => prompt.input = typed,
+        }
+        prompt.cursor = prompt.input.chars().count();
+        self.mark_redraw();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_newest_last_without_repeats() {
+        let mut entries = Vec::new();
+        for text in ["a", "b", " ", "a"] {
+            push(&mut entries, text);
+        }
+        assert_eq!(entries, ["b", "a"]);
+        for i in 0..HISTORY_SIZE {
+            push(&mut entries, &i.to_string());
+        }
+        assert_eq!(entries.len(), HISTORY_SIZE);
+        assert_eq!(entries[0], "0");
+    }
+
+    #[test]
+    fn steps_back_and_forth() {
+        assert_eq!(step(3, None, true), Some(2));
+        assert_eq!(step(3, Some(0), true), Some(0));
+        assert_eq!(step(3, Some(1), false), Some(2));
+        assert_eq!(step(3, Some(2), false), None);
+        assert_eq!(step(0, None, true), None);
+        assert_eq!(step(3, None, false), None);
+    }
+}
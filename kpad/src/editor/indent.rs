@@ -0,0 +1,60 @@
+//! Indenting and dedenting whole lines: Tab on a selection of several lines indents each of them
+//! by `tab_width` spaces (blank lines stay empty), Shift+Tab dedents the selected lines, or the
+//! cursor line, by up to `tab_width` spaces or one tab. The selection is kept, one undo step.
+
+use super::Editor; // editor state
+
+/// `line` indented by `width` spaces, or dedented by up to `width` leading spaces or one tab.
+fn shift_line(line: &str, width: usize, dedent: bool) -> String {
+    if !dedent {
+        return if line.trim().is_empty() { line.to_string() } else { format!("{}{line}", " ".repeat(width)) };
+    }
+    if let Some(rest) = line.strip_prefix('\t') {
+        return rest.to_string();
+    }
+    let spaces = line.chars().take(width).take_while(|&c| c == ' ').count();
+    line[spaces..].to_string()
+}
+
+impl Editor {
+    /// Tab (indent) or Shift+Tab (`dedent`) on whole lines. `false` when Tab should type spaces
+    /// instead: there is no selection spanning lines.
+    pub(crate) fn shift_lines(&mut self, dedent: bool) -> bool {
+        let (first, last) = match self.selection_range() {
+            // A selection ending at column 0 does not include that line.
+            Some((a, b)) if b.y > a.y => (a.y, if b.x == 0 { b.y - 1 } else { b.y }),
+            _ if dedent => (self.doc.cursor.y, self.doc.cursor.y),
+            _ => return false,
+        };
+        let old: Vec<String> = (first..=last).map(|y| self.doc.buf.line(y).into_owned()).collect();
+        let new: Vec<String> = old.iter().map(|l| shift_line(l, self.tab_width, dedent)).collect();
+        if old == new { return true; }
+        self.replace_lines(first, last, &new.join("\n"));
+        // Keep the cursor and anchor on their text.
+        let moved = |y: usize, x: usize| {
+            if y < first || y > last || x == 0 { return x; }
+            let (before, after) = (old[y - first].chars().count(), new[y - first].chars().count());
+            (x + after).saturating_sub(before)
+        };
+        self.doc.cursor.x = moved(self.doc.cursor.y, self.doc.cursor.x);
+        if let Some(a) = self.doc.anchor.as_mut() {
+            a.x = moved(a.y, a.x);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shifts_lines_by_tab_width() {
+        assert_eq!(shift_line("x = 1", 4, false), "    x = 1");
+        assert_eq!(shift_line("  ", 4, false), "  ");
+        assert_eq!(shift_line("      x", 4, true), "  x");
+        assert_eq!(shift_line("  x", 4, true), "x");
+        assert_eq!(shift_line("\t  x", 4, true), "  x");
+        assert_eq!(shift_line("x", 4, true), "x");
+    }
+}
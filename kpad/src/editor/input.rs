@@ -1,11 +1,12 @@
 //! Input handling: keyboard, mouse, and prompt events.
 
 use crate::commands::canonical_key_string; // canonical key representation
-use crate::types::{EditOperation, Pos, Prompt, PromptKind}; // core types
-use crate::utils::clamp_usize; // comparison helpers
+use crate::types::{EditOperation, Pos, PromptKind}; // core types
+use crate::utils::parse_location; // LINE:COL in Goto line
+
 use super::Editor; // editor state
 use anyhow::Result; // anyhow error handling
-use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseEvent, MouseEventKind}; // terminal events
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind}; // terminal events
 use std::cmp::min; // comparison helpers
 use std::fs; // file system access
 use std::path::Path; // file path handling
@@ -111,67 +112,74 @@ fn longest_common_prefix(strings: &[String]) -> String {
 impl Editor {
     /// Top-level mouse handler.
     pub fn handle_mouse(&mut self, mouse: MouseEvent) -> Result<()> {
+        self.finish_scroll_animation();
+        if self.context_menu.is_some() {
+            self.handle_context_menu_mouse(mouse);
+            return Ok(());
+        }
         let shift = mouse.modifiers.contains(KeyModifiers::SHIFT);
 
         match mouse.kind {
             MouseEventKind::ScrollUp => {
-                if shift && !self.word_wrap {
+                if shift && !self.wrapping() {
                     // Shift + Scroll Up = Scroll Left
-                    let mut p = self.cursor;
+                    let mut p = self.doc.cursor;
                     p.x = p.x.saturating_sub(1);
-                    self.cursor = self.buf.clamp_pos(p);
+                    self.doc.cursor = self.doc.buf.clamp_pos(p);
                     self.ensure_visible()?;
+                    self.clear_selection();
+                    self.mark_redraw();
                 } else {
-                    // Normal Scroll Up = Move Cursor Up
-                    let mut p = self.cursor;
-                    p.y = p.y.saturating_sub(1);
-                    p.x = min(p.x, self.buf.line_len_chars(p.y));
-                    self.cursor = self.buf.clamp_pos(p);
+                    // Normal Scroll Up = Scroll the view; the cursor and selection stay
+                    self.scroll_view(false)?;
                 }
-                self.clear_selection();
-                self.ensure_visible()?;
-                self.mark_redraw();
             }
             MouseEventKind::ScrollDown => {
-                if shift && !self.word_wrap {
+                if shift && !self.wrapping() {
                     // Shift + Scroll Down = Scroll Right
-                    let mut p = self.cursor;
+                    let mut p = self.doc.cursor;
                     p.x += 1;
-                    self.cursor = self.buf.clamp_pos(p);
+                    self.doc.cursor = self.doc.buf.clamp_pos(p);
                     self.ensure_visible()?;
+                    self.clear_selection();
+                    self.mark_redraw();
                 } else {
-                    // Normal Scroll Down = Move Cursor Down
-                    let mut p = self.cursor;
-                    if p.y + 1 < self.buf.line_count() {
-                        p.y += 1;
-                        p.x = min(p.x, self.buf.line_len_chars(p.y));
-                    }
-                    self.cursor = self.buf.clamp_pos(p);
+                    // Normal Scroll Down = Scroll the view; the cursor and selection stay
+                    self.scroll_view(true)?;
                 }
-                self.clear_selection();
-                self.ensure_visible()?;
-                self.mark_redraw();
             }
             MouseEventKind::ScrollLeft => {
-                if !self.word_wrap {
-                    let mut p = self.cursor;
+                if !self.wrapping() {
+                    let mut p = self.doc.cursor;
                     p.x = p.x.saturating_sub(1);
-                    self.cursor = self.buf.clamp_pos(p);
+                    self.doc.cursor = self.doc.buf.clamp_pos(p);
                     self.clear_selection();
                     self.ensure_visible()?;
                     self.mark_redraw();
                 }
             }
             MouseEventKind::ScrollRight => {
-                if !self.word_wrap {
-                    let mut p = self.cursor;
+                if !self.wrapping() {
+                    let mut p = self.doc.cursor;
                     p.x += 1;
-                    self.cursor = self.buf.clamp_pos(p);
+                    self.doc.cursor = self.doc.buf.clamp_pos(p);
                     self.clear_selection();
                     self.ensure_visible()?;
                     self.mark_redraw();
                 }
             }
+            MouseEventKind::Down(MouseButton::Left) if !self.overlay_shown() && self.prompt.is_none() => {
+                self.handle_click(mouse.column, mouse.row, shift)?;
+            }
+            MouseEventKind::Drag(MouseButton::Left) if !self.overlay_shown() && self.prompt.is_none() => {
+                self.handle_drag(mouse.column, mouse.row)?;
+            }
+            MouseEventKind::Down(MouseButton::Right) if !self.overlay_shown() && self.prompt.is_none() => {
+                self.open_context_menu(mouse.column, mouse.row);
+            }
+            MouseEventKind::Down(MouseButton::Middle) if !self.overlay_shown() && self.prompt.is_none() => {
+                self.middle_click_paste(mouse.column, mouse.row)?;
+            }
             _ => {}
         }
         Ok(())
@@ -181,10 +189,16 @@ impl Editor {
     ///
     /// Returns `Ok(true)` if the editor should quit, `Ok(false)` otherwise.
     pub fn handle_key(&mut self, key: KeyEvent) -> Result<bool> {
-        // If help or stats is shown, any key closes it
-        if self.show_help || self.show_stats {
+        // Input interrupts a running smooth scroll so keys act on the final viewport.
+        self.finish_scroll_animation();
+
+        // If an overlay is shown, any key closes it
+        if self.overlay_shown() {
             self.show_help = false;
             self.show_stats = false;
+            self.show_plugins = false;
+            self.show_keys = false;
+            self.file_info = None;
             self.mark_redraw();
             return Ok(false);
         }
@@ -193,28 +207,65 @@ impl Editor {
         if self.prompt.is_some() {
             return self.handle_prompt_key(key);
         }
+        if self.color_picker.is_some() {
+            self.handle_color_picker_key(key);
+            return Ok(false);
+        }
+        if self.buffer_list.is_some() {
+            self.handle_buffer_list_key(key)?;
+            return Ok(false);
+        }
+        if self.diff_view.is_some() {
+            self.handle_diff_key(key);
+            return Ok(false);
+        }
+        if self.grep_view.is_some() {
+            self.handle_grep_key(key)?;
+            return Ok(false);
+        }
+        if self.lsp.list.is_some() {
+            self.handle_diagnostics_key(key)?;
+            return Ok(false);
+        }
+        if self.paste_history.is_some() {
+            self.handle_paste_history_key(key)?;
+            return Ok(false);
+        }
+        if self.context_menu.is_some() {
+            return Ok(self.handle_context_menu_key(key));
+        }
+        if self.completion.is_some() && self.handle_completion_key(key)? {
+            return Ok(false);
+        }
+        if self.modal.is_some() && self.handle_modal_key(key)? {
+            return Ok(false);
+        }
 
         let shift = key.modifiers.contains(KeyModifiers::SHIFT);
-        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let typing = matches!(key.code, KeyCode::Char(_)) && !key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+        if !typing {
+            self.auto_close.forget();
+        }
 
         // Turn the raw key event into a canonical string like "Ctrl+S"
         let key_str = canonical_key_string(&key);
 
-        // F1 toggles help
-        if key.code == KeyCode::F(1) {
-            self.show_help = true;
-            self.mark_redraw();
-            return Ok(false);
-        }
-        // F2 toggles stats
-        if key.code == KeyCode::F(2) {
-            self.show_stats = true;
-            self.mark_redraw();
-            return Ok(false);
-        }
-
-        // Movement keys (selection-aware)
+        // Movement keys (selection-aware); Ctrl+PageUp/PageDown switch tabs
         match key.code {
+            KeyCode::PageUp if key.modifiers == KeyModifiers::CONTROL => {
+                self.prev_buffer()?;
+                return Ok(false);
+            }
+            KeyCode::PageDown if key.modifiers == KeyModifiers::CONTROL => {
+                self.next_buffer()?;
+                return Ok(false);
+            }
+            KeyCode::Up | KeyCode::Down | KeyCode::Left | KeyCode::Right if key.modifiers == KeyModifiers::ALT | KeyModifiers::SHIFT => {
+                self.extend_block(key)?;
+                return Ok(false);
+            }
+            // Alt+Left/Right are jump_back / jump_forward bindings.
+            KeyCode::Left | KeyCode::Right if key.modifiers == KeyModifiers::ALT => {}
             KeyCode::Up | KeyCode::Down | KeyCode::Left | KeyCode::Right | KeyCode::Home | KeyCode::End
             | KeyCode::PageUp | KeyCode::PageDown => {
                 let selecting = shift;
@@ -224,108 +275,108 @@ impl Editor {
             _ => {}
         }
 
-        // If key matches a registered command (built-in or plugin), run it
+        // Every other shortcut (Ctrl+S, F1, ...) is a command binding, so the config can remap it
         if let Some(cmd_name) = self.commands.resolve_key(&key_str) {
-            return Ok(self.run_command_by_name(&cmd_name)?);
+            return Ok(self.run_command_reporting(&cmd_name));
         }
 
-        // Common direct-edit keys
-        match (key.code, ctrl) {
-            (KeyCode::Char('q'), true) => return Ok(self.try_quit()),
-            (KeyCode::Char('s'), true) => { self.cmd_save()?; return Ok(false); }
-            (KeyCode::Char('o'), true) => { self.prompt = Some(Prompt::new(PromptKind::Open, "")); self.mark_redraw(); return Ok(false); }
-            (KeyCode::Char('f'), true) => { self.prompt = Some(Prompt::new(PromptKind::Find, self.last_find.clone().unwrap_or_default())); self.mark_redraw(); return Ok(false); }
-            (KeyCode::Char('p'), true) => { self.prompt = Some(Prompt::new(PromptKind::Command, "")); self.mark_redraw(); return Ok(false); }
-            (KeyCode::Char('g'), true) => { self.prompt = Some(Prompt::new(PromptKind::GotoLine, "")); self.mark_redraw(); return Ok(false); }
-            (KeyCode::Char('a'), true) => { self.select_all(); self.ensure_visible()?; return Ok(false); }
-            (KeyCode::Char('z'), true) => { self.undo()?; return Ok(false); }
-            (KeyCode::Char('y'), true) => { self.redo()?; return Ok(false); }
-            (KeyCode::Char('c'), true) => { self.copy()?; return Ok(false); }
-            (KeyCode::Char('x'), true) => { self.cut()?; return Ok(false); }
-            (KeyCode::Char('v'), true) => { self.paste()?; return Ok(false); }
-            _ => {}
+        // Remaining text-editing keys are refused in read-only buffers.
+        let edits = match key.code {
+            KeyCode::Enter | KeyCode::Backspace | KeyCode::Delete | KeyCode::Tab | KeyCode::BackTab => true,
+            KeyCode::Char(_) => !key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT),
+            _ => false,
+        };
+        if edits && !self.ensure_writable() {
+            return Ok(false);
         }
 
         match key.code {
             KeyCode::Esc => {
                 self.clear_selection();
             }
+            KeyCode::Backspace | KeyCode::Delete if self.block_delete(key.code == KeyCode::Delete) => self.ensure_visible()?,
+            KeyCode::Tab if self.block_type(&" ".repeat(self.tab_width)) => self.ensure_visible()?,
+            KeyCode::Tab | KeyCode::BackTab if self.shift_lines(key.code == KeyCode::BackTab) => self.ensure_visible()?,
+            KeyCode::Char(ch) if typing && self.block_type(&ch.to_string()) => self.ensure_visible()?,
+            KeyCode::Enter if self.block_range().is_some() => {
+                self.clear_selection();
+                return self.handle_key(key);
+            }
+            KeyCode::Enter if self.enter_in_pair()? => {}
+            KeyCode::Backspace if self.delete_pair(false)? => {}
+            KeyCode::Delete if self.delete_pair(true)? => {}
             KeyCode::Enter => {
-                let op = EditOperation::Insert { pos: self.cursor, text: "\n".to_string() };
-                self.record_edit(op);
+                self.record_insert("\n");
                 if self.selection_range().is_some() {
                     self.delete_selection();
                 } else {
                     self.mark_redraw();
                 }
-                self.cursor = self.buf.insert_newline(self.cursor);
-                self.dirty = true;
+                self.doc.cursor = self.doc.buf.insert_newline(self.doc.cursor);
+                self.doc.dirty = true;
                 self.ensure_visible()?;
             }
             KeyCode::Backspace => {
                 if let Some((a, b)) = self.selection_range() {
-                    let deleted_text = self.buf.get_range(a, b);
+                    let deleted_text = self.doc.buf.get_range(a, b);
                     let op = EditOperation::Delete { start: a, _end: b, deleted_text };
                     self.record_edit(op);
                     self.delete_selection();
-                } else if self.cursor.y > 0 || self.cursor.x > 0 {
-                    let end = self.cursor;
-                    let start = if self.cursor.x > 0 {
-                        Pos { y: self.cursor.y, x: self.cursor.x - 1 }
+                } else if self.doc.cursor.y > 0 || self.doc.cursor.x > 0 {
+                    let end = self.doc.cursor;
+                    let start = if self.doc.cursor.x > 0 {
+                        Pos { y: self.doc.cursor.y, x: self.doc.cursor.x - 1 }
                     } else {
-                        let prev_y = self.cursor.y - 1;
-                        Pos { y: prev_y, x: self.buf.line_len_chars(prev_y) }
+                        let prev_y = self.doc.cursor.y - 1;
+                        Pos { y: prev_y, x: self.doc.buf.line_len_chars(prev_y) }
                     };
-                    let deleted_text = self.buf.get_range(start, end);
-                    let op = EditOperation::Delete { start, _end: end, deleted_text };
-                    self.record_edit(op);
-                    self.cursor = self.buf.delete_backspace(self.cursor);
-                    self.dirty = true;
+                    self.record_backspace(start, self.doc.buf.get_range(start, end));
+                    self.doc.cursor = self.doc.buf.delete_backspace(self.doc.cursor);
+                    self.doc.dirty = true;
                     self.mark_redraw();
                 }
                 self.ensure_visible()?;
             }
             KeyCode::Delete => {
                 if let Some((a, b)) = self.selection_range() {
-                    let deleted_text = self.buf.get_range(a, b);
+                    let deleted_text = self.doc.buf.get_range(a, b);
                     let op = EditOperation::Delete { start: a, _end: b, deleted_text };
                     self.record_edit(op);
                     self.delete_selection();
                 } else {
-                    let start = self.cursor;
-                    let end = if self.cursor.x < self.buf.line_len_chars(self.cursor.y) {
-                        Pos { y: self.cursor.y, x: self.cursor.x + 1 }
-                    } else if self.cursor.y + 1 < self.buf.line_count() {
-                        Pos { y: self.cursor.y + 1, x: 0 }
+                    let start = self.doc.cursor;
+                    let end = if self.doc.cursor.x < self.doc.buf.line_len_chars(self.doc.cursor.y) {
+                        Pos { y: self.doc.cursor.y, x: self.doc.cursor.x + 1 }
+                    } else if self.doc.cursor.y + 1 < self.doc.buf.line_count() {
+                        Pos { y: self.doc.cursor.y + 1, x: 0 }
                     } else {
                         start
                     };
 
                     if start != end {
-                        let deleted_text = self.buf.get_range(start, end);
+                        let deleted_text = self.doc.buf.get_range(start, end);
                         let op = EditOperation::Delete { start, _end: end, deleted_text };
                         self.record_edit(op);
-                        self.cursor = self.buf.delete_delete(self.cursor);
-                        self.dirty = true;
+                        self.doc.cursor = self.doc.buf.delete_delete(self.doc.cursor);
+                        self.doc.dirty = true;
                         self.mark_redraw();
                     }
                 }
                 self.ensure_visible()?;
             }
             KeyCode::Tab => {
-                let op = EditOperation::Insert { pos: self.cursor, text: "    ".to_string() };
-                self.record_edit(op);
-                self.replace_selection_or_insert("    ");
+                let indent = " ".repeat(self.tab_width);
+                self.record_insert(&indent);
+                self.replace_selection_or_insert(&indent);
                 self.ensure_visible()?;
             }
             KeyCode::Char(ch) => {
                 // Text input (ignore control chars)
                 if key.modifiers.contains(KeyModifiers::CONTROL) || key.modifiers.contains(KeyModifiers::ALT) {
                     // ignore (handled above / keymap)
-                } else {
+                } else if !self.type_paired(ch)? {
                     let text = ch.to_string();
-                    let op = EditOperation::Insert { pos: self.cursor, text: text.clone() };
-                    self.record_edit(op);
+                    if self.selection_range().is_some() { self.record_insert(&text); } else { self.record_typed(ch); }
                     self.replace_selection_or_insert(&text);
                     self.ensure_visible()?;
                 }
@@ -338,7 +389,8 @@ impl Editor {
 
     /// Quit handling with a safety confirmation if there are unsaved changes.
     pub fn try_quit(&mut self) -> bool {
-        if !self.dirty {
+        let dirty = self.dirty_buffer_count();
+        if dirty == 0 {
             return true;
         }
         let now = Instant::now();
@@ -348,12 +400,18 @@ impl Editor {
             }
         }
         self.last_quit_hint = Some(now);
-        self.set_status("Unsaved changes! Press Ctrl+Q again to quit.", Duration::from_secs(2));
+        let msg = if dirty == 1 {
+            "Unsaved changes! Press Ctrl+Q again to quit.".to_string()
+        } else {
+            format!("Unsaved changes in {dirty} buffers! Press Ctrl+Q again to quit.")
+        };
+        self.set_status(msg, Duration::from_secs(2));
         false
     }
 
     /// Handle keys while a prompt is active.
     pub fn handle_prompt_key(&mut self, key: KeyEvent) -> Result<bool> {
+        if self.handle_palette_key(key) { return Ok(false); }
         let Some(prompt) = &mut self.prompt else { return Ok(false); };
 
         let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
@@ -365,14 +423,18 @@ impl Editor {
                 return Ok(false);
             }
             (KeyCode::Tab, _) | (KeyCode::BackTab, _) => {
-                // Tab completion for Open/SaveAs prompts
-                if prompt.kind == PromptKind::Open || prompt.kind == PromptKind::SaveAs {
+                // Tab completion of paths (Open/SaveAs) and command arguments (`set wr` -> `set wrap`)
+                if matches!(prompt.kind, PromptKind::Open | PromptKind::SaveAs | PromptKind::Command) {
                     let shift = key.code == KeyCode::BackTab;
+                    let command = prompt.kind == PromptKind::Command;
+                    let commands = &self.commands;
+                    let complete = |input: &str| if command { commands.complete_args(input) } else { get_path_completions(input) };
+                    let sep = if command { ' ' } else { '/' };
 
-                    // Check if input changed since last Tab
-                    if prompt.completion_base != prompt.input {
+                    // Check if input changed since last Tab (cycling replaces it with candidates)
+                    if !prompt.is_cycling() && prompt.completion_base != prompt.input {
                         // Fresh completion: get new completions
-                        prompt.completions = get_path_completions(&prompt.input);
+                        prompt.completions = complete(&prompt.input);
                         prompt.completion_base = prompt.input.clone();
                         prompt.completion_index = None;
                     }
@@ -385,7 +447,7 @@ impl Editor {
                         prompt.cursor = prompt.input.chars().count();
                         prompt.completion_base = prompt.input.clone();
                         // Get new completions for the completed path
-                        prompt.completions = get_path_completions(&prompt.input);
+                        prompt.completions = complete(&prompt.input);
                     } else {
                         // Multiple matches
                         if prompt.completion_index.is_none() {
@@ -395,7 +457,7 @@ impl Editor {
                                 prompt.input = prefix;
                                 prompt.cursor = prompt.input.chars().count();
                                 prompt.completion_base = prompt.input.clone();
-                                prompt.completions = get_path_completions(&prompt.input);
+                                prompt.completions = complete(&prompt.input);
                             } else {
                                 // Already at common prefix, start cycling
                                 prompt.completion_index = Some(0);
@@ -417,7 +479,7 @@ impl Editor {
 
                         // Show completion options in status
                         let display: Vec<&str> = prompt.completions.iter()
-                            .map(|s| s.rsplit('/').next().unwrap_or(s).trim_end_matches('/'))
+                            .map(|s| s.rsplit(sep).next().unwrap_or(s).trim_end_matches('/'))
                             .take(8)
                             .collect();
                         let msg = if prompt.completions.len() > 8 {
@@ -436,6 +498,7 @@ impl Editor {
                 let input = prompt.input.clone();
                 self.prompt = None;
                 self.mark_redraw();
+                self.remember_prompt_input(kind, &input);
 
                 match kind {
                     PromptKind::Open => {
@@ -450,33 +513,59 @@ impl Editor {
                         if p.as_os_str().is_empty() {
                             return Ok(false);
                         }
-                        self.save_to_path(p)?;
+                        if let Err(e) = self.save_as(&p) {
+                            self.set_error(format!("{e:#}"), Duration::from_secs(5));
+                        }
+                    }
+                    PromptKind::DeleteFile => {
+                        self.confirm_delete_file(&input);
+                    }
+                    PromptKind::MakeReadOnly | PromptKind::MakeWritable | PromptKind::SaveReadOnly => {
+                        self.confirm_readonly(kind, &input)?;
                     }
                     PromptKind::Find => {
                         self.find_next(input.trim())?;
                     }
+                    PromptKind::Rename => {
+                        self.apply_rename(&input)?;
+                    }
+                    PromptKind::MacroName => self.save_macro(&input),
+                    // Answered in `ask_user`, which takes Enter before it gets here.
+                    PromptKind::PluginInput | PromptKind::PluginConfirm => {}
+                    PromptKind::PluginName => {
+                        self.create_plugin(input.trim())?;
+                    }
+                    PromptKind::PinLines => {
+                        match input.trim().parse::<usize>() {
+                            Ok(n) => self.pin_lines(n)?,
+                            Err(_) => self.set_status("Enter a number of lines.", Duration::from_secs(2)),
+                        }
+                    }
+                    PromptKind::WordGoal => {
+                        match input.trim().parse::<usize>() {
+                            Ok(n) => self.set_word_goal(n),
+                            Err(_) => self.set_status("Enter a number of words.", Duration::from_secs(2)),
+                        }
+                    }
+                    PromptKind::Grep => self.start_grep(input.trim()),
+                    PromptKind::Pomodoro => {
+                        match input.trim().parse::<u64>() {
+                            Ok(n) => self.start_pomodoro(n),
+                            Err(_) => self.set_status("Enter a number of minutes.", Duration::from_secs(2)),
+                        }
+                    }
                     PromptKind::GotoLine => {
-                        let n: isize = input.trim().parse().unwrap_or(1);
-                        let target = clamp_usize(n - 1, 0, self.buf.line_count().saturating_sub(1));
-                        self.cursor.y = target;
-                        self.cursor.x = min(self.cursor.x, self.buf.line_len_chars(self.cursor.y));
-                        self.clear_selection();
-                        self.ensure_visible()?;
+                        let (n, col) = parse_location(&input).unwrap_or((1, None));
+                        self.goto_line(n, col)?;
                     }
                     PromptKind::Command => {
                         let cmdline = input.trim();
                         if cmdline.is_empty() {
                             return Ok(false);
                         }
-                        // Support Vim-like shorthands
+                        // Vim-like `:w` (the shorthands are aliases)
                         let cmd = cmdline.trim_start_matches(':');
-                        let cmd = match cmd {
-                            "w" => "save",
-                            "q" => "quit",
-                            "wq" => "save_and_quit",
-                            other => other,
-                        };
-                        let should_quit = self.run_command_by_name(cmd)?;
+                        let should_quit = self.run_command_reporting(cmd);
                         if should_quit {
                             return Ok(true);
                         }
@@ -515,6 +604,9 @@ impl Editor {
                 prompt.cursor = 0;
                 self.mark_redraw();
             }
+            (KeyCode::Up | KeyCode::Down, _) => {
+                self.browse_prompt_history(key.code == KeyCode::Up);
+            }
             (KeyCode::End, _) => {
                 prompt.cursor = prompt.input.chars().count();
                 self.mark_redraw();
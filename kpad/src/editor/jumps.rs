@@ -0,0 +1,168 @@
+//! Jump list: where the cursor was before a long move (goto line, a search hit, opening a
+//! file), walked with `jump_back` (Alt+Left) and `jump_forward` (Alt+Right) like a browser's
+//! history. A new jump after going back drops the entries ahead of it.
+
+use super::Editor; // editor state
+use crate::types::Pos; // jump positions
+use anyhow::Result; // anyhow error handling
+use std::mem; // jumps set aside while reopening a file
+use std::path::PathBuf; // buffer of a jump
+use std::time::Duration; // timing for status messages
+
+/// Most jumps kept; the oldest are dropped first.
+const MAX_JUMPS: usize = 100;
+
+/// A cursor position in a buffer (`None` for an unnamed one).
+#[derive(Clone, Debug, PartialEq)]
+struct Location {
+    path: Option<PathBuf>,
+    pos: Pos,
+}
+
+/// Recorded jumps and where `jump_back` / `jump_forward` are in them.
+#[derive(Default)]
+pub(crate) struct JumpList {
+    entries: Vec<Location>,
+    /// Entry the cursor was last sent to; `entries.len()` when not walking the list.
+    index: usize,
+}
+
+impl JumpList {
+    /// Record the position a jump leaves, dropping the entries after the current one.
+    fn push(&mut self, at: Location) {
+        self.entries.truncate(self.index);
+        if self.entries.last() != Some(&at) {
+            self.entries.push(at);
+        }
+        if self.entries.len() > MAX_JUMPS {
+            self.entries.remove(0);
+        }
+        self.index = self.entries.len();
+    }
+
+    /// The entry before the current one. Going back from the newest end first records `here`,
+    /// so `forward` can return to it.
+    fn back(&mut self, here: Location) -> Option<Location> {
+        if self.index == self.entries.len() {
+            self.push(here);
+            self.index = self.entries.len() - 1;
+        }
+        if self.index == 0 {
+            return None;
+        }
+        self.index -= 1;
+        self.entries.get(self.index).cloned()
+    }
+
+    /// The entry after the current one.
+    fn forward(&mut self) -> Option<Location> {
+        if self.index + 1 >= self.entries.len() {
+            return None;
+        }
+        self.index += 1;
+        self.entries.get(self.index).cloned()
+    }
+}
+
+impl Editor {
+    /// Where the cursor is now.
+    fn location(&self) -> Location {
+        Location { path: self.doc.file_path.clone(), pos: self.doc.cursor }
+    }
+
+    /// Record the cursor position before a jump moves it.
+    pub(crate) fn push_jump(&mut self) {
+        // An untouched `<new file>` buffer is replaced by the next open: nowhere to go back to.
+        if self.doc.file_path.is_none() && !self.doc.dirty && self.doc.buf.text.len_chars() == 0 { return; }
+        let here = self.location();
+        self.jumps.push(here);
+    }
+
+    /// Go back to where the cursor was before the last jump (Alt+Left).
+    pub fn jump_back(&mut self) -> Result<()> {
+        let here = self.location();
+        let Some(at) = self.jumps.back(here) else {
+            self.set_status("No earlier jump.", Duration::from_secs(2));
+            return Ok(());
+        };
+        self.go_to_location(&at)
+    }
+
+    /// Undo a `jump_back` (Alt+Right).
+    pub fn jump_forward(&mut self) -> Result<()> {
+        let Some(at) = self.jumps.forward() else {
+            self.set_status("No later jump.", Duration::from_secs(2));
+            return Ok(());
+        };
+        self.go_to_location(&at)
+    }
+
+    /// Move the cursor to `at`, switching to (or reopening) its file without recording a jump.
+    fn go_to_location(&mut self, at: &Location) -> Result<()> {
+        if let Some(path) = at.path.as_ref().filter(|p| self.doc.file_path.as_ref() != Some(p)) {
+            // Reopening would record a jump of its own and cut the list short.
+            let jumps = mem::take(&mut self.jumps);
+            let res = self.open_path(path.clone());
+            self.jumps = jumps;
+            res?;
+        } else if at.path.is_none() && self.doc.file_path.is_some() {
+            let Some(i) = (0..self.buffers.len()).find(|&i| i != self.active && self.buffers[i].file_path.is_none()) else {
+                self.set_status("That buffer is closed.", Duration::from_secs(2));
+                return Ok(());
+            };
+            self.switch_buffer(i)?;
+        }
+        self.doc.cursor = self.doc.buf.clamp_pos(at.pos);
+        self.clear_selection();
+        self.mark_redraw();
+        self.ensure_visible()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(y: usize) -> Location {
+        Location { path: None, pos: Pos { y, x: 0 } }
+    }
+
+    #[test]
+    fn walks_back_and_forward() {
+        let mut jumps = JumpList::default();
+        jumps.push(at(1));
+        jumps.push(at(5));
+        assert_eq!(jumps.back(at(9)), Some(at(5)));
+        assert_eq!(jumps.back(at(5)), Some(at(1)));
+        assert_eq!(jumps.back(at(1)), None);
+        assert_eq!(jumps.forward(), Some(at(5)));
+        assert_eq!(jumps.forward(), Some(at(9)));
+        assert_eq!(jumps.forward(), None);
+    }
+
+    #[test]
+    fn new_jump_drops_forward_entries() {
+        let mut jumps = JumpList::default();
+        jumps.push(at(1));
+        jumps.push(at(2));
+        jumps.push(at(3));
+        assert_eq!(jumps.back(at(4)), Some(at(3)));
+        assert_eq!(jumps.back(at(3)), Some(at(2)));
+        jumps.push(at(2));
+        jumps.push(at(7));
+        assert_eq!(jumps.forward(), None);
+        assert_eq!(jumps.back(at(8)), Some(at(7)));
+        assert_eq!(jumps.back(at(7)), Some(at(2)));
+        assert_eq!(jumps.back(at(2)), Some(at(1)));
+    }
+
+    #[test]
+    fn keeps_at_most_max_jumps() {
+        let mut jumps = JumpList::default();
+        for y in 0..MAX_JUMPS + 10 {
+            jumps.push(at(y));
+        }
+        assert_eq!(jumps.entries.len(), MAX_JUMPS);
+        assert_eq!(jumps.entries[0], at(10));
+    }
+}
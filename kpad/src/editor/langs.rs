@@ -0,0 +1,140 @@
+//! Built-in language packs: highlight rules for Rust, Python, JSON, TOML and Markdown, so common
+//! files are colored without any plugin.
+//!
+//! They are registered as the highlighter's fallback rules: an extension with plugin rules uses
+//! those instead.
+
+use crate::types::{HighlightColor, HighlightRule}; // highlight rule types
+use crate::types::HighlightColor::{Blue, Cyan, Green, Grey, Magenta, Yellow}; // rule colors
+use super::Highlighter; // rule registration
+
+/// One rule: pattern, color, priority, capture group.
+type Rule = (&'static str, HighlightColor, i32, usize);
+
+/// Double-quoted string with backslash escapes.
+const DQ_STRING: &str = r#""(?:\\.|[^"\\])*""#;
+
+/// Number literal (with `_` separators, fraction, exponent and a type suffix).
+const NUMBER: &str = r"\b\d[\d_]*(?:\.\d[\d_]*)?(?:[eE][+-]?\d+)?[a-z0-9]*\b";
+
+const RUST: &[Rule] = &[
+    (r"\b(?:as|async|await|break|const|continue|crate|dyn|else|enum|extern|fn|for|if|impl|in|let|loop|match|mod|move|mut|pub|ref|return|self|Self|static|struct|super|trait|type|unsafe|use|where|while)\b", Magenta, 2, 0),
+    (r"\b[A-Z][A-Za-z0-9_]*\b", Cyan, 1, 0),
+    (r"\b(?:bool|char|str|u8|u16|u32|u64|u128|usize|i8|i16|i32|i64|i128|isize|f32|f64)\b", Cyan, 1, 0),
+    (r"\b(?:true|false)\b", Yellow, 2, 0),
+    (NUMBER, Yellow, 1, 0),
+    (r"\b[a-z_][a-z0-9_]*!", Blue, 2, 0),
+    (r"#!?\[[^\]]*\]", Grey, 3, 0),
+    (DQ_STRING, Green, 5, 0),
+    (r"'(?:\\.|[^'\\])'", Green, 5, 0),
+    // A `//` inside a string does not start a comment.
+    (r#"^(?:[^"/]|"(?:\\.|[^"\\])*"|/[^/"])*(//.*)$"#, Grey, 10, 1),
+];
+
+const PYTHON: &[Rule] = &[
+    (r"\b(?:and|as|assert|async|await|break|class|continue|def|del|elif|else|except|finally|for|from|global|if|import|in|is|lambda|nonlocal|not|or|pass|raise|return|try|while|with|yield)\b", Magenta, 2, 0),
+    (r"\b(?:True|False|None)\b", Yellow, 2, 0),
+    (NUMBER, Yellow, 1, 0),
+    (r"\b(?:self|cls|print|len|range|str|int|float|list|dict|set|tuple|bool|open|isinstance|super)\b", Cyan, 1, 0),
+    (r"\b(?:def|class)\s+([A-Za-z_]\w*)", Blue, 3, 1),
+    (r"^\s*@[\w.]+", Blue, 3, 0),
+    (DQ_STRING, Green, 5, 0),
+    (r"'(?:\\.|[^'\\])*'", Green, 5, 0),
+    (r#"^(?:[^"'#]|"(?:\\.|[^"\\])*"|'(?:\\.|[^'\\])*')*(#.*)$"#, Grey, 10, 1),
+];
+
+const JSON: &[Rule] = &[
+    (DQ_STRING, Green, 5, 0),
+    (r#"("(?:\\.|[^"\\])*")\s*:"#, Cyan, 6, 1),
+    (r"-?\b\d+(?:\.\d+)?(?:[eE][+-]?\d+)?\b", Yellow, 1, 0),
+    (r"\b(?:true|false|null)\b", Magenta, 2, 0),
+];
+
+const TOML: &[Rule] = &[
+    (r"^\s*\[\[?[^\]]*\]\]?", Magenta, 6, 0),
+    (r#"^\s*([A-Za-z0-9_.\-"]+)\s*="#, Cyan, 4, 1),
+    (r"\b(?:true|false)\b", Yellow, 2, 0),
+    (r"[+-]?\b\d[\d_:\-T.]*\b", Yellow, 1, 0),
+    (DQ_STRING, Green, 5, 0),
+    (r"'[^']*'", Green, 5, 0),
+    (r#"^(?:[^"'#]|"(?:\\.|[^"\\])*"|'[^']*')*(#.*)$"#, Grey, 10, 1),
+];
+
+const MARKDOWN: &[Rule] = &[
+    (r"^#{1,6}\s.*$", Magenta, 10, 0),
+    (r"\*\*[^*]+\*\*|__[^_]+__", Yellow, 5, 0),
+    (r"`[^`]+`", Green, 6, 0),
+    (r"^(?:```|~~~).*$", Green, 8, 0),
+    (r"\[[^\]]*\]\([^)]*\)", Blue, 4, 0),
+    (r"^\s*([-*+]|\d+\.)\s", Cyan, 3, 1),
+    (r"^\s*>.*$", Grey, 2, 0),
+];
+
+/// Name, extensions and rules of each built-in language.
+const LANGUAGES: &[(&str, &[&str], &[Rule])] = &[
+    ("Rust", &["rs"], RUST),
+    ("Python", &["py", "pyw"], PYTHON),
+    ("JSON", &["json"], JSON),
+    ("TOML", &["toml"], TOML),
+    ("Markdown", &["md", "markdown"], MARKDOWN),
+];
+
+/// Name of the built-in language of files with extension `ext` (lowercase).
+pub(crate) fn language_name(ext: &str) -> Option<&'static str> {
+    LANGUAGES.iter().find(|(_, exts, _)| exts.contains(&ext)).map(|(name, _, _)| *name)
+}
+
+/// A highlighter with the built-in language rules as fallbacks.
+pub(crate) fn builtin_highlighter() -> Highlighter {
+    let mut highlighter = Highlighter::new();
+    for (_, exts, rules) in LANGUAGES {
+        for ext in *exts {
+            for &(pattern, color, priority, group) in *rules {
+                let rule = HighlightRule { pattern: pattern.to_string(), color, priority, group };
+                highlighter.register_builtin_rule(ext, rule);
+            }
+        }
+    }
+    highlighter
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::HighlightColor::Red;
+
+    /// Colors of each char of `line` in a file with extension `ext`.
+    fn colors(ext: &str, line: &str) -> Vec<Option<HighlightColor>> {
+        let mut h = builtin_highlighter();
+        h.set_file_extension(ext);
+        let spans = h.get_highlights(0, line);
+        (0..line.chars().count()).map(|i| h.color_at(&spans, i)).collect()
+    }
+
+    #[test]
+    fn comments_start_outside_strings() {
+        let c = colors("rs", r#"let u = "a//b"; // note"#);
+        assert_eq!(c[0], Some(Magenta));
+        assert_eq!(c[10], Some(Green));
+        assert_eq!(c[17], Some(Grey));
+        let c = colors("py", "x = '#' # hi");
+        assert_eq!(c[5], Some(Green));
+        assert_eq!(c[9], Some(Grey));
+    }
+
+    #[test]
+    fn names_languages_by_extension() {
+        assert_eq!(language_name("pyw"), Some("Python"));
+        assert_eq!(language_name("markdown"), Some("Markdown"));
+        assert_eq!(language_name("txt"), None);
+    }
+
+    #[test]
+    fn plugin_rules_replace_builtin_ones() {
+        let mut h = builtin_highlighter();
+        h.set_file_extension("json");
+        assert!(!h.get_highlights(0, "true").is_empty());
+        h.register_rule("json", HighlightRule { pattern: "x".to_string(), color: Red, priority: 0, group: 0 });
+        assert!(h.get_highlights(0, "true").is_empty());
+    }
+}
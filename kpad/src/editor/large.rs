@@ -0,0 +1,64 @@
+//! Large files: UTF-8 files are read in chunks (with a progress line for big ones), and a file
+//! above `large_file_mb` (kpad.toml) opens in large-file mode, without syntax highlighting and
+//! word wrap, which both scan lines such a file has millions of.
+
+use crate::buffer::Buffer; // document model
+use super::Editor; // editor state
+use anyhow::{bail, Context, Result}; // anyhow error handling
+use crossterm::{cursor, style, terminal, QueueableCommand}; // progress line
+use std::fs::File; // file handle
+use std::io::{self, Write}; // progress output
+use std::path::Path; // file paths
+use std::time::Duration; // timing for status messages
+
+/// Files from this size up show a progress line while loading.
+const PROGRESS_MIN_BYTES: u64 = 16 << 20;
+
+/// Read `path` as UTF-8 chunk by chunk; once the UI is up, big files show their progress on the
+/// bottom row.
+pub(crate) fn read_utf8_file(path: &Path) -> Result<Buffer> {
+    let file = File::open(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let total = file.metadata().map_or(0, |m| m.len());
+    // Raw mode means the editor owns the screen; headless runs must not draw.
+    let show = total >= PROGRESS_MIN_BYTES && terminal::is_raw_mode_enabled().unwrap_or(false);
+    let name = path.file_name().map_or_else(|| path.display().to_string(), |n| n.to_string_lossy().into_owned());
+    let mut shown = None;
+    let progress = |read: usize| {
+        let percent = u64::try_from(read).unwrap_or(u64::MAX).saturating_mul(100) / total.max(1);
+        if show && shown != Some(percent) {
+            shown = Some(percent);
+            // Drawing is best effort; a failure only loses the indicator.
+            let _ = draw_progress(&name, percent);
+        }
+    };
+    match Buffer::read_utf8(file, progress) {
+        Err(e) if e.kind() == io::ErrorKind::InvalidData => bail!("{} is not UTF-8 (open it with --encoding)", path.display()),
+        result => result.with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
+
+/// Draw `Loading NAME... P%` on the bottom row.
+fn draw_progress(name: &str, percent: u64) -> io::Result<()> {
+    let (_, rows) = terminal::size()?;
+    let mut stdout = io::stdout();
+    stdout.queue(cursor::MoveTo(0, rows.saturating_sub(1)))?;
+    stdout.queue(terminal::Clear(terminal::ClearType::CurrentLine))?;
+    stdout.queue(style::Print(format!(" Loading {name}... {percent}%")))?;
+    stdout.flush()
+}
+
+impl Editor {
+    /// Whether the active buffer is at least `large_file_mb`.
+    pub(crate) fn is_large_file(&self) -> bool {
+        u64::try_from(self.doc.buf.len_bytes()).unwrap_or(u64::MAX) >= self.large_file_bytes
+    }
+
+    /// Put a newly opened buffer in large-file mode if it is large, and say so.
+    pub(crate) fn detect_large_file(&mut self) {
+        self.large_file = self.is_large_file();
+        if self.large_file {
+            let mb = self.doc.buf.len_bytes() >> 20;
+            self.set_status(format!("Large file ({mb} MB): syntax highlighting and word wrap are off"), Duration::from_secs(4));
+        }
+    }
+}
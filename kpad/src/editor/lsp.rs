@@ -0,0 +1,251 @@
+//! The editor side of the language server client (see `crate::lsp`): `sync_lsp` starts the
+//! server for the active file's extension and keeps it up to date with the buffer; diagnostics
+//! are kept by document URI, underlined in the text with a sign in the gutter, and listed by the
+//! `diagnostics` command.
+
+use super::Editor; // editor state
+use crate::lsp::{language_id, path_to_uri, utf16_to_char, Diagnostic, Server, Severity}; // protocol
+use crate::types::Pos; // jump target
+use anyhow::Result; // anyhow error handling
+use crossterm::{ // terminal manipulation
+    cursor,
+    event::{KeyCode, KeyEvent},
+    style::{self, Color},
+    terminal::{self, ClearType},
+    QueueableCommand,
+};
+use std::collections::HashMap; // servers, documents, diagnostics
+use std::fs; // canonical document paths
+use std::io::{Stdout, Write}; // output handling
+use std::ops::Range; // underlined chars
+use std::path::{Path, PathBuf}; // document paths
+use std::time::Duration; // timing for status messages
+
+/// A file the server was told about.
+struct Document {
+    uri: String,
+    /// Version last sent.
+    version: u64,
+    /// `Buffer::revision` of the text last sent.
+    revision: u64,
+}
+
+/// Language servers and what they reported.
+#[derive(Default)]
+pub(crate) struct Lsp {
+    /// Server command lines by file extension (`[lsp]` in kpad.toml).
+    commands: HashMap<String, Vec<String>>,
+    /// Servers by extension; `None` once one failed to start or stopped, so it is not retried.
+    servers: HashMap<String, Option<Server>>,
+    /// Documents opened on a server, by buffer path.
+    documents: HashMap<PathBuf, Document>,
+    /// Latest diagnostics by document URI.
+    diagnostics: HashMap<String, Vec<Diagnostic>>,
+    /// Selected row of the open diagnostics list (`diagnostics` command).
+    pub(crate) list: Option<usize>,
+}
+
+impl Lsp {
+    /// No servers yet; `commands` from `[lsp]`.
+    pub(crate) fn new(commands: HashMap<String, Vec<String>>) -> Self {
+        Self { commands, ..Self::default() }
+    }
+}
+
+impl Editor {
+    /// Start the language server for the active file if it has one, and send it the buffer
+    /// text when the server has not seen this revision. Called by the main loop before each
+    /// render; does nothing headless and in large-file mode.
+    pub fn sync_lsp(&mut self) {
+        if self.lsp.commands.is_empty() || self.large_file { return; }
+        let Some(tx) = self.bus.clone() else { return; };
+        let Some(path) = self.doc.file_path.clone() else { return; };
+        let ext = self.file_extension();
+        if !self.lsp.servers.contains_key(&ext) {
+            let Some(command) = self.lsp.commands.get(&ext).cloned() else { return; };
+            let root = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+            let server = Server::start(&command, &root, &ext, tx)
+                .map_err(|e| self.set_error(format!("Language server for .{ext}: {e:#}"), Duration::from_secs(5)))
+                .ok();
+            self.lsp.servers.insert(ext.clone(), server);
+        }
+        let Some(Some(server)) = self.lsp.servers.get(&ext) else { return; };
+        let revision = self.doc.buf.revision();
+        match self.lsp.documents.get_mut(&path) {
+            Some(doc) if doc.revision == revision => {}
+            Some(doc) => {
+                doc.version += 1;
+                doc.revision = revision;
+                server.did_change(&doc.uri, doc.version, &self.doc.buf.text.to_string());
+            }
+            None => {
+                let uri = path_to_uri(&fs::canonicalize(&path).unwrap_or_else(|_| std::env::current_dir().unwrap_or_default().join(&path)));
+                server.did_open(&uri, language_id(&ext), &self.doc.buf.text.to_string());
+                self.lsp.documents.insert(path, Document { uri, version: 1, revision });
+            }
+        }
+    }
+
+    /// New diagnostics for `uri` from a server.
+    pub fn set_lsp_diagnostics(&mut self, uri: String, mut diagnostics: Vec<Diagnostic>) {
+        diagnostics.sort_by_key(|d| (d.start, d.severity));
+        self.lsp.diagnostics.insert(uri, diagnostics);
+        self.mark_redraw();
+    }
+
+    /// The server for `ext` exited; its diagnostics go away and it is not restarted.
+    pub fn lsp_stopped(&mut self, ext: &str) {
+        if let Some(server) = self.lsp.servers.get_mut(ext) {
+            if server.take().is_some() {
+                self.set_error(format!("The language server for .{ext} stopped."), Duration::from_secs(4));
+            }
+        }
+        let Lsp { documents, diagnostics, .. } = &mut self.lsp;
+        documents.retain(|path, doc| {
+            let served = path.extension().is_some_and(|e| e.to_string_lossy().eq_ignore_ascii_case(ext));
+            if served { diagnostics.remove(&doc.uri); }
+            !served
+        });
+        self.mark_redraw();
+    }
+
+    /// No buffer shows `path` any more: tell its server and forget the document and its
+    /// diagnostics.
+    pub(crate) fn lsp_close(&mut self, path: &Path) {
+        let Some(doc) = self.lsp.documents.remove(path) else { return; };
+        let ext = path.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+        if let Some(Some(server)) = self.lsp.servers.get(&ext) {
+            server.did_close(&doc.uri);
+        }
+        self.lsp.diagnostics.remove(&doc.uri);
+    }
+
+    /// Diagnostics of the active buffer, in position order.
+    fn buffer_diagnostics(&self) -> &[Diagnostic] {
+        self.doc.file_path.as_ref()
+            .and_then(|p| self.lsp.documents.get(p))
+            .and_then(|doc| self.lsp.diagnostics.get(&doc.uri))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Chars of line `y` to underline, with their severity. An empty range still marks one char.
+    pub(crate) fn line_diagnostics(&self, y: usize, line: &str) -> Vec<(Range<usize>, Severity)> {
+        let len = line.chars().count();
+        self.buffer_diagnostics().iter().filter(|d| (d.start.0..=d.end.0).contains(&y)).map(|d| {
+            let start = if d.start.0 == y { utf16_to_char(line, d.start.1) } else { 0 };
+            let end = if d.end.0 == y { utf16_to_char(line, d.end.1) } else { len };
+            (start..end.max(start + 1), d.severity)
+        }).collect()
+    }
+
+    /// The most serious diagnostic starting on line `y`, for its gutter sign.
+    pub(crate) fn line_severity(&self, y: usize) -> Option<Severity> {
+        self.buffer_diagnostics().iter().filter(|d| d.start.0 == y).map(|d| d.severity).min()
+    }
+
+    /// Theme color of a severity.
+    pub(crate) fn severity_color(&self, severity: Severity) -> Color {
+        match severity {
+            Severity::Error => self.theme.diagnostic_error,
+            Severity::Warning => self.theme.diagnostic_warning,
+            Severity::Information | Severity::Hint => self.theme.diagnostic_info,
+        }
+    }
+
+    /// Open the list of the active buffer's diagnostics (`diagnostics` command).
+    pub fn show_diagnostics(&mut self) {
+        if self.buffer_diagnostics().is_empty() {
+            let msg = if self.lsp.commands.is_empty() { "No language servers configured ([lsp] in kpad.toml)." } else { "No diagnostics." };
+            self.set_status(msg, Duration::from_secs(2));
+            return;
+        }
+        // Start at the first diagnostic on or below the cursor line.
+        let at = self.buffer_diagnostics().iter().position(|d| d.start.0 >= self.doc.cursor.y);
+        self.lsp.list = Some(at.unwrap_or(0));
+        self.mark_redraw();
+    }
+
+    /// Keys in the diagnostics list: Up/Down, PageUp/PageDown, Home/End select; Enter moves
+    /// the cursor to the diagnostic; Esc or q close the list.
+    pub(crate) fn handle_diagnostics_key(&mut self, key: KeyEvent) -> Result<()> {
+        let page = usize::from(self.screen_size().map_or(24, |(_, h)| h)).saturating_sub(3).max(1);
+        let last = self.buffer_diagnostics().len().saturating_sub(1);
+        let Some(selected) = self.lsp.list.as_mut() else { return Ok(()); };
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.lsp.list = None,
+            KeyCode::Up => *selected = selected.saturating_sub(1),
+            KeyCode::Down => *selected = (*selected + 1).min(last),
+            KeyCode::PageUp => *selected = selected.saturating_sub(page),
+            KeyCode::PageDown => *selected = (*selected + page).min(last),
+            KeyCode::Home => *selected = 0,
+            KeyCode::End => *selected = last,
+            KeyCode::Enter => {
+                let index = *selected;
+                self.lsp.list = None;
+                self.mark_redraw();
+                let Some(&Diagnostic { start: (y, col), .. }) = self.buffer_diagnostics().get(index) else { return Ok(()); };
+                let y = y.min(self.doc.buf.line_count().saturating_sub(1));
+                let x = utf16_to_char(&self.doc.buf.line(y), col);
+                self.doc.cursor = self.doc.buf.clamp_pos(Pos { y, x });
+                self.clear_selection();
+                return self.ensure_visible();
+            }
+            _ => {}
+        }
+        self.mark_redraw();
+        Ok(())
+    }
+
+    /// Render the diagnostics list: title row, one row per diagnostic, key hint row.
+    pub fn render_diagnostics(&mut self, stdout: &mut Stdout) -> Result<()> {
+        let Some(selected) = self.lsp.list else { return Ok(()); };
+        let diagnostics = self.buffer_diagnostics();
+        let selected = selected.min(diagnostics.len().saturating_sub(1));
+        let (w, h) = terminal::size()?;
+        let width = usize::from(w);
+
+        stdout.queue(cursor::Hide)?;
+        stdout.queue(style::ResetColor)?;
+        stdout.queue(terminal::Clear(ClearType::All))?;
+
+        let name = self.doc.file_path.as_ref().map(|p| p.display().to_string()).unwrap_or_default();
+        let title = format!(" DIAGNOSTICS: {name} ({})", diagnostics.len());
+        stdout.queue(cursor::MoveTo(0, 0))?;
+        stdout.queue(style::SetBackgroundColor(self.theme.status_bg))?;
+        stdout.queue(style::SetForegroundColor(self.theme.status_fg))?;
+        stdout.queue(style::Print(format!("{:<width$}", title.chars().take(width).collect::<String>())))?;
+        stdout.queue(style::ResetColor)?;
+
+        // The list scrolls to keep the selected diagnostic on screen.
+        let list_h = usize::from(h).saturating_sub(2).max(1);
+        let skip = (selected + 1).saturating_sub(list_h);
+        for (row, (i, d)) in (1..h.saturating_sub(1)).zip(diagnostics.iter().enumerate().skip(skip)) {
+            // Servers count columns in UTF-16 units; show chars, like the status line.
+            let y = d.start.0.min(self.doc.buf.line_count().saturating_sub(1));
+            let column = utf16_to_char(&self.doc.buf.line(y), d.start.1) + 1;
+            let location = format!(" {}:{column}: {}: ", d.start.0 + 1, d.severity.label());
+            // Messages can span lines; the list shows the first.
+            let message = d.message.lines().next().unwrap_or_default();
+            let text: String = location.chars().chain(message.chars()).take(width).collect();
+            stdout.queue(cursor::MoveTo(0, row))?;
+            if i == selected {
+                stdout.queue(style::SetBackgroundColor(Color::White))?;
+                stdout.queue(style::SetForegroundColor(Color::Black))?;
+                stdout.queue(style::Print(format!("{text:<width$}")))?;
+                stdout.queue(style::ResetColor)?;
+            } else {
+                stdout.queue(style::SetForegroundColor(self.severity_color(d.severity)))?;
+                stdout.queue(style::Print(&location))?;
+                stdout.queue(style::ResetColor)?;
+                stdout.queue(style::Print(text.chars().skip(location.chars().count()).collect::<String>()))?;
+            }
+        }
+
+        stdout.queue(cursor::MoveTo(0, h.saturating_sub(1)))?;
+        stdout.queue(style::SetForegroundColor(Color::DarkGrey))?;
+        stdout.queue(style::Print(" Up/Down PgUp/PgDn Home/End select   Enter go to   Esc close"))?;
+        stdout.queue(style::ResetColor)?;
+        stdout.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,248 @@
+//! Keyboard macros: `record_macro` (F7) starts recording keys and stops it again, `play_macro`
+//! (F8) replays the last recording, and `save_macro` names it (optionally with a key), which
+//! makes it a command of its own, remembered in `<state dir>/macros.json`. Macros can also be
+//! written in kpad.toml under `[macros]` and bound under `[keybindings]` like any command.
+//!
+//! Macros are stored as text: typed characters stand for themselves, other keys are written
+//! in angle brackets (`<Enter>`, `<Ctrl+S>`, `<Shift+Right>`) and `<` itself as `<lt>`.
+
+use super::Editor; // editor state
+use crate::commands::{canonical_key_string, normalize_key_string, Command, CommandRegistry, CommandSource}; // macro commands
+use crate::plugins::PluginStore; // macros.json in the state directory
+use crate::types::{Prompt, PromptKind}; // save prompt
+use anyhow::{anyhow, bail, Result}; // anyhow error handling
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers}; // recorded keys
+use serde_json::{json, Value}; // stored macros
+use std::collections::HashMap; // macros from kpad.toml
+use std::time::Duration; // timing for status messages
+
+/// Macro recording and playback state.
+#[derive(Default)]
+pub(crate) struct Recorder {
+    /// Keys recorded so far; `None` when not recording.
+    keys: Option<Vec<KeyEvent>>,
+    /// Where the key that started the current action (a key binding, or the palette with what
+    /// was typed into it) begins in `keys`; stopping drops the keys from there on.
+    mark: usize,
+    /// The last recording, in macro notation.
+    last: String,
+    /// Whether a macro is running.
+    playing: bool,
+}
+
+/// `keys` in macro notation.
+pub(crate) fn encode_keys(keys: &[KeyEvent]) -> String {
+    let mut text = String::new();
+    for key in keys {
+        match key.code {
+            KeyCode::Char('<') if !key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => text.push_str("<lt>"),
+            KeyCode::Char(c) if !key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => text.push(c),
+            _ => {
+                text.push('<');
+                text.push_str(&canonical_key_string(key));
+                text.push('>');
+            }
+        }
+    }
+    text
+}
+
+/// The key named by a chord like `Ctrl+S` or `PageDown`.
+fn parse_chord(chord: &str) -> Option<KeyEvent> {
+    if chord.eq_ignore_ascii_case("lt") {
+        return Some(KeyEvent::new(KeyCode::Char('<'), KeyModifiers::NONE));
+    }
+    let canonical = normalize_key_string(chord);
+    let mut parts: Vec<&str> = canonical.split('+').collect();
+    let name = parts.pop()?;
+    let mut modifiers = KeyModifiers::NONE;
+    for part in parts {
+        modifiers |= match part {
+            "Ctrl" => KeyModifiers::CONTROL,
+            "Alt" => KeyModifiers::ALT,
+            _ => KeyModifiers::SHIFT,
+        };
+    }
+    let code = match name {
+        "Enter" => KeyCode::Enter,
+        "Esc" => KeyCode::Esc,
+        "Backspace" => KeyCode::Backspace,
+        "Delete" => KeyCode::Delete,
+        "Insert" => KeyCode::Insert,
+        "Tab" => KeyCode::Tab,
+        "BackTab" => KeyCode::BackTab,
+        "Left" => KeyCode::Left,
+        "Right" => KeyCode::Right,
+        "Up" => KeyCode::Up,
+        "Down" => KeyCode::Down,
+        "Home" => KeyCode::Home,
+        "End" => KeyCode::End,
+        "PageUp" => KeyCode::PageUp,
+        "PageDown" => KeyCode::PageDown,
+        _ if name.chars().count() > 1 => KeyCode::F(name.strip_prefix('F')?.parse().ok()?),
+        _ => KeyCode::Char(name.chars().next()?.to_ascii_lowercase()),
+    };
+    Some(KeyEvent::new(code, modifiers))
+}
+
+/// The keys of a macro written in macro notation.
+pub(crate) fn decode_keys(text: &str) -> Result<Vec<KeyEvent>> {
+    let mut keys = Vec::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        if c == '<' {
+            let end = rest.find('>').ok_or_else(|| anyhow!("Unclosed '<' in macro (write '<lt>' for '<')"))?;
+            let chord = &rest[1..end];
+            keys.push(parse_chord(chord).ok_or_else(|| anyhow!("Unknown key <{chord}> in macro"))?);
+            rest = &rest[end + 1..];
+        } else {
+            let code = if c == '\n' { KeyCode::Enter } else { KeyCode::Char(c) };
+            keys.push(KeyEvent::new(code, KeyModifiers::NONE));
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    Ok(keys)
+}
+
+/// A macro command named `name` that types `keys`.
+fn macro_command(name: &str, keys: &str, key: Option<String>) -> Command {
+    Command {
+        name: name.to_string(),
+        description: format!("Macro: {keys}"),
+        key,
+        source: CommandSource::Macro(keys.to_string()),
+    }
+}
+
+/// Whether `name` can name a macro: letters, digits and `_`, and not a command of kpad's own
+/// or a plugin's.
+fn check_name(reg: &CommandRegistry, name: &str) -> Result<()> {
+    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        bail!("Macro names are letters, digits and _");
+    }
+    if reg.get(name).is_some_and(|c| !matches!(c.source, CommandSource::Macro(_))) {
+        bail!("'{name}' is already a command");
+    }
+    Ok(())
+}
+
+/// Register the `[macros]` of kpad.toml and the saved macros as commands; returns a status
+/// message listing bad entries.
+pub fn register_macros(reg: &mut CommandRegistry, config: &HashMap<String, String>) -> Option<String> {
+    let mut problems = Vec::new();
+    for (name, keys) in config {
+        match check_name(reg, name).and_then(|()| decode_keys(keys)) {
+            Ok(_) => reg.register(macro_command(name, keys, None)),
+            Err(e) => problems.push(format!("macro '{name}': {e:#}")),
+        }
+    }
+    // A missing or unreadable store just means no saved macros.
+    let saved = PluginStore::named("macros").and_then(|store| store.entries()).unwrap_or_default();
+    for (name, value) in saved {
+        let Some(keys) = value.get("keys").and_then(Value::as_str) else { continue; };
+        let key = value.get("key").and_then(Value::as_str).map(normalize_key_string);
+        if check_name(reg, &name).is_ok() {
+            reg.register(macro_command(&name, keys, key));
+        }
+    }
+    problems.sort();
+    (!problems.is_empty()).then(|| format!("Config macros: {}", problems.join("; ")))
+}
+
+impl Editor {
+    /// Start or stop recording keys (`record_macro`).
+    pub fn toggle_macro_recording(&mut self) {
+        if let Some(mut keys) = self.macros.keys.take() {
+            keys.truncate(self.macros.mark);
+            self.macros.last = encode_keys(&keys);
+            let msg = format!("Recorded {} keys (play_macro replays them, save_macro names them).", keys.len());
+            self.set_status(msg, Duration::from_secs(3));
+        } else {
+            self.macros.keys = Some(Vec::new());
+            self.macros.mark = 0;
+            self.set_status("Recording a macro; record_macro again stops.", Duration::from_secs(3));
+        }
+        self.mark_redraw();
+    }
+
+    /// Whether keys are being recorded.
+    pub(crate) fn recording_macro(&self) -> bool {
+        self.macros.keys.is_some()
+    }
+
+    /// Add a key read from the terminal to the recording, if there is one.
+    pub fn record_key(&mut self, key: KeyEvent) {
+        let prompt_open = self.prompt.is_some();
+        let Some(keys) = &mut self.macros.keys else { return; };
+        if !prompt_open {
+            self.macros.mark = keys.len();
+        }
+        keys.push(key);
+    }
+
+    /// Type the keys of a macro written in macro notation.
+    pub(crate) fn run_macro(&mut self, keys: &str) -> Result<()> {
+        if self.macros.playing { bail!("A macro cannot run another macro"); }
+        let keys = decode_keys(keys)?;
+        self.macros.playing = true;
+        let res = keys.into_iter().try_for_each(|key| self.handle_key(key).map(drop));
+        self.macros.playing = false;
+        res
+    }
+
+    /// Replay the last recording (`play_macro`).
+    pub fn play_macro(&mut self) -> Result<()> {
+        if self.macros.last.is_empty() {
+            self.set_status("No macro recorded yet (record_macro).", Duration::from_secs(2));
+            return Ok(());
+        }
+        let keys = self.macros.last.clone();
+        self.run_macro(&keys)
+    }
+
+    /// Ask for the name (and optionally a key) to save the last recording under (`save_macro`).
+    pub fn prompt_save_macro(&mut self) {
+        if self.macros.last.is_empty() {
+            self.set_status("No macro recorded yet (record_macro).", Duration::from_secs(2));
+            return;
+        }
+        self.prompt = Some(Prompt::new(PromptKind::MacroName, ""));
+        self.mark_redraw();
+    }
+
+    /// Save the last recording as the command named by `input` (`name` or `name key`).
+    pub(crate) fn save_macro(&mut self, input: &str) {
+        let mut words = input.split_whitespace();
+        let name = words.next().unwrap_or_default();
+        let key = words.next().map(normalize_key_string);
+        if let Err(e) = check_name(&self.commands, name) {
+            self.set_error(format!("{e:#}"), Duration::from_secs(3));
+            return;
+        }
+        let keys = self.macros.last.clone();
+        self.commands.register(macro_command(name, &keys, key.clone()));
+        let bound = key.as_ref().map(|k| format!(" on {k}")).unwrap_or_default();
+        let saved = PluginStore::named("macros").and_then(|store| store.set(name, Some(json!({ "keys": keys, "key": key }))));
+        match saved {
+            Ok(()) => self.set_status(format!("Saved macro '{name}'{bound}."), Duration::from_secs(3)),
+            Err(e) => self.set_status(format!("Macro '{name}'{bound} (not remembered: {e:#})"), Duration::from_secs(4)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_round_trip_through_notation() {
+        let keys = decode_keys("a<lt>b<Enter><ctrl+s><Shift+Right><F3>").unwrap();
+        assert_eq!(keys.len(), 7);
+        assert_eq!(keys[1], KeyEvent::new(KeyCode::Char('<'), KeyModifiers::NONE));
+        assert_eq!(keys[4], KeyEvent::new(KeyCode::Char('s'), KeyModifiers::CONTROL));
+        assert_eq!(keys[5], KeyEvent::new(KeyCode::Right, KeyModifiers::SHIFT));
+        assert_eq!(encode_keys(&keys), "a<lt>b<Enter><Ctrl+S><Shift+Right><F3>");
+        assert!(decode_keys("<Enter").is_err());
+        assert!(decode_keys("<Nope>").is_err());
+    }
+}
@@ -0,0 +1,147 @@
+//! Search match count: finding shows `Match 3 of 17` in the status bar. Counting scans the
+//! whole buffer, so the match list is cached per query until the text changes (the buffer's
+//! `revision`).
+//!
+//! Find in selection: Ctrl+F with text selected searches only that range. Each match found is
+//! selected, and F3/Shift+F3 cycle through the matches in the range while a match stays
+//! selected and the text is unchanged.
+
+use crate::buffer::Buffer; // document model
+use crate::types::{Pos, Prompt, PromptKind, SelectionKind}; // positions, find prompt, stream selection
+use super::Editor; // editor state
+use anyhow::Result; // anyhow error handling
+use std::time::Duration; // timing for status messages
+
+/// Start positions (char indices) of every match of `query` in one text revision.
+pub(crate) struct MatchCache {
+    query: String,
+    revision: u64,
+    starts: Vec<usize>,
+}
+
+/// The range a Find started with a selection searches.
+pub(crate) struct FindScope {
+    start: Pos,
+    end: Pos,
+    /// Buffer revision the range belongs to; any edit ends the scope.
+    revision: u64,
+}
+
+/// Char indices where `query` matches in `buf`, overlapping matches included (F3 visits them).
+fn match_starts(buf: &Buffer, query: &str) -> Vec<usize> {
+    let mut starts = Vec::new();
+    let mut from = 0;
+    while let Some(i) = buf.search_from(query, from) {
+        starts.push(i);
+        from = i + 1;
+    }
+    starts
+}
+
+/// `Match N of M` for the match starting at char `at` among `starts`.
+fn match_text(starts: &[usize], at: usize) -> String {
+    match starts.binary_search(&at) {
+        Ok(i) => format!("Match {} of {}", i + 1, starts.len()),
+        Err(_) => format!("{} matches", starts.len()),
+    }
+}
+
+/// The match of `len` chars to select inside the char range `scope`: the first one after
+/// `current` (`forward`) or the last one before it, wrapping around, or the first one without a
+/// `current`. Returns its start, its 1-based number and the number of matches in the range.
+fn next_in_scope(starts: &[usize], scope: (usize, usize), len: usize, current: Option<usize>, forward: bool) -> Option<(usize, usize, usize)> {
+    let inside: Vec<usize> = starts.iter().copied().filter(|&s| s >= scope.0 && s + len <= scope.1).collect();
+    let i = match current {
+        None => 0,
+        Some(c) if forward => inside.iter().position(|&s| s > c).unwrap_or(0),
+        Some(c) => inside.iter().rposition(|&s| s < c).or_else(|| inside.len().checked_sub(1))?,
+    };
+    inside.get(i).map(|&s| (s, i + 1, inside.len()))
+}
+
+impl Editor {
+    /// Match starts of `query`, rescanned only when the query or the text changed.
+    fn cached_matches(&mut self, query: &str) -> &[usize] {
+        let revision = self.doc.buf.revision();
+        if !self.match_cache.as_ref().is_some_and(|c| c.query == query && c.revision == revision) {
+            self.match_cache = None;
+        }
+        let buf = &self.doc.buf;
+        &self.match_cache.get_or_insert_with(|| MatchCache {
+            query: query.to_string(),
+            revision,
+            starts: match_starts(buf, query),
+        }).starts
+    }
+
+    /// Where the match of `query` at the cursor falls among all of them, for the status bar.
+    pub(crate) fn match_position(&mut self, query: &str) -> String {
+        let at = self.doc.buf.pos_to_char_idx_public(self.doc.cursor);
+        match_text(self.cached_matches(query), at)
+    }
+
+    /// Open the Find prompt; a selection becomes the range searched, unless it is a match
+    /// inside the current range.
+    pub fn open_find(&mut self) {
+        if self.find_scope_range().is_none() {
+            self.find_scope = self.selection_range().map(|(start, end)| FindScope { start, end, revision: self.doc.buf.revision() });
+        }
+        self.prompt = Some(Prompt::new(PromptKind::Find, self.last_find.clone().unwrap_or_default()));
+        self.mark_redraw();
+    }
+
+    /// The range Find searches, while the text is unchanged and the selection lies inside it.
+    pub(crate) fn find_scope_range(&self) -> Option<(Pos, Pos)> {
+        let scope = self.find_scope.as_ref().filter(|s| s.revision == self.doc.buf.revision())?;
+        let (a, b) = self.selection_range()?;
+        (a >= scope.start && b <= scope.end).then_some((scope.start, scope.end))
+    }
+
+    /// Select the next (`forward`) or previous match of `query` in the find range, wrapping
+    /// within it; `restart` selects its first match (a new search).
+    pub(crate) fn find_in_scope(&mut self, query: &str, forward: bool, restart: bool) -> Result<()> {
+        let Some((start, end)) = self.find_scope_range() else { return Ok(()); };
+        let scope = (self.doc.buf.pos_to_char_idx_public(start), self.doc.buf.pos_to_char_idx_public(end));
+        let current = self.selection_range().map(|(a, _)| self.doc.buf.pos_to_char_idx_public(a)).filter(|_| !restart);
+        let len = query.chars().count();
+        let found = next_in_scope(self.cached_matches(query), scope, len, current, forward);
+        let Some((at, n, total)) = found else {
+            self.set_error(format!("No matches in the selection: {query}"), Duration::from_secs(2));
+            return Ok(());
+        };
+        self.doc.anchor = Some(self.doc.buf.char_idx_to_pos_public(at));
+        self.doc.cursor = self.doc.buf.char_idx_to_pos_public(at + len);
+        self.selection_kind = SelectionKind::Stream;
+        self.mark_redraw();
+        self.ensure_visible()?;
+        self.set_status(format!("Match {n} of {total} in selection: {query}"), Duration::from_secs(2));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_the_match_at_the_cursor() {
+        let buf = Buffer::from_string("aaa b\naa");
+        let starts = match_starts(&buf, "aa");
+        assert_eq!(starts, [0, 1, 6]);
+        assert_eq!(match_text(&starts, 1), "Match 2 of 3");
+        assert_eq!(match_text(&starts, 6), "Match 3 of 3");
+        assert_eq!(match_text(&starts, 4), "3 matches");
+    }
+
+    #[test]
+    fn cycles_inside_the_selection() {
+        let starts = [0, 10, 20, 30];
+        assert_eq!(next_in_scope(&starts, (5, 25), 2, None, true), Some((10, 1, 2)));
+        assert_eq!(next_in_scope(&starts, (5, 25), 2, Some(10), true), Some((20, 2, 2)));
+        assert_eq!(next_in_scope(&starts, (5, 25), 2, Some(20), true), Some((10, 1, 2)));
+        assert_eq!(next_in_scope(&starts, (5, 25), 2, Some(10), false), Some((20, 2, 2)));
+        // A match running past the end of the range does not count.
+        assert_eq!(next_in_scope(&starts, (5, 21), 2, None, true), Some((10, 1, 1)));
+        assert_eq!(next_in_scope(&starts, (1, 9), 2, None, true), None);
+    }
+}
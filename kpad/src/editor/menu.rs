@@ -0,0 +1,172 @@
+//! Right-click context menu: Cut, Copy, Paste, Select All and Find, then the items plugins add
+//! with `menu = "Label"` on a `[[commands]]` entry.
+//!
+//! Up/Down or the mouse pointer pick an item; Enter or a click runs it. Esc, any other key or
+//! a click outside the menu closes it.
+
+use super::Editor; // editor state
+use anyhow::Result; // anyhow error handling
+use crossterm::{ // terminal manipulation
+    cursor,
+    event::{KeyCode, KeyEvent, MouseButton, MouseEvent, MouseEventKind},
+    style::{self, Color},
+    QueueableCommand,
+};
+use std::io::Stdout; // output handling
+
+/// Built-in items: label and command.
+const BUILTIN_ITEMS: [(&str, &str); 5] = [
+    ("Cut", "cut"),
+    ("Copy", "copy"),
+    ("Paste", "paste"),
+    ("Select All", "select_all"),
+    ("Find", "find"),
+];
+
+/// An open context menu.
+pub(crate) struct ContextMenu {
+    /// Label and command of each item.
+    items: Vec<(String, String)>,
+    selected: usize,
+    /// Screen cell the menu was opened at.
+    at: (usize, usize),
+}
+
+/// Start of a menu `len` cells long opened at `click` on a screen `screen` cells long: at the
+/// click if it fits, else ending there.
+fn place(click: usize, len: usize, screen: usize) -> usize {
+    if click + len <= screen { click } else { click.saturating_sub(len) }
+}
+
+impl Editor {
+    /// Open the context menu for a right click at (`col`, `row`). A click outside the selection
+    /// moves the cursor there first, like a left click.
+    pub(crate) fn open_context_menu(&mut self, col: u16, row: u16) {
+        let (col, row) = (usize::from(col), usize::from(row));
+        let Some(pos) = self.pos_at(col, row) else { return; };
+        let at = (pos.y, pos.x);
+        let in_selection = self.selection_range().is_some_and(|(a, b)| (a.y, a.x) <= at && at < (b.y, b.x));
+        if !in_selection {
+            self.doc.anchor = None;
+            self.doc.cursor = pos;
+        }
+        self.completion = None;
+        let items = BUILTIN_ITEMS.iter()
+            .map(|&(label, command)| (label.to_string(), command.to_string()))
+            .chain(self.plugins.menu_items().cloned())
+            .collect();
+        self.context_menu = Some(ContextMenu { items, selected: 0, at: (col, row) });
+        self.mark_redraw();
+    }
+
+    /// Size of the menu box (columns, rows) and its top-left cell.
+    fn context_menu_rect(&self, menu: &ContextMenu) -> ((usize, usize), (usize, usize)) {
+        let (w, h) = self.screen_size().map_or((80, 24), |(w, h)| (usize::from(w), usize::from(h)));
+        let label_w = menu.items.iter().map(|(label, _)| label.chars().count()).max().unwrap_or(0);
+        let key_w = menu.items.iter().map(|(_, cmd)| self.menu_key(cmd).len()).max().unwrap_or(0);
+        let size = ((label_w + key_w + 4).min(w), menu.items.len().min(h.saturating_sub(1)));
+        let top_left = (place(menu.at.0, size.0, w), place(menu.at.1, size.1, h.saturating_sub(1)));
+        (size, top_left)
+    }
+
+    /// First key bound to `command`, shown right of its label.
+    fn menu_key(&self, command: &str) -> String {
+        self.commands.keys_for(command).first().map(|k| (*k).to_string()).unwrap_or_default()
+    }
+
+    /// Index of the menu item at screen cell (`col`, `row`).
+    fn context_menu_item_at(&self, col: usize, row: usize) -> Option<usize> {
+        let menu = self.context_menu.as_ref()?;
+        let ((w, h), (left, top)) = self.context_menu_rect(menu);
+        (col >= left && col < left + w && row >= top && row < top + h).then_some(row - top)
+    }
+
+    /// Close the menu and run item `i`. Returns `true` if the editor should quit.
+    fn run_context_menu_item(&mut self, i: usize) -> bool {
+        let Some(menu) = self.context_menu.take() else { return false; };
+        self.mark_redraw();
+        menu.items.get(i).is_some_and(|(_, command)| self.run_command_reporting(command))
+    }
+
+    /// Keys while the menu is open. Returns `true` if the editor should quit.
+    pub(crate) fn handle_context_menu_key(&mut self, key: KeyEvent) -> bool {
+        let Some(menu) = &mut self.context_menu else { return false; };
+        let last = menu.items.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Down => menu.selected = if menu.selected == last { 0 } else { menu.selected + 1 },
+            KeyCode::Up => menu.selected = menu.selected.checked_sub(1).unwrap_or(last),
+            KeyCode::Home => menu.selected = 0,
+            KeyCode::End => menu.selected = last,
+            KeyCode::Enter => {
+                let i = menu.selected;
+                return self.run_context_menu_item(i);
+            }
+            _ => self.context_menu = None,
+        }
+        self.mark_redraw();
+        false
+    }
+
+    /// Mouse events while the menu is open: hovering selects, a click runs or closes.
+    pub(crate) fn handle_context_menu_mouse(&mut self, mouse: MouseEvent) {
+        let item = self.context_menu_item_at(usize::from(mouse.column), usize::from(mouse.row));
+        match mouse.kind {
+            MouseEventKind::Moved => {
+                if let (Some(i), Some(menu)) = (item, &mut self.context_menu) {
+                    if menu.selected != i {
+                        menu.selected = i;
+                        self.mark_redraw();
+                    }
+                }
+            }
+            MouseEventKind::Down(MouseButton::Left) => {
+                match item {
+                    Some(i) => { self.run_context_menu_item(i); }
+                    None => self.context_menu = None,
+                }
+                self.mark_redraw();
+            }
+            MouseEventKind::Down(MouseButton::Right) if item.is_none() => {
+                self.context_menu = None;
+                self.open_context_menu(mouse.column, mouse.row);
+            }
+            MouseEventKind::Down(_) | MouseEventKind::ScrollUp | MouseEventKind::ScrollDown => {
+                self.context_menu = None;
+                self.mark_redraw();
+            }
+            _ => {}
+        }
+    }
+
+    /// Draw the menu box at the right-click position, flipped to stay on screen.
+    pub(crate) fn render_context_menu(&self, stdout: &mut Stdout) -> Result<()> {
+        let Some(menu) = &self.context_menu else { return Ok(()); };
+        let ((w, h), (left, top)) = self.context_menu_rect(menu);
+        for (row, (label, command)) in menu.items.iter().enumerate().take(h) {
+            let (Ok(col), Ok(line)) = (u16::try_from(left), u16::try_from(top + row)) else { break; };
+            let selected = row == menu.selected;
+            let key = self.menu_key(command);
+            let label_w = w.saturating_sub(key.len() + 3);
+            let text: String = format!(" {label:<label_w$} {key} ").chars().take(w).collect();
+            stdout.queue(cursor::MoveTo(col, line))?;
+            stdout.queue(style::SetBackgroundColor(if selected { Color::DarkBlue } else { self.caps.color(Color::AnsiValue(236)) }))?;
+            stdout.queue(style::SetForegroundColor(if selected { Color::White } else { Color::Grey }))?;
+            stdout.queue(style::Print(text))?;
+        }
+        stdout.queue(style::ResetColor)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn menu_stays_on_screen() {
+        assert_eq!(place(10, 5, 80), 10);
+        assert_eq!(place(77, 5, 80), 72);
+        assert_eq!(place(75, 5, 80), 75);
+        assert_eq!(place(3, 30, 20), 0);
+    }
+}
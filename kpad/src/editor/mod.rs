@@ -1,158 +1,529 @@
 //! Editor: the main application state and all editing operations.
 
+mod ansi; // strip terminal escape sequences
+mod ask; // questions from plugins
+mod attrs; // read-only file attribute
+mod block; // column (block) selection
+mod breadcrumb; // scope line above the text area
+mod buffers; // multiple open buffers
+mod click; // mouse click selection
+mod collab; // pair editing session
 mod builtin_commands; // built-in command registration
 mod clipboard; // clipboard operations
+mod color; // hex color swatch and picker
+mod complete; // word completion popup
+mod diff; // diff overlay
+mod dups; // repeated lines
+mod export; // copy with highlight colors
 mod file_ops; // open, save, search
+mod fileinfo; // file information overlay
+mod filetype; // plugin file-type bundles: comments, build
+mod focus; // pomodoro focus timer
+mod goal; // word-count goal
+mod grep; // find in files
 pub mod highlight; // syntax highlighting
+mod history; // prompt history
+mod indent; // indent/dedent selected lines
 mod input; // keyboard, mouse, prompt handling
+mod jumps; // jump list (jump_back / jump_forward)
+mod langs; // built-in language highlight rules
+mod macros; // keyboard macros
+mod large; // chunked loading, large-file mode
+mod lsp; // language server diagnostics
+mod matches; // search match count
+mod menu; // right-click context menu
+mod modal; // vim-style modal editing
 mod movement; // cursor movement
+mod pairs; // auto-closing brackets and quotes
+mod palette; // command palette suggestions
+mod pin; // pinned header lines
+mod preview; // file preview for the Open prompt
+mod rename; // rename identifier (whole-word replace)
+mod scope; // expand/shrink selection by scope
+mod sort; // sort, unique and reverse lines
+mod ring; // clipboard history
 mod render; // terminal rendering
 mod screens; // help and stats overlays
+mod settings; // set and theme commands
+mod split; // side-by-side panes on one buffer
+mod tabs; // tab bar of open buffers
+mod trash; // delete to the system trash
 mod undo; // undo/redo operations
+mod validate; // json/toml validators, diagnostics
+mod wrap; // word wrap rows
 
+use crate::bidi; // bi-directional text reordering
+use buffers::{BufferList, BufferState}; // inactive buffers, buffer list overlay
+use click::LastClick; // multi-click detection
+use color::ColorPicker; // color picker state
+use rename::RenameTarget; // pending rename
+use complete::Completion; // completion popup
+use menu::ContextMenu; // right-click menu
+use diff::DiffView; // diff overlay
+use grep::GrepView; // find-in-files results
+use file_ops::read_buffer; // reading files in an encoding
+use attrs::zone_note; // downloaded-file note at startup
 use crate::buffer::Buffer; // document model
-use crate::commands::{CommandRegistry, CommandSource}; // command system
-use crate::plugins::{Hook, PluginManager}; // plugin system
-use crate::types::{LineEnding, Pos, Prompt, StatusMsg, UndoEntry}; // core types
-use crate::utils::{default_plugin_dirs, digits}; // utility functions
-use anyhow::{Context, Result}; // anyhow error handling
+use crate::bus::{Bus, Message}; // background work posting to the main loop
+use crate::config::{Bell, Config, Keymap, MiddleClick, Sort, Theme, WrapIndent}; // user configuration
+use crate::commands::{split_args, CommandRegistry, CommandSource}; // command system
+use crate::plugins::{Hook, PluginManager, PluginTimer}; // plugin system
+use crate::remote::is_remote; // remote locations
+use crate::scaffold; // plugin skeletons (plugin_new)
+use crate::terminal::TermCaps; // terminal capability fallback
+use crate::types::{EditOperation, LineEnding, Pos, Prompt, SelectionKind, StatusMsg}; // core types
+use crate::utils::{char_width, clamp_usize, default_plugin_dirs, digits, scroll_with_margin}; // utility functions
+use anyhow::{anyhow, bail, Result}; // anyhow error handling
 use crossterm::terminal; // terminal manipulation
+use encoding_rs::Encoding; // --encoding
 use std::cmp::max; // comparison helpers
-use std::fs; // file system access
-use std::mem; // memory manipulation
-use std::path::PathBuf; // file path handling
+use std::collections::HashMap; // plugin registers
+use std::io::{self, Write}; // terminal bell
+use std::path::{Path, PathBuf}; // file path handling
+use std::rc::Rc; // shared plugin manager
+use std::sync::mpsc::Sender; // message bus sender
 use std::time::{Duration, Instant}; // timing
-use unicode_width::UnicodeWidthChar; // character width handling
 
 pub use builtin_commands::register_builtin_commands;
+pub use grep::GrepHit;
 pub use highlight::Highlighter;
+pub use macros::register_macros;
+use render::text_area_width;
+
+/// Status message shown at startup.
+const START_HINT: &str = "Ctrl+P commands • Ctrl+S save • Ctrl+Q quit";
+
+/// Screen size assumed without a terminal.
+const HEADLESS_SIZE: (u16, u16) = (80, 24);
+
+/// Maximum nesting of plugin calls (commands and hooks triggered from plugins).
+const MAX_PLUGIN_DEPTH: usize = 8;
+
+/// Maximum number of pending `api.defer` timers.
+const MAX_PLUGIN_TIMERS: usize = 64;
+
+/// Quiet period after a plugin file change before plugins are reloaded.
+const PLUGIN_RELOAD_DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// How long the cursor has to rest before the `on_cursor_move` hooks run.
+const CURSOR_HOOK_DEBOUNCE: Duration = Duration::from_millis(150);
+
+/// Viewport jumps larger than this many rows are animated when smooth scrolling is on.
+const SMOOTH_SCROLL_MIN_ROWS: usize = 3;
 
 /// The top-level application state.
 pub struct Editor {
-    /// The editable document (lines of text).
-    pub buf: Buffer,
-    /// Cursor position in the buffer.
-    pub cursor: Pos,
-    /// Selection anchor.
-    pub anchor: Option<Pos>,
-    /// Viewport scroll position.
-    pub scroll_y: usize,
-    pub scroll_x: usize,
-    /// Path we'll save to.
-    pub file_path: Option<PathBuf>,
-    /// "Dirty" means there are unsaved changes.
-    pub dirty: bool,
+    /// The active buffer: text, cursor, scroll, file, undo history.
+    pub(crate) doc: BufferState,
+    /// All open buffers. The entry at `active` is a placeholder: the active buffer's state lives
+    /// in `doc`.
+    pub(crate) buffers: Vec<BufferState>,
+    /// Index of the active buffer in `buffers`.
+    pub(crate) active: usize,
+    /// Index of the previously active buffer (`alternate_buffer`).
+    pub(crate) alternate: Option<usize>,
+    /// Open buffer list overlay (`buffers` command).
+    pub(crate) buffer_list: Option<BufferList>,
+    /// Identifier and range of the `rename_word` prompt being answered.
+    pub(crate) rename: Option<RenameTarget>,
+    /// Open word-completion popup (`complete_word`).
+    pub(crate) completion: Option<Completion>,
+    /// Open right-click context menu.
+    pub(crate) context_menu: Option<ContextMenu>,
+    /// Previous left click, for double and triple clicks.
+    pub(crate) last_click: Option<LastClick>,
+    /// Open diff overlay (`diff`, `diff_clipboard`).
+    pub(crate) diff_view: Option<DiffView>,
+    /// Open find-in-files results (`grep`).
+    pub(crate) grep_view: Option<GrepView>,
+    /// Clipboard history, newest first (see ring.rs).
+    pub(crate) clip_ring: Vec<String>,
+    /// Selected entry of the open clipboard history list (`paste_history`).
+    pub(crate) paste_history: Option<usize>,
+    /// Named registers plugins stash text in (`api.register_set`).
+    pub(crate) registers: HashMap<String, String>,
+    /// Posts background results (grep hits) to the main loop; `None` when headless.
+    pub(crate) bus: Option<Sender<Message>>,
+    /// The bus itself, read by `ask_user` while a plugin waits for an answer.
+    pub(crate) events: Option<Rc<Bus>>,
+    /// Lines of the open file info overlay (`file_info`).
+    pub(crate) file_info: Option<Vec<String>>,
+    /// Tracks close confirmation timing for a buffer with unsaved changes.
+    pub(crate) last_close_hint: Option<Instant>,
     /// Optional bottom-line prompt.
     pub(crate) prompt: Option<Prompt>,
     /// Short-lived status message.
     pub(crate) status: Option<StatusMsg>,
     /// Tracks quit confirmation timing.
     pub(crate) last_quit_hint: Option<Instant>,
-    /// Undo and redo stacks.
-    pub(crate) undo: Vec<UndoEntry>,
-    pub(crate) redo: Vec<UndoEntry>,
+    /// Most undo steps kept (`undo_steps`).
+    pub(crate) undo_step_limit: usize,
+    /// Most bytes the undo steps may hold (`undo_mb`).
+    pub(crate) undo_byte_limit: usize,
+    /// How `sort_lines` compares lines (`sort` in kpad.toml).
+    pub(crate) sort: Sort,
     /// Clipboard access.
     pub(crate) clipboard: Option<arboard::Clipboard>,
     /// Command registry.
     pub(crate) commands: CommandRegistry,
-    /// Loaded plugins.
-    pub(crate) plugins: PluginManager,
+    /// Loaded plugins. Shared so a running plugin keeps its manager alive while it calls back
+    /// into the editor (commands, hooks, even a reload).
+    pub(crate) plugins: Rc<PluginManager>,
     /// Last find query.
     pub(crate) last_find: Option<String>,
     /// Whether the screen needs to be redrawn.
     pub(crate) needs_redraw: bool,
-    /// Whether word wrapping is enabled.
+    /// Whether word wrapping is enabled (`wrapping` tells whether lines are actually wrapped).
     pub word_wrap: bool,
+    /// Indentation of continuation rows (`wrap_indent`).
+    pub(crate) wrap_indent: WrapIndent,
+    /// Line length the guide column follows (`ruler`); 0: no guide.
+    pub(crate) ruler: usize,
+    /// Whether the active buffer is in large-file mode (no highlighting or word wrap).
+    pub(crate) large_file: bool,
+    /// Size from which files open in large-file mode (`large_file_mb`).
+    pub(crate) large_file_bytes: u64,
+    /// Spaces inserted by Tab (`tab_width` in kpad.toml).
+    pub tab_width: usize,
+    /// Colors of the editor chrome.
+    pub(crate) theme: Theme,
+    /// What the terminal can draw; glyphs and colors fall back to ASCII and 16 colors without it.
+    pub(crate) caps: TermCaps,
+    /// Directories plugins are loaded from: the defaults plus `plugin_dirs` from kpad.toml.
+    pub(crate) plugin_dirs: Vec<PathBuf>,
     /// Whether the help screen is displayed.
     pub show_help: bool,
     /// Whether the stats screen is displayed.
     pub show_stats: bool,
+    /// Whether the plugins overlay is displayed.
+    pub show_plugins: bool,
+    /// Whether the key bindings overlay (`keys` command) is displayed.
+    pub show_keys: bool,
     /// Syntax highlighter for plugin-registered rules.
     pub highlighter: Highlighter,
+    /// Whether Left/Right follow visual order on lines containing right-to-left text.
+    pub bidi_visual_cursor: bool,
+    /// Whether East Asian ambiguous-width chars are drawn double width (CJK terminals).
+    pub ambiguous_wide: bool,
+    /// Whether tabs, trailing spaces and non-breaking spaces are drawn as visible marks.
+    pub show_whitespace: bool,
+    /// Lines of context kept above/below the cursor when scrolling (`scrolloff`).
+    pub scrolloff: usize,
+    /// Lines per mouse wheel step (`scroll_lines`).
+    pub(crate) scroll_lines: usize,
+    /// Whether the tab bar is shown when several buffers are open (`tab_bar` in kpad.toml).
+    pub tab_bar: bool,
+    /// Whether the breadcrumb line (scope around the cursor) is shown above the text.
+    pub breadcrumb: bool,
+    /// Whether the panes of a split view scroll together.
+    pub scrollbind: bool,
+    /// Whether large viewport jumps animate over a few frames.
+    pub smooth_scroll: bool,
+    /// Target row of an in-progress smooth scroll (advanced by `tick`).
+    pub(crate) scroll_target: Option<usize>,
+    /// Wake-up interval while time-based UI is pending (animations).
+    pub poll_interval: Duration,
+    /// Wake-up interval when nothing is pending.
+    pub idle_poll_interval: Duration,
+    /// Low-power mode: when idle, sleep until input arrives instead of waking periodically.
+    pub low_power: bool,
+    /// Running without a terminal (`--execute`): no screen, commands see an 80x24 one.
+    pub headless: bool,
+    /// Encoding files are opened in (`--encoding`); `None` means UTF-8.
+    pub(crate) open_encoding: Option<&'static Encoding>,
+    /// Auto-closing brackets and quotes (settings and pending closers).
+    pub(crate) auto_close: pairs::AutoClose,
+    /// Feedback on errors (`bell` in kpad.toml).
+    pub(crate) bell: Bell,
+    /// What a middle click pastes (`middle_click` in kpad.toml).
+    pub(crate) middle_click: MiddleClick,
+    /// Modal (vim-style) editing state; `None` for the usual modeless editing.
+    pub(crate) modal: Option<modal::Modal>,
+    /// Keyboard macro recording and playback.
+    pub(crate) macros: macros::Recorder,
+    /// Prompt history by kind (Up/Down in prompts).
+    pub(crate) history: history::History,
+    /// Pair editing session (`--attach`).
+    pub(crate) collab: Option<collab::Session>,
+    /// Whether the selection is a stream of text or a block (Alt+Shift+Arrow).
+    pub(crate) selection_kind: SelectionKind,
+    /// Text last copied from a block selection: pasting it inserts a column (see block.rs).
+    pub(crate) block_copy: Option<String>,
+    /// Problems reported by validators (`json_validate`, `api.diagnostic`), read by `--check`.
+    pub(crate) diagnostics: Vec<String>,
+    /// Open color picker (`color_picker` command), editing a `#RRGGBB` literal.
+    pub(crate) color_picker: Option<ColorPicker>,
+    /// Nesting depth of plugin calls (plugins can run commands that run plugins).
+    pub(crate) plugin_depth: usize,
+    /// When a pending (debounced) plugin auto-reload is due.
+    pub(crate) plugin_reload_at: Option<Instant>,
+    /// Plugin functions scheduled with `api.defer`, fired by `tick`.
+    pub(crate) plugin_timers: Vec<PluginTimer>,
+    /// Label of the open `PluginInput` / `PluginConfirm` prompt.
+    pub(crate) plugin_prompt: Option<String>,
+    /// Buffer and cursor position last seen by `track_cursor`.
+    cursor_seen: (usize, Pos),
+    /// When the pending (debounced) `on_cursor_move` hook is due.
+    cursor_hook_at: Option<Instant>,
+    /// Status bar text set by plugins (`api.set_status_segment`), by plugin id.
+    pub(crate) status_segments: Vec<(String, String)>,
+    /// When the open typing undo step was last extended (see undo.rs).
+    pub(crate) typing_at: Option<Instant>,
+    /// Repeated lines marked by `find_duplicates`.
+    pub(crate) duplicates: Option<dups::Duplicates>,
+    /// Matches of the last counted search (`Match 3 of 17`).
+    pub(crate) match_cache: Option<matches::MatchCache>,
+    /// Range searched by a Find started with a selection.
+    pub(crate) find_scope: Option<matches::FindScope>,
+    /// Running focus session (`pomodoro`).
+    pub(crate) focus: Option<focus::FocusTimer>,
+    /// Language servers (`[lsp]` in kpad.toml) and their diagnostics.
+    pub(crate) lsp: lsp::Lsp,
+    /// Cursor positions before long moves (`jump_back` / `jump_forward`).
+    pub(crate) jumps: jumps::JumpList,
+    /// Selections `expand_selection` grew from, for `shrink_selection`.
+    pub(crate) scope: scope::ScopeHistory,
 }
 
 impl Editor {
-    /// Create a new editor.
-    pub fn new(path: Option<PathBuf>) -> Result<Self> {
+    /// Create a new editor; `encoding` reads files in that encoding instead of UTF-8 until
+    /// `open_encoding` is cleared.
+    pub fn new(path: Option<PathBuf>, encoding: Option<&'static Encoding>) -> Result<Self> {
         let mut buf = Buffer::new();
         let mut file_path = None;
-        let mut highlighter = Highlighter::new();
+        let mut highlighter = langs::builtin_highlighter();
+        // Remote files are downloaded after startup so connection errors reach the status line.
+        let (path, remote_path) = match path {
+            Some(p) if p.to_str().is_some_and(is_remote) => (None, Some(p)),
+            p => (p, None),
+        };
 
         if let Some(p) = path {
             if p.exists() {
-                let s = fs::read_to_string(&p)
-                    .with_context(|| format!("Failed to read file: {}", p.display()))?;
-                buf = Buffer::from_string(&s);
-                file_path = Some(p.clone());
-            } else {
-                file_path = Some(p.clone());
+                buf = read_buffer(&p, encoding)?;
             }
+            file_path = Some(p.clone());
             // Set file extension for highlighter
             if let Some(ext) = p.extension().and_then(|e| e.to_str()) {
                 highlighter.set_file_extension(ext);
             }
         }
 
+        // A broken config file should not keep the editor from starting; it is reported below.
+        let (config, config_error) = match Config::load() {
+            Ok(c) => (c, None),
+            Err(e) => (Config::default(), Some(format!("Config error: {e:#}"))),
+        };
+        let caps = TermCaps::detect(|name| std::env::var(name).ok());
+        let theme = Theme::named(&config.theme).unwrap_or_else(|| Theme::named("dark").expect("built-in theme")).map(|c| caps.color(c));
+        let mut plugin_dirs = default_plugin_dirs()?;
+        plugin_dirs.extend(config.plugin_dirs.iter().cloned());
+
         let clipboard = arboard::Clipboard::new().ok();
         let mut commands = CommandRegistry::new();
         register_builtin_commands(&mut commands);
-        let plugins = PluginManager::load(default_plugin_dirs()?, &mut commands)?;
+        let plugins = Rc::new(PluginManager::load(plugin_dirs.clone(), &mut commands));
+        let macro_errors = register_macros(&mut commands, &config.macros);
+        let alias_errors = config.add_aliases(&mut commands);
+        let binding_errors = config.bind_keys(&mut commands);
 
         let mut ed = Self {
-            buf,
-            cursor: Pos { y: 0, x: 0 },
-            anchor: None,
-            scroll_y: 0,
-            scroll_x: 0,
-            file_path,
-            dirty: false,
+            doc: BufferState {
+                buf,
+                file_path,
+                ..BufferState::empty()
+            },
+            buffers: vec![BufferState::empty()],
+            active: 0,
+            alternate: None,
+            buffer_list: None,
+            rename: None,
+            completion: None,
+            context_menu: None,
+            last_click: None,
+            diff_view: None,
+            grep_view: None,
+            clip_ring: Vec::new(),
+            paste_history: None,
+            registers: HashMap::new(),
+            bus: None,
+            events: None,
+            file_info: None,
+            last_close_hint: None,
             prompt: None,
             status: None,
             last_quit_hint: None,
-            undo: vec![],
-            redo: vec![],
+            undo_step_limit: config.undo_step_limit(),
+            undo_byte_limit: config.undo_byte_limit(),
+            sort: config.sort,
             clipboard,
             commands,
             plugins,
             last_find: None,
             needs_redraw: true,
-            word_wrap: false,
+            word_wrap: config.word_wrap,
+            wrap_indent: config.wrap_indent,
+            ruler: config.ruler,
+            large_file: false,
+            large_file_bytes: config.large_file_bytes(),
+            tab_width: config.tab_width,
+            theme,
+            caps,
+            plugin_dirs,
             show_help: false,
             show_stats: false,
+            show_plugins: false,
+            show_keys: false,
             highlighter,
+            bidi_visual_cursor: false,
+            ambiguous_wide: false,
+            show_whitespace: false,
+            scrolloff: config.scrolloff,
+            scroll_lines: config.scroll_lines,
+            tab_bar: config.tab_bar,
+            breadcrumb: false,
+            scrollbind: false,
+            smooth_scroll: false,
+            scroll_target: None,
+            poll_interval: Duration::from_millis(config.poll_ms),
+            idle_poll_interval: Duration::from_millis(config.idle_poll_ms),
+            low_power: config.low_power,
+            headless: false,
+            diagnostics: Vec::new(),
+            auto_close: pairs::AutoClose::new(&config),
+            bell: config.bell,
+            middle_click: config.middle_click,
+            modal: (config.keymap == Keymap::Vim).then(modal::Modal::new),
+            macros: macros::Recorder::default(),
+            history: history::History::load(),
+            collab: None,
+            selection_kind: SelectionKind::Stream,
+            block_copy: None,
+            open_encoding: encoding,
+            color_picker: None,
+            plugin_depth: 0,
+            plugin_reload_at: None,
+            plugin_timers: Vec::new(),
+            plugin_prompt: None,
+            cursor_seen: (0, Pos { y: 0, x: 0 }),
+            cursor_hook_at: None,
+            status_segments: Vec::new(),
+            typing_at: None,
+            duplicates: None,
+            match_cache: None,
+            find_scope: None,
+            focus: None,
+            lsp: lsp::Lsp::new(config.lsp.clone()),
+            jumps: jumps::JumpList::default(),
+            scope: scope::ScopeHistory::default(),
         };
 
-        if let Some(p) = ed.file_path.clone() {
-            let mut plugins = mem::take(&mut ed.plugins);
-            plugins.call_hook(&mut ed, Hook::OnOpen, Some(&p))?;
-            ed.plugins = plugins;
+        ed.apply_plugin_filetypes();
+        if let Some(p) = ed.doc.file_path.clone() {
+            ed.run_hook(Hook::Open, Some(&p))?;
         }
 
-        ed.set_status("Ctrl+P commands • Ctrl+S save • Ctrl+Q quit", Duration::from_secs(4));
+        ed.set_status(ed.doc.file_path.as_deref().and_then(zone_note).unwrap_or_else(|| START_HINT.to_string()), Duration::from_secs(4));
+        ed.detect_large_file();
+        ed.load_word_goal();
+        ed.report_plugin_problems();
+        if let Some(msg) = config_error.or(macro_errors).or(alias_errors).or(binding_errors) {
+            ed.set_status(msg, Duration::from_secs(6));
+        }
+        if let Some(p) = remote_path {
+            ed.open_path(p)?;
+        }
         Ok(ed)
     }
 
+    /// Directories plugins are loaded from (and watched for auto-reload).
+    pub fn plugin_dirs(&self) -> &[PathBuf] {
+        &self.plugin_dirs
+    }
+
     /// Mark that the screen needs to be redrawn.
     pub fn mark_redraw(&mut self) {
         self.needs_redraw = true;
     }
 
+    /// Whether lines are wrapped: word wrap is on and the buffer is not a large file.
+    pub(crate) fn wrapping(&self) -> bool {
+        self.word_wrap && !self.large_file
+    }
+
     /// Toggle word wrapping.
     pub fn toggle_word_wrap(&mut self) {
+        if self.large_file {
+            self.set_status("Word wrap is off in large files.", Duration::from_secs(2));
+            return;
+        }
         self.word_wrap = !self.word_wrap;
-        if self.word_wrap { self.scroll_x = 0; }
+        if self.word_wrap { self.doc.scroll_x = 0; }
         self.set_status(format!("Word wrap: {}", if self.word_wrap { "on" } else { "off" }), Duration::from_secs(2));
         self.mark_redraw();
     }
 
+    /// Toggle logical/visual cursor movement on bi-directional lines.
+    pub fn toggle_bidi_visual_cursor(&mut self) {
+        self.bidi_visual_cursor = !self.bidi_visual_cursor;
+        self.set_status(format!("Bidi cursor movement: {}", if self.bidi_visual_cursor { "visual" } else { "logical" }), Duration::from_secs(2));
+    }
+
+    /// Draw with ASCII glyphs and the 16 basic colors only (`--ascii`).
+    pub fn use_ascii(&mut self) {
+        self.caps = TermCaps::ASCII;
+        self.theme = self.theme.map(|c| self.caps.color(c));
+        self.mark_redraw();
+    }
+
+    /// Toggle drawing whitespace as visible marks (`show_whitespace`).
+    pub fn toggle_show_whitespace(&mut self) {
+        self.show_whitespace = !self.show_whitespace;
+        self.set_status(format!("Show whitespace: {}", if self.show_whitespace { "on" } else { "off" }), Duration::from_secs(2));
+        self.mark_redraw();
+    }
+
+    /// Toggle treating ambiguous-width chars as wide.
+    pub fn toggle_ambiguous_wide(&mut self) -> Result<()> {
+        self.ambiguous_wide = !self.ambiguous_wide;
+        self.set_status(format!("Ambiguous-width chars: {}", if self.ambiguous_wide { "wide" } else { "narrow" }), Duration::from_secs(2));
+        self.mark_redraw();
+        // Column positions changed, so re-derive the scroll offsets.
+        self.ensure_visible()
+    }
+
+    /// Cycle the scroll margin through common presets.
+    pub fn cycle_scrolloff(&mut self) -> Result<()> {
+        const PRESETS: [usize; 4] = [0, 3, 5, 10];
+        let next = PRESETS.iter().position(|&n| n == self.scrolloff).map_or(0, |i| (i + 1) % PRESETS.len());
+        self.scrolloff = PRESETS[next];
+        self.set_status(format!("Scroll margin: {} lines", self.scrolloff), Duration::from_secs(2));
+        // Re-apply the margin right away.
+        self.ensure_visible()
+    }
+
+    /// Display width of `ch` in terminal cells, honoring the ambiguous-width setting.
+    pub fn char_width(&self, ch: char) -> usize {
+        char_width(ch, self.ambiguous_wide)
+    }
+
+    /// Whether the buffer may be edited; explains why not on the status line.
+    pub(crate) fn ensure_writable(&mut self) -> bool {
+        if self.doc.read_only {
+            self.set_error("Read-only buffer (Save As to edit a copy).", Duration::from_secs(2));
+        }
+        !self.doc.read_only
+    }
+
     /// Toggle line endings.
     pub fn toggle_line_ending(&mut self) {
-        self.buf.line_ending = match self.buf.line_ending {
+        if !self.ensure_writable() { return; }
+        self.doc.buf.line_ending = match self.doc.buf.line_ending {
             LineEnding::LF => LineEnding::CRLF,
             LineEnding::CRLF => LineEnding::LF,
         };
-        self.dirty = true;
-        self.set_status(format!("Line endings: {}", self.buf.line_ending.name()), Duration::from_secs(2));
+        self.doc.dirty = true;
+        self.set_status(format!("Line endings: {}", self.doc.buf.line_ending.name()), Duration::from_secs(2));
         self.mark_redraw();
     }
 
@@ -164,6 +535,187 @@ impl Editor {
                 self.mark_redraw();
             }
         }
+        self.advance_scroll_animation();
+        self.advance_focus_timer();
+        self.run_due_plugin_timers();
+        if self.cursor_hook_at.is_some_and(|at| Instant::now() >= at) {
+            self.cursor_hook_at = None;
+            if let Err(e) = self.run_hook(Hook::CursorMove, None) {
+                self.set_status(format!("{e:#}"), Duration::from_secs(3));
+            }
+            self.mark_redraw();
+        }
+        if self.plugin_reload_at.is_some_and(|at| Instant::now() >= at) {
+            self.plugin_reload_at = None;
+            if let Err(e) = self.reload_plugins() {
+                self.set_status(format!("Plugin reload failed: {e:#}"), Duration::from_secs(4));
+            }
+        }
+    }
+
+    /// How long the main loop may sleep before `tick` needs to run.
+    ///
+    /// Returns `None` when nothing time-based is pending and low-power mode is on, meaning the
+    /// loop can block until the next message arrives.
+    pub fn next_wakeup(&self) -> Option<Duration> {
+        if self.scroll_target.is_some() {
+            return Some(self.poll_interval);
+        }
+        let deadlines = [
+            self.status.as_ref().map(|st| st.until),
+            self.plugin_reload_at,
+            self.cursor_hook_at,
+            self.plugin_timers.iter().map(|t| t.at).min(),
+            self.focus_deadline(),
+        ];
+        if let Some(at) = deadlines.into_iter().flatten().min() {
+            return Some(at.saturating_duration_since(Instant::now()));
+        }
+        if self.low_power { None } else { Some(self.idle_poll_interval) }
+    }
+
+    /// Let background work (grep) post its results to the main loop through `bus`, and plugin
+    /// questions wait for keys on it.
+    pub fn set_bus(&mut self, bus: &Rc<Bus>) {
+        self.bus = Some(bus.sender());
+        self.events = Some(Rc::clone(bus));
+    }
+
+    /// Called after each input: when the cursor moved (or another buffer became active),
+    /// (re)schedule the `on_cursor_move` hooks to run once it rests.
+    pub fn track_cursor(&mut self) {
+        let seen = (self.active, self.doc.cursor);
+        if seen == self.cursor_seen { return; }
+        self.cursor_seen = seen;
+        if self.plugins.has_hook(Hook::CursorMove) {
+            self.cursor_hook_at = Some(Instant::now() + CURSOR_HOOK_DEBOUNCE);
+        }
+    }
+
+    /// Show `text` in the status bar for plugin `plugin_id`; empty text removes it.
+    pub(crate) fn set_status_segment(&mut self, plugin_id: &str, text: String) {
+        self.status_segments.retain(|(id, _)| id != plugin_id);
+        if !text.is_empty() {
+            self.status_segments.push((plugin_id.to_string(), text));
+        }
+        self.mark_redraw();
+    }
+
+    /// A plugin file changed on disk: reload once changes settle.
+    pub fn schedule_plugin_reload(&mut self) {
+        self.plugin_reload_at = Some(Instant::now() + PLUGIN_RELOAD_DEBOUNCE);
+    }
+
+    /// Schedule plugin function `func` to run after `delay`.
+    pub(crate) fn schedule_plugin_timer(&mut self, plugin_id: String, func: String, delay: Duration) -> Result<()> {
+        if self.plugin_timers.len() >= MAX_PLUGIN_TIMERS {
+            bail!("too many pending timers (limit {MAX_PLUGIN_TIMERS})");
+        }
+        self.plugin_timers.push(PluginTimer { at: Instant::now() + delay, plugin_id, func });
+        Ok(())
+    }
+
+    /// Run the plugin timers that are due. Failures are reported on the status line.
+    fn run_due_plugin_timers(&mut self) {
+        let now = Instant::now();
+        let (due, pending): (Vec<_>, Vec<_>) = self.plugin_timers.drain(..).partition(|t| t.at <= now);
+        self.plugin_timers = pending;
+        for t in due {
+            let plugins = Rc::clone(&self.plugins);
+            let res = self
+                .with_plugin_depth(|ed| plugins.run_command(ed, &t.plugin_id, &t.func, &[]))
+                .and_then(|()| self.ensure_visible());
+            if let Err(e) = res {
+                self.set_status(format!("{e:#}"), Duration::from_secs(3));
+            }
+            self.mark_redraw();
+        }
+    }
+
+    /// Reload all plugins from disk (commands, hooks and highlight rules).
+    ///
+    /// Highlight rules are registered by plugin hooks, so they are cleared and the `on_open`
+    /// hooks run again for the current file.
+    pub fn reload_plugins(&mut self) -> Result<()> {
+        self.commands.remove_plugin_commands();
+        self.highlighter.clear_all_rules();
+        self.plugin_timers.clear();
+        self.status_segments.clear();
+        self.plugins = Rc::new(PluginManager::load(self.plugin_dirs.clone(), &mut self.commands));
+        self.apply_plugin_filetypes();
+
+        if let Some(p) = self.doc.file_path.clone() {
+            self.run_hook(Hook::Open, Some(&p))?;
+        }
+        if !self.report_plugin_problems() {
+            self.set_status(format!("Reloaded {} plugin(s).", self.plugins.len()), Duration::from_secs(2));
+        }
+        self.mark_redraw();
+        Ok(())
+    }
+
+    /// Write a plugin skeleton named `name` into the user plugin directory, load it and open
+    /// its script (`plugin_new`).
+    pub(crate) fn create_plugin(&mut self, name: &str) -> Result<()> {
+        match scaffold::new_user_plugin(name) {
+            Ok(script) => {
+                self.reload_plugins()?;
+                self.open_path(script.clone())?;
+                self.set_status(format!("Created {}; edit it, save, and run {name}_hello.", script.display()), Duration::from_secs(5));
+            }
+            Err(e) => self.set_status(format!("{e:#}"), Duration::from_secs(4)),
+        }
+        Ok(())
+    }
+
+    /// Show plugin load errors and compatibility warnings on the status line.
+    /// Returns `false` when there is nothing to report.
+    fn report_plugin_problems(&mut self) -> bool {
+        let mut parts = Vec::new();
+        if !self.plugins.errors().is_empty() {
+            parts.push(format!("Plugin errors: {}", self.plugins.errors().join("; ")));
+        }
+        let warnings = self.plugins.warnings();
+        if !warnings.is_empty() {
+            parts.push(format!("Plugin warnings: {}", warnings.join("; ")));
+        }
+        if parts.is_empty() {
+            return false;
+        }
+        self.set_status(parts.join(" | "), Duration::from_secs(6));
+        true
+    }
+
+    /// Toggle low-power idle mode.
+    pub fn toggle_low_power(&mut self) {
+        self.low_power = !self.low_power;
+        self.set_status(format!("Low-power mode: {}", if self.low_power { "on" } else { "off" }), Duration::from_secs(2));
+    }
+
+    /// Move one frame toward the smooth-scroll target, easing out (half the remaining distance).
+    fn advance_scroll_animation(&mut self) {
+        let Some(target) = self.scroll_target else { return; };
+        let step = (target.abs_diff(self.doc.scroll_y) / 2).max(1);
+        self.doc.scroll_y = if target > self.doc.scroll_y { self.doc.scroll_y + step } else { self.doc.scroll_y - step };
+        if self.doc.scroll_y == target {
+            self.scroll_target = None;
+        }
+        self.mark_redraw();
+    }
+
+    /// Jump straight to the end of any running smooth scroll (called when input arrives).
+    pub fn finish_scroll_animation(&mut self) {
+        if let Some(target) = self.scroll_target.take() {
+            self.doc.scroll_y = target;
+            self.mark_redraw();
+        }
+    }
+
+    /// Toggle animated scrolling for large jumps.
+    pub fn toggle_smooth_scroll(&mut self) {
+        self.smooth_scroll = !self.smooth_scroll;
+        self.finish_scroll_animation();
+        self.set_status(format!("Smooth scrolling: {}", if self.smooth_scroll { "on" } else { "off" }), Duration::from_secs(2));
     }
 
     /// Called when the terminal is resized.
@@ -175,164 +727,287 @@ impl Editor {
 
     /// Show a message in the status bar.
     pub fn set_status(&mut self, msg: impl Into<String>, ttl: Duration) {
-        self.status = Some(StatusMsg { text: msg.into(), until: Instant::now() + ttl });
+        self.status = Some(StatusMsg { text: msg.into(), until: Instant::now() + ttl, alert: false });
         self.mark_redraw();
     }
 
+    /// Show a status message highlighted; the terminal bell rings too if `bell` is audible.
+    pub fn set_alert(&mut self, msg: impl Into<String>, ttl: Duration) {
+        self.set_status(msg, ttl);
+        self.ring_bell(true);
+    }
+
+    /// Show an error on the status line with the configured `bell`.
+    pub fn set_error(&mut self, msg: impl Into<String>, ttl: Duration) {
+        self.set_status(msg, ttl);
+        self.ring_bell(false);
+    }
+
+    /// Give the current status message the configured bell; `flash` highlights it regardless.
+    fn ring_bell(&mut self, flash: bool) {
+        if let Some(st) = &mut self.status {
+            st.alert = flash || self.bell.visual();
+        }
+        if self.bell.audible() && !self.headless {
+            // The bell is a courtesy; a failed write only loses the sound.
+            let mut stdout = io::stdout();
+            let _ = stdout.write_all(b"\x07").and_then(|()| stdout.flush());
+        }
+    }
+
     /// Return the normalized selection range.
     pub fn selection_range(&self) -> Option<(Pos, Pos)> {
-        let a = self.anchor?;
-        if a == self.cursor { None }
-        else if a <= self.cursor { Some((a, self.cursor)) }
-        else { Some((self.cursor, a)) }
+        let a = self.doc.anchor?;
+        if a == self.doc.cursor { None }
+        else if a <= self.doc.cursor { Some((a, self.doc.cursor)) }
+        else { Some((self.doc.cursor, a)) }
     }
 
     /// Clear any selection.
     pub fn clear_selection(&mut self) {
-        self.anchor = None;
+        self.doc.anchor = None;
+        self.selection_kind = SelectionKind::Stream;
         self.mark_redraw();
     }
 
     /// Select the entire buffer.
     pub fn select_all(&mut self) {
-        self.anchor = Some(Pos { y: 0, x: 0 });
-        let last_y = self.buf.line_count().saturating_sub(1);
-        let last_x = self.buf.line_len_chars(last_y);
-        self.cursor = Pos { y: last_y, x: last_x };
+        self.doc.anchor = Some(Pos { y: 0, x: 0 });
+        self.selection_kind = SelectionKind::Stream;
+        let last_y = self.doc.buf.line_count().saturating_sub(1);
+        let last_x = self.doc.buf.line_len_chars(last_y);
+        self.doc.cursor = Pos { y: last_y, x: last_x };
         self.mark_redraw();
     }
 
     /// Extract the selected text.
     pub fn selected_text(&self) -> String {
         let Some((a, b)) = self.selection_range() else { return String::new(); };
-        self.buf.get_range(a, b)
+        self.doc.buf.get_range(a, b)
     }
 
     /// Delete the current selection.
     pub fn delete_selection(&mut self) {
         if let Some((a, b)) = self.selection_range() {
-            self.cursor = self.buf.delete_range(a, b);
+            self.doc.cursor = self.doc.buf.delete_range(a, b);
             self.clear_selection();
-            self.dirty = true;
+            self.doc.dirty = true;
         }
     }
 
+    /// Replace lines `first..=last` with `text` as a single undoable edit.
+    pub fn replace_lines(&mut self, first: usize, last: usize, text: &str) {
+        let end = Pos { y: last, x: self.doc.buf.line_len_chars(last) };
+        self.replace_range(Pos { y: first, x: 0 }, end, text);
+    }
+
+    /// Replace the text between `start` and `end` with `text` as a single undoable edit.
+    pub fn replace_range(&mut self, start: Pos, end: Pos, text: &str) {
+        let old = self.doc.buf.get_range(start, end);
+        self.record_edit(EditOperation::Replace { start, old, new: text.to_string() });
+        self.doc.buf.delete_range(start, end);
+        self.doc.buf.insert_str(start, text);
+        self.doc.dirty = true;
+        self.mark_redraw();
+    }
+
+    /// `replace_range` for an edit made away from the cursor (a remote peer, a plugin): the
+    /// cursor and selection anchor keep their place in the text around it.
+    pub fn replace_range_in_place(&mut self, start: Pos, end: Pos, text: &str) {
+        let (at, to) = (self.doc.buf.pos_to_char_idx_public(start), self.doc.buf.pos_to_char_idx_public(end));
+        let inserted = text.chars().count();
+        let cursor = collab::shift(self.doc.buf.pos_to_char_idx_public(self.doc.cursor), at, to - at, inserted);
+        let anchor = self.doc.anchor.map(|a| collab::shift(self.doc.buf.pos_to_char_idx_public(a), at, to - at, inserted));
+        self.replace_range(start, end, text);
+        self.doc.cursor = self.doc.buf.char_idx_to_pos_public(cursor);
+        self.doc.anchor = anchor.map(|a| self.doc.buf.char_idx_to_pos_public(a));
+    }
+
     /// Replace the selection or insert at cursor.
     pub fn replace_selection_or_insert(&mut self, text: &str) {
         if self.selection_range().is_some() { self.delete_selection(); }
-        self.cursor = self.buf.insert_str(self.cursor, text);
-        self.dirty = true;
+        self.doc.cursor = self.doc.buf.insert_str(self.doc.cursor, text);
+        self.doc.dirty = true;
         self.mark_redraw();
     }
 
+    /// Scroll the focused pane by `scroll_lines` rows (mouse wheel), leaving the cursor and
+    /// selection alone; scrolling down stops with the last line at the bottom.
+    pub(crate) fn scroll_view(&mut self, down: bool) -> Result<()> {
+        let (w, h) = self.screen_size()?;
+        let width = self.pane_columns(w as usize).1;
+        let editor_h = self.text_rows(h as usize);
+        let last = self.document_rows(width).saturating_sub(editor_h).max(self.doc.scroll_y);
+        self.doc.scroll_y = if down {
+            (self.doc.scroll_y + self.scroll_lines).min(last)
+        } else {
+            self.doc.scroll_y.saturating_sub(self.scroll_lines)
+        };
+        self.doc.scroll_y = self.doc.scroll_y.max(self.pinned_scroll_min(width));
+        self.mark_redraw();
+        Ok(())
+    }
+
     /// Update scroll so the cursor is visible.
     pub fn ensure_visible(&mut self) -> Result<()> {
-        let (w, h) = terminal::size()?;
-        let width = w as usize;
-        let height = h as usize;
-        let prompt_lines = if self.prompt.is_some() { 1 } else { 0 };
-        let editor_h = height.saturating_sub(prompt_lines + 1);
-        let old_scroll_y = self.scroll_y;
-        let old_scroll_x = self.scroll_x;
-
-        if self.word_wrap {
+        let (w, h) = self.screen_size()?;
+        let width = self.pane_columns(w as usize).1;
+        let editor_h = self.text_rows(h as usize);
+        let old_scroll_y = self.doc.scroll_y;
+        let old_scroll_x = self.doc.scroll_x;
+
+        if self.wrapping() {
             self.ensure_visible_wrapped(width, editor_h)?;
         } else {
             self.ensure_visible_normal(width, editor_h)?;
         }
+        // A cursor on a pinned line is always visible; the scrolled text stays where it was.
+        if self.doc.cursor.y < self.pinned_rows() {
+            self.doc.scroll_y = old_scroll_y;
+        }
+        self.doc.scroll_y = self.doc.scroll_y.max(self.pinned_scroll_min(width));
 
-        if old_scroll_y != self.scroll_y || old_scroll_x != self.scroll_x {
+        // Large jumps (PageDown, goto, find) animate toward the new position from `tick`.
+        if self.smooth_scroll && self.doc.scroll_y.abs_diff(old_scroll_y) > SMOOTH_SCROLL_MIN_ROWS {
+            self.scroll_target = Some(self.doc.scroll_y);
+            self.doc.scroll_y = old_scroll_y;
+        }
+
+        if old_scroll_y != self.doc.scroll_y || old_scroll_x != self.doc.scroll_x {
             self.mark_redraw();
         }
         Ok(())
     }
 
     fn ensure_visible_wrapped(&mut self, width: usize, editor_h: usize) -> Result<()> {
-        let lnw = max(2, digits(self.buf.line_count()));
+        let lnw = max(2, digits(self.doc.buf.line_count()));
         let gutter = lnw + 2;
-        let avail = width.saturating_sub(gutter);
+        let avail = text_area_width(width, gutter);
 
+        // Count every screen row: the total is needed so the margin never scrolls past the end.
         let mut cursor_screen_row = 0;
-        for i in 0..self.buf.line_count() {
-            let line = self.buf.line(i);
-            if i == self.cursor.y {
-                let mut current_col = 0;
-                let mut seg_idx = 0;
-                for (char_i, ch) in line.chars().enumerate() {
-                    if char_i >= self.cursor.x { break; }
-                    let ch_w = UnicodeWidthChar::width(ch).unwrap_or(1);
-                    if current_col + ch_w > avail {
-                        seg_idx += 1;
-                        current_col = 0;
-                    }
-                    current_col += ch_w;
-                }
-                cursor_screen_row += seg_idx;
-                break;
-            }
-            if line.is_empty() {
-                cursor_screen_row += 1;
-            } else {
-                let mut current_col = 0;
-                let mut rows = 1;
-                for ch in line.chars() {
-                    let ch_w = UnicodeWidthChar::width(ch).unwrap_or(1);
-                    if current_col + ch_w > avail {
-                        rows += 1;
-                        current_col = 0;
-                    }
-                    current_col += ch_w;
-                }
-                cursor_screen_row += rows;
+        let mut total_rows = 0;
+        for i in 0..self.doc.buf.line_count() {
+            let line = self.doc.buf.line(i);
+            let segments = self.calculate_wrap_segments(&line, avail);
+            if i == self.doc.cursor.y {
+                cursor_screen_row = total_rows + segments.iter().rposition(|&start| start <= self.doc.cursor.x).unwrap_or(0);
             }
+            total_rows += segments.len();
         }
 
-        if cursor_screen_row < self.scroll_y {
-            self.scroll_y = cursor_screen_row;
-        } else if cursor_screen_row >= self.scroll_y + editor_h {
-            self.scroll_y = cursor_screen_row.saturating_sub(editor_h.saturating_sub(1));
-        }
-        self.scroll_x = 0;
+        self.doc.scroll_y = scroll_with_margin(self.doc.scroll_y, cursor_screen_row, total_rows, editor_h, self.scrolloff);
+        self.doc.scroll_x = 0;
         Ok(())
     }
 
     fn ensure_visible_normal(&mut self, width: usize, editor_h: usize) -> Result<()> {
-        if self.cursor.y < self.scroll_y {
-            self.scroll_y = self.cursor.y;
-        } else if self.cursor.y >= self.scroll_y + editor_h {
-            self.scroll_y = self.cursor.y.saturating_sub(editor_h.saturating_sub(1));
-        }
+        self.doc.scroll_y = scroll_with_margin(self.doc.scroll_y, self.doc.cursor.y, self.doc.buf.line_count(), editor_h, self.scrolloff);
 
-        let lnw = max(2, digits(self.buf.line_count()));
+        let lnw = max(2, digits(self.doc.buf.line_count()));
         let gutter = lnw + 2;
-        let avail = width.saturating_sub(gutter).saturating_sub(1);
+        let avail = text_area_width(width, gutter);
 
-        let line = self.buf.line(self.cursor.y);
-        let cursor_col: usize = line.chars().take(self.cursor.x)
-            .map(|ch| UnicodeWidthChar::width(ch).unwrap_or(1)).sum();
-        let scroll_col: usize = line.chars().take(self.scroll_x)
-            .map(|ch| UnicodeWidthChar::width(ch).unwrap_or(1)).sum();
+        // Horizontal scrolling works in visual (display) columns so RTL lines scroll correctly.
+        let line = self.doc.buf.line(self.doc.cursor.y);
+        let chars: Vec<char> = line.chars().collect();
+        let order = bidi::display_order(&line);
+        let cursor_vis = bidi::logical_to_visual(&order, self.doc.cursor.x);
+        let cursor_col: usize = order.iter().take(cursor_vis)
+            .map(|&i| self.char_width(chars[i])).sum();
+        let scroll_col: usize = order.iter().take(self.doc.scroll_x)
+            .map(|&i| self.char_width(chars[i])).sum();
+        // The whole cursor cell must fit, so a wide char never straddles the right edge.
+        let cursor_w = chars.get(self.doc.cursor.x).map_or(1, |&ch| self.char_width(ch));
 
         if cursor_col < scroll_col {
-            self.scroll_x = self.cursor.x;
-        } else if cursor_col >= scroll_col + avail {
-            let target_col = cursor_col.saturating_sub(avail.saturating_sub(1));
+            self.doc.scroll_x = cursor_vis;
+        } else if cursor_col + cursor_w > scroll_col + avail {
+            let target_col = (cursor_col + cursor_w).saturating_sub(avail);
             let mut col = 0;
             let mut new_scroll_x = 0;
-            for (i, ch) in line.chars().enumerate() {
-                if col >= target_col { new_scroll_x = i; break; }
-                col += UnicodeWidthChar::width(ch).unwrap_or(1);
+            for (vis, &i) in order.iter().enumerate() {
+                if col >= target_col { new_scroll_x = vis; break; }
+                col += self.char_width(chars[i]);
             }
-            self.scroll_x = new_scroll_x;
+            self.doc.scroll_x = new_scroll_x;
         }
         Ok(())
     }
 
-    /// Run a command by name.
-    pub fn run_command_by_name(&mut self, name: &str) -> Result<bool> {
-        let name = name.trim();
-        if name.eq_ignore_ascii_case("quit") { return Ok(self.try_quit()); }
+    /// Center the viewport on line `y`, moving the cursor there if it is on another line.
+    pub fn center_on_line(&mut self, y: usize) -> Result<()> {
+        let y = y.min(self.doc.buf.line_count().saturating_sub(1));
+        if self.doc.cursor.y != y {
+            self.doc.cursor = Pos { y, x: self.doc.cursor.x.min(self.doc.buf.line_len_chars(y)) };
+            self.doc.anchor = None;
+        }
+        let (w, h) = self.screen_size()?;
+        let width = self.pane_columns(w as usize).1;
+        let editor_h = self.text_rows(h as usize);
+
+        // In wrap mode the scroll position counts screen rows, not lines.
+        let row = if self.wrapping() {
+            let gutter = max(2, digits(self.doc.buf.line_count())) + 2;
+            let avail = text_area_width(width, gutter);
+            (0..y).map(|i| self.calculate_wrap_segments(&self.doc.buf.line(i), avail).len()).sum()
+        } else {
+            y
+        };
+        self.scroll_target = None;
+        self.doc.scroll_y = row.saturating_sub(editor_h / 2).max(self.pinned_scroll_min(width));
+        self.mark_redraw();
+        Ok(())
+    }
+
+    /// Move the cursor to 1-based line `n` and, if given, 1-based char column `col`, clamped to
+    /// the buffer (Goto line, `--line`, `FILE:LINE:COL`).
+    pub fn goto_line(&mut self, n: isize, col: Option<usize>) -> Result<()> {
+        self.push_jump();
+        self.doc.cursor.y = clamp_usize(n - 1, 0, self.doc.buf.line_count().saturating_sub(1));
+        let x = col.map_or(self.doc.cursor.x, |c| c.saturating_sub(1));
+        self.doc.cursor.x = x.min(self.doc.buf.line_len_chars(self.doc.cursor.y));
+        self.clear_selection();
+        self.ensure_visible()
+    }
+
+    /// Run a plugin lifecycle hook on all plugins.
+    pub(crate) fn run_hook(&mut self, hook: Hook, path: Option<&PathBuf>) -> Result<()> {
+        let plugins = Rc::clone(&self.plugins);
+        self.with_plugin_depth(|ed| plugins.call_hook(ed, hook, path))
+    }
+
+    /// Run a Rhai script file against the plugin API (`--script`).
+    pub fn run_script_file(&mut self, path: &Path) -> Result<()> {
+        let plugins = Rc::clone(&self.plugins);
+        self.with_plugin_depth(|ed| plugins.run_script(ed, path))
+    }
+
+    /// Run `f` one plugin-call level deeper, refusing runaway recursion (a command that runs
+    /// itself, an `on_open` hook that opens a file, ...).
+    fn with_plugin_depth(&mut self, f: impl FnOnce(&mut Self) -> Result<()>) -> Result<()> {
+        if self.plugin_depth >= MAX_PLUGIN_DEPTH {
+            bail!("Plugin calls nested too deeply (limit {MAX_PLUGIN_DEPTH})");
+        }
+        self.plugin_depth += 1;
+        let res = f(self);
+        self.plugin_depth -= 1;
+        res
+    }
+
+    /// Run a command line: a command name or alias, then its arguments (`goto_line 120`,
+    /// `set wrap on`; see `split_args`).
+    pub fn run_command_by_name(&mut self, line: &str) -> Result<bool> {
+        let words = split_args(&self.commands.expand_alias(line))?;
+        let Some((name, args)) = words.split_first() else { return Ok(false); };
+        let name = name.as_str();
+        let takes_no_args = || if args.is_empty() { Ok(()) } else { Err(anyhow!("'{name}' takes no arguments")) };
+        if name.eq_ignore_ascii_case("quit") {
+            takes_no_args()?;
+            return Ok(self.try_quit());
+        }
         if name.eq_ignore_ascii_case("save_and_quit") {
+            takes_no_args()?;
             self.cmd_save()?;
             return Ok(self.try_quit());
         }
@@ -340,14 +1015,20 @@ impl Editor {
         let cmd_opt = self.commands.get(name).cloned();
         if let Some(cmd) = cmd_opt {
             match cmd.source {
-                CommandSource::Builtin(f) => { f(self)?; }
+                CommandSource::Builtin(f) => {
+                    takes_no_args()?;
+                    f(self)?;
+                }
+                CommandSource::BuiltinArgs(f) => f(self, args)?,
                 CommandSource::Plugin { plugin_id, func } => {
-                    let mut plugins = mem::take(&mut self.plugins);
-                    let res = plugins.run_command(self, &plugin_id, &func);
-                    self.plugins = plugins;
-                    res?;
+                    let plugins = Rc::clone(&self.plugins);
+                    self.with_plugin_depth(|ed| plugins.run_command(ed, &plugin_id, &func, args))?;
                     self.ensure_visible()?;
                 }
+                CommandSource::Macro(keys) => {
+                    takes_no_args()?;
+                    self.run_macro(&keys)?;
+                }
             }
             self.mark_redraw();
             Ok(false)
@@ -356,8 +1037,45 @@ impl Editor {
             if let Some(suggestion) = self.commands.suggest_command(name) {
                 msg.push_str(&format!(". Did you mean '{}'?", suggestion.name));
             }
-            self.set_status(msg, Duration::from_secs(3));
+            self.set_error(msg, Duration::from_secs(3));
             Ok(false)
         }
     }
+
+    /// Run a command from a key, the palette or the context menu. Errors (a failed save, ...) go
+    /// to the status line with the bell. Returns `true` if the editor should quit.
+    pub(crate) fn run_command_reporting(&mut self, name: &str) -> bool {
+        self.run_command_by_name(name).unwrap_or_else(|e| {
+            self.set_error(format!("{e:#}"), Duration::from_secs(5));
+            false
+        })
+    }
+
+    /// Terminal size in columns and rows; a fixed 80x24 without a terminal (`--execute`).
+    pub(crate) fn screen_size(&self) -> Result<(u16, u16)> {
+        if self.headless { return Ok(HEADLESS_SIZE); }
+        Ok(terminal::size()?)
+    }
+
+    /// Run a command line without a terminal (`--execute`). Returns the status message it left and
+    /// whether it ends the run (`quit`, `save_and_quit`). Unknown commands and commands that
+    /// wait for prompt input are errors.
+    pub fn run_command_headless(&mut self, line: &str) -> Result<(Option<String>, bool)> {
+        let expanded = self.commands.expand_alias(line);
+        let name = expanded.split_whitespace().next().unwrap_or_default();
+        if name.eq_ignore_ascii_case("quit") { return Ok((None, true)); }
+        let quits = name.eq_ignore_ascii_case("save_and_quit");
+        if !quits && self.commands.get(name).is_none() {
+            let hint = self.commands.suggest_command(name)
+                .map(|c| format!(" (did you mean '{}'?)", c.name))
+                .unwrap_or_default();
+            bail!("Unknown command '{name}'{hint}");
+        }
+        self.status = None;
+        self.run_command_by_name(line)?;
+        if self.prompt.is_some() {
+            bail!("'{name}' asks for input, which --execute cannot answer");
+        }
+        Ok((self.status.take().map(|s| s.text), quits))
+    }
 }
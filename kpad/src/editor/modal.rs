@@ -0,0 +1,389 @@
+//! Optional vim-style modal editing (`keymap = "vim"` in kpad.toml, or the `toggle_modal`
+//! command). Normal mode moves with `h j k l w b 0 $ gg G` and applies the operators `d`, `y`
+//! and `c` to a motion (`dw`, `y$`, `dgg`) or to whole lines (`dd`, `yy`, `cc`); `i a I A o O`
+//! enter Insert mode, where keys behave as usual until Esc; `v` starts Visual mode, where
+//! motions extend the selection and `d`/`y`/`c` act on it. Also `x`, `p`/`P` (put the last
+//! deleted or yanked text) and `u` (undo). Ctrl/Alt chords and the arrow keys keep their usual
+//! meaning in every mode.
+
+use super::Editor; // editor state
+use crate::types::Pos; // positions
+use anyhow::Result; // anyhow error handling
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers}; // terminal events
+use std::time::Duration; // timing for status messages
+
+/// Modal editing mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Mode {
+    Normal,
+    Insert,
+    Visual,
+}
+
+/// Modal editing state.
+pub(crate) struct Modal {
+    mode: Mode,
+    /// Keys of an unfinished command, e.g. `d` or `dg`.
+    keys: String,
+    /// Text of the last delete or yank, for `p`/`P`.
+    register: String,
+    /// Whether the register holds whole lines (from `dd`, `yj`, ...).
+    linewise: bool,
+}
+
+impl Modal {
+    pub(crate) fn new() -> Self {
+        Self { mode: Mode::Normal, keys: String::new(), register: String::new(), linewise: false }
+    }
+}
+
+/// A cursor motion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Motion {
+    Left,
+    Down,
+    Up,
+    Right,
+    WordForward,
+    WordBack,
+    LineStart,
+    LineEnd,
+    FirstLine,
+    LastLine,
+}
+
+impl Motion {
+    /// The motion of a single key.
+    fn from_key(ch: char) -> Option<Self> {
+        Some(match ch {
+            'h' => Self::Left,
+            'j' => Self::Down,
+            'k' => Self::Up,
+            'l' => Self::Right,
+            'w' => Self::WordForward,
+            'b' => Self::WordBack,
+            '0' => Self::LineStart,
+            '$' => Self::LineEnd,
+            'G' => Self::LastLine,
+            _ => return None,
+        })
+    }
+
+    /// Whether an operator with this motion acts on whole lines.
+    fn linewise(self) -> bool {
+        matches!(self, Self::Down | Self::Up | Self::FirstLine | Self::LastLine)
+    }
+}
+
+/// An operator applied to the text a motion moves over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Delete,
+    Yank,
+    Change,
+}
+
+impl Operator {
+    fn from_key(ch: char) -> Option<Self> {
+        match ch {
+            'd' => Some(Self::Delete),
+            'y' => Some(Self::Yank),
+            'c' => Some(Self::Change),
+            _ => None,
+        }
+    }
+}
+
+/// What the keys typed so far in Normal mode mean.
+#[derive(Debug, PartialEq, Eq)]
+enum Action {
+    /// Not complete yet (`d`, `g`, `dg`).
+    Pending,
+    Move(Motion),
+    Apply(Operator, Motion),
+    /// An operator typed twice (`dd`): the current line.
+    ApplyLine(Operator),
+    /// Any other single key (`i`, `x`, `p`, ...).
+    Key(char),
+    /// Not a command; the keys are dropped.
+    Invalid,
+}
+
+/// Parse the keys of a Normal mode command: `[operator] motion`, an operator twice, or a key.
+fn parse_keys(keys: &str) -> Action {
+    let mut chars = keys.chars();
+    let op = chars.clone().next().and_then(Operator::from_key);
+    if op.is_some() {
+        chars.next();
+    }
+    let rest = chars.as_str();
+    let motion = match rest {
+        "" | "g" => return Action::Pending,
+        "gg" => Some(Motion::FirstLine),
+        _ if rest.chars().count() == 1 => rest.chars().next().and_then(Motion::from_key),
+        _ => None,
+    };
+    match (op, motion) {
+        (Some(op), Some(m)) => Action::Apply(op, m),
+        (Some(op), None) if rest.chars().next().and_then(Operator::from_key) == Some(op) => Action::ApplyLine(op),
+        (None, Some(m)) => Action::Move(m),
+        (None, None) if rest.chars().count() == 1 => rest.chars().next().map_or(Action::Invalid, Action::Key),
+        _ => Action::Invalid,
+    }
+}
+
+impl Editor {
+    /// Turn modal editing on (in Normal mode) or off.
+    pub fn toggle_modal(&mut self) {
+        self.modal = if self.modal.is_some() { None } else { Some(Modal::new()) };
+        self.clear_selection();
+        let state = if self.modal.is_some() { "on" } else { "off" };
+        self.set_status(format!("Modal editing {state}."), Duration::from_secs(2));
+    }
+
+    /// Mode indicator for the status bar; empty without modal editing.
+    pub(crate) fn modal_label(&self) -> &'static str {
+        match self.modal.as_ref().map(|m| m.mode) {
+            None => "",
+            Some(Mode::Normal) => "-- NORMAL -- ",
+            Some(Mode::Insert) => "-- INSERT -- ",
+            Some(Mode::Visual) => "-- VISUAL -- ",
+        }
+    }
+
+    fn set_mode(&mut self, mode: Mode) {
+        if let Some(modal) = self.modal.as_mut() {
+            modal.mode = mode;
+            modal.keys.clear();
+        }
+        self.mark_redraw();
+    }
+
+    /// Handle a key in modal editing; `false` leaves it to the usual key handling.
+    pub(crate) fn handle_modal_key(&mut self, key: KeyEvent) -> Result<bool> {
+        let Some(mode) = self.modal.as_ref().map(|m| m.mode) else { return Ok(false); };
+        if mode == Mode::Insert {
+            if key.code != KeyCode::Esc { return Ok(false); }
+            // Like vim, leaving Insert mode steps back onto the last typed char.
+            self.doc.cursor.x = self.doc.cursor.x.saturating_sub(1);
+            self.set_mode(Mode::Normal);
+            return Ok(true);
+        }
+        let ch = match key.code {
+            KeyCode::Char(ch) if !key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => ch,
+            KeyCode::Esc => {
+                self.clear_selection();
+                self.set_mode(Mode::Normal);
+                return Ok(true);
+            }
+            // Text-editing keys do nothing outside Insert mode.
+            KeyCode::Enter | KeyCode::Backspace | KeyCode::Delete | KeyCode::Tab => return Ok(true),
+            _ => return Ok(false),
+        };
+
+        if mode == Mode::Visual {
+            if let Some(op) = Operator::from_key(if ch == 'x' { 'd' } else { ch }) {
+                if let Some((start, end)) = self.selection_range() {
+                    // The selection includes the char under the cursor (or the line break at
+                    // the end of a line).
+                    let end = if end.x < self.doc.buf.line_len_chars(end.y) {
+                        Pos { y: end.y, x: end.x + 1 }
+                    } else if end.y + 1 < self.doc.buf.line_count() {
+                        Pos { y: end.y + 1, x: 0 }
+                    } else {
+                        end
+                    };
+                    self.apply_operator(op, start, end, false);
+                } else {
+                    self.clear_selection();
+                    self.set_mode(Mode::Normal);
+                }
+                self.keep_cursor_on_char();
+                return self.ensure_visible().map(|()| true);
+            }
+        }
+
+        let Some(modal) = self.modal.as_mut() else { return Ok(false); };
+        modal.keys.push(ch);
+        let action = parse_keys(&modal.keys);
+        if action != Action::Pending {
+            modal.keys.clear();
+        }
+        match action {
+            Action::Pending | Action::Invalid => {}
+            Action::Move(m) => {
+                self.doc.cursor = self.motion_target(m);
+                if mode == Mode::Normal { self.clear_selection(); }
+            }
+            Action::Apply(op, m) => {
+                // As in vim, `cw` on a word changes only to its end, keeping the space after it.
+                let on_word = self.doc.buf.line(self.doc.cursor.y).chars().nth(self.doc.cursor.x).is_some_and(|c| !c.is_whitespace());
+                let target = if op == Operator::Change && m == Motion::WordForward && on_word {
+                    Pos { y: self.doc.cursor.y, x: self.word_range_at(self.doc.cursor).1 }
+                } else {
+                    self.motion_target(m)
+                };
+                self.apply_operator(op, self.doc.cursor, target, m.linewise());
+            }
+            Action::ApplyLine(op) => self.apply_operator(op, self.doc.cursor, self.doc.cursor, true),
+            Action::Key(ch) => self.modal_key(ch)?,
+        }
+        self.keep_cursor_on_char();
+        self.mark_redraw();
+        self.ensure_visible()?;
+        Ok(true)
+    }
+
+    /// Outside Insert mode the cursor rests on a char, as in vim: never past the end of the line.
+    fn keep_cursor_on_char(&mut self) {
+        if self.modal.as_ref().is_some_and(|m| m.mode != Mode::Insert) {
+            let len = self.doc.buf.line_len_chars(self.doc.cursor.y);
+            self.doc.cursor.x = self.doc.cursor.x.min(len.saturating_sub(1));
+        }
+    }
+
+    /// Where motion `m` moves the cursor.
+    fn motion_target(&self, m: Motion) -> Pos {
+        let p = self.doc.cursor;
+        let last = self.doc.buf.line_count() - 1;
+        let target = match m {
+            Motion::Left => Pos { y: p.y, x: p.x.saturating_sub(1) },
+            Motion::Right => Pos { y: p.y, x: p.x + 1 },
+            Motion::Up => Pos { y: p.y.saturating_sub(1), x: p.x },
+            Motion::Down => Pos { y: (p.y + 1).min(last), x: p.x },
+            Motion::WordForward => self.move_to_next_boundary(p),
+            Motion::WordBack => self.move_to_prev_boundary(p),
+            Motion::LineStart => Pos { y: p.y, x: 0 },
+            Motion::LineEnd => Pos { y: p.y, x: self.doc.buf.line_len_chars(p.y) },
+            Motion::FirstLine => Pos { y: 0, x: 0 },
+            Motion::LastLine => Pos { y: last, x: 0 },
+        };
+        self.doc.buf.clamp_pos(target)
+    }
+
+    /// Apply `op` to the text between `a` and `b`, or to their whole lines with `linewise`. The
+    /// text goes into the register; a change continues in Insert mode.
+    fn apply_operator(&mut self, op: Operator, a: Pos, b: Pos, linewise: bool) {
+        let (mut start, mut end) = if a <= b { (a, b) } else { (b, a) };
+        if linewise {
+            start.x = 0;
+            end.x = self.doc.buf.line_len_chars(end.y);
+        }
+        let mut text = self.doc.buf.get_range(start, end);
+        if linewise { text.push('\n'); }
+        if let Some(modal) = self.modal.as_mut() {
+            modal.register = text;
+            modal.linewise = linewise;
+        }
+        self.clear_selection();
+        if op == Operator::Yank {
+            // Yanking lines keeps the column (`yy` does not move the cursor).
+            self.doc.cursor = if linewise { a.min(b) } else { start };
+            self.set_mode(Mode::Normal);
+            return;
+        }
+        if !self.ensure_writable() { return; }
+        // Deleting whole lines also removes a line break; a change keeps one empty line.
+        if linewise && op == Operator::Delete {
+            if end.y + 1 < self.doc.buf.line_count() {
+                end = Pos { y: end.y + 1, x: 0 };
+            } else if start.y > 0 {
+                start = Pos { y: start.y - 1, x: self.doc.buf.line_len_chars(start.y - 1) };
+            }
+        }
+        if start != end { self.replace_range(start, end, ""); }
+        self.doc.cursor = if linewise && op == Operator::Delete {
+            self.doc.buf.clamp_pos(Pos { y: a.y.min(b.y), x: 0 })
+        } else {
+            self.doc.buf.clamp_pos(start)
+        };
+        self.set_mode(if op == Operator::Change { Mode::Insert } else { Mode::Normal });
+    }
+
+    /// Single-key Normal and Visual mode commands.
+    fn modal_key(&mut self, ch: char) -> Result<()> {
+        let p = self.doc.cursor;
+        let len = self.doc.buf.line_len_chars(p.y);
+        match ch {
+            'i' => self.set_mode(Mode::Insert),
+            'a' => {
+                self.doc.cursor.x = (p.x + 1).min(len);
+                self.set_mode(Mode::Insert);
+            }
+            'I' => {
+                self.doc.cursor.x = self.doc.buf.line(p.y).chars().take_while(|c| c.is_whitespace()).count();
+                self.set_mode(Mode::Insert);
+            }
+            'A' => {
+                self.doc.cursor.x = len;
+                self.set_mode(Mode::Insert);
+            }
+            'o' | 'O' => {
+                if !self.ensure_writable() { return Ok(()); }
+                let at = if ch == 'o' { Pos { y: p.y, x: len } } else { Pos { y: p.y, x: 0 } };
+                self.replace_range(at, at, "\n");
+                self.doc.cursor = Pos { y: if ch == 'o' { p.y + 1 } else { p.y }, x: 0 };
+                self.set_mode(Mode::Insert);
+            }
+            'v' if self.doc.anchor.is_some() => {
+                self.clear_selection();
+                self.set_mode(Mode::Normal);
+            }
+            'v' => {
+                self.doc.anchor = Some(p);
+                self.set_mode(Mode::Visual);
+            }
+            'x' if p.x < len => self.apply_operator(Operator::Delete, p, Pos { y: p.y, x: p.x + 1 }, false),
+            'p' | 'P' => self.put(ch == 'p'),
+            'u' => self.undo()?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Put the register after (`after`) or before the cursor; whole lines go below or above
+    /// the cursor line.
+    fn put(&mut self, after: bool) {
+        let Some((text, linewise)) = self.modal.as_ref().map(|m| (m.register.clone(), m.linewise)) else { return; };
+        if text.is_empty() {
+            self.set_status("Nothing to put.", Duration::from_secs(2));
+            return;
+        }
+        if !self.ensure_writable() { return; }
+        let p = self.doc.cursor;
+        if !linewise {
+            let at = Pos { y: p.y, x: if after { (p.x + 1).min(self.doc.buf.line_len_chars(p.y)) } else { p.x } };
+            self.replace_range(at, at, &text);
+            self.doc.cursor = self.doc.buf.calc_end_pos(at, &text);
+        } else if after && p.y + 1 == self.doc.buf.line_count() {
+            // Below the last line: the line break goes in front.
+            let at = Pos { y: p.y, x: self.doc.buf.line_len_chars(p.y) };
+            self.replace_range(at, at, &format!("\n{}", &text[..text.len() - 1]));
+            self.doc.cursor = Pos { y: p.y + 1, x: 0 };
+        } else {
+            let y = if after { p.y + 1 } else { p.y };
+            self.replace_range(Pos { y, x: 0 }, Pos { y, x: 0 }, &text);
+            self.doc.cursor = Pos { y, x: 0 };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_operators_and_motions() {
+        assert_eq!(parse_keys("w"), Action::Move(Motion::WordForward));
+        assert_eq!(parse_keys("g"), Action::Pending);
+        assert_eq!(parse_keys("gg"), Action::Move(Motion::FirstLine));
+        assert_eq!(parse_keys("d"), Action::Pending);
+        assert_eq!(parse_keys("d$"), Action::Apply(Operator::Delete, Motion::LineEnd));
+        assert_eq!(parse_keys("cg"), Action::Pending);
+        assert_eq!(parse_keys("cgg"), Action::Apply(Operator::Change, Motion::FirstLine));
+        assert_eq!(parse_keys("yy"), Action::ApplyLine(Operator::Yank));
+        assert_eq!(parse_keys("dy"), Action::Invalid);
+        assert_eq!(parse_keys("x"), Action::Key('x'));
+        assert_eq!(parse_keys("gx"), Action::Invalid);
+    }
+}
@@ -1,104 +1,161 @@
 //! Cursor movement and boundary detection.
 
-use crate::types::Pos; // document position type
+use crate::bidi; // bi-directional text reordering
+use crate::types::{Pos, SelectionKind}; // document position type, stream selection
 use super::Editor; // main editor logic
 use anyhow::Result; // anyhow error handling
-use crossterm::{event::{KeyCode, KeyEvent, KeyModifiers}, terminal}; // terminal events and manipulation
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers}; // terminal events
 use std::cmp::min; // comparison helpers
 
+/// Where Home goes from column `x` of `line`: the first non-blank character, or column 0 when
+/// already there.
+fn smart_home(line: &str, x: usize) -> usize {
+    let indent = line.chars().take_while(|c| c.is_whitespace()).count();
+    if x == indent { 0 } else { indent }
+}
+
+/// First line of the next paragraph below line `y`: a non-blank line after a blank one.
+fn next_paragraph(y: usize, line_count: usize, blank: impl Fn(usize) -> bool) -> Option<usize> {
+    (y + 1..line_count).find(|&i| !blank(i) && blank(i - 1))
+}
+
+/// First line of the nearest paragraph starting before column `x` of line `y`.
+fn prev_paragraph(y: usize, x: usize, blank: impl Fn(usize) -> bool) -> Option<usize> {
+    let end = if x > 0 { y + 1 } else { y };
+    (0..end).rev().find(|&i| !blank(i) && (i == 0 || blank(i - 1)))
+}
+
 impl Editor {
     /// Move the cursor in response to a movement key.
     ///
     /// If `selecting` is true (Shift held), we create/extend a selection. Otherwise we clear
     /// the selection as we move.
     pub fn move_cursor(&mut self, key: KeyEvent, selecting: bool) -> Result<()> {
-        if selecting && self.anchor.is_none() {
-            self.anchor = Some(self.cursor);
+        if selecting && self.doc.anchor.is_none() {
+            self.doc.anchor = Some(self.doc.cursor);
             self.mark_redraw();
         }
+        if selecting {
+            self.selection_kind = SelectionKind::Stream;
+        }
         if !selecting {
             self.clear_selection();
         }
 
-        let (_w, h) = terminal::size()?;
-        let height = h as usize;
-        let prompt_lines = if self.prompt.is_some() { 1 } else { 0 };
-        let status_lines = 1;
-        let editor_h = height.saturating_sub(prompt_lines + status_lines);
+        let (_w, h) = self.screen_size()?;
+        let editor_h = self.text_rows(h as usize);
 
-        let mut p = self.cursor;
+        let mut p = self.doc.cursor;
         let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
 
         match key.code {
             KeyCode::Left => {
                 if ctrl {
                     p = self.move_to_prev_boundary(p);
+                } else if let Some(next) = self.visual_step(p, false) {
+                    p = next;
                 } else if p.x > 0 {
                     p.x -= 1;
                 } else if p.y > 0 {
                     p.y -= 1;
-                    p.x = self.buf.line_len_chars(p.y);
+                    p.x = self.doc.buf.line_len_chars(p.y);
                 }
             }
             KeyCode::Right => {
                 if ctrl {
                     p = self.move_to_next_boundary(p);
+                } else if let Some(next) = self.visual_step(p, true) {
+                    p = next;
                 } else {
-                    let len = self.buf.line_len_chars(p.y);
+                    let len = self.doc.buf.line_len_chars(p.y);
                     if p.x < len {
                         p.x += 1;
-                    } else if p.y + 1 < self.buf.line_count() {
+                    } else if p.y + 1 < self.doc.buf.line_count() {
                         p.y += 1;
                         p.x = 0;
                     }
                 }
             }
             KeyCode::Up => {
-                if ctrl {
+                if alt {
+                    p = self.move_to_prev_paragraph(p);
+                } else if ctrl {
                     p = self.move_to_prev_line_boundary(p);
                 } else if p.y > 0 {
                     p.y -= 1;
-                    p.x = min(p.x, self.buf.line_len_chars(p.y));
+                    p.x = min(p.x, self.doc.buf.line_len_chars(p.y));
                 }
             }
             KeyCode::Down => {
-                if ctrl {
+                if alt {
+                    p = self.move_to_next_paragraph(p);
+                } else if ctrl {
                     p = self.move_to_next_line_boundary(p);
-                } else if p.y + 1 < self.buf.line_count() {
+                } else if p.y + 1 < self.doc.buf.line_count() {
                     p.y += 1;
-                    p.x = min(p.x, self.buf.line_len_chars(p.y));
+                    p.x = min(p.x, self.doc.buf.line_len_chars(p.y));
                 }
             }
-            KeyCode::Home => {
-                p.y = 0;
-                p.x = 0;
-            }
-            KeyCode::End => {
-                p.y = self.buf.line_count().saturating_sub(1);
-                p.x = self.buf.line_len_chars(p.y);
+            KeyCode::Home if ctrl => p = Pos { y: 0, x: 0 },
+            KeyCode::Home => p.x = smart_home(&self.doc.buf.line(p.y), p.x),
+            KeyCode::End if ctrl => {
+                p.y = self.doc.buf.line_count().saturating_sub(1);
+                p.x = self.doc.buf.line_len_chars(p.y);
             }
+            KeyCode::End => p.x = self.doc.buf.line_len_chars(p.y),
             KeyCode::PageUp => {
                 let jump = editor_h.saturating_sub(1);
                 p.y = p.y.saturating_sub(jump);
-                p.x = min(p.x, self.buf.line_len_chars(p.y));
+                p.x = min(p.x, self.doc.buf.line_len_chars(p.y));
             }
             KeyCode::PageDown => {
                 let jump = editor_h.saturating_sub(1);
-                p.y = min(p.y + jump, self.buf.line_count().saturating_sub(1));
-                p.x = min(p.x, self.buf.line_len_chars(p.y));
+                p.y = min(p.y + jump, self.doc.buf.line_count().saturating_sub(1));
+                p.x = min(p.x, self.doc.buf.line_len_chars(p.y));
             }
             _ => {}
         }
 
-        let old_cursor = self.cursor;
-        self.cursor = self.buf.clamp_pos(p);
-        if old_cursor != self.cursor {
+        let old_cursor = self.doc.cursor;
+        self.doc.cursor = self.doc.buf.clamp_pos(p);
+        if old_cursor != self.doc.cursor {
             self.mark_redraw();
         }
         self.ensure_visible()?;
         Ok(())
     }
 
+    /// One visual step left/right on a line with right-to-left text.
+    ///
+    /// Returns `None` when visual movement is disabled or the line is pure LTR, so the caller
+    /// falls back to ordinary logical movement.
+    fn visual_step(&self, p: Pos, right: bool) -> Option<Pos> {
+        if !self.bidi_visual_cursor {
+            return None;
+        }
+        let order = bidi::visual_order(&self.doc.buf.line(p.y))?;
+        let vis = bidi::logical_to_visual(&order, p.x);
+        let next = if right {
+            if vis + 1 < order.len() {
+                Pos { y: p.y, x: order[vis + 1] }
+            } else if vis + 1 == order.len() {
+                Pos { y: p.y, x: order.len() }
+            } else if p.y + 1 < self.doc.buf.line_count() {
+                Pos { y: p.y + 1, x: 0 }
+            } else {
+                p
+            }
+        } else if vis > 0 {
+            Pos { y: p.y, x: order[vis - 1] }
+        } else if p.y > 0 {
+            Pos { y: p.y - 1, x: self.doc.buf.line_len_chars(p.y - 1) }
+        } else {
+            p
+        };
+        Some(next)
+    }
+
     /// Helper to categorize a character for boundary detection.
     fn get_char_category(&self, ch: char) -> usize {
         if ch.is_whitespace() {
@@ -110,12 +167,24 @@ impl Editor {
         }
     }
 
+    /// Char range `(start, end)` of the run of same-category chars (word, punctuation or
+    /// whitespace) at `p`; a position at the end of the line uses the char before it.
+    pub(crate) fn word_range_at(&self, p: Pos) -> (usize, usize) {
+        let chars: Vec<char> = self.doc.buf.line(p.y).chars().collect();
+        if chars.is_empty() { return (0, 0); }
+        let x = p.x.min(chars.len() - 1);
+        let cat = self.get_char_category(chars[x]);
+        let start = chars[..x].iter().rposition(|&c| self.get_char_category(c) != cat).map_or(0, |i| i + 1);
+        let end = chars[x..].iter().position(|&c| self.get_char_category(c) != cat).map_or(chars.len(), |i| x + i);
+        (start, end)
+    }
+
     /// Move to the first boundary on the next line.
     fn move_to_next_line_boundary(&self, p: Pos) -> Pos {
-        let line_count = self.buf.line_count();
+        let line_count = self.doc.buf.line_count();
         if p.y + 1 < line_count {
             let next_y = p.y + 1;
-            let line = self.buf.line(next_y);
+            let line = self.doc.buf.line(next_y);
             let chars: Vec<char> = line.chars().collect();
 
             // Find first non-whitespace character on the next line
@@ -126,14 +195,14 @@ impl Editor {
             return Pos { y: next_y, x: i };
         }
         // If on last line, move to end of document
-        Pos { y: p.y, x: self.buf.line_len_chars(p.y) }
+        Pos { y: p.y, x: self.doc.buf.line_len_chars(p.y) }
     }
 
     /// Move to the first boundary on the previous line.
     fn move_to_prev_line_boundary(&self, p: Pos) -> Pos {
         if p.y > 0 {
             let prev_y = p.y - 1;
-            let line = self.buf.line(prev_y);
+            let line = self.doc.buf.line(prev_y);
             let chars: Vec<char> = line.chars().collect();
 
             // Find first non-whitespace character on the previous line
@@ -147,12 +216,29 @@ impl Editor {
         Pos { y: 0, x: 0 }
     }
 
+    /// Move to the start of the next blank-line-separated paragraph (Alt+Down), or the end of
+    /// the buffer after the last one.
+    fn move_to_next_paragraph(&self, p: Pos) -> Pos {
+        let blank = |y: usize| self.doc.buf.line(y).trim().is_empty();
+        if let Some(y) = next_paragraph(p.y, self.doc.buf.line_count(), blank) {
+            return Pos { y, x: 0 };
+        }
+        let y = self.doc.buf.line_count().saturating_sub(1);
+        Pos { y, x: self.doc.buf.line_len_chars(y) }
+    }
+
+    /// Move to the start of this paragraph, or the previous one when already there (Alt+Up).
+    fn move_to_prev_paragraph(&self, p: Pos) -> Pos {
+        let blank = |y: usize| self.doc.buf.line(y).trim().is_empty();
+        Pos { y: prev_paragraph(p.y, p.x, blank).unwrap_or(0), x: 0 }
+    }
+
     /// Move to the next boundary (word start or punctuation).
-    fn move_to_next_boundary(&self, p: Pos) -> Pos {
-        let line_count = self.buf.line_count();
+    pub(crate) fn move_to_next_boundary(&self, p: Pos) -> Pos {
+        let line_count = self.doc.buf.line_count();
         if p.y >= line_count { return p; }
 
-        let line = self.buf.line(p.y);
+        let line = self.doc.buf.line(p.y);
         let chars: Vec<char> = line.chars().collect();
 
         if p.x >= chars.len() {
@@ -185,16 +271,16 @@ impl Editor {
     }
 
     /// Move to the previous boundary (word start or punctuation).
-    fn move_to_prev_boundary(&self, p: Pos) -> Pos {
+    pub(crate) fn move_to_prev_boundary(&self, p: Pos) -> Pos {
         if p.x == 0 {
             if p.y > 0 {
                 let prev_y = p.y - 1;
-                return Pos { y: prev_y, x: self.buf.line_len_chars(prev_y) };
+                return Pos { y: prev_y, x: self.doc.buf.line_len_chars(prev_y) };
             }
             return p;
         }
 
-        let line = self.buf.line(p.y);
+        let line = self.doc.buf.line(p.y);
         let chars: Vec<char> = line.chars().collect();
         let mut i = p.x - 1;
 
@@ -214,3 +300,31 @@ impl Editor {
         Pos { y: p.y, x: i }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn home_alternates_between_indent_and_column_zero() {
+        assert_eq!(smart_home("    let x;", 8), 4);
+        assert_eq!(smart_home("    let x;", 4), 0);
+        assert_eq!(smart_home("    let x;", 0), 4);
+        assert_eq!(smart_home("x", 1), 0);
+        assert_eq!(smart_home("", 0), 0);
+    }
+
+    #[test]
+    fn paragraph_starts_follow_blank_lines() {
+        let lines = ["one", "two", "", "  ", "three", "four", "", "five"];
+        let blank = |y: usize| lines[y].trim().is_empty();
+        assert_eq!(next_paragraph(0, lines.len(), blank), Some(4));
+        assert_eq!(next_paragraph(2, lines.len(), blank), Some(4));
+        assert_eq!(next_paragraph(4, lines.len(), blank), Some(7));
+        assert_eq!(next_paragraph(7, lines.len(), blank), None);
+        assert_eq!(prev_paragraph(5, 2, blank), Some(4));
+        assert_eq!(prev_paragraph(4, 1, blank), Some(4));
+        assert_eq!(prev_paragraph(4, 0, blank), Some(0));
+        assert_eq!(prev_paragraph(0, 0, blank), None);
+    }
+}
@@ -0,0 +1,203 @@
+//! Auto-closing brackets and quotes: typing `(`, `[`, `{`, `"` or `'` also inserts the closing
+//! character after the cursor, and typing that closing character over an auto-inserted one
+//! steps past it instead of adding another. Backspace or Delete on an empty pair removes both
+//! characters, and Enter between brackets puts an indented blank line between them.
+//!
+//! Which pairs close depends on the file extension (`[auto_close_pairs]` in kpad.toml, else
+//! `default_pairs`). A pair only opens before whitespace, a closing bracket or the end of the
+//! line, and quotes only outside words, so `don't` and `f(x` type normally.
+
+use super::Editor; // editor state
+use crate::config::Config; // auto_close settings
+use crate::types::{EditOperation, Pos}; // undo records, positions
+use anyhow::Result; // anyhow error handling
+use std::collections::HashMap; // pairs by extension
+use std::time::Duration; // timing for status messages
+
+/// Pairs for `ext` when the config does not name any: no single quote in prose (apostrophes)
+/// and Rust (lifetimes).
+fn default_pairs(ext: &str) -> &'static str {
+    match ext {
+        "" | "txt" | "md" | "markdown" | "rs" => "()[]{}\"\"",
+        _ => "()[]{}\"\"''",
+    }
+}
+
+/// Whether typing `open` (closed by `close`) between `prev` and `next` starts a pair.
+fn opens_pair(open: char, close: char, prev: Option<char>, next: Option<char>) -> bool {
+    let before_ok = next.is_none_or(|n| n.is_whitespace() || matches!(n, ')' | ']' | '}'));
+    // A quote after a word char ends something (`don't`, `"a"`), it does not open.
+    let quote_ok = close != open || !prev.is_some_and(|p| p.is_alphanumeric() || p == open);
+    before_ok && quote_ok
+}
+
+/// Text Enter inserts between brackets on a line indented by `indent`, and the column of the
+/// cursor on the blank line it opens.
+fn pair_break(indent: &str, tab_width: usize) -> (String, usize) {
+    let inner = format!("{indent}{}", " ".repeat(tab_width));
+    let col = inner.chars().count();
+    (format!("\n{inner}\n{indent}"), col)
+}
+
+/// Auto-close settings and the closing characters typed for the user.
+pub(crate) struct AutoClose {
+    pub(crate) enabled: bool,
+    /// Pair characters by lowercase extension, from `[auto_close_pairs]`.
+    pairs_by_ext: HashMap<String, String>,
+    /// Positions of auto-inserted closing characters, innermost last. Only plain typing keeps
+    /// them; any other key forgets them.
+    pending: Vec<Pos>,
+}
+
+impl AutoClose {
+    pub(crate) fn new(config: &Config) -> Self {
+        Self {
+            enabled: config.auto_close,
+            pairs_by_ext: config.auto_close_pairs.iter().map(|(k, v)| (k.to_lowercase(), v.clone())).collect(),
+            pending: Vec::new(),
+        }
+    }
+
+    /// Closing character of `open` in files with extension `ext`.
+    fn closer(&self, ext: &str, open: char) -> Option<char> {
+        let pairs = self.pairs_by_ext.get(ext).map_or_else(|| default_pairs(ext), String::as_str);
+        let chars: Vec<char> = pairs.chars().collect();
+        chars.chunks_exact(2).find(|p| p[0] == open).map(|p| p[1])
+    }
+
+    /// Forget the auto-inserted closers (the cursor left the pair).
+    pub(crate) fn forget(&mut self) {
+        self.pending.clear();
+    }
+
+    /// Move pending closers right of `at` on its line by `n` chars (text typed at `at`).
+    fn shift(&mut self, at: Pos, n: usize) {
+        for p in self.pending.iter_mut().filter(|p| p.y == at.y && p.x >= at.x) {
+            p.x += n;
+        }
+    }
+}
+
+impl Editor {
+    /// Type `ch` with auto-closing. Returns `false` when `ch` should be inserted normally.
+    pub(crate) fn type_paired(&mut self, ch: char) -> Result<bool> {
+        if !self.auto_close.enabled || self.selection_range().is_some() {
+            self.auto_close.forget();
+            return Ok(false);
+        }
+        let line: Vec<char> = self.doc.buf.line(self.doc.cursor.y).chars().collect();
+        let prev = self.doc.cursor.x.checked_sub(1).and_then(|x| line.get(x)).copied();
+        let next = line.get(self.doc.cursor.x).copied();
+
+        // Typing the closer the editor inserted: step over it.
+        if next == Some(ch) && self.auto_close.pending.last() == Some(&self.doc.cursor) {
+            self.auto_close.pending.pop();
+            self.doc.cursor.x += 1;
+            self.mark_redraw();
+            self.ensure_visible()?;
+            return Ok(true);
+        }
+
+        let closer = self.auto_close.closer(&self.pair_ext(), ch).filter(|&close| opens_pair(ch, close, prev, next));
+        let Some(close) = closer else {
+            self.auto_close.shift(self.doc.cursor, 1);
+            return Ok(false);
+        };
+
+        let text = format!("{ch}{close}");
+        self.record_edit(EditOperation::Insert { pos: self.doc.cursor, text: text.clone() });
+        self.auto_close.shift(self.doc.cursor, 2);
+        self.replace_selection_or_insert(&text);
+        self.doc.cursor.x -= 1;
+        self.auto_close.pending.push(self.doc.cursor);
+        self.ensure_visible()?;
+        Ok(true)
+    }
+
+    /// Lowercase extension of the file, which picks the pairs.
+    fn pair_ext(&self) -> String {
+        self.doc.file_path.as_ref()
+            .and_then(|p| p.extension())
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default()
+    }
+
+    /// Whether the chars at `x` and `x + 1` of the cursor line are an empty pair.
+    fn empty_pair_at(&self, x: usize) -> bool {
+        if !self.auto_close.enabled || self.selection_range().is_some() { return false; }
+        let line = self.doc.buf.line(self.doc.cursor.y);
+        let mut chars = line.chars().skip(x);
+        let (Some(open), Some(next)) = (chars.next(), chars.next()) else { return false; };
+        self.auto_close.closer(&self.pair_ext(), open) == Some(next)
+    }
+
+    /// Backspace inside an empty pair (`(|)`), or Delete before one (`|()`, `forward`): remove
+    /// both characters. Returns `false` when the key should act normally.
+    pub(crate) fn delete_pair(&mut self, forward: bool) -> Result<bool> {
+        let Some(x) = (if forward { Some(self.doc.cursor.x) } else { self.doc.cursor.x.checked_sub(1) }) else { return Ok(false); };
+        if !self.empty_pair_at(x) { return Ok(false); }
+        let start = Pos { y: self.doc.cursor.y, x };
+        let end = Pos { y: start.y, x: x + 2 };
+        let deleted_text = self.doc.buf.get_range(start, end);
+        self.record_edit(EditOperation::Delete { start, _end: end, deleted_text });
+        self.doc.cursor = self.doc.buf.delete_range(start, end);
+        self.doc.dirty = true;
+        self.mark_redraw();
+        self.ensure_visible()?;
+        Ok(true)
+    }
+
+    /// Enter between brackets (`{|}`): open an indented blank line between them. Returns `false`
+    /// when Enter should act normally.
+    pub(crate) fn enter_in_pair(&mut self) -> Result<bool> {
+        let Some(x) = self.doc.cursor.x.checked_sub(1) else { return Ok(false); };
+        let line = self.doc.buf.line(self.doc.cursor.y);
+        let is_bracket = line.chars().nth(x).is_some_and(|c| matches!(c, '(' | '[' | '{'));
+        if !is_bracket || !self.empty_pair_at(x) { return Ok(false); }
+        let indent: String = line.chars().take_while(|c| *c == ' ' || *c == '\t').collect();
+        let (text, col) = pair_break(&indent, self.tab_width);
+        self.record_edit(EditOperation::Insert { pos: self.doc.cursor, text: text.clone() });
+        let y = self.doc.cursor.y;
+        self.replace_selection_or_insert(&text);
+        self.doc.cursor = Pos { y: y + 1, x: col };
+        self.ensure_visible()?;
+        Ok(true)
+    }
+
+    /// Turn auto-closing of brackets and quotes on or off (`toggle_auto_close`).
+    pub fn toggle_auto_close(&mut self) {
+        self.auto_close.enabled = !self.auto_close.enabled;
+        self.auto_close.forget();
+        let state = if self.auto_close.enabled { "on" } else { "off" };
+        self.set_status(format!("Auto-close brackets: {state}"), Duration::from_secs(2));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opens_pairs_only_where_they_help() {
+        let mut config = Config::default();
+        config.auto_close_pairs.insert("TXT".to_string(), "<>".to_string());
+        let pairs = AutoClose::new(&config);
+        assert_eq!(pairs.closer("py", '\''), Some('\''));
+        assert_eq!(pairs.closer("rs", '\''), None);
+        assert_eq!(pairs.closer("txt", '<'), Some('>'));
+        assert_eq!(pairs.closer("txt", '('), None);
+
+        assert!(opens_pair('(', ')', Some('f'), None));
+        assert!(opens_pair('[', ']', None, Some(')')));
+        assert!(!opens_pair('(', ')', None, Some('x')));
+        assert!(opens_pair('"', '"', Some(' '), Some(' ')));
+        assert!(!opens_pair('\'', '\'', Some('n'), Some('t')));
+        assert!(!opens_pair('\'', '\'', Some('n'), None));
+    }
+
+    #[test]
+    fn enter_between_brackets_indents_one_level() {
+        assert_eq!(pair_break("", 4), ("\n    \n".to_string(), 4));
+        assert_eq!(pair_break("\t", 2), ("\n\t  \n\t".to_string(), 3));
+    }
+}
@@ -0,0 +1,107 @@
+//! The command palette's suggestion list: the commands matching what was typed, shown above the
+//! command prompt. Up/Down highlight a suggestion (once something is typed; in an empty prompt
+//! they browse the prompt history), Tab completes the command name to the highlighted or first
+//! suggestion, and Enter runs the highlighted one.
+
+use super::Editor; // editor state
+use crate::types::PromptKind; // the command prompt
+use crossterm::event::{KeyCode, KeyEvent}; // key handling
+
+/// Suggestions listed above the command prompt.
+pub(crate) const PALETTE_ROWS: usize = 10;
+
+/// The row Up (`back`) or Down highlights next among `len` suggestions when `at` is highlighted
+/// (`None`: none is). Up from the first row goes back to none; Down stops at the last.
+fn step(len: usize, at: Option<usize>, back: bool) -> Option<usize> {
+    match (at, back) {
+        _ if len == 0 => None,
+        (None, false) => Some(0),
+        (Some(i), false) => Some((i + 1).min(len - 1)),
+        (Some(i), true) => i.checked_sub(1),
+        (None, true) => None,
+    }
+}
+
+impl Editor {
+    /// Names of the commands listed above the command prompt; empty for other prompts.
+    pub(crate) fn palette_suggestions(&self) -> Vec<String> {
+        match &self.prompt {
+            Some(p) if p.kind == PromptKind::Command => {
+                self.commands.search(p.input.trim(), PALETTE_ROWS).into_iter().map(|c| c.name.clone()).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// The highlighted suggestion, if it still belongs to the input (typing clears it).
+    pub(crate) fn palette_selection(&self) -> Option<usize> {
+        let p = self.prompt.as_ref()?;
+        p.selected.as_ref().filter(|(_, input)| *input == p.input).map(|(i, _)| *i)
+    }
+
+    /// Keys the command palette takes before the prompt does: Tab and Up/Down while they apply
+    /// (`true`). Enter on a highlighted suggestion first puts its name in the prompt.
+    pub(crate) fn handle_palette_key(&mut self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Tab => self.complete_command_name(),
+            KeyCode::Up | KeyCode::Down => self.move_palette_selection(key.code == KeyCode::Up),
+            KeyCode::Enter => {
+                let name = self.palette_selection().and_then(|i| self.palette_suggestions().into_iter().nth(i));
+                if let (Some(p), Some(name)) = (&mut self.prompt, name) {
+                    p.input = name;
+                }
+                false
+            }
+            _ => false,
+        }
+    }
+
+    /// Up (`back`) or Down in the command prompt: move the highlight in the suggestion list.
+    /// `false` when they browse the prompt history instead (another prompt, nothing typed, or
+    /// already browsing).
+    fn move_palette_selection(&mut self, back: bool) -> bool {
+        let at = self.palette_selection();
+        let suggestions = self.palette_suggestions();
+        let Some(p) = &mut self.prompt else { return false; };
+        if p.kind != PromptKind::Command || p.input.trim().is_empty() || p.history.is_some() {
+            return false;
+        }
+        p.selected = step(suggestions.len(), at, back).map(|i| (i, p.input.clone()));
+        self.mark_redraw();
+        true
+    }
+
+    /// Tab in the command prompt while typing a command name: complete it to the highlighted
+    /// or first suggestion. `false` when there is nothing to complete (arguments are typed, or
+    /// the name is complete), so Tab completes arguments instead.
+    fn complete_command_name(&mut self) -> bool {
+        let at = self.palette_selection();
+        let suggestions = self.palette_suggestions();
+        let Some(p) = &mut self.prompt else { return false; };
+        let typed = p.input.trim();
+        if typed.contains(' ') { return false; }
+        let Some(name) = suggestions.get(at.unwrap_or(0)) else { return false; };
+        if name.eq_ignore_ascii_case(typed) { return false; }
+        p.input.clone_from(name);
+        p.cursor = p.input.chars().count();
+        p.selected = None;
+        self.mark_redraw();
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highlight_moves_within_the_list() {
+        assert_eq!(step(3, None, false), Some(0));
+        assert_eq!(step(3, Some(0), false), Some(1));
+        assert_eq!(step(3, Some(2), false), Some(2));
+        assert_eq!(step(3, Some(1), true), Some(0));
+        assert_eq!(step(3, Some(0), true), None);
+        assert_eq!(step(3, None, true), None);
+        assert_eq!(step(0, None, false), None);
+    }
+}
@@ -0,0 +1,93 @@
+//! Pinned lines: the first N lines of the buffer stay at the top of the text area while the
+//! rest scrolls beneath them, like frozen rows in a spreadsheet (CSV headers, file preambles).
+//!
+//! The scrolled area never shows the pinned lines: `scroll_y` is kept at or below the first
+//! unpinned line (or its first wrapped row), so the rest of the scroll logic is unchanged.
+
+use super::render::text_area_width; // text width next to the gutter
+use super::Editor; // editor state
+use crate::types::{Prompt, PromptKind}; // pin count prompt
+use crate::utils::digits; // gutter width
+use anyhow::Result; // anyhow error handling
+use crossterm::{ // terminal manipulation
+    cursor,
+    style::{self, Color},
+    terminal::{self, ClearType},
+    QueueableCommand,
+};
+use std::cmp::max; // comparison helpers
+use std::io::Stdout; // output handling
+use std::time::Duration; // timing for status messages
+
+/// Most lines that can be pinned; more would leave little room for scrolling text.
+pub(crate) const MAX_PINNED_LINES: usize = 20;
+
+/// Background of pinned rows.
+const PINNED_BG: Color = Color::AnsiValue(236);
+
+impl Editor {
+    /// Number of rows taken by pinned lines (never more than the buffer has).
+    pub(crate) fn pinned_rows(&self) -> usize {
+        self.doc.pinned_lines.min(self.doc.buf.line_count())
+    }
+
+    /// Smallest `scroll_y` that keeps the pinned lines out of the scrolled area on a terminal
+    /// `width` columns wide.
+    pub(crate) fn pinned_scroll_min(&self, width: usize) -> usize {
+        let pinned = self.pinned_rows();
+        if !self.wrapping() {
+            return pinned;
+        }
+        let gutter = max(2, digits(self.doc.buf.line_count())) + 2;
+        let avail = text_area_width(width, gutter);
+        (0..pinned).map(|i| self.calculate_wrap_segments(&self.doc.buf.line(i), avail).len()).sum()
+    }
+
+    /// Ask how many lines to pin (`pin_lines` command); the current count is prefilled.
+    pub fn prompt_pin_lines(&mut self) {
+        self.prompt = Some(Prompt::new(PromptKind::PinLines, self.doc.pinned_lines.to_string()));
+        self.mark_redraw();
+    }
+
+    /// Pin the first `n` lines at the top of the text area; 0 unpins.
+    pub fn pin_lines(&mut self, n: usize) -> Result<()> {
+        if n > MAX_PINNED_LINES {
+            self.set_status(format!("At most {MAX_PINNED_LINES} lines can be pinned."), Duration::from_secs(2));
+            return Ok(());
+        }
+        self.doc.pinned_lines = n;
+        let msg = match n {
+            0 => "Lines unpinned.".to_string(),
+            1 => "Pinned the first line.".to_string(),
+            n => format!("Pinned the first {n} lines."),
+        };
+        self.set_status(msg, Duration::from_secs(2));
+        self.scroll_target = None;
+        self.ensure_visible()?;
+        self.mark_redraw();
+        Ok(())
+    }
+
+    /// Draw the pinned lines between the breadcrumb and the scrolled text, in the `width`
+    /// columns starting at `left`.
+    pub(crate) fn render_pinned_lines(&mut self, stdout: &mut Stdout, left: usize, width: usize, gutter: usize) -> Result<()> {
+        let lnw = gutter - 2;
+        let avail = text_area_width(width, gutter);
+        let top = self.header_rows();
+        for y in 0..self.pinned_rows() {
+            let (Ok(col), Ok(row)) = (u16::try_from(left), u16::try_from(top + y)) else { break; };
+            let bg = if y == self.doc.cursor.y { self.theme.current_line } else { self.caps.color(PINNED_BG) };
+            stdout.queue(cursor::MoveTo(col, row))?;
+            stdout.queue(terminal::Clear(ClearType::UntilNewLine))?;
+            stdout.queue(style::SetBackgroundColor(bg))?;
+            stdout.queue(style::SetForegroundColor(Color::DarkGrey))?;
+            stdout.queue(style::Print(format!("{:>lnw$}{}", y + 1, self.caps.glyph("┃ ", "| "))))?;
+            stdout.queue(style::ResetColor)?;
+            let col_used = self.render_line_content(stdout, y, avail, Some(bg))?;
+            stdout.queue(style::SetBackgroundColor(bg))?;
+            stdout.queue(style::Print(" ".repeat(width.saturating_sub(gutter + col_used))))?;
+            stdout.queue(style::ResetColor)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,82 @@
+//! File preview for the Open prompt: while Tab completion has picked a file, its first lines are
+//! shown dimmed over the lower part of the text area.
+
+use super::Editor; // editor state
+use crate::types::PromptKind; // open prompt detection
+use anyhow::Result; // anyhow error handling
+use crossterm::{ // terminal manipulation
+    cursor,
+    style::{self, Color},
+    terminal::{self, ClearType},
+    QueueableCommand,
+};
+use std::fs::File; // reading the previewed file
+use std::io::{Read, Stdout}; // bounded reads, output handling
+use std::path::Path; // previewed path
+
+/// Most lines shown in the preview.
+const PREVIEW_LINES: usize = 40;
+
+/// Most bytes read from the previewed file.
+const PREVIEW_BYTES: u64 = 64 * 1024;
+
+/// Background of the preview header.
+const HEADER_BG: Color = Color::AnsiValue(235);
+
+/// The first lines of the file at `path`, or a one-line note for binary or unreadable files.
+fn preview_lines(path: &Path) -> Vec<String> {
+    let mut bytes = Vec::new();
+    let read = File::open(path).and_then(|f| f.take(PREVIEW_BYTES).read_to_end(&mut bytes));
+    if let Err(e) = read {
+        return vec![format!("(cannot read: {e})")];
+    }
+    if bytes.contains(&0) {
+        return vec!["(binary file)".to_string()];
+    }
+    String::from_utf8_lossy(&bytes).lines().take(PREVIEW_LINES).map(|l| l.replace('\t', "    ")).collect()
+}
+
+impl Editor {
+    /// File to preview: the Open prompt's input, when Tab completion (not typing) put a file
+    /// name there.
+    fn preview_path(&self) -> Option<&Path> {
+        let p = self.prompt.as_ref()?;
+        let path = Path::new(&p.input);
+        (p.kind == PromptKind::Open && p.is_completed() && path.is_file()).then_some(path)
+    }
+
+    /// Draw the preview of the completed file above the prompt; `bottom` is the first row below
+    /// the text area.
+    pub(crate) fn render_open_preview(&self, stdout: &mut Stdout, width: usize, top: usize, bottom: usize) -> Result<()> {
+        let Some(path) = self.preview_path() else { return Ok(()); };
+        let lines = preview_lines(path);
+        let rows = (PREVIEW_LINES + 1).min(bottom.saturating_sub(top));
+        let first = bottom - rows;
+
+        let rule = self.caps.glyph("─", "-");
+        let title = format!("{rule}{rule} Preview: {} ", path.display());
+        let title: String = title.chars().take(width).collect();
+        let fill = width.saturating_sub(title.chars().count());
+        stdout.queue(cursor::MoveTo(0, u16::try_from(first).unwrap_or(u16::MAX)))?;
+        stdout.queue(style::SetBackgroundColor(self.caps.color(HEADER_BG)))?;
+        stdout.queue(style::SetForegroundColor(Color::Grey))?;
+        stdout.queue(style::Print(format!("{title}{}", rule.repeat(fill))))?;
+        stdout.queue(style::ResetColor)?;
+
+        for row in 1..rows {
+            let Ok(y) = u16::try_from(first + row) else { break; };
+            stdout.queue(cursor::MoveTo(0, y))?;
+            stdout.queue(terminal::Clear(ClearType::UntilNewLine))?;
+            let Some(line) = lines.get(row - 1) else { continue; };
+            let mut used = 0;
+            let shown: String = line.chars().take_while(|&c| {
+                used += self.char_width(c);
+                used <= width
+            }).collect();
+            stdout.queue(style::SetForegroundColor(Color::DarkGrey))?;
+            stdout.queue(style::Print(shown))?;
+            stdout.queue(style::ResetColor)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,137 @@
+//! Rename the identifier under the cursor: a whole-word find/replace over the buffer (or the
+//! selection), applied as a single undo step.
+
+use super::Editor; // editor state
+use crate::types::{Pos, Prompt, PromptKind}; // positions, rename prompt
+use anyhow::Result; // anyhow error handling
+use std::time::Duration; // timing for status messages
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Char offsets of the whole-word occurrences of `word` in `text`.
+fn whole_word_matches(text: &str, word: &str) -> Vec<usize> {
+    let chars: Vec<char> = text.chars().collect();
+    let needle: Vec<char> = word.chars().collect();
+    if needle.is_empty() {
+        return Vec::new();
+    }
+    let mut hits = Vec::new();
+    let mut i = 0;
+    while i + needle.len() <= chars.len() {
+        let bounded = (i == 0 || !is_ident_char(chars[i - 1]))
+            && chars.get(i + needle.len()).is_none_or(|&c| !is_ident_char(c));
+        if bounded && chars[i..i + needle.len()] == needle[..] {
+            hits.push(i);
+            i += needle.len();
+        } else {
+            i += 1;
+        }
+    }
+    hits
+}
+
+/// The identifier to rename and the range it is renamed in.
+pub(crate) struct RenameTarget {
+    word: String,
+    start: Pos,
+    end: Pos,
+}
+
+impl Editor {
+    /// The identifier touching the cursor (under it or just before it).
+    fn ident_at_cursor(&self) -> Option<String> {
+        let chars: Vec<char> = self.doc.buf.line(self.doc.cursor.y).chars().collect();
+        let x = self.doc.cursor.x.min(chars.len());
+        let at = if chars.get(x).is_some_and(|&c| is_ident_char(c)) { x } else { x.checked_sub(1)? };
+        if !is_ident_char(chars[at]) {
+            return None;
+        }
+        let start = chars[..at].iter().rposition(|&c| !is_ident_char(c)).map_or(0, |i| i + 1);
+        let end = chars[at..].iter().position(|&c| !is_ident_char(c)).map_or(chars.len(), |i| at + i);
+        Some(chars[start..end].iter().collect())
+    }
+
+    /// Start renaming the identifier under the cursor (`rename_word` command): the prompt is
+    /// prefilled with it and the status line shows how many matches will change.
+    pub fn prompt_rename_word(&mut self) {
+        let Some(word) = self.ident_at_cursor() else {
+            self.set_status("No identifier under the cursor.", Duration::from_secs(2));
+            return;
+        };
+        if !self.ensure_writable() { return; }
+        let last = self.doc.buf.line_count() - 1;
+        let (start, end, scope) = match self.selection_range() {
+            Some((a, b)) => (a, b, "selection"),
+            None => (Pos { y: 0, x: 0 }, Pos { y: last, x: self.doc.buf.line_len_chars(last) }, "buffer"),
+        };
+        let count = whole_word_matches(&self.doc.buf.get_range(start, end), &word).len();
+        if count == 0 {
+            self.set_status(format!("No whole-word matches of '{word}' in the {scope}."), Duration::from_secs(2));
+            return;
+        }
+        let plural = if count == 1 { "" } else { "es" };
+        self.set_status(format!("Rename '{word}': {count} match{plural} in the {scope}"), Duration::from_secs(10));
+        self.prompt = Some(Prompt::new(PromptKind::Rename, word.clone()));
+        self.rename = Some(RenameTarget { word, start, end });
+        self.mark_redraw();
+    }
+
+    /// Replace every whole-word match of the pending rename with `new_name` as one undo step.
+    pub(crate) fn apply_rename(&mut self, new_name: &str) -> Result<()> {
+        let Some(RenameTarget { word, start, end }) = self.rename.take() else { return Ok(()); };
+        let new_name = new_name.trim();
+        if new_name.is_empty() || new_name == word {
+            self.set_status("Rename cancelled.", Duration::from_secs(2));
+            return Ok(());
+        }
+        let text = self.doc.buf.get_range(start, end);
+        let hits = whole_word_matches(&text, &word);
+        let (Some(&first), Some(&last)) = (hits.first(), hits.last()) else { return Ok(()); };
+        let word_len = word.chars().count();
+        let last_end = last + word_len;
+
+        // Only the span from the first to the last match is replaced, to keep the undo entry small.
+        let chars: Vec<char> = text.chars().collect();
+        let mut replaced = String::new();
+        let mut at = first;
+        for &hit in &hits {
+            replaced.extend(&chars[at..hit]);
+            replaced.push_str(new_name);
+            at = hit + word_len;
+        }
+
+        // Keep the cursor on the same text: shift it by the growth of the matches before it.
+        let base = self.doc.buf.pos_to_char_idx_public(start);
+        let cursor = self.doc.buf.pos_to_char_idx_public(self.doc.cursor);
+        let new_len = new_name.chars().count();
+        let before = hits.iter().filter(|&&h| base + h + word_len <= cursor).count();
+        let inside = hits.iter().find(|&&h| base + h < cursor && cursor < base + h + word_len);
+        let new_cursor = match inside {
+            Some(&h) => base + h + before * new_len - before * word_len,
+            None => cursor + before * new_len - before * word_len,
+        };
+
+        let from = self.doc.buf.char_idx_to_pos_public(base + first);
+        let to = self.doc.buf.char_idx_to_pos_public(base + last_end);
+        self.replace_range(from, to, &replaced);
+        self.doc.cursor = self.doc.buf.char_idx_to_pos_public(new_cursor);
+        self.clear_selection();
+        let plural = if hits.len() == 1 { "" } else { "es" };
+        self.set_status(format!("Renamed {} match{plural} of '{word}' to '{new_name}'.", hits.len()), Duration::from_secs(3));
+        self.ensure_visible()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_whole_words_only() {
+        assert_eq!(whole_word_matches("foo foobar bar_foo foo(foo)", "foo"), [0, 19, 23]);
+        assert_eq!(whole_word_matches("größe größer", "größe"), [0]);
+        assert!(whole_word_matches("x", "").is_empty());
+    }
+}
@@ -1,8 +1,11 @@
 //! Rendering: drawing the editor UI to the terminal.
 
-use crate::types::PromptKind; // prompt types
-use crate::utils::digits; // digit counting helper
+use crate::bidi; // bi-directional text reordering
+use crate::lsp::Severity; // diagnostic underlines
+use crate::types::{HighlightSpan, PromptKind}; // highlight spans, prompt types
+use crate::utils::{digits, safe_mode, scroll_position}; // digit counting, safe mode tag, status-bar scroll position
 use super::highlight::highlight_to_crossterm; // highlight color conversion
+use super::palette::PALETTE_ROWS; // suggestions above the command prompt
 use super::Editor; // editor state
 use anyhow::Result; // anyhow error handling
 use crossterm::{ // terminal manipulation
@@ -13,7 +16,34 @@ use crossterm::{ // terminal manipulation
 };
 use std::cmp::max; // comparison helpers
 use std::io::{Stdout, Write}; // output handling
-use unicode_width::UnicodeWidthChar; // character width handling
+use std::ops::Range; // chars of a wrapped row
+
+/// Drawn in the cells left over when a wide char does not fit at the right edge.
+const WIDE_CHAR_PLACEHOLDER: &str = ">";
+
+/// Marks drawn by `show_whitespace` for a tab, a trailing space and a non-breaking space, with
+/// ASCII stand-ins for ASCII terminals and for ones that draw the marks double width.
+const WHITESPACE_MARKS: [(char, char); 3] = [('→', '>'), ('·', '.'), ('␣', '_')];
+
+/// The `show_whitespace` mark slot of `ch`; plain spaces only count when `trailing`.
+fn whitespace_mark(ch: char, trailing: bool) -> Option<usize> {
+    match ch {
+        '\t' => Some(0),
+        ' ' if trailing => Some(1),
+        '\u{a0}' | '\u{2007}' | '\u{202f}' => Some(2),
+        _ => None,
+    }
+}
+
+/// The most serious of the `diagnostics` (from `line_diagnostics`) covering char `i`.
+fn diagnostic_at(diagnostics: &[(Range<usize>, Severity)], i: usize) -> Option<Severity> {
+    diagnostics.iter().filter(|(chars, _)| chars.contains(&i)).map(|&(_, severity)| severity).min()
+}
+
+/// Columns available for text: the full width minus the gutter and the scroll indicator column.
+pub(crate) fn text_area_width(width: usize, gutter: usize) -> usize {
+    width.saturating_sub(gutter + 1)
+}
 
 impl Editor {
     /// Render the entire UI.
@@ -23,155 +53,217 @@ impl Editor {
 
         if self.show_help { return self.render_help(stdout); }
         if self.show_stats { return self.render_stats(stdout); }
+        if self.show_plugins { return self.render_plugins(stdout); }
+        if self.show_keys { return self.render_keys(stdout); }
+        if self.file_info.is_some() { return self.render_file_info(stdout); }
+        if self.buffer_list.is_some() { return self.render_buffers(stdout); }
+        if self.diff_view.is_some() { return self.render_diff(stdout); }
+        if self.grep_view.is_some() { return self.render_grep(stdout); }
+        if self.lsp.list.is_some() { return self.render_diagnostics(stdout); }
+        if self.paste_history.is_some() { return self.render_paste_history(stdout); }
 
         let (w, h) = terminal::size()?;
         let width = w as usize;
         let height = h as usize;
 
-        let lnw = max(2, digits(self.buf.line_count()));
-        let gutter = lnw + 2;
-
         let has_prompt = self.prompt.is_some();
-        let editor_h = height.saturating_sub(1 + if has_prompt { 1 } else { 0 });
-        let prompt_y = if has_prompt { editor_h } else { 0 };
+        let top = self.text_top();
+        let editor_h = self.text_rows(height);
+        let prompt_y = if has_prompt { top + editor_h } else { 0 };
         let status_y = height.saturating_sub(1);
 
         stdout.queue(cursor::Hide)?;
         stdout.queue(style::ResetColor)?;
 
-        let rows_rendered = if self.word_wrap {
-            self.render_lines_wrapped(stdout, width, editor_h, gutter)?
-        } else {
-            self.render_lines_normal(stdout, width, editor_h, gutter)?
-        };
-
-        for row in rows_rendered..editor_h {
-            stdout.queue(cursor::MoveTo(0, row as u16))?;
-            stdout.queue(terminal::Clear(ClearType::CurrentLine))?;
-            stdout.queue(style::SetForegroundColor(Color::DarkGrey))?;
-            stdout.queue(style::Print("~"))?;
-            stdout.queue(style::ResetColor)?;
+        self.sync_scrollbind();
+        // Rows are cleared up to the end of the line, so with a split the left pane goes first.
+        if self.doc.split.is_some() && self.doc.split_focus_right {
+            self.with_other_pane(|ed| ed.render_text_area(stdout, width, editor_h))?;
+        }
+        let gutter = self.render_text_area(stdout, width, editor_h)?;
+        if self.doc.split.is_some() && !self.doc.split_focus_right {
+            self.with_other_pane(|ed| ed.render_text_area(stdout, width, editor_h))?;
         }
 
+        if self.tab_bar_shown() { self.render_tab_bar(stdout, width)?; }
+        if self.breadcrumb { self.render_breadcrumb(stdout, width)?; }
+
+        self.render_open_preview(stdout, width, top, top + editor_h)?;
         if let Some(p) = &self.prompt {
-            self.render_prompt(stdout, prompt_y, editor_h, width)?;
+            self.render_prompt(stdout, prompt_y, top + editor_h, width)?;
             stdout.queue(cursor::MoveTo(0, prompt_y as u16))?;
             stdout.queue(terminal::Clear(ClearType::CurrentLine))?;
             stdout.queue(style::SetForegroundColor(Color::Yellow))?;
             let label = match p.kind {
                 PromptKind::Open => "Open: ",
                 PromptKind::SaveAs => "Save as: ",
+                PromptKind::Find if self.find_scope_range().is_some() => "Find in selection: ",
                 PromptKind::Find => "Find: ",
                 PromptKind::Command => "Command: ",
-                PromptKind::GotoLine => "Goto line: ",
+                PromptKind::GotoLine => "Goto line[:column]: ",
+                PromptKind::PinLines => "Pin lines (0 to unpin): ",
+                PromptKind::WordGoal => "Word goal (0 to clear): ",
+                PromptKind::Pomodoro => "Focus minutes (0 to stop): ",
+                PromptKind::Grep => "Grep (regex): ",
+                PromptKind::Rename => "Rename to: ",
+                PromptKind::DeleteFile => "Move this file to the trash? (y/N): ",
+                PromptKind::MakeReadOnly => "Make this file read-only? (y/N): ",
+                PromptKind::MakeWritable => "Clear the read-only attribute? (y/N): ",
+                PromptKind::SaveReadOnly => "The file is read-only. Make it writable and save? (y/N): ",
+                PromptKind::PluginName => "New plugin name: ",
+                PromptKind::MacroName => "Save macro as (name, optionally a key like Alt+1): ",
+                PromptKind::PluginInput | PromptKind::PluginConfirm => self.plugin_prompt.as_deref().unwrap_or(""),
             };
             stdout.queue(style::Print(label))?;
             stdout.queue(style::ResetColor)?;
             stdout.queue(style::Print(&p.input))?;
         }
 
-        self.render_status_bar(stdout, status_y, width)?;
-
-        let (cursor_x, cursor_y) = self.calculate_cursor_position(width, gutter, editor_h)?;
-        let final_x = cursor_x.min(width.saturating_sub(1));
-        let final_y = cursor_y.min(editor_h.saturating_sub(1));
+        self.render_color_picker(stdout, width, top + editor_h)?;
+        self.render_status_bar(stdout, status_y, width, editor_h)?;
 
-        stdout.queue(cursor::MoveTo(final_x as u16, final_y as u16))?;
-        stdout.queue(cursor::Show)?;
+        let (left, pane_w) = self.pane_columns(width);
+        let (cursor_x, cursor_y) = self.calculate_cursor_position(pane_w, gutter, editor_h)?;
+        let final_x = left + cursor_x.min(pane_w.saturating_sub(1));
+        let final_y = if self.doc.cursor.y < self.pinned_rows() {
+            Some(self.header_rows() + self.doc.cursor.y)
+        } else {
+            cursor_y.map(|y| top + y)
+        };
+        self.render_completion(stdout, final_x, final_y.unwrap_or(top), width, top + editor_h)?;
+        self.render_context_menu(stdout)?;
+
+        stdout.queue(cursor::MoveTo(final_x as u16, final_y.unwrap_or(top) as u16))?;
+        // While a smooth scroll is running, or the mouse wheel scrolled the cursor out of view,
+        // keep it hidden.
+        if self.scroll_target.is_none() && final_y.is_some() {
+            stdout.queue(cursor::Show)?;
+        }
         stdout.flush()?;
         Ok(())
     }
 
-    fn render_lines_normal(&mut self, stdout: &mut Stdout, width: usize, editor_h: usize, gutter: usize) -> Result<usize> {
+    /// Draw the focused pane's text (pinned lines, scrolled lines, `~` filler) in its columns of
+    /// a terminal `width` columns wide. Returns the gutter width.
+    fn render_text_area(&mut self, stdout: &mut Stdout, width: usize, editor_h: usize) -> Result<usize> {
+        let (left, width) = self.pane_columns(width);
+        let col = u16::try_from(left).unwrap_or(u16::MAX);
+        let lnw = max(2, digits(self.doc.buf.line_count()));
+        let gutter = lnw + 2;
+        // Pinning lines or resizing can leave the scrolled area overlapping the pinned lines.
+        self.doc.scroll_y = self.doc.scroll_y.max(self.pinned_scroll_min(width));
+
+        let rows_rendered = if self.wrapping() {
+            self.render_lines_wrapped(stdout, col, width, editor_h, gutter)?
+        } else {
+            self.render_lines_normal(stdout, col, width, editor_h, gutter)?
+        };
+        self.render_pinned_lines(stdout, left, width, gutter)?;
+
+        for row in rows_rendered..editor_h {
+            stdout.queue(cursor::MoveTo(col, (self.text_top() + row) as u16))?;
+            stdout.queue(terminal::Clear(ClearType::UntilNewLine))?;
+            stdout.queue(style::SetForegroundColor(self.theme.gutter))?;
+            stdout.queue(style::Print("~"))?;
+            stdout.queue(style::ResetColor)?;
+        }
+        Ok(gutter)
+    }
+
+    fn render_lines_normal(&mut self, stdout: &mut Stdout, left: u16, width: usize, editor_h: usize, gutter: usize) -> Result<usize> {
         let lnw = gutter - 2;
-        let avail = width.saturating_sub(gutter);
+        let avail = text_area_width(width, gutter);
 
         for row in 0..editor_h {
-            let y = self.scroll_y + row;
-            stdout.queue(cursor::MoveTo(0, row as u16))?;
-            stdout.queue(terminal::Clear(ClearType::CurrentLine))?;
+            let y = self.doc.scroll_y + row;
+            stdout.queue(cursor::MoveTo(left, (self.text_top() + row) as u16))?;
+            stdout.queue(terminal::Clear(ClearType::UntilNewLine))?;
 
-            if y >= self.buf.line_count() {
-                stdout.queue(style::SetForegroundColor(Color::DarkGrey))?;
+            if y >= self.doc.buf.line_count() {
+                stdout.queue(style::SetForegroundColor(self.theme.gutter))?;
                 stdout.queue(style::Print("~"))?;
                 stdout.queue(style::ResetColor)?;
                 continue;
             }
 
-            let is_current_line = y == self.cursor.y;
-            let base_bg = if is_current_line { Some(Color::DarkBlue) } else { None };
+            let is_current_line = y == self.doc.cursor.y;
+            let base_bg = if is_current_line { Some(self.theme.current_line) } else { None };
 
             if let Some(bg) = base_bg { stdout.queue(style::SetBackgroundColor(bg))?; }
-            stdout.queue(style::SetForegroundColor(Color::DarkGrey))?;
+            stdout.queue(style::SetForegroundColor(self.gutter_color(y)))?;
             stdout.queue(style::Print(format!("{:>width$}", y + 1, width = lnw)))?;
-            stdout.queue(style::Print("│ "))?;
+            stdout.queue(style::SetForegroundColor(self.theme.gutter))?;
+            self.render_gutter_separator(stdout, Some(y))?;
             stdout.queue(style::ResetColor)?;
 
-            self.render_line_content(stdout, y, avail, base_bg)?;
-
-            if is_current_line {
-                let line = self.buf.line(y);
-                let chars: Vec<char> = line.chars().skip(self.scroll_x).collect();
-                let col_used: usize = chars.iter().take_while(|_| true)
-                    .map(|ch| UnicodeWidthChar::width(*ch).unwrap_or(1))
-                    .take_while({ let mut sum = 0; move |w| { sum += w; sum <= avail }})
-                    .sum();
-                if col_used < avail {
-                    stdout.queue(style::SetBackgroundColor(Color::DarkBlue))?;
-                    stdout.queue(style::Print(" ".repeat(avail - col_used)))?;
-                    stdout.queue(style::ResetColor)?;
-                }
+            let col_used = self.render_line_content(stdout, y, avail, base_bg)?;
+            let col_used = self.render_ruler(stdout, col_used, avail, base_bg)?;
+
+            if is_current_line && col_used < avail {
+                stdout.queue(style::SetBackgroundColor(self.theme.current_line))?;
+                stdout.queue(style::Print(" ".repeat(avail - col_used)))?;
+                stdout.queue(style::ResetColor)?;
             }
 
-            self.render_scroll_indicator(stdout, row, width, editor_h)?;
+            self.render_scroll_indicator(stdout, row, usize::from(left) + width, editor_h)?;
         }
         Ok(editor_h)
     }
 
-    fn render_lines_wrapped(&mut self, stdout: &mut Stdout, width: usize, editor_h: usize, gutter: usize) -> Result<usize> {
+    fn render_lines_wrapped(&mut self, stdout: &mut Stdout, left: u16, width: usize, editor_h: usize, gutter: usize) -> Result<usize> {
         let lnw = gutter - 2;
-        let avail = width.saturating_sub(gutter);
+        let avail = text_area_width(width, gutter);
         let mut rows_rendered = 0;
         let mut current_screen_row = 0;
 
-        let line_count = self.buf.line_count();
+        let line_count = self.doc.buf.line_count();
         for line_idx in 0..line_count {
-            let line = self.buf.line(line_idx).to_string();
+            let line = self.doc.buf.line(line_idx).to_string();
             let segments = self.calculate_wrap_segments(&line, avail);
+            let pad = self.wrap_pad(&line, avail);
+            let line_len = line.chars().count();
 
             for (seg_idx, &start_char_idx) in segments.iter().enumerate() {
-                if current_screen_row >= self.scroll_y && rows_rendered < editor_h {
+                if current_screen_row >= self.doc.scroll_y && rows_rendered < editor_h {
                     let screen_row = rows_rendered;
-                    stdout.queue(cursor::MoveTo(0, screen_row as u16))?;
-                    stdout.queue(terminal::Clear(ClearType::CurrentLine))?;
+                    stdout.queue(cursor::MoveTo(left, (self.text_top() + screen_row) as u16))?;
+                    stdout.queue(terminal::Clear(ClearType::UntilNewLine))?;
 
-                    let is_current_line = line_idx == self.cursor.y;
-                    let base_bg = if is_current_line { Some(Color::DarkBlue) } else { None };
+                    let is_current_line = line_idx == self.doc.cursor.y;
+                    let base_bg = if is_current_line { Some(self.theme.current_line) } else { None };
 
                     if let Some(bg) = base_bg { stdout.queue(style::SetBackgroundColor(bg))?; }
-                    stdout.queue(style::SetForegroundColor(Color::DarkGrey))?;
+                    stdout.queue(style::SetForegroundColor(self.theme.gutter))?;
                     if seg_idx == 0 {
+                        stdout.queue(style::SetForegroundColor(self.gutter_color(line_idx)))?;
                         stdout.queue(style::Print(format!("{:>width$}", line_idx + 1, width = lnw)))?;
+                        stdout.queue(style::SetForegroundColor(self.theme.gutter))?;
                     } else {
-                        stdout.queue(style::Print(" ".repeat(lnw)))?;
+                        stdout.queue(style::Print(" ".repeat(lnw - 1)))?;
+                        stdout.queue(style::SetForegroundColor(self.theme.wrap_marker))?;
+                        stdout.queue(style::Print(self.caps.glyph("↪", ">")))?;
+                        stdout.queue(style::SetForegroundColor(self.theme.gutter))?;
                     }
-                    stdout.queue(style::Print("│ "))?;
+                    self.render_gutter_separator(stdout, (seg_idx == 0).then_some(line_idx))?;
                     stdout.queue(style::ResetColor)?;
 
-                    self.render_wrapped_segment(stdout, line_idx, &line, start_char_idx, avail, base_bg)?;
+                    let pad = if seg_idx == 0 { 0 } else { pad };
+                    if pad > 0 {
+                        if let Some(bg) = base_bg { stdout.queue(style::SetBackgroundColor(bg))?; }
+                        stdout.queue(style::Print(" ".repeat(pad)))?;
+                        stdout.queue(style::ResetColor)?;
+                    }
+                    let end_char_idx = segments.get(seg_idx + 1).copied().unwrap_or(line_len);
+                    let col_used = pad + self.render_wrapped_segment(stdout, line_idx, &line, start_char_idx..end_char_idx, avail - pad, base_bg)?;
+                    let col_used = self.render_ruler(stdout, col_used, avail, base_bg)?;
 
-                    if is_current_line {
-                        let chars: Vec<char> = line.chars().skip(start_char_idx).collect();
-                        let mut col_used = 0;
-                        for ch in chars { let w = UnicodeWidthChar::width(ch).unwrap_or(1); if col_used + w > avail { break; } col_used += w; }
-                        if col_used < avail {
-                            stdout.queue(style::SetBackgroundColor(Color::DarkBlue))?;
-                            stdout.queue(style::Print(" ".repeat(avail - col_used)))?;
-                            stdout.queue(style::ResetColor)?;
-                        }
+                    if is_current_line && col_used < avail {
+                        stdout.queue(style::SetBackgroundColor(self.theme.current_line))?;
+                        stdout.queue(style::Print(" ".repeat(avail - col_used)))?;
+                        stdout.queue(style::ResetColor)?;
                     }
 
-                    self.render_scroll_indicator(stdout, screen_row, width, editor_h)?;
+                    self.render_scroll_indicator(stdout, screen_row, usize::from(left) + width, editor_h)?;
                     rows_rendered += 1;
                 }
                 current_screen_row += 1;
@@ -181,107 +273,156 @@ impl Editor {
         Ok(rows_rendered)
     }
 
-    fn calculate_wrap_segments(&self, line: &str, avail: usize) -> Vec<usize> {
-        let mut segments = Vec::new();
-        if line.is_empty() { segments.push(0); return segments; }
-        let mut current_col = 0;
-        let mut start_idx = 0;
-        for (i, ch) in line.chars().enumerate() {
-            let ch_w = UnicodeWidthChar::width(ch).unwrap_or(1);
-            if current_col + ch_w > avail { segments.push(start_idx); start_idx = i; current_col = 0; }
-            current_col += ch_w;
-        }
-        segments.push(start_idx);
-        segments
+    /// Syntax highlight spans of line `y`; none in large-file mode.
+    fn line_highlights(&mut self, y: usize, line: &str) -> Vec<HighlightSpan> {
+        if self.large_file { Vec::new() } else { self.highlighter.get_highlights(y, line) }
     }
 
-    fn render_wrapped_segment(&mut self, stdout: &mut Stdout, line_idx: usize, line: &str, start_char_idx: usize, avail: usize, base_bg: Option<Color>) -> Result<()> {
+    /// The char drawn for `ch`: its `show_whitespace` mark (and `true`), or `ch` itself.
+    fn displayed_char(&self, ch: char, trailing: bool) -> (char, bool) {
+        let Some(i) = whitespace_mark(ch, trailing).filter(|_| self.show_whitespace) else { return (ch, false); };
+        let (mark, ascii) = WHITESPACE_MARKS[i];
+        (if self.caps.unicode && self.char_width(mark) == self.char_width(ch) { mark } else { ascii }, true)
+    }
+
+    /// Render the chars `chars` of `line` as one wrapped row, clipped to `avail` columns;
+    /// returns the number of screen columns used.
+    fn render_wrapped_segment(&mut self, stdout: &mut Stdout, line_idx: usize, line: &str, chars: Range<usize>, avail: usize, base_bg: Option<Color>) -> Result<usize> {
         let sel = self.selection_range();
 
         // Get syntax highlights for this line
-        let highlights = self.highlighter.get_highlights(line_idx, line);
-
-        let chars: Vec<char> = line.chars().skip(start_char_idx).collect();
-        let mut col_used = 0;
-        let mut seg_char_i = start_char_idx;
-
-        for ch in chars {
-            let ch_w = UnicodeWidthChar::width(ch).unwrap_or(1);
-            if col_used + ch_w > avail { break; }
+        let highlights = self.line_highlights(line_idx, line);
+        let diagnostics = self.line_diagnostics(line_idx, line);
+
+        // Collect the logical chars that fit in this row, then reorder them for display.
+        let line_chars: Vec<char> = line.chars().collect();
+        let mut seg: Vec<usize> = Vec::new();
+        let mut seg_w = 0;
+        let start_char_idx = chars.start;
+        for (i, &ch) in line_chars.iter().enumerate().take(chars.end).skip(start_char_idx) {
+            let ch_w = self.char_width(ch);
+            if seg_w + ch_w > avail { break; }
+            seg_w += ch_w;
+            seg.push(i);
+        }
+        let seg_text: String = seg.iter().map(|&i| line_chars[i]).collect();
+        if let Some(order) = bidi::visual_order(&seg_text) {
+            seg = order.into_iter().map(|i| start_char_idx + i).collect();
+        }
 
+        let trailing_from = line.trim_end_matches([' ', '\t']).chars().count();
+        for seg_char_i in seg {
+            let (ch, is_mark) = self.displayed_char(line_chars[seg_char_i], seg_char_i >= trailing_from);
             let selected = self.is_char_selected(sel, line_idx, seg_char_i);
 
             // Determine color: selection overrides syntax highlighting
             if selected {
-                stdout.queue(style::SetForegroundColor(Color::Black))?;
-                stdout.queue(style::SetBackgroundColor(Color::Grey))?;
+                stdout.queue(style::SetForegroundColor(self.theme.selection_fg))?;
+                stdout.queue(style::SetBackgroundColor(self.theme.selection_bg))?;
                 stdout.queue(style::SetAttribute(Attribute::Bold))?;
             } else {
                 // Check for syntax highlight color
                 let hl_color = self.highlighter.color_at(&highlights, seg_char_i);
                 if let Some(bg) = base_bg { stdout.queue(style::SetBackgroundColor(bg))?; }
-                if let Some(hc) = hl_color {
+                if is_mark {
+                    stdout.queue(style::SetForegroundColor(Color::DarkGrey))?;
+                } else if let Some(hc) = hl_color {
                     stdout.queue(style::SetForegroundColor(highlight_to_crossterm(hc)))?;
                 } else {
                     stdout.queue(style::SetForegroundColor(Color::Reset))?;
                 }
             }
+            if let Some(severity) = diagnostic_at(&diagnostics, seg_char_i) {
+                self.queue_underline(stdout, severity)?;
+            }
 
             stdout.queue(style::Print(ch))?;
             stdout.queue(style::ResetColor)?;
             stdout.queue(style::SetAttribute(Attribute::Reset))?;
-
-            col_used += ch_w;
-            seg_char_i += 1;
         }
-        Ok(())
+        Ok(seg_w)
     }
 
-    fn render_line_content(&mut self, stdout: &mut Stdout, y: usize, avail: usize, base_bg: Option<Color>) -> Result<()> {
-        let line = self.buf.line(y).to_string();
+    /// Render the visible part of line `y`; returns the number of screen columns used.
+    pub(crate) fn render_line_content(&mut self, stdout: &mut Stdout, y: usize, avail: usize, base_bg: Option<Color>) -> Result<usize> {
+        let line = self.doc.buf.line(y).to_string();
         let sel = self.selection_range();
 
         // Get syntax highlights for this line
-        let highlights = self.highlighter.get_highlights(y, &line);
+        let highlights = self.line_highlights(y, &line);
+        let diagnostics = self.line_diagnostics(y, &line);
 
+        // Draw in visual order so right-to-left runs display correctly.
+        let line_chars: Vec<char> = line.chars().collect();
+        let order = bidi::display_order(&line);
         let mut col_used = 0;
-        let mut char_i = self.scroll_x;
-        let chars: Vec<char> = if self.scroll_x < line.chars().count() { line.chars().skip(self.scroll_x).collect() } else { vec![] };
-
-        for ch in chars {
-            let ch_w = UnicodeWidthChar::width(ch).unwrap_or(1);
-            if col_used + ch_w > avail { break; }
+        let trailing_from = line.trim_end_matches([' ', '\t']).chars().count();
 
+        for &char_i in order.iter().skip(self.doc.scroll_x) {
+            let (ch, is_mark) = self.displayed_char(line_chars[char_i], char_i >= trailing_from);
+            let ch_w = self.char_width(ch);
             let selected = self.is_char_selected(sel, y, char_i);
 
+            // A wide char straddling the right edge would be clipped by the terminal (or wrap onto
+            // the next row), so pad the remaining cells with a placeholder instead.
+            if col_used + ch_w > avail {
+                let bg = if selected { Some(self.theme.selection_bg) } else { base_bg };
+                if let Some(bg) = bg { stdout.queue(style::SetBackgroundColor(bg))?; }
+                stdout.queue(style::SetForegroundColor(Color::DarkGrey))?;
+                stdout.queue(style::Print(WIDE_CHAR_PLACEHOLDER.repeat(avail - col_used)))?;
+                stdout.queue(style::ResetColor)?;
+                col_used = avail;
+                break;
+            }
+
             // Determine color: selection overrides syntax highlighting
             if selected {
-                stdout.queue(style::SetForegroundColor(Color::Black))?;
-                stdout.queue(style::SetBackgroundColor(Color::Grey))?;
+                stdout.queue(style::SetForegroundColor(self.theme.selection_fg))?;
+                stdout.queue(style::SetBackgroundColor(self.theme.selection_bg))?;
                 stdout.queue(style::SetAttribute(Attribute::Bold))?;
             } else {
                 // Check for syntax highlight color
                 let hl_color = self.highlighter.color_at(&highlights, char_i);
                 if let Some(bg) = base_bg { stdout.queue(style::SetBackgroundColor(bg))?; }
-                if let Some(hc) = hl_color {
+                if is_mark {
+                    stdout.queue(style::SetForegroundColor(Color::DarkGrey))?;
+                } else if let Some(hc) = hl_color {
                     stdout.queue(style::SetForegroundColor(highlight_to_crossterm(hc)))?;
                 } else {
                     stdout.queue(style::SetForegroundColor(Color::Reset))?;
                 }
             }
+            if let Some(severity) = diagnostic_at(&diagnostics, char_i) {
+                self.queue_underline(stdout, severity)?;
+            }
 
             stdout.queue(style::Print(ch))?;
             stdout.queue(style::ResetColor)?;
             stdout.queue(style::SetAttribute(Attribute::Reset))?;
 
             col_used += ch_w;
-            char_i += 1;
         }
-        Ok(())
+        Ok(col_used)
+    }
+
+    /// Draw the `ruler` guide on a row whose text took `col_used` of the `avail` columns and
+    /// ends before the guide: blanks up to it, then the guide cell. Returns the columns used.
+    fn render_ruler(&self, stdout: &mut Stdout, col_used: usize, avail: usize, base_bg: Option<Color>) -> Result<usize> {
+        let scrolled = if self.wrapping() { 0 } else { self.doc.scroll_x };
+        let guide = self.ruler.checked_sub(scrolled).filter(|&col| self.ruler > 0 && col >= col_used && col < avail);
+        let Some(col) = guide else { return Ok(col_used); };
+        if let Some(bg) = base_bg { stdout.queue(style::SetBackgroundColor(bg))?; }
+        stdout.queue(style::Print(" ".repeat(col - col_used)))?;
+        stdout.queue(style::SetBackgroundColor(self.theme.ruler))?;
+        stdout.queue(style::Print(" "))?;
+        stdout.queue(style::ResetColor)?;
+        Ok(col + 1)
     }
 
     fn is_char_selected(&self, sel: Option<(crate::types::Pos, crate::types::Pos)>, y: usize, char_i: usize) -> bool {
-        if let Some((a, b)) = sel {
+        if let Some(b) = self.block_range() {
+            (b.top..=b.bottom).contains(&y) && (b.left..b.right).contains(&char_i)
+        } else if let Some((a, b)) = sel {
             if y < a.y || y > b.y { false }
             else if y == a.y && y == b.y { char_i >= a.x && char_i < b.x }
             else if y == a.y { char_i >= a.x }
@@ -290,19 +431,46 @@ impl Editor {
         } else { false }
     }
 
-    fn render_scroll_indicator(&self, stdout: &mut Stdout, row: usize, width: usize, editor_h: usize) -> Result<()> {
-        let total_lines = self.buf.line_count();
+    /// The `│ ` between the line number of line `y` (none on continuation rows) and the text,
+    /// or the sign of the line's most serious diagnostic in its color.
+    fn render_gutter_separator(&self, stdout: &mut Stdout, y: Option<usize>) -> Result<()> {
+        if let Some(severity) = y.and_then(|y| self.line_severity(y)) {
+            stdout.queue(style::SetForegroundColor(self.severity_color(severity)))?;
+            stdout.queue(style::Print(self.caps.glyph("● ", "* ")))?;
+        } else {
+            stdout.queue(style::Print(self.caps.glyph("│ ", "| ")))?;
+        }
+        Ok(())
+    }
+
+    /// Underline the next char for a diagnostic; in its color where the terminal has 256 colors.
+    fn queue_underline(&self, stdout: &mut Stdout, severity: Severity) -> Result<()> {
+        if self.caps.colors_256 {
+            stdout.queue(style::SetUnderlineColor(self.severity_color(severity)))?;
+        }
+        stdout.queue(style::SetAttribute(Attribute::Underlined))?;
+        Ok(())
+    }
+
+    /// Color of line `y`'s number: marked duplicates stand out.
+    fn gutter_color(&self, y: usize) -> Color {
+        if self.is_duplicate_line(y) { Color::Yellow } else { self.theme.gutter }
+    }
+
+    /// Draw the scroll indicator cell of text row `row` in the column left of `right`.
+    fn render_scroll_indicator(&self, stdout: &mut Stdout, row: usize, right: usize, editor_h: usize) -> Result<()> {
+        let total_lines = self.doc.buf.line_count();
         let thumb_size = max(1, (editor_h * editor_h) / max(1, total_lines));
-        let thumb_start = (self.scroll_y * editor_h) / max(1, total_lines);
+        let thumb_start = (self.doc.scroll_y * editor_h) / max(1, total_lines);
         let thumb_end = thumb_start + thumb_size;
 
-        stdout.queue(cursor::MoveTo((width - 1) as u16, row as u16))?;
+        stdout.queue(cursor::MoveTo((right - 1) as u16, (self.text_top() + row) as u16))?;
         if row >= thumb_start && row < thumb_end {
             stdout.queue(style::SetForegroundColor(Color::White))?;
-            stdout.queue(style::Print("█"))?;
+            stdout.queue(style::Print(self.caps.glyph("█", "#")))?;
         } else {
             stdout.queue(style::SetForegroundColor(Color::DarkGrey))?;
-            stdout.queue(style::Print("│"))?;
+            stdout.queue(style::Print(self.caps.glyph("│", "|")))?;
         }
         stdout.queue(style::ResetColor)?;
         Ok(())
@@ -311,7 +479,8 @@ impl Editor {
     fn render_prompt(&self, stdout: &mut Stdout, prompt_y: usize, editor_h: usize, width: usize) -> Result<()> {
         if let Some(p) = &self.prompt {
             if p.kind == PromptKind::Command {
-                let hits = self.commands.search(p.input.trim(), 10);
+                let hits = self.commands.search(p.input.trim(), PALETTE_ROWS);
+                let selected = self.palette_selection();
                 if !hits.is_empty() {
                     let list_h = hits.len();
                     let start_y = prompt_y.saturating_sub(list_h);
@@ -320,16 +489,18 @@ impl Editor {
                         if row >= editor_h { continue; }
                         stdout.queue(cursor::MoveTo(0, row as u16))?;
                         stdout.queue(terminal::Clear(ClearType::CurrentLine))?;
-                        stdout.queue(style::SetBackgroundColor(Color::AnsiValue(235)))?;
+                        let bg = if selected == Some(i) { Color::AnsiValue(238) } else { Color::AnsiValue(235) };
+                        stdout.queue(style::SetBackgroundColor(self.caps.color(bg)))?;
                         stdout.queue(style::SetForegroundColor(Color::Yellow))?;
                         stdout.queue(style::Print(format!("  {:15}", cmd.name)))?;
                         stdout.queue(style::SetForegroundColor(Color::White))?;
-                        stdout.queue(style::Print(format!(" │ {:30}", cmd.description)))?;
-                        if let Some(key) = &cmd.key {
+                        stdout.queue(style::Print(format!(" {} {:30}", self.caps.glyph("│", "|"), cmd.description)))?;
+                        let keys = self.commands.keys_for(&cmd.name).join(", ");
+                        if !keys.is_empty() {
                             stdout.queue(style::SetForegroundColor(Color::Grey))?;
-                            stdout.queue(style::Print(format!(" ({})", key)))?;
+                            stdout.queue(style::Print(format!(" ({})", keys)))?;
                         }
-                        let used = 2 + 15 + 3 + 30 + cmd.key.as_ref().map(|k| k.len() + 3).unwrap_or(0);
+                        let used = 2 + 15 + 3 + 30 + if keys.is_empty() { 0 } else { keys.len() + 3 };
                         if used < width { stdout.queue(style::Print(" ".repeat(width - used)))?; }
                         stdout.queue(style::ResetColor)?;
                     }
@@ -339,51 +510,101 @@ impl Editor {
         Ok(())
     }
 
-    fn render_status_bar(&self, stdout: &mut Stdout, status_y: usize, width: usize) -> Result<()> {
+    /// Screen rows the whole buffer takes in a pane of `pane_w` columns (its lines without wrap).
+    pub(crate) fn document_rows(&self, pane_w: usize) -> usize {
+        if !self.wrapping() { return self.doc.buf.line_count(); }
+        let avail = text_area_width(pane_w, max(2, digits(self.doc.buf.line_count())) + 2);
+        (0..self.doc.buf.line_count()).map(|y| self.calculate_wrap_segments(&self.doc.buf.line(y), avail).len()).sum()
+    }
+
+    fn render_status_bar(&self, stdout: &mut Stdout, status_y: usize, width: usize, editor_h: usize) -> Result<()> {
         stdout.queue(cursor::MoveTo(0, status_y as u16))?;
         stdout.queue(terminal::Clear(ClearType::CurrentLine))?;
-        stdout.queue(style::SetForegroundColor(Color::Black))?;
-        stdout.queue(style::SetBackgroundColor(Color::White))?;
-
-        let path_str = self.file_path.as_ref().map(|p| p.display().to_string()).unwrap_or_else(|| "<new file>".to_string());
-        let sel_info = if let Some((a, b)) = self.selection_range() { format!("SEL {}:{}-{}:{}", a.y + 1, a.x + 1, b.y + 1, b.x + 1) } else { " ".to_string() };
-        let dirty = if self.dirty { "*" } else { " " };
+        // Alerts flash the whole bar.
+        let (fg, bg) = if self.status.as_ref().is_some_and(|s| s.alert) { (Color::Black, Color::Yellow) } else { (self.theme.status_fg, self.theme.status_bg) };
+        stdout.queue(style::SetForegroundColor(fg))?;
+        stdout.queue(style::SetBackgroundColor(bg))?;
+
+        let mut path_str = match (&self.doc.remote, &self.doc.file_path, &self.doc.url) {
+            (Some(r), _, _) => r.url(),
+            (None, Some(p), _) => p.display().to_string(),
+            (None, None, Some(u)) => u.clone(),
+            (None, None, None) => "<new file>".to_string(),
+        };
+        if self.doc.read_only { path_str.push_str(" [RO]"); }
+        if self.large_file { path_str.push_str(" [LARGE]"); }
+        if safe_mode() { path_str.push_str(" [SAFE]"); }
+        if self.recording_macro() { path_str.push_str(" [REC]"); }
+        if self.doc.buf.encoding != encoding_rs::UTF_8 { path_str = format!("{path_str} [{}]", self.doc.buf.encoding.name()); }
+        if self.buffer_count() > 1 { path_str = format!("[{}/{}] {path_str}", self.active + 1, self.buffer_count()); }
+        let sel_info = if let Some(b) = self.block_range() {
+            format!("BLOCK {}x{}", b.bottom - b.top + 1, b.right - b.left)
+        } else if let Some((a, b)) = self.selection_range() { format!("SEL {}:{}-{}:{}", a.y + 1, a.x + 1, b.y + 1, b.x + 1) } else { " ".to_string() };
+        let dirty = if self.doc.dirty { "*" } else { " " };
         let msg = self.status.as_ref().map(|s| s.text.clone()).unwrap_or_default();
-        let wrap_info = if self.word_wrap { "[WRAP]" } else { "" };
-
-        let left = format!(" {}{} {} {}  Ln {}, Col {}  {} ", dirty, "", path_str, wrap_info, self.cursor.y + 1, self.cursor.x + 1, sel_info);
+        let wrap_info = [self.language_label(), self.wrapping().then(|| "WRAP".to_string())].into_iter().flatten().map(|s| format!("[{s}]")).collect::<Vec<_>>().concat();
+        let goal_info = [self.word_goal_progress(), self.focus_countdown()].into_iter().flatten()
+            .chain(self.status_segments.iter().map(|(_, text)| text.clone()))
+            .map(|g| format!(" {g} ")).collect::<Vec<_>>().concat();
+
+        // A `#RRGGBB` literal under the cursor gets a swatch at the right end.
+        let color = self.color_under_cursor();
+        let width = if color.is_some() { width.saturating_sub(3) } else { width };
+
+        // Pinned lines stay on screen, so only the rows below them count.
+        let pane_w = self.pane_columns(width).1;
+        let pinned = self.pinned_scroll_min(pane_w);
+        let scrolled = scroll_position(self.doc.scroll_y.saturating_sub(pinned), editor_h, self.document_rows(pane_w).saturating_sub(pinned));
+        let left = format!(" {}{} {} {}  Ln {}/{}, Col {}  {scrolled} {} {} ", self.modal_label(), dirty, path_str, wrap_info, self.doc.cursor.y + 1, self.doc.buf.line_count(), self.doc.cursor.x + 1, goal_info, sel_info);
         let mut bar = left;
         if !msg.is_empty() { bar.push_str(" | "); bar.push_str(&msg); }
         if bar.chars().count() < width { bar.push_str(&" ".repeat(width - bar.chars().count())); }
         else { bar = bar.chars().take(width).collect(); }
 
         stdout.queue(style::Print(bar))?;
+        if let Some([r, g, b]) = color {
+            stdout.queue(style::SetBackgroundColor(self.caps.color(Color::Rgb { r, g, b })))?;
+            stdout.queue(style::Print("  "))?;
+            stdout.queue(style::SetBackgroundColor(Color::White))?;
+            stdout.queue(style::Print(" "))?;
+        }
         stdout.queue(style::ResetColor)?;
         Ok(())
     }
 
-    fn calculate_cursor_position(&self, width: usize, gutter: usize, _editor_h: usize) -> Result<(usize, usize)> {
-        let avail = width.saturating_sub(gutter);
-        if self.word_wrap {
+    /// The cursor's column and row in the text area; no row when it is scrolled out of view.
+    fn calculate_cursor_position(&self, width: usize, gutter: usize, editor_h: usize) -> Result<(usize, Option<usize>)> {
+        let avail = text_area_width(width, gutter);
+        // Pinned lines are drawn unwrapped, so their cursor column is computed as without wrap.
+        if self.wrapping() && self.doc.cursor.y >= self.pinned_rows() {
             let mut current_screen_row = 0;
-            for line_idx in 0..self.buf.line_count() {
-                let line = self.buf.line(line_idx);
+            for line_idx in 0..self.doc.buf.line_count() {
+                let line = self.doc.buf.line(line_idx);
                 let segments = self.calculate_wrap_segments(&line, avail);
-                if line_idx == self.cursor.y {
+                if line_idx == self.doc.cursor.y {
                     let mut seg_idx = 0;
-                    for (i, &start) in segments.iter().enumerate() { if self.cursor.x >= start { seg_idx = i; } else { break; } }
-                    let cursor_y = (current_screen_row + seg_idx).saturating_sub(self.scroll_y);
+                    for (i, &start) in segments.iter().enumerate() { if self.doc.cursor.x >= start { seg_idx = i; } else { break; } }
+                    let cursor_y = (current_screen_row + seg_idx).checked_sub(self.doc.scroll_y).filter(|&y| y < editor_h);
                     let start_char = segments[seg_idx];
-                    let col: usize = line.chars().skip(start_char).take(self.cursor.x - start_char).map(|ch| UnicodeWidthChar::width(ch).unwrap_or(1)).sum();
-                    return Ok((gutter + col, cursor_y));
+                    let end_char = segments.get(seg_idx + 1).copied().unwrap_or_else(|| line.chars().count());
+                    let seg_chars: Vec<char> = line.chars().skip(start_char).take(end_char - start_char).collect();
+                    let seg_text: String = seg_chars.iter().collect();
+                    let order = bidi::display_order(&seg_text);
+                    let vis = bidi::logical_to_visual(&order, self.doc.cursor.x - start_char);
+                    let pad = if seg_idx == 0 { 0 } else { self.wrap_pad(&line, avail) };
+                    let col: usize = order.iter().take(vis).map(|&i| self.char_width(seg_chars[i])).sum();
+                    return Ok((gutter + pad + col, cursor_y));
                 }
                 current_screen_row += segments.len();
             }
-            Ok((gutter, 0))
+            Ok((gutter, None))
         } else {
-            let cursor_row = self.cursor.y.saturating_sub(self.scroll_y);
-            let line = self.buf.line(self.cursor.y);
-            let col: usize = line.chars().skip(self.scroll_x).take(self.cursor.x.saturating_sub(self.scroll_x)).map(|ch| UnicodeWidthChar::width(ch).unwrap_or(1)).sum();
+            let cursor_row = self.doc.cursor.y.checked_sub(self.doc.scroll_y).filter(|&y| y < editor_h);
+            let line = self.doc.buf.line(self.doc.cursor.y);
+            let chars: Vec<char> = line.chars().collect();
+            let order = bidi::display_order(&line);
+            let vis = bidi::logical_to_visual(&order, self.doc.cursor.x);
+            let col: usize = order.iter().take(vis).skip(self.doc.scroll_x).map(|&i| self.char_width(chars[i])).sum();
             Ok((gutter + col, cursor_row))
         }
     }
@@ -0,0 +1,146 @@
+//! Clipboard history: every cut and copy is also kept in a ring of the last `RING_SIZE` texts
+//! (newest first, without repeats), which works even when the system clipboard does not.
+//! `paste_history` (Ctrl+Shift+V) lists them; Enter or a digit pastes one. Plugins get named
+//! registers (`api.register_set`/`register_get`) for stashing text of their own.
+
+use super::Editor; // editor state
+use anyhow::Result; // anyhow error handling
+use crossterm::{ // terminal manipulation
+    cursor,
+    event::{KeyCode, KeyEvent},
+    style::{self, Color},
+    terminal::{self, ClearType},
+    QueueableCommand,
+};
+use std::io::{Stdout, Write}; // output handling
+use std::time::Duration; // timing for status messages
+
+/// Texts kept in the clipboard history.
+const RING_SIZE: usize = 20;
+
+/// Put `text` at the front of `ring`, dropping an earlier copy of it and the oldest entries
+/// past `RING_SIZE`. Empty text is ignored.
+fn remember(ring: &mut Vec<String>, text: &str) {
+    if text.is_empty() { return; }
+    ring.retain(|t| t != text);
+    ring.insert(0, text.to_string());
+    ring.truncate(RING_SIZE);
+}
+
+/// One-row preview of a history entry: line breaks shown as `⏎`, tabs as spaces.
+fn preview(text: &str) -> String {
+    text.chars().map(|c| match c {
+        '\n' => '⏎',
+        '\t' | '\r' => ' ',
+        c => c,
+    }).collect()
+}
+
+impl Editor {
+    /// Add cut or copied text to the clipboard history.
+    pub(crate) fn remember_clip(&mut self, text: &str) {
+        remember(&mut self.clip_ring, text);
+    }
+
+    /// Show the clipboard history (`paste_history`).
+    pub fn open_paste_history(&mut self) {
+        if self.clip_ring.is_empty() {
+            self.set_status("Clipboard history is empty.", Duration::from_secs(2));
+            return;
+        }
+        self.paste_history = Some(0);
+        self.mark_redraw();
+    }
+
+    /// Keys in the history list: Up/Down select, Enter or 1-9 paste, Esc closes.
+    pub(crate) fn handle_paste_history_key(&mut self, key: KeyEvent) -> Result<()> {
+        let Some(selected) = self.paste_history else { return Ok(()); };
+        let last = self.clip_ring.len().saturating_sub(1);
+        let pick = match key.code {
+            KeyCode::Up => {
+                self.paste_history = Some(selected.saturating_sub(1));
+                None
+            }
+            KeyCode::Down => {
+                self.paste_history = Some((selected + 1).min(last));
+                None
+            }
+            KeyCode::Enter => Some(selected),
+            KeyCode::Char(c @ '1'..='9') => Some(c as usize - '1' as usize).filter(|&i| i <= last),
+            KeyCode::Esc => {
+                self.paste_history = None;
+                None
+            }
+            _ => None,
+        };
+        self.mark_redraw();
+        let Some(i) = pick else { return Ok(()); };
+        self.paste_history = None;
+        if !self.ensure_writable() { return Ok(()); }
+        let text = self.clip_ring[i].clone();
+        self.remember_clip(&text);
+        self.record_insert(&text);
+        self.replace_selection_or_insert(&text);
+        self.ensure_visible()
+    }
+
+    /// Render the history list, newest entry first.
+    pub fn render_paste_history(&mut self, stdout: &mut Stdout) -> Result<()> {
+        let Some(selected) = self.paste_history else { return Ok(()); };
+        let (w, h) = terminal::size()?;
+        let width = usize::from(w);
+
+        stdout.queue(cursor::Hide)?;
+        stdout.queue(style::ResetColor)?;
+        stdout.queue(terminal::Clear(ClearType::All))?;
+
+        stdout.queue(cursor::MoveTo(0, 0))?;
+        stdout.queue(style::SetBackgroundColor(self.theme.status_bg))?;
+        stdout.queue(style::SetForegroundColor(self.theme.status_fg))?;
+        stdout.queue(style::Print(format!("{:<width$}", format!(" CLIPBOARD HISTORY ({} entries)", self.clip_ring.len()))))?;
+        stdout.queue(style::ResetColor)?;
+
+        for (row, (i, text)) in (1..h.saturating_sub(1)).zip(self.clip_ring.iter().enumerate()) {
+            let key = if i < 9 { format!("{}", i + 1) } else { " ".to_string() };
+            let lines = text.lines().count();
+            let size = if lines > 1 { format!("  ({lines} lines)") } else { String::new() };
+            let line: String = format!(" {key}  {}{size}", preview(text)).chars().take(width).collect();
+            stdout.queue(cursor::MoveTo(0, row))?;
+            if i == selected {
+                stdout.queue(style::SetBackgroundColor(Color::White))?;
+                stdout.queue(style::SetForegroundColor(Color::Black))?;
+                stdout.queue(style::Print(format!("{line:<width$}")))?;
+                stdout.queue(style::ResetColor)?;
+            } else {
+                stdout.queue(style::Print(line))?;
+            }
+        }
+
+        stdout.queue(cursor::MoveTo(0, h.saturating_sub(1)))?;
+        stdout.queue(style::SetForegroundColor(Color::DarkGrey))?;
+        stdout.queue(style::Print(" Up/Down select   Enter or 1-9 paste   Esc close"))?;
+        stdout.queue(style::ResetColor)?;
+        stdout.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_newest_first_without_repeats() {
+        let mut ring = Vec::new();
+        for text in ["a", "b", "", "a"] {
+            remember(&mut ring, text);
+        }
+        assert_eq!(ring, ["a", "b"]);
+        for i in 0..RING_SIZE {
+            remember(&mut ring, &i.to_string());
+        }
+        assert_eq!(ring.len(), RING_SIZE);
+        assert_eq!(ring[0], (RING_SIZE - 1).to_string());
+        assert_eq!(preview("x\ty\nz"), "x y⏎z");
+    }
+}
@@ -0,0 +1,196 @@
+//! Select by scope: `expand_selection` grows the selection to the smallest enclosing unit —
+//! word, quoted string or bracket contents, the same with their delimiters, line, paragraph,
+//! whole buffer — and `shrink_selection` steps back through the selections it grew from.
+
+use super::Editor; // editor state
+use crate::types::{Pos, SelectionKind}; // selection ends, stream selection
+use anyhow::Result; // anyhow error handling
+use ropey::Rope; // bracket scanning
+use std::time::Duration; // timing for status messages
+
+/// Quote characters that delimit strings.
+const QUOTES: [char; 3] = ['"', '\'', '`'];
+
+/// The opening bracket of a closing one.
+fn opener_of(c: char) -> Option<char> {
+    match c {
+        ')' => Some('('),
+        ']' => Some('['),
+        '}' => Some('{'),
+        _ => None,
+    }
+}
+
+/// The closing bracket of an opening one.
+fn closer_of(c: char) -> Option<char> {
+    match c {
+        '(' => Some(')'),
+        '[' => Some(']'),
+        '{' => Some('}'),
+        _ => None,
+    }
+}
+
+/// Char indices of the innermost bracket pair around `start..end`, the opener before `start`
+/// and its closer at or after `end`. Brackets inside strings are not told apart.
+fn enclosing_brackets(text: &Rope, start: usize, end: usize) -> Option<(usize, usize)> {
+    let mut closers = Vec::new();
+    let mut chars = text.chars_at(start);
+    let mut open = start;
+    while let Some(c) = chars.prev() {
+        open -= 1;
+        if opener_of(c).is_some() {
+            closers.push(c);
+            continue;
+        }
+        let Some(close) = closer_of(c) else { continue; };
+        if let Some(pending) = closers.pop() {
+            // Mismatched pairs are skipped over like matched ones.
+            if pending != close { closers.clear(); }
+            continue;
+        }
+        let mut depth = 0usize;
+        for (i, d) in text.chars_at(end).enumerate() {
+            if d == c {
+                depth += 1;
+            } else if d == close {
+                if depth == 0 { return Some((open, end + i)); }
+                depth -= 1;
+            }
+        }
+        // Unclosed: keep looking further out.
+    }
+    None
+}
+
+/// Columns of the quotes of the innermost string around `start..end` in `line`, pairing quotes
+/// left to right and skipping backslash-escaped ones.
+fn enclosing_quotes(line: &[char], start: usize, end: usize) -> Option<(usize, usize)> {
+    let mut open: Option<(usize, char)> = None;
+    let mut found = None;
+    let mut i = 0;
+    while i < line.len() {
+        let c = line[i];
+        match open {
+            Some(_) if c == '\\' => i += 1,
+            Some((at, q)) if c == q => {
+                if at < start && end <= i { found = Some((at, i)); }
+                open = None;
+            }
+            None if QUOTES.contains(&c) => open = Some((i, c)),
+            _ => {}
+        }
+        i += 1;
+    }
+    found
+}
+
+/// Selections `expand_selection` made, to go back to with `shrink_selection`.
+#[derive(Default)]
+pub(crate) struct ScopeHistory {
+    /// Anchor and cursor before each expansion, oldest first.
+    previous: Vec<(Option<Pos>, Pos)>,
+    /// Anchor and cursor the last expansion left; any other selection starts over.
+    grown: Option<(Pos, Pos)>,
+}
+
+impl Editor {
+    /// Ranges (char indices) that could be selected around the cursor or selection.
+    fn scope_candidates(&self, start: Pos, end: Pos) -> Vec<(usize, usize)> {
+        let idx = |p: Pos| self.doc.buf.pos_to_char_idx_public(p);
+        let mut ranges = Vec::new();
+        if start.y == end.y {
+            let (a, b) = self.word_range_at(start);
+            ranges.push((idx(Pos { y: start.y, x: a }), idx(Pos { y: start.y, x: b })));
+            let line: Vec<char> = self.doc.buf.line(start.y).chars().collect();
+            if let Some((a, b)) = enclosing_quotes(&line, start.x, end.x) {
+                ranges.push((idx(Pos { y: start.y, x: a + 1 }), idx(Pos { y: start.y, x: b })));
+                ranges.push((idx(Pos { y: start.y, x: a }), idx(Pos { y: start.y, x: b + 1 })));
+            }
+        }
+        if let Some((a, b)) = enclosing_brackets(&self.doc.buf.text, idx(start), idx(end)) {
+            ranges.push((a + 1, b));
+            ranges.push((a, b + 1));
+        }
+        ranges.push((idx(Pos { y: start.y, x: 0 }), idx(Pos { y: end.y, x: self.doc.buf.line_len_chars(end.y) })));
+        // The paragraph: non-blank lines around the selected ones.
+        let blank = |y: usize| self.doc.buf.line(y).trim().is_empty();
+        let top = (0..start.y).rev().take_while(|&y| !blank(y)).last().unwrap_or(start.y);
+        let bottom = (end.y + 1..self.doc.buf.line_count()).take_while(|&y| !blank(y)).last().unwrap_or(end.y);
+        ranges.push((idx(Pos { y: top, x: 0 }), idx(Pos { y: bottom, x: self.doc.buf.line_len_chars(bottom) })));
+        ranges.push((0, self.doc.buf.text.len_chars()));
+        ranges
+    }
+
+    /// Select the smallest scope around the selection (or cursor) that is larger than it
+    /// (`expand_selection`).
+    pub fn expand_selection(&mut self) -> Result<()> {
+        let (start, end) = self.selection_range().unwrap_or((self.doc.cursor, self.doc.cursor));
+        let (from, to) = (self.doc.buf.pos_to_char_idx_public(start), self.doc.buf.pos_to_char_idx_public(end));
+        let best = self.scope_candidates(start, end).into_iter()
+            .filter(|&(a, b)| a <= from && to <= b && b - a > to - from)
+            .min_by_key(|&(a, b)| b - a);
+        let Some((a, b)) = best else {
+            self.set_status("The whole buffer is selected.", Duration::from_secs(2));
+            return Ok(());
+        };
+        if self.scope.grown != self.doc.anchor.map(|anchor| (anchor, self.doc.cursor)) {
+            self.scope.previous.clear();
+        }
+        self.scope.previous.push((self.doc.anchor, self.doc.cursor));
+        let (anchor, cursor) = (self.doc.buf.char_idx_to_pos_public(a), self.doc.buf.char_idx_to_pos_public(b));
+        self.doc.anchor = Some(anchor);
+        self.doc.cursor = cursor;
+        self.selection_kind = SelectionKind::Stream;
+        self.scope.grown = Some((anchor, cursor));
+        self.mark_redraw();
+        self.ensure_visible()
+    }
+
+    /// Go back to the selection before the last `expand_selection` (`shrink_selection`).
+    pub fn shrink_selection(&mut self) -> Result<()> {
+        let current = self.doc.anchor.map(|anchor| (anchor, self.doc.cursor));
+        let previous = if current.is_some() && self.scope.grown == current { self.scope.previous.pop() } else { None };
+        let Some((anchor, cursor)) = previous else {
+            self.scope = ScopeHistory::default();
+            self.set_status("Nothing to shrink; expand_selection first.", Duration::from_secs(2));
+            return Ok(());
+        };
+        self.doc.anchor = anchor;
+        self.doc.cursor = cursor;
+        self.selection_kind = SelectionKind::Stream;
+        self.scope.grown = anchor.map(|anchor| (anchor, cursor));
+        self.mark_redraw();
+        self.ensure_visible()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chars(s: &str) -> Vec<char> {
+        s.chars().collect()
+    }
+
+    #[test]
+    fn finds_innermost_enclosing_brackets() {
+        let text = Rope::from_str("f(a, [b, c], {\n  d\n})");
+        // Inside "b": the square brackets.
+        assert_eq!(enclosing_brackets(&text, 6, 7), Some((5, 10)));
+        // Their contents with the brackets: the parentheses.
+        assert_eq!(enclosing_brackets(&text, 5, 11), Some((1, 20)));
+        // Across lines, skipping the closed square pair on the way back.
+        assert_eq!(enclosing_brackets(&text, 17, 17), Some((13, 19)));
+        assert_eq!(enclosing_brackets(&text, 0, 1), None);
+    }
+
+    #[test]
+    fn finds_enclosing_quotes() {
+        let line = chars(r#"say("a \"b\" c", 'd')"#);
+        assert_eq!(enclosing_quotes(&line, 8, 9), Some((4, 14)));
+        assert_eq!(enclosing_quotes(&line, 18, 18), Some((17, 19)));
+        // Between the strings.
+        assert_eq!(enclosing_quotes(&line, 15, 15), None);
+    }
+}
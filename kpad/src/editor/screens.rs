@@ -1,5 +1,7 @@
-//! Full-screen overlays: help screen, statistics screen.
+//! Full-screen overlays: help screen, statistics screen, plugins screen, key bindings, buffer
+//! list.
 
+use crate::plugins::PLUGIN_API_VERSION; // api version shown in the plugins overlay
 use crate::types::DocumentStats; // document statistics type
 use super::Editor; // main editor state
 use anyhow::Result; // anyhow error handling
@@ -12,6 +14,11 @@ use crossterm::{ // terminal manipulation
 use std::io::{Stdout, Write}; // output handling
 
 impl Editor {
+    /// Whether a full-screen overlay that any key closes is shown.
+    pub(crate) fn overlay_shown(&self) -> bool {
+        self.show_help || self.show_stats || self.show_plugins || self.show_keys || self.file_info.is_some()
+    }
+
     /// Calculate document statistics.
     pub fn calculate_stats(&self) -> DocumentStats {
         let mut word_count = 0;
@@ -19,8 +26,8 @@ impl Editor {
         let mut byte_count = 0;
         let mut histogram = vec![0; 10];
 
-        for y in 0..self.buf.line_count() {
-            let line = self.buf.line(y);
+        for y in 0..self.doc.buf.line_count() {
+            let line = self.doc.buf.line(y);
             char_count += line.chars().count();
             byte_count += line.len();
             word_count += line.split_whitespace().count();
@@ -28,17 +35,17 @@ impl Editor {
             histogram[bucket] += 1;
         }
 
-        let le_len = self.buf.line_ending.as_str().len();
-        if self.buf.line_count() > 1 {
-            byte_count += (self.buf.line_count() - 1) * le_len;
+        let le_len = self.doc.buf.line_ending.as_str().len();
+        if self.doc.buf.line_count() > 1 {
+            byte_count += (self.doc.buf.line_count() - 1) * le_len;
         }
 
         DocumentStats {
-            line_count: self.buf.line_count(),
+            line_count: self.doc.buf.line_count(),
             word_count,
             char_count,
             byte_count,
-            line_ending: self.buf.line_ending,
+            line_ending: self.doc.buf.line_ending,
             encoding: "UTF-8 (Unicode)",
             line_length_histogram: histogram,
         }
@@ -66,6 +73,7 @@ impl Editor {
             format!("  File Size:  {} bytes", stats.byte_count),
             format!("  End of Line: {} ({})", stats.line_ending.name(), stats.line_ending.as_str().escape_debug()),
             format!("  Encoding:   {}", stats.encoding),
+            format!("  Undo:       {} steps, {} redo, {} bytes", self.doc.undo.len(), self.doc.redo.len(), self.undo_memory()),
             "".to_string(),
             " LINE LENGTH DISTRIBUTION: ".to_string(),
         ];
@@ -75,7 +83,7 @@ impl Editor {
         for (i, &count) in stats.line_length_histogram.iter().enumerate() {
             let label = if i == 9 { "90+ ".to_string() } else { format!("{:>2}-{} ", i * 10, (i + 1) * 10 - 1) };
             let bar_len = (count * chart_width) / max_val;
-            let bar = "█".repeat(bar_len);
+            let bar = self.caps.glyph("█", "#").repeat(bar_len);
             lines.push(format!("  {} {:<30} ({})", label, bar, count));
         }
 
@@ -93,6 +101,156 @@ impl Editor {
         Ok(())
     }
 
+    /// Render the plugins screen: loaded plugins with versions, warnings and load errors.
+    pub fn render_plugins(&mut self, stdout: &mut Stdout) -> Result<()> {
+        let (w, h) = terminal::size()?;
+
+        stdout.queue(cursor::Hide)?;
+        stdout.queue(style::SetBackgroundColor(Color::DarkCyan))?;
+        stdout.queue(style::SetForegroundColor(Color::White))?;
+        stdout.queue(terminal::Clear(ClearType::All))?;
+
+        let mut lines = vec![
+            " PLUGINS ".to_string(),
+            "=========".to_string(),
+            format!(" kpad plugin API: v{PLUGIN_API_VERSION}"),
+            String::new(),
+        ];
+        for info in self.plugins.infos() {
+            let version = info.version.as_deref().unwrap_or("-");
+            let api = info.api_version.map_or_else(|| "-".to_string(), |v| format!("v{v}"));
+            let exec = if info.exec { "  [exec]" } else { "" };
+            lines.push(format!("  {:<20} {:<24} {:<10} API {}{exec}", info.id, info.name, version, api));
+            if let Some(warning) = &info.warning {
+                lines.push(format!("      ! {warning}"));
+            }
+        }
+        if self.plugins.len() == 0 {
+            lines.push("  (no plugins loaded)".to_string());
+        }
+        if !self.plugins.errors().is_empty() {
+            lines.push(String::new());
+            lines.push(" FAILED TO LOAD:".to_string());
+            for err in self.plugins.errors() {
+                lines.push(format!("  {err}"));
+            }
+        }
+        lines.push(String::new());
+        lines.push(" Press any key to close... ".to_string());
+
+        // Left-aligned block, centered as a whole; long lines are cut at the screen edge.
+        let longest = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+        let x = w.saturating_sub(u16::try_from(longest).unwrap_or(w)) / 2;
+        let start_y = h.saturating_sub(u16::try_from(lines.len()).unwrap_or(h)) / 2;
+        for (y, line) in (start_y..h).zip(&lines) {
+            let shown: String = line.chars().take(usize::from(w - x)).collect();
+            stdout.queue(cursor::MoveTo(x, y))?;
+            stdout.queue(style::Print(shown))?;
+        }
+
+        stdout.flush()?;
+        Ok(())
+    }
+
+    /// Render the effective key bindings (`keys` command), in as many columns as the height needs.
+    pub fn render_keys(&mut self, stdout: &mut Stdout) -> Result<()> {
+        let (w, h) = terminal::size()?;
+
+        stdout.queue(cursor::Hide)?;
+        stdout.queue(style::SetBackgroundColor(Color::DarkCyan))?;
+        stdout.queue(style::SetForegroundColor(Color::White))?;
+        stdout.queue(terminal::Clear(ClearType::All))?;
+
+        let bindings = self.commands.bindings();
+        let key_w = bindings.iter().map(|(key, _, _)| key.chars().count()).max().unwrap_or(0);
+        let entries: Vec<String> = bindings.into_iter()
+            .map(|(key, cmd, user)| format!("{key:<key_w$}  {cmd}{}", if user { " *" } else { "" }))
+            .collect();
+        let header = [" KEY BINDINGS ", "==============", " * = from kpad.toml", ""];
+        let footer = " Press any key to close... ";
+
+        let rows = usize::from(h).saturating_sub(header.len() + 2).max(1);
+        let col_w = entries.iter().map(|e| e.chars().count()).max().unwrap_or(0) + 3;
+        for (y, line) in (0..h).zip(header) {
+            stdout.queue(cursor::MoveTo(1, y))?;
+            stdout.queue(style::Print(line))?;
+        }
+        for (i, entry) in entries.iter().enumerate() {
+            let x = 2 + (i / rows) * col_w;
+            let (Ok(col), Ok(row)) = (u16::try_from(x), u16::try_from(header.len() + i % rows)) else { break; };
+            if col >= w { break; }
+            let shown: String = entry.chars().take(usize::from(w - col)).collect();
+            stdout.queue(cursor::MoveTo(col, row))?;
+            stdout.queue(style::Print(shown))?;
+        }
+        let footer_y = u16::try_from(header.len() + rows.min(entries.len()) + 1).unwrap_or(h).min(h.saturating_sub(1));
+        stdout.queue(cursor::MoveTo(1, footer_y))?;
+        stdout.queue(style::Print(footer))?;
+
+        stdout.flush()?;
+        Ok(())
+    }
+
+    /// Render the buffer list (`buffers` command).
+    pub fn render_buffers(&mut self, stdout: &mut Stdout) -> Result<()> {
+        let Some(list) = &self.buffer_list else { return Ok(()); };
+        let (w, h) = terminal::size()?;
+        let width = usize::from(w);
+
+        stdout.queue(cursor::Hide)?;
+        stdout.queue(style::SetBackgroundColor(Color::DarkCyan))?;
+        stdout.queue(style::SetForegroundColor(Color::White))?;
+        stdout.queue(terminal::Clear(ClearType::All))?;
+
+        let hits = self.buffer_matches(&list.filter);
+        let name_w = hits.iter().map(|&i| self.buffer_name(i).chars().count()).max().unwrap_or(0).clamp(12, 32);
+        let mut lines = vec![
+            format!(" BUFFERS ({} open)", self.buffers.len()),
+            format!(" Filter: {}", list.filter),
+            String::new(),
+        ];
+        let first = lines.len();
+        for &i in &hits {
+            let marker = if i == self.active { '>' } else { ' ' };
+            let dirty = if self.buffer_dirty(i) { '*' } else { ' ' };
+            let name: String = self.buffer_name(i).chars().take(name_w).collect();
+            let path = self.buffer_path(i).unwrap_or_default();
+            lines.push(format!(" {marker}{:>3} {dirty} {name:<name_w$} {:>7} lines  {path}", i + 1, self.buffer_lines(i)));
+        }
+        if hits.is_empty() {
+            lines.push("   (no matching buffers)".to_string());
+        }
+        lines.push(String::new());
+        match list.confirm_close {
+            Some(i) => lines.push(format!(" {} has unsaved changes. Press d again to close it anyway.", self.buffer_name(i))),
+            None => lines.push(" Enter switch   d / Del close   type to filter   Esc back".to_string()),
+        }
+
+        // Rows past the screen are cut; the selection stays visible by scrolling the list.
+        let list_h = usize::from(h).saturating_sub(first + 2).max(1);
+        let skip = (list.selected + 1).saturating_sub(list_h).min(hits.len().saturating_sub(1));
+        let shown = lines[..first].iter().enumerate()
+            .chain(lines[first..].iter().enumerate().skip(skip).map(|(i, l)| (first + i, l)));
+        for (row, (i, line)) in (0..h).zip(shown) {
+            let selected = !hits.is_empty() && i == first + list.selected;
+            let mut text: String = line.chars().take(width).collect();
+            text.push_str(&" ".repeat(width.saturating_sub(text.chars().count())));
+            stdout.queue(cursor::MoveTo(0, row))?;
+            if selected {
+                stdout.queue(style::SetBackgroundColor(Color::White))?;
+                stdout.queue(style::SetForegroundColor(Color::Black))?;
+            }
+            stdout.queue(style::Print(text))?;
+            if selected {
+                stdout.queue(style::SetBackgroundColor(Color::DarkCyan))?;
+                stdout.queue(style::SetForegroundColor(Color::White))?;
+            }
+        }
+
+        stdout.flush()?;
+        Ok(())
+    }
+
     /// Render the help screen.
     pub fn render_help(&mut self, stdout: &mut Stdout) -> Result<()> {
         let (w, h) = terminal::size()?;
@@ -112,25 +270,41 @@ impl Editor {
             "  Arrows          Move cursor by 1 character / 1 line",
             "  Ctrl + Left     Jump to previous word or punctuation",
             "  Ctrl + Right    Jump to next word or punctuation",
-            "  Home / End      Jump to top / bottom of document",
+            "  Home / End      Line start (first non-blank, again: column 0) / end",
+            "  Ctrl + Home/End Jump to top / bottom of document",
             "  PageUp / Down   Move up / down one full screen",
+            "  Alt + Up / Down Previous / next paragraph",
+            "  Alt+Left/Right  Back / forward through jumps (goto, find, open)",
             "",
             " SELECTION:",
             "  Shift + Arrows  Select text while moving",
             "  Ctrl + A        Select All",
+            "  Alt + = / -     Expand / shrink selection (word, string, brackets, line, ...)",
+            "  Click / Drag    Move cursor / select (Shift + Click extends)",
+            "  Double / Triple Click  Select word / line",
             "",
             " EDITING:",
             "  Ctrl + S        Save file",
-            "  Ctrl + O        Open file",
+            "  Ctrl + O        Open file (in a new buffer)",
+            "  Ctrl + Tab      Next buffer (Ctrl+Shift+Tab: previous)",
+            "  Ctrl + ^        Previously active buffer",
             "  Ctrl + Z        Undo",
             "  Ctrl + Y        Redo",
             "  Ctrl + C / X    Copy / Cut selection",
             "  Ctrl + V        Paste",
-            "  Tab             Insert 4 spaces",
+            "  Tab             Insert spaces (tab_width); indent selected lines",
+            "  Shift + Tab     Dedent the selected lines or the cursor line",
+            "  Ctrl + N        Complete word (open buffers, dictionary)",
+            "",
+            " SEARCH:",
+            "  Ctrl + F        Find (Enter to find next)",
+            "  F3 / Shift + F3 Next / previous match",
             "",
             " SYSTEM:",
             "  Ctrl + P        Command Palette (Discovery)",
+            "  Up / Down       Prompt history (in a prompt)",
             "  Alt + W         Toggle Word Wrap",
+            "  Alt + V / O     Split view / Focus other pane",
             "  Ctrl + Q        Quit (asks if unsaved)",
             "  F1 / :help      Toggle this Help screen",
             "",
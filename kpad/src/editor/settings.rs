@@ -0,0 +1,97 @@
+//! Changing settings for the session from the command palette: `set NAME VALUE` for the
+//! settings in `SETTINGS` (as in kpad.toml, with `on`/`off` for switches) and `theme NAME`.
+//! kpad.toml is left alone.
+
+use super::builtin_commands::SETTINGS; // settings `set` knows
+use super::modal::Modal; // keymap = vim
+use super::Editor; // editor state
+use crate::config::{Keymap, Theme, MAX_TAB_WIDTH}; // setting values
+use anyhow::{anyhow, bail, Result}; // anyhow error handling
+use serde::de::{value, Deserialize, IntoDeserializer}; // values named as in kpad.toml
+use std::time::Duration; // timing for status messages
+
+/// `on` or `off`.
+fn parse_switch(name: &str, value: &str) -> Result<bool> {
+    match value {
+        "on" => Ok(true),
+        "off" => Ok(false),
+        _ => bail!("{name} is on or off"),
+    }
+}
+
+/// A setting value spelled as in kpad.toml (`bell = "visual"`).
+fn parse_value<'a, T: Deserialize<'a>>(name: &str, value: &'a str) -> Result<T> {
+    T::deserialize(IntoDeserializer::<value::Error>::into_deserializer(value))
+        .map_err(|_| anyhow!("Unknown {name} '{value}'"))
+}
+
+impl Editor {
+    /// `set NAME VALUE`.
+    pub(crate) fn set_option(&mut self, name: &str, value: &str) -> Result<()> {
+        match name {
+            "wrap" => {
+                if parse_switch(name, value)? != self.word_wrap {
+                    // Reports the change, or why wrap stays off.
+                    self.toggle_word_wrap();
+                    return Ok(());
+                }
+            }
+            "wrap_indent" => self.wrap_indent = parse_value(name, value)?,
+            "tab_width" => {
+                self.tab_width = value.parse().ok()
+                    .filter(|n| (1..=MAX_TAB_WIDTH).contains(n))
+                    .ok_or_else(|| anyhow!("tab_width must be between 1 and {MAX_TAB_WIDTH}"))?;
+            }
+            "ruler" => self.ruler = value.parse().map_err(|_| anyhow!("ruler is a column number (0: off)"))?,
+            "scrolloff" => {
+                self.scrolloff = value.parse().map_err(|_| anyhow!("scrolloff is a number of lines"))?;
+                self.ensure_visible()?;
+            }
+            "auto_close" => {
+                if parse_switch(name, value)? != self.auto_close.enabled {
+                    self.toggle_auto_close();
+                }
+            }
+            "bell" => self.bell = parse_value(name, value)?,
+            "middle_click" => self.middle_click = parse_value(name, value)?,
+            "keymap" => {
+                let vim = parse_value::<Keymap>(name, value)? == Keymap::Vim;
+                if vim != self.modal.is_some() {
+                    self.modal = vim.then(Modal::new);
+                    self.clear_selection();
+                }
+            }
+            _ => {
+                let names: Vec<&str> = SETTINGS.iter().map(|(n, _)| *n).collect();
+                bail!("Unknown setting '{name}' (available: {})", names.join(", "));
+            }
+        }
+        self.set_status(format!("{name} = {value}"), Duration::from_secs(2));
+        self.mark_redraw();
+        Ok(())
+    }
+
+    /// `theme NAME`.
+    pub(crate) fn set_theme(&mut self, name: &str) -> Result<()> {
+        let theme = Theme::named(name)
+            .ok_or_else(|| anyhow!("Unknown theme '{name}' (available: {})", Theme::NAMES.join(", ")))?;
+        self.theme = theme.map(|c| self.caps.color(c));
+        self.set_status(format!("Theme: {name}"), Duration::from_secs(2));
+        self.mark_redraw();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Bell;
+
+    #[test]
+    fn parses_values_as_in_the_config_file() {
+        assert_eq!(parse_value::<Bell>("bell", "visual").unwrap(), Bell::Visual);
+        assert!(parse_value::<Bell>("bell", "loud").is_err());
+        assert!(parse_switch("wrap", "on").unwrap());
+        assert!(parse_switch("wrap", "yes").is_err());
+    }
+}
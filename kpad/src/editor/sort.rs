@@ -0,0 +1,122 @@
+//! Reordering lines: `sort_lines`, `sort_lines_desc`, `unique_lines` (drop lines seen before)
+//! and `reverse_lines` work on the lines of the selection, or the whole buffer, as one undo step.
+//! With `sort = "natural"` (the default) runs of digits compare by value, so `file9` sorts
+//! before `file10`.
+
+use super::Editor; // editor state
+use crate::config::Sort; // how lines compare
+use crate::types::Pos; // positions
+use anyhow::Result; // anyhow error handling
+use std::cmp::Ordering; // line comparison
+use std::collections::HashSet; // lines seen
+use std::time::Duration; // timing for status messages
+
+/// What to do with the lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LineOrder {
+    /// Sorted (`sort_lines`).
+    Ascending,
+    /// Sorted, largest first (`sort_lines_desc`).
+    Descending,
+    /// Without lines seen before (`unique_lines`).
+    Unique,
+    /// Last line first (`reverse_lines`).
+    Reverse,
+}
+
+/// Compare lines, with runs of digits by value when `numeric`.
+fn compare_lines(a: &str, b: &str, numeric: bool) -> Ordering {
+    if !numeric { return a.cmp(b); }
+    let (mut x, mut y) = (a, b);
+    while let (Some(cx), Some(cy)) = (x.chars().next(), y.chars().next()) {
+        let (ord, rest_x, rest_y) = if cx.is_ascii_digit() && cy.is_ascii_digit() {
+            let nx = x.find(|c: char| !c.is_ascii_digit()).unwrap_or(x.len());
+            let ny = y.find(|c: char| !c.is_ascii_digit()).unwrap_or(y.len());
+            let (dx, dy) = (x[..nx].trim_start_matches('0'), y[..ny].trim_start_matches('0'));
+            (dx.len().cmp(&dy.len()).then_with(|| dx.cmp(dy)), &x[nx..], &y[ny..])
+        } else {
+            (cx.cmp(&cy), &x[cx.len_utf8()..], &y[cy.len_utf8()..])
+        };
+        if ord != Ordering::Equal { return ord; }
+        (x, y) = (rest_x, rest_y);
+    }
+    // Equal up to the shorter one, or equal by value ("01" and "1"): decide plainly.
+    x.len().cmp(&y.len()).then_with(|| a.cmp(b))
+}
+
+/// `lines` reordered by `order`.
+fn reorder(mut lines: Vec<String>, order: LineOrder, numeric: bool) -> Vec<String> {
+    match order {
+        LineOrder::Ascending => lines.sort_by(|a, b| compare_lines(a, b, numeric)),
+        LineOrder::Descending => lines.sort_by(|a, b| compare_lines(b, a, numeric)),
+        LineOrder::Unique => {
+            let mut seen = HashSet::new();
+            lines.retain(|l| seen.insert(l.clone()));
+        }
+        LineOrder::Reverse => lines.reverse(),
+    }
+    lines
+}
+
+impl Editor {
+    /// Reorder the selected lines (a selection ending at column 0 leaves that line out) or all
+    /// lines but a trailing empty one; the selection then covers the result.
+    pub(crate) fn reorder_lines(&mut self, order: LineOrder) -> Result<()> {
+        if !self.ensure_writable() { return Ok(()); }
+        let selection = self.selection_range();
+        let (first, last) = match selection {
+            Some((a, b)) if b.x == 0 && b.y > a.y => (a.y, b.y - 1),
+            Some((a, b)) => (a.y, b.y),
+            None => {
+                let last = self.doc.buf.line_count() - 1;
+                (0, last - usize::from(last > 0 && self.doc.buf.line_len_chars(last) == 0))
+            }
+        };
+        let old: Vec<String> = (first..=last).map(|y| self.doc.buf.line(y).into_owned()).collect();
+        let count = old.len();
+        let new = reorder(old.clone(), order, self.sort == Sort::Natural);
+        let removed = count - new.len();
+        if new != old {
+            self.replace_lines(first, last, &new.join("\n"));
+        }
+        let end = first + new.len() - 1;
+        if selection.is_some() {
+            self.doc.anchor = Some(Pos { y: first, x: 0 });
+            self.doc.cursor = Pos { y: end, x: self.doc.buf.line_len_chars(end) };
+        } else {
+            self.doc.cursor = self.doc.buf.clamp_pos(self.doc.cursor);
+        }
+        let msg = match order {
+            LineOrder::Unique if removed == 0 => "No repeated lines.".to_string(),
+            LineOrder::Unique => format!("Removed {removed} repeated line{}.", if removed == 1 { "" } else { "s" }),
+            LineOrder::Reverse => format!("Reversed {count} lines."),
+            _ => format!("Sorted {count} lines."),
+        };
+        self.set_status(msg, Duration::from_secs(2));
+        self.ensure_visible()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.split('\n').map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn sorts_numbers_by_value() {
+        let l = lines("file10\nfile9\nFile1\nfile09\nb");
+        assert_eq!(reorder(l.clone(), LineOrder::Ascending, true), lines("File1\nb\nfile09\nfile9\nfile10"));
+        assert_eq!(reorder(l.clone(), LineOrder::Ascending, false), lines("File1\nb\nfile09\nfile10\nfile9"));
+        assert_eq!(reorder(l, LineOrder::Descending, true), lines("file10\nfile9\nfile09\nb\nFile1"));
+        assert_eq!(compare_lines("a2b", "a2", true), Ordering::Greater);
+    }
+
+    #[test]
+    fn keeps_first_of_repeated_lines_and_reverses() {
+        assert_eq!(reorder(lines("b\na\nb\n\na\n"), LineOrder::Unique, true), lines("b\na\n"));
+        assert_eq!(reorder(lines("1\n2\n3"), LineOrder::Reverse, true), lines("3\n2\n1"));
+    }
+}
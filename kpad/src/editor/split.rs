@@ -0,0 +1,141 @@
+//! Split view: two side-by-side panes showing the same buffer, each with its own cursor,
+//! selection and scroll position. Edits go to the shared buffer, so both panes see them.
+//!
+//! The focused pane lives in `Editor::doc` (`cursor`, `scroll_y`, ...) like an unsplit view;
+//! the other one waits in `doc.split`. Drawing the other pane swaps it in for the
+//! duration of the call.
+
+use super::Editor; // editor state
+use crate::types::{Pos, SelectionKind}; // cursor positions, stream selection
+use anyhow::Result; // anyhow error handling
+use std::mem; // swapping pane state
+use std::time::Duration; // timing for status messages
+
+/// View state of the pane that does not have focus.
+pub(crate) struct Pane {
+    cursor: Pos,
+    anchor: Option<Pos>,
+    scroll_y: usize,
+    scroll_x: usize,
+    /// Buffer line count when the pane was last drawn, to follow lines added or removed above it.
+    lines: usize,
+    /// Focused pane's `scroll_y` when the panes were last synchronized (`scrollbind`).
+    bound_scroll: usize,
+}
+
+impl Editor {
+    /// Left column and width of the focused pane on a terminal `width` columns wide.
+    pub(crate) fn pane_columns(&self, width: usize) -> (usize, usize) {
+        if self.doc.split.is_none() {
+            return (0, width);
+        }
+        let left_w = width / 2;
+        if self.doc.split_focus_right { (left_w, width - left_w) } else { (0, left_w) }
+    }
+
+    /// Split the view into two panes on the same buffer, or go back to a single pane (keeping
+    /// the focused one).
+    pub fn toggle_split(&mut self) -> Result<()> {
+        self.finish_scroll_animation();
+        if self.doc.split.take().is_some() {
+            self.doc.split_focus_right = false;
+            self.set_status("Split closed.", Duration::from_secs(2));
+        } else {
+            self.doc.split = Some(Pane {
+                cursor: self.doc.cursor,
+                anchor: None,
+                scroll_y: self.doc.scroll_y,
+                scroll_x: self.doc.scroll_x,
+                lines: self.doc.buf.line_count(),
+                bound_scroll: self.doc.scroll_y,
+            });
+            self.doc.split_focus_right = true;
+            self.set_status("Split view: Alt+O switches panes.", Duration::from_secs(2));
+        }
+        // Pane widths changed, so horizontal scroll may need to follow the cursor.
+        self.ensure_visible()?;
+        self.mark_redraw();
+        Ok(())
+    }
+
+    /// Move focus to the other pane.
+    pub fn focus_other_pane(&mut self) {
+        if self.doc.split.is_none() {
+            self.set_status("No split view.", Duration::from_secs(2));
+            return;
+        }
+        self.finish_scroll_animation();
+        self.sync_scrollbind();
+        self.swap_pane();
+        self.clamp_pane();
+        let scroll_y = self.doc.scroll_y;
+        if let Some(pane) = self.doc.split.as_mut() { pane.bound_scroll = scroll_y; }
+        self.mark_redraw();
+    }
+
+    /// Toggle scroll binding: the panes of a split view scroll together.
+    pub fn toggle_scrollbind(&mut self) {
+        self.scrollbind = !self.scrollbind;
+        // Start from the current positions rather than catching up on earlier scrolling.
+        let scroll_y = self.doc.scroll_y;
+        if let Some(pane) = self.doc.split.as_mut() { pane.bound_scroll = scroll_y; }
+        let msg = match (self.scrollbind, self.doc.split.is_some()) {
+            (true, true) => "Scroll binding on.",
+            (true, false) => "Scroll binding on (takes effect in a split view).",
+            (false, _) => "Scroll binding off.",
+        };
+        self.set_status(msg, Duration::from_secs(2));
+    }
+
+    /// With `scrollbind` on, scroll the unfocused pane (and its cursor) by as many rows as the
+    /// focused one has scrolled since the last call.
+    pub(crate) fn sync_scrollbind(&mut self) {
+        let (scroll_y, last_row) = (self.doc.scroll_y, self.doc.buf.line_count().saturating_sub(1));
+        let bind = self.scrollbind;
+        let Some(pane) = self.doc.split.as_mut() else { return; };
+        let old = pane.bound_scroll;
+        if bind && scroll_y != old {
+            pane.scroll_y = (pane.scroll_y + scroll_y).saturating_sub(old).min(last_row);
+            pane.cursor.y = (pane.cursor.y + scroll_y).saturating_sub(old).min(last_row);
+        }
+        pane.bound_scroll = scroll_y;
+    }
+
+    /// Run `f` with the unfocused pane swapped in as if it had focus.
+    pub(crate) fn with_other_pane<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        self.swap_pane();
+        self.clamp_pane();
+        let out = f(self);
+        self.swap_pane();
+        out
+    }
+
+    /// Exchange the focused view state with `split`.
+    fn swap_pane(&mut self) {
+        let Some(pane) = self.doc.split.as_mut() else { return; };
+        mem::swap(&mut self.doc.cursor, &mut pane.cursor);
+        mem::swap(&mut self.doc.anchor, &mut pane.anchor);
+        self.selection_kind = SelectionKind::Stream;
+        mem::swap(&mut self.doc.scroll_y, &mut pane.scroll_y);
+        mem::swap(&mut self.doc.scroll_x, &mut pane.scroll_x);
+        self.doc.split_focus_right = !self.doc.split_focus_right;
+    }
+
+    /// Fit the swapped-in pane to edits made from the other one: lines added or removed above
+    /// its cursor shift it along, and positions past the end are clamped.
+    fn clamp_pane(&mut self) {
+        let lines = self.doc.buf.line_count();
+        let Some(pane) = self.doc.split.as_mut() else { return; };
+        // `pane` now holds the view that made the edits; its cursor marks where they happened.
+        let edited_above = pane.cursor.y < self.doc.cursor.y;
+        if edited_above && lines != pane.lines {
+            let shift = |v: usize| (v + lines).saturating_sub(pane.lines);
+            self.doc.cursor.y = shift(self.doc.cursor.y);
+            self.doc.scroll_y = shift(self.doc.scroll_y);
+            self.doc.anchor = self.doc.anchor.map(|a| Pos { y: shift(a.y), x: a.x });
+        }
+        pane.lines = lines;
+        self.doc.cursor = self.doc.buf.clamp_pos(self.doc.cursor);
+        self.doc.anchor = self.doc.anchor.map(|a| self.doc.buf.clamp_pos(a));
+    }
+}
@@ -0,0 +1,133 @@
+//! Tab bar: with two or more buffers open, the top row lists them (`*` marks unsaved changes).
+//! Clicking a tab switches to it and Ctrl+PageUp/PageDown go to the previous/next one. When the
+//! tabs do not fit, the bar scrolls to keep the active tab visible and `<`/`>` mark hidden ones.
+//!
+//! `toggle_tab_bar` (or `tab_bar = false` in kpad.toml) hides it.
+
+use super::Editor; // editor state
+use anyhow::Result; // anyhow error handling
+use crossterm::{ // terminal manipulation
+    cursor,
+    style::{self, Color},
+    terminal::{self, ClearType},
+    QueueableCommand,
+};
+use std::io::Stdout; // output handling
+
+/// Longest buffer name shown in a tab; longer names are cut.
+const MAX_TAB_NAME_CHARS: usize = 24;
+
+/// Tabs shown on a bar `width` columns wide as (tab index, start column), scrolled so that tab
+/// `active` is visible. A column is kept for `<` or `>` where tabs are hidden.
+fn visible_tabs(widths: &[usize], active: usize, width: usize) -> Vec<(usize, usize)> {
+    let more_right = |i: usize| usize::from(i + 1 < widths.len());
+    let mut first = 0;
+    while first < active && usize::from(first > 0) + widths[first..=active].iter().sum::<usize>() + more_right(active) > width {
+        first += 1;
+    }
+    let mut col = usize::from(first > 0);
+    let mut tabs = Vec::new();
+    for (i, &w) in widths.iter().enumerate().skip(first) {
+        if i > active && col + w + more_right(i) > width { break; }
+        tabs.push((i, col));
+        col += w;
+    }
+    tabs
+}
+
+impl Editor {
+    /// Whether the tab bar takes the top row.
+    pub(crate) fn tab_bar_shown(&self) -> bool {
+        self.tab_bar && self.buffer_count() > 1
+    }
+
+    /// Tab labels (` name* `) and the visible tabs on a bar `width` columns wide.
+    fn tab_layout(&self, width: usize) -> (Vec<String>, Vec<(usize, usize)>) {
+        let labels: Vec<String> = (0..self.buffer_count()).map(|i| {
+            let name = self.buffer_name(i);
+            let name = if name.chars().count() > MAX_TAB_NAME_CHARS {
+                let cut: String = name.chars().take(MAX_TAB_NAME_CHARS - 1).collect();
+                format!("{cut}{}", self.caps.glyph("…", "~"))
+            } else {
+                name
+            };
+            format!(" {name}{} ", if self.buffer_dirty(i) { "*" } else { "" })
+        }).collect();
+        let widths: Vec<usize> = labels.iter().map(|l| l.chars().map(|c| self.char_width(c)).sum()).collect();
+        let tabs = visible_tabs(&widths, self.active, width);
+        (labels, tabs)
+    }
+
+    /// Switch to the tab at column `col` of the tab bar.
+    pub(crate) fn click_tab(&mut self, col: u16) -> Result<()> {
+        let (w, _) = self.screen_size()?;
+        let (labels, tabs) = self.tab_layout(usize::from(w));
+        let col = usize::from(col);
+        let hit = tabs.iter().find(|&&(i, start)| col >= start && col < start + labels[i].chars().count());
+        hit.map_or(Ok(()), |&(i, _)| self.switch_buffer(i))
+    }
+
+    /// Show or hide the tab bar.
+    pub fn toggle_tab_bar(&mut self) -> Result<()> {
+        self.tab_bar = !self.tab_bar;
+        self.ensure_visible()?;
+        self.mark_redraw();
+        Ok(())
+    }
+
+    /// Draw the tab bar on the top row.
+    pub(crate) fn render_tab_bar(&self, stdout: &mut Stdout, width: usize) -> Result<()> {
+        let (labels, tabs) = self.tab_layout(width);
+        let bar_bg = self.caps.color(Color::AnsiValue(236));
+        stdout.queue(cursor::MoveTo(0, 0))?;
+        stdout.queue(terminal::Clear(ClearType::CurrentLine))?;
+        stdout.queue(style::SetBackgroundColor(bar_bg))?;
+        stdout.queue(style::SetForegroundColor(Color::Grey))?;
+        stdout.queue(style::Print(" ".repeat(width)))?;
+        if tabs.first().is_some_and(|&(i, _)| i > 0) {
+            stdout.queue(cursor::MoveTo(0, 0))?;
+            stdout.queue(style::Print("<"))?;
+        }
+        let mut end = 0;
+        for &(i, start) in &tabs {
+            let label: String = labels[i].chars().take(width.saturating_sub(start)).collect();
+            let (Ok(col), true) = (u16::try_from(start), i == self.active) else {
+                stdout.queue(cursor::MoveTo(u16::try_from(start).unwrap_or(u16::MAX), 0))?;
+                stdout.queue(style::Print(label))?;
+                end = start + labels[i].chars().count();
+                continue;
+            };
+            stdout.queue(cursor::MoveTo(col, 0))?;
+            stdout.queue(style::SetBackgroundColor(self.theme.status_bg))?;
+            stdout.queue(style::SetForegroundColor(self.theme.status_fg))?;
+            stdout.queue(style::Print(label))?;
+            stdout.queue(style::SetBackgroundColor(bar_bg))?;
+            stdout.queue(style::SetForegroundColor(Color::Grey))?;
+            end = start + labels[i].chars().count();
+        }
+        if tabs.last().is_some_and(|&(i, _)| i + 1 < labels.len()) && end < width {
+            stdout.queue(cursor::MoveTo(u16::try_from(width - 1).unwrap_or(u16::MAX), 0))?;
+            stdout.queue(style::Print(">"))?;
+        }
+        stdout.queue(style::ResetColor)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scrolls_to_the_active_tab() {
+        let widths = [10, 10, 10, 10, 10];
+        assert_eq!(visible_tabs(&widths, 0, 80), [(0, 0), (1, 10), (2, 20), (3, 30), (4, 40)]);
+        // Room for two tabs and the `>` column.
+        assert_eq!(visible_tabs(&widths, 0, 25), [(0, 0), (1, 10)]);
+        // The active tab pulls the bar right; `<` takes column 0.
+        assert_eq!(visible_tabs(&widths, 3, 25), [(2, 1), (3, 11)]);
+        assert_eq!(visible_tabs(&widths, 4, 25), [(3, 1), (4, 11)]);
+        // A tab wider than the bar is still shown (cut when drawn).
+        assert_eq!(visible_tabs(&[40, 10], 0, 20), [(0, 0)]);
+    }
+}
@@ -0,0 +1,89 @@
+//! Destructive file operations go through the system trash (Recycle Bin on Windows) so they can
+//! be undone outside kpad: `delete_file`, and Save As over an existing file.
+
+use super::Editor; // editor state
+use crate::remote::RemotePath; // remote locations are not trashed
+use crate::types::{Prompt, PromptKind}; // delete confirmation prompt
+use anyhow::{Context, Result}; // anyhow error handling
+use std::fs::{self, File}; // temporary file for Save As
+use std::io::{BufWriter, Write}; // writing the temporary file
+use std::path::{Path, PathBuf}; // trashed paths
+use std::time::Duration; // timing for status messages
+
+/// Move `path` to the trash.
+fn move_to_trash(path: &Path) -> Result<()> {
+    trash::delete(path).with_context(|| format!("Failed to move {} to the trash", path.display()))
+}
+
+/// Hidden file next to `path` that Save As writes before replacing `path`.
+fn temp_sibling(path: &Path) -> PathBuf {
+    let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    path.with_file_name(format!(".{name}.kpad-save"))
+}
+
+impl Editor {
+    /// Ask before moving the current file to the trash (`delete_file` command).
+    pub fn prompt_delete_file(&mut self) {
+        if self.doc.remote.is_some() {
+            self.set_status("Remote files cannot be deleted from kpad.", Duration::from_secs(2));
+            return;
+        }
+        match &self.doc.file_path {
+            Some(p) if p.exists() => {
+                self.prompt = Some(Prompt::new(PromptKind::DeleteFile, ""));
+                self.mark_redraw();
+            }
+            _ => self.set_status("No file on disk to delete.", Duration::from_secs(2)),
+        }
+    }
+
+    /// Answer to the `delete_file` prompt: `y` moves the file to the trash. The buffer stays
+    /// open and unsaved, so saving brings the file back.
+    pub(crate) fn confirm_delete_file(&mut self, answer: &str) {
+        let Some(path) = self.doc.file_path.clone() else { return; };
+        if !matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+            self.set_status("Delete cancelled.", Duration::from_secs(2));
+            return;
+        }
+        match move_to_trash(&path) {
+            Ok(()) => {
+                self.doc.dirty = true;
+                self.set_status(format!("Moved {} to the trash (save to restore it).", path.display()), Duration::from_secs(4));
+            }
+            Err(e) => self.set_status(format!("{e:#}"), Duration::from_secs(5)),
+        }
+    }
+
+    /// Save As to `path`. A different file already there is replaced only once the text is
+    /// safely written next to it: the old file then goes to the trash and the new one takes its
+    /// place. Nothing changes on disk if writing or trashing fails.
+    pub(crate) fn save_as(&mut self, path: &Path) -> Result<()> {
+        let replaces = self.doc.file_path.as_deref() != Some(path)
+            && path.is_file()
+            && path.to_str().and_then(RemotePath::parse).is_none();
+        if !replaces {
+            return self.save_to_path(path.to_path_buf());
+        }
+        let tmp = temp_sibling(path);
+        let written = self.write_temp(&tmp).and_then(|()| move_to_trash(path));
+        if let Err(e) = written {
+            let _ = fs::remove_file(&tmp);
+            self.set_status(format!("Not saved: {e:#}"), Duration::from_secs(5));
+            return Ok(());
+        }
+        fs::rename(&tmp, path)
+            .with_context(|| format!("Failed to rename {} to {} (the old file is in the trash)", tmp.display(), path.display()))?;
+        self.finish_save(path, None)?;
+        self.set_status(format!("Saved: {} (the old file is in the trash)", path.display()), Duration::from_secs(3));
+        Ok(())
+    }
+
+    /// Write the buffer to `tmp`.
+    fn write_temp(&self, tmp: &Path) -> Result<()> {
+        let file = File::create(tmp).with_context(|| format!("Failed to create {}", tmp.display()))?;
+        let mut writer = BufWriter::new(file);
+        self.doc.buf.write_to(&mut writer)
+            .and_then(|()| writer.flush())
+            .with_context(|| format!("Failed writing {}", tmp.display()))
+    }
+}
@@ -1,50 +1,135 @@
 //! Undo/redo operations.
+//!
+//! Typing is undone a word at a time: single characters typed (or deleted with Backspace) in
+//! quick succession at the same spot join one undo step until a new word or line starts. Any
+//! other edit, a pause, or moving the cursor starts a new step.
 
-use crate::types::{EditOperation, UndoEntry}; // undo/redo types
+use crate::types::{EditOperation, Pos, UndoEntry}; // undo/redo types
 use super::Editor; // main editor logic
 use anyhow::Result; // anyhow error handling
+use std::time::{Duration, Instant}; // typing pauses
+
+/// Keystrokes further apart than this start a new undo step.
+const TYPING_PAUSE: Duration = Duration::from_secs(1);
+
+/// Whether adjacent chars `before` and `after`, typed or deleted one by one, belong to the same
+/// undo step: a line break or a word starting after whitespace begins a new one.
+fn same_step(before: char, after: char) -> bool {
+    before != '\n' && after != '\n' && (after.is_whitespace() || !before.is_whitespace())
+}
+
+/// Drop the oldest entries of `history` beyond `max_steps` or `max_bytes` in total (see
+/// `UndoEntry::size`), always keeping the newest one.
+fn trim_history(history: &mut Vec<UndoEntry>, max_steps: usize, max_bytes: usize) {
+    let mut total = 0;
+    let keep = history.iter().rev().take(max_steps).take_while(|e| {
+        total += e.size();
+        total <= max_bytes
+    }).count().max(1);
+    history.drain(..history.len().saturating_sub(keep));
+}
 
 impl Editor {
     /// Record an edit for undo.
     pub fn record_edit(&mut self, op: EditOperation) {
-        const CAP: usize = 1000;
+        self.typing_at = None;
         let entry = UndoEntry {
             op,
-            cursor_before: self.cursor,
-            anchor_before: self.anchor,
+            cursor_before: self.doc.cursor,
+            anchor_before: self.doc.anchor,
         };
-        self.undo.push(entry);
-        if self.undo.len() > CAP {
-            self.undo.drain(0..(self.undo.len() - CAP));
-        }
-        self.redo.clear();
+        self.doc.undo.push(entry);
+        trim_history(&mut self.doc.undo, self.undo_step_limit, self.undo_byte_limit);
+        self.doc.redo.clear();
         self.highlighter.invalidate_all();
     }
 
+    /// Record typing or pasting `text` at the cursor; with a selection it is a `Replace`, so
+    /// undo brings back the selected text and the selection.
+    pub(crate) fn record_insert(&mut self, text: &str) {
+        let op = match self.selection_range() {
+            Some((a, b)) => EditOperation::Replace { start: a, old: self.doc.buf.get_range(a, b), new: text.to_string() },
+            None => EditOperation::Insert { pos: self.doc.cursor, text: text.to_string() },
+        };
+        self.record_edit(op);
+    }
+
+    /// Whether the last keystroke recorded an undo step that typing may still extend.
+    fn typing_recently(&self) -> bool {
+        self.typing_at.is_some_and(|t| t.elapsed() <= TYPING_PAUSE)
+    }
+
+    /// Record typing `ch` at the cursor with nothing selected, extending the typing step that
+    /// ended at the cursor when `ch` continues its word.
+    pub(crate) fn record_typed(&mut self, ch: char) {
+        if self.typing_recently() {
+            if let Some(UndoEntry { op: EditOperation::Insert { pos, text }, .. }) = self.doc.undo.last_mut() {
+                if self.doc.buf.calc_end_pos(*pos, text) == self.doc.cursor && text.chars().last().is_some_and(|c| same_step(c, ch)) {
+                    text.push(ch);
+                    self.typing_at = Some(Instant::now());
+                    return;
+                }
+            }
+        }
+        self.record_insert(&ch.to_string());
+        self.typing_at = Some(Instant::now());
+    }
+
+    /// Record Backspace deleting `deleted` (one char) from `start` to the cursor, extending the
+    /// Backspace step that began at the cursor when it is still in the same word.
+    pub(crate) fn record_backspace(&mut self, start: Pos, deleted: String) {
+        if self.typing_recently() {
+            if let Some(UndoEntry { op: EditOperation::Delete { start: s, deleted_text, .. }, .. }) = self.doc.undo.last_mut() {
+                let joins = deleted.chars().last().zip(deleted_text.chars().next()).is_some_and(|(a, b)| same_step(a, b));
+                if *s == self.doc.cursor && joins {
+                    deleted_text.insert_str(0, &deleted);
+                    *s = start;
+                    self.typing_at = Some(Instant::now());
+                    return;
+                }
+            }
+        }
+        self.record_edit(EditOperation::Delete { start, _end: self.doc.cursor, deleted_text: deleted });
+        self.typing_at = Some(Instant::now());
+    }
+
+    /// Memory held by the active buffer's undo and redo steps.
+    pub(crate) fn undo_memory(&self) -> usize {
+        self.doc.undo.iter().chain(&self.doc.redo).map(UndoEntry::size).sum()
+    }
+
     /// Undo the most recent edit.
     pub fn undo(&mut self) -> Result<()> {
-        if let Some(entry) = self.undo.pop() {
+        if !self.ensure_writable() { return Ok(()); }
+        self.typing_at = None;
+        if let Some(entry) = self.doc.undo.pop() {
             let redo_op = match &entry.op {
                 EditOperation::Insert { pos, text } => {
-                    let end = self.buf.calc_end_pos(*pos, text);
-                    self.buf.delete_range(*pos, end);
+                    let end = self.doc.buf.calc_end_pos(*pos, text);
+                    self.doc.buf.delete_range(*pos, end);
                     EditOperation::Delete { start: *pos, _end: end, deleted_text: text.clone() }
                 }
                 EditOperation::Delete { start, _end: _, deleted_text } => {
-                    self.buf.insert_str(*start, deleted_text);
+                    self.doc.buf.insert_str(*start, deleted_text);
                     EditOperation::Insert { pos: *start, text: deleted_text.clone() }
                 }
+                EditOperation::Replace { start, old, new } => {
+                    let end = self.doc.buf.calc_end_pos(*start, new);
+                    self.doc.buf.delete_range(*start, end);
+                    self.doc.buf.insert_str(*start, old);
+                    EditOperation::Replace { start: *start, old: new.clone(), new: old.clone() }
+                }
             };
 
-            self.redo.push(UndoEntry {
+            self.doc.redo.push(UndoEntry {
                 op: redo_op,
-                cursor_before: self.cursor,
-                anchor_before: self.anchor,
+                cursor_before: self.doc.cursor,
+                anchor_before: self.doc.anchor,
             });
 
-            self.cursor = entry.cursor_before;
-            self.anchor = entry.anchor_before;
-            self.dirty = true;
+            self.doc.cursor = entry.cursor_before;
+            self.doc.anchor = entry.anchor_before;
+            self.doc.dirty = true;
             self.highlighter.invalidate_all();
             self.mark_redraw();
             self.ensure_visible()?;
@@ -54,28 +139,36 @@ impl Editor {
 
     /// Redo the most recently undone edit.
     pub fn redo(&mut self) -> Result<()> {
-        if let Some(entry) = self.redo.pop() {
+        if !self.ensure_writable() { return Ok(()); }
+        self.typing_at = None;
+        if let Some(entry) = self.doc.redo.pop() {
             let undo_op = match &entry.op {
                 EditOperation::Insert { pos, text } => {
-                    let end = self.buf.calc_end_pos(*pos, text);
-                    self.buf.delete_range(*pos, end);
+                    let end = self.doc.buf.calc_end_pos(*pos, text);
+                    self.doc.buf.delete_range(*pos, end);
                     EditOperation::Delete { start: *pos, _end: end, deleted_text: text.clone() }
                 }
                 EditOperation::Delete { start, _end: _, deleted_text } => {
-                    self.buf.insert_str(*start, deleted_text);
+                    self.doc.buf.insert_str(*start, deleted_text);
                     EditOperation::Insert { pos: *start, text: deleted_text.clone() }
                 }
+                EditOperation::Replace { start, old, new } => {
+                    let end = self.doc.buf.calc_end_pos(*start, new);
+                    self.doc.buf.delete_range(*start, end);
+                    self.doc.buf.insert_str(*start, old);
+                    EditOperation::Replace { start: *start, old: new.clone(), new: old.clone() }
+                }
             };
 
-            self.undo.push(UndoEntry {
+            self.doc.undo.push(UndoEntry {
                 op: undo_op,
-                cursor_before: self.cursor,
-                anchor_before: self.anchor,
+                cursor_before: self.doc.cursor,
+                anchor_before: self.doc.anchor,
             });
 
-            self.cursor = entry.cursor_before;
-            self.anchor = entry.anchor_before;
-            self.dirty = true;
+            self.doc.cursor = entry.cursor_before;
+            self.doc.anchor = entry.anchor_before;
+            self.doc.dirty = true;
             self.highlighter.invalidate_all();
             self.mark_redraw();
             self.ensure_visible()?;
@@ -83,3 +176,38 @@ impl Editor {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typing_steps_end_at_words_and_lines() {
+        assert!(same_step('h', 'e'));
+        assert!(same_step('o', ' '));
+        assert!(same_step(' ', ' '));
+        assert!(!same_step(' ', 'w'));
+        assert!(!same_step('o', '\n'));
+        assert!(!same_step('\n', 'a'));
+    }
+
+    #[test]
+    fn history_is_trimmed_by_steps_and_bytes() {
+        let entry = |text: &str| UndoEntry {
+            op: EditOperation::Insert { pos: Pos { y: 0, x: 0 }, text: text.to_string() },
+            cursor_before: Pos { y: 0, x: 0 },
+            anchor_before: None,
+        };
+        let mut history: Vec<UndoEntry> = ["a", "b", "c", "d"].into_iter().map(entry).collect();
+        trim_history(&mut history, 3, usize::MAX);
+        assert_eq!(history.len(), 3);
+        let small = entry("x").size();
+        history.push(entry(&"y".repeat(100)));
+        trim_history(&mut history, 10, small);
+        assert_eq!(history.len(), 1, "the newest step stays even when it is too big");
+        history.insert(0, entry("z"));
+        let total = history.iter().map(UndoEntry::size).sum();
+        trim_history(&mut history, 10, total);
+        assert_eq!(history.len(), 2);
+    }
+}
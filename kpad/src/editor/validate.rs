@@ -0,0 +1,82 @@
+//! Syntax validators for structured files (`json_validate`, `toml_validate`) and the diagnostics
+//! list they share with plugins (`api.diagnostic`).
+//!
+//! A failed check shows its message in the status bar and is added to `Editor::diagnostics`,
+//! which `kpad --check` reports; the validators also move the cursor to the error.
+
+use super::Editor; // editor state
+use crate::types::Pos; // error positions
+use anyhow::Result; // anyhow error handling
+use std::time::Duration; // timing for status messages
+
+/// A syntax error: 1-based line and column, and the message.
+type SyntaxError = (usize, usize, String);
+
+/// First JSON syntax error of `text`.
+fn json_error(text: &str) -> Option<SyntaxError> {
+    let e = serde_json::from_str::<serde_json::Value>(text).err()?;
+    let msg = e.to_string();
+    // The message ends with its own " at line L column C".
+    let msg = msg.rfind(" at line ").map_or(msg.as_str(), |i| &msg[..i]).to_string();
+    Some((e.line(), e.column(), msg))
+}
+
+/// First TOML syntax error of `text`.
+fn toml_error(text: &str) -> Option<SyntaxError> {
+    let e = text.parse::<toml::Table>().err()?;
+    let offset = e.span().map_or(0, |s| s.start).min(text.len());
+    let before = &text[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+    // Messages can span lines ("invalid string\nexpected ..."); keep diagnostics to one.
+    let msg = e.message().trim_end().lines().collect::<Vec<_>>().join("; ");
+    Some((line, column, msg))
+}
+
+impl Editor {
+    /// Record a failed check: status bar plus `diagnostics` (for `--check`).
+    pub(crate) fn add_diagnostic(&mut self, msg: String) {
+        self.set_status(msg.clone(), Duration::from_secs(5));
+        self.diagnostics.push(msg);
+    }
+
+    /// Check the buffer as JSON (`json_validate` command).
+    pub fn json_validate(&mut self) -> Result<()> {
+        let error = json_error(&self.doc.buf.to_string());
+        self.report_validation("JSON", error)
+    }
+
+    /// Check the buffer as TOML (`toml_validate` command).
+    pub fn toml_validate(&mut self) -> Result<()> {
+        let error = toml_error(&self.doc.buf.to_string());
+        self.report_validation("TOML", error)
+    }
+
+    fn report_validation(&mut self, format: &str, error: Option<SyntaxError>) -> Result<()> {
+        let Some((line, column, msg)) = error else {
+            self.set_status(format!("Valid {format}."), Duration::from_secs(2));
+            return Ok(());
+        };
+        self.add_diagnostic(format!("{line}:{column}: {format} error: {msg}"));
+        self.doc.anchor = None;
+        self.doc.cursor = self.doc.buf.clamp_pos(Pos { y: line.saturating_sub(1), x: column.saturating_sub(1) });
+        self.ensure_visible()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locates_syntax_errors() {
+        assert_eq!(json_error("{\"a\": [1, 2]}"), None);
+        let (line, column, msg) = json_error("{\n  \"a\": 1,\n  \"b\" 2\n}").unwrap();
+        assert_eq!((line, column), (3, 7));
+        assert!(!msg.contains(" at line"), "{msg}");
+
+        assert_eq!(toml_error("a = 1\n[t]\nb = \"x\"\n"), None);
+        let (line, column, _) = toml_error("a = 1\nb = = 2\n").unwrap();
+        assert_eq!((line, column), (2, 5));
+    }
+}
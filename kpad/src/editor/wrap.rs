@@ -0,0 +1,86 @@
+//! Word wrap layout: where a long line breaks into screen rows. A row ends after the last
+//! whitespace that fits, so words move whole to the next row; a word longer than the row is
+//! split where the row is full. One space at the break may hang past the edge instead of
+//! starting the next row. With `wrap_indent`, continuation rows are indented like the line
+//! (`same`) or one level deeper (`indent`).
+
+use super::Editor; // editor state
+use crate::config::WrapIndent; // continuation row indentation
+
+/// Start index of each row `chars` wraps into at `avail` columns; continuation rows are
+/// `indent` columns narrower. `width` gives the columns of a char.
+fn wrap_points(chars: &[char], avail: usize, indent: usize, width: impl Fn(char) -> usize) -> Vec<usize> {
+    let mut starts = vec![0];
+    let (mut start, mut col, mut limit, mut last_break) = (0, 0, avail.max(1), None);
+    for (i, &ch) in chars.iter().enumerate() {
+        let w = width(ch);
+        let blank = ch.is_whitespace();
+        if col + w > limit && i > start && !(blank && col <= limit) {
+            limit = avail.saturating_sub(indent).max(1);
+            // Move the word begun after the last whitespace along, if it fits the next row.
+            let carried = last_break.map(|b| (b, chars[b..i].iter().map(|&c| width(c)).sum::<usize>()));
+            (start, col) = match carried {
+                Some((b, carried)) if carried + w <= limit => (b, carried),
+                _ => (i, 0),
+            };
+            starts.push(start);
+            last_break = None;
+        }
+        col += w;
+        if blank { last_break = Some(i + 1); }
+    }
+    starts
+}
+
+impl Editor {
+    /// Start char index of each screen row `line` wraps into at `avail` columns.
+    pub(crate) fn calculate_wrap_segments(&self, line: &str, avail: usize) -> Vec<usize> {
+        let chars: Vec<char> = line.chars().collect();
+        wrap_points(&chars, avail, self.wrap_pad(line, avail), |c| self.char_width(c))
+    }
+
+    /// Columns the continuation rows of `line` start with (`wrap_indent`); none when that
+    /// would leave less than half of the `avail` columns for text.
+    pub(crate) fn wrap_pad(&self, line: &str, avail: usize) -> usize {
+        let extra = match self.wrap_indent {
+            WrapIndent::None => return 0,
+            WrapIndent::Same => 0,
+            WrapIndent::Indent => self.tab_width,
+        };
+        let own: usize = line.chars().take_while(|&c| c == ' ' || c == '\t').map(|c| self.char_width(c)).sum();
+        let pad = own + extra;
+        if pad * 2 <= avail { pad } else { 0 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(line: &str, avail: usize, indent: usize) -> Vec<usize> {
+        let chars: Vec<char> = line.chars().collect();
+        wrap_points(&chars, avail, indent, |_| 1)
+    }
+
+    #[test]
+    fn breaks_after_the_last_whitespace() {
+        assert_eq!(rows("", 8, 0), [0]);
+        assert_eq!(rows("aaa bbb ccc", 8, 0), [0, 8]);
+        assert_eq!(rows("aaa bbb ccc", 5, 0), [0, 4, 8]);
+        // The space at the edge hangs; the next row starts with the word.
+        assert_eq!(rows("aaaa bbbb", 4, 0), [0, 5]);
+        assert_eq!(rows("aaaa   bbbb", 4, 0), [0, 5, 7]);
+    }
+
+    #[test]
+    fn splits_words_longer_than_a_row() {
+        assert_eq!(rows("abcdefghij", 4, 0), [0, 4, 8]);
+        assert_eq!(rows("ab cdefghij", 4, 0), [0, 3, 7]);
+    }
+
+    #[test]
+    fn continuation_rows_are_narrower_by_the_indent() {
+        assert_eq!(rows("  aaa bbb ccc", 8, 2), [0, 6, 10]);
+        assert_eq!(rows("  abcdefghij", 6, 2), [0, 6, 10]);
+    }
+}
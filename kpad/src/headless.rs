@@ -0,0 +1,134 @@
+//! Non-interactive runs: `kpad FILE... --execute "trim_whitespace; save; quit"` runs commands
+//! from the command registry on each file and exits, for scripts and CI.
+//!
+//! `kpad --script transform.rhai FILE...` runs a Rhai script against the plugin API (`api`) on
+//! each file and saves the files it changed; without files it filters stdin to stdout.
+//!
+//! `kpad --check FILE...` runs the validation commands for each file's extension (`[check]` in
+//! kpad.toml, or the built-in `json_validate`/`toml_validate`), prints their diagnostics to
+//! stderr and fails when any file has problems, for pre-commit hooks.
+
+use crate::config::Config; // [check] commands
+use crate::editor::Editor; // editor state and command registry
+use anyhow::{bail, Context, Result}; // anyhow error handling
+use encoding_rs::Encoding; // --encoding
+use std::io; // --script output
+use std::path::{Path, PathBuf}; // file paths
+
+/// Run the `;`-separated `script` on each of `files` in turn (on one empty buffer without
+/// files), read in `encoding` if given. Status messages are printed to stderr; the first
+/// failing command stops the run.
+pub fn execute(files: &[PathBuf], script: &str, encoding: Option<&'static Encoding>) -> Result<()> {
+    let commands: Vec<&str> = script.split(';').map(str::trim).filter(|c| !c.is_empty()).collect();
+    if files.is_empty() {
+        return run_script(None, &commands, encoding);
+    }
+    for file in files {
+        run_script(Some(file.clone()), &commands, encoding)?;
+    }
+    Ok(())
+}
+
+/// Run `commands` on `file` until the end or `quit`; unsaved changes are dropped with a warning.
+fn run_script(file: Option<PathBuf>, commands: &[&str], encoding: Option<&'static Encoding>) -> Result<()> {
+    let label = file.as_ref().map_or_else(|| "(new buffer)".to_string(), |p| p.display().to_string());
+    let mut editor = Editor::new(file, encoding)?;
+    editor.headless = true;
+    for name in commands {
+        let (status, quit) = editor.run_command_headless(name).with_context(|| label.clone())?;
+        if let Some(msg) = status {
+            eprintln!("{label}: {msg}");
+        }
+        if quit { break; }
+    }
+    if editor.doc.dirty {
+        eprintln!("{label}: unsaved changes discarded (add 'save' to the script)");
+    }
+    Ok(())
+}
+
+/// Run the Rhai file `script` on each of `files` (read in `encoding` if given) and save the
+/// ones it changed, or without files, on stdin, printing the result to stdout. Status messages
+/// go to stderr; a script error (e.g. `throw`) stops the run, so the exit code is 1.
+pub fn script(files: &[PathBuf], script: &Path, encoding: Option<&'static Encoding>) -> Result<()> {
+    if files.is_empty() {
+        let mut editor = Editor::new(None, encoding)?;
+        editor.headless = true;
+        editor.open_stdin()?;
+        run_script_file(&mut editor, script, "(stdin)")?;
+        return editor.doc.buf.write_to(io::stdout().lock()).context("Failed to write stdout");
+    }
+    for file in files {
+        let label = file.display().to_string();
+        let mut editor = Editor::new(Some(file.clone()), encoding)?;
+        editor.headless = true;
+        run_script_file(&mut editor, script, &label)?;
+        if editor.doc.dirty {
+            editor.run_command_headless("save").with_context(|| label.clone())?;
+        }
+    }
+    Ok(())
+}
+
+/// Run `script` in `editor`, printing the status message and diagnostics it left.
+fn run_script_file(editor: &mut Editor, script: &Path, label: &str) -> Result<()> {
+    editor.status = None;
+    editor.run_script_file(script).with_context(|| label.to_string())?;
+    if let Some(msg) = editor.status.take() {
+        eprintln!("{label}: {}", msg.text);
+    }
+    for diagnostic in editor.diagnostics.drain(..) {
+        eprintln!("{label}:{diagnostic}");
+    }
+    Ok(())
+}
+
+/// Validate each of `files` (read in `encoding` if given); an error names how many files
+/// failed, so the exit code is 1.
+pub fn check(files: &[PathBuf], encoding: Option<&'static Encoding>) -> Result<()> {
+    if files.is_empty() {
+        bail!("--check needs at least one file");
+    }
+    let config = Config::load()?;
+    let mut failed = 0;
+    for file in files {
+        if !check_file(&config, file, encoding)? {
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        bail!("{failed} of {} files failed the check", files.len());
+    }
+    Ok(())
+}
+
+/// Run the check commands on `file`, printing `file:line:column: message` diagnostics;
+/// returns whether it passed. Missing files and command errors count as failures.
+fn check_file(config: &Config, file: &Path, encoding: Option<&'static Encoding>) -> Result<bool> {
+    let label = file.display().to_string();
+    if !file.is_file() {
+        eprintln!("{label}: no such file");
+        return Ok(false);
+    }
+    let ext = file.extension().map(|e| e.to_string_lossy().into_owned()).unwrap_or_default();
+    let commands = config.check_commands(&ext);
+    if commands.is_empty() {
+        eprintln!("{label}: no checks for this file type");
+        return Ok(true);
+    }
+    let mut editor = Editor::new(Some(file.to_path_buf()), encoding)?;
+    editor.headless = true;
+    let mut passed = true;
+    for name in &commands {
+        if let Err(e) = editor.run_command_headless(name) {
+            eprintln!("{label}: {name}: {e:#}");
+            passed = false;
+            continue;
+        }
+        for diagnostic in editor.diagnostics.drain(..) {
+            eprintln!("{label}:{diagnostic}");
+            passed = false;
+        }
+    }
+    Ok(passed)
+}
@@ -0,0 +1,138 @@
+//! Single-instance mode: `kpad --reuse FILE` hands the file to an already running kpad.
+//!
+//! The running instance listens on a local socket (a named pipe on Windows, a Unix-domain
+//! socket in a directory only the user can enter elsewhere). The protocol is one absolute path
+//! per line, answered with `ok` once the file is open or `error: <message>` if it could not be
+//! opened.
+
+use crate::bus::Message; // message bus messages
+use crate::remote::is_remote; // remote locations are forwarded verbatim
+use anyhow::Result; // anyhow error handling
+#[cfg(unix)]
+use interprocess::local_socket::GenericFilePath; // socket files
+#[cfg(not(unix))]
+use interprocess::local_socket::GenericNamespaced; // named pipes
+use interprocess::local_socket::{prelude::*, ListenerOptions, Name, Stream}; // local sockets
+use std::io::{self, BufRead, BufReader, Write}; // line-based protocol
+use std::path::{Path, PathBuf}; // file path handling
+use std::sync::mpsc::{self, Sender}; // bus sender, open results
+use std::thread; // background listener
+
+/// The per-user name of local socket `service` ("kpad" here, "kpad-pair" for pair editing).
+/// On Unix it is a file in `socket_dir`, so other users can neither connect nor collide.
+#[cfg(unix)]
+pub(crate) fn socket_name(service: &str) -> io::Result<Name<'static>> {
+    socket_dir()?.join(format!("{service}.sock")).to_fs_name::<GenericFilePath>()
+}
+
+/// The per-user name of local socket `service`: a named pipe, which only its creator may write.
+#[cfg(not(unix))]
+pub(crate) fn socket_name(service: &str) -> io::Result<Name<'static>> {
+    let user = std::env::var("USERNAME").or_else(|_| std::env::var("USER")).unwrap_or_default();
+    format!("{service}-{user}.sock").to_ns_name::<GenericNamespaced>()
+}
+
+/// `kpad-<uid>` in the runtime directory (or the temp directory), see `private_dir`.
+#[cfg(unix)]
+fn socket_dir() -> io::Result<PathBuf> {
+    private_dir(&dirs::runtime_dir().unwrap_or_else(std::env::temp_dir), "kpad")
+}
+
+/// `<prefix>-<uid>` in `parent`, created with mode 0700. One that already exists must be a
+/// real directory of this user that nobody else can enter.
+#[cfg(unix)]
+pub(crate) fn private_dir(parent: &Path, prefix: &str) -> io::Result<PathBuf> {
+    use std::fs::{self, DirBuilder};
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+
+    // SAFETY: getuid has no preconditions and cannot fail.
+    let uid = unsafe { libc::getuid() };
+    let dir = parent.join(format!("{prefix}-{uid}"));
+    match DirBuilder::new().mode(0o700).create(&dir) {
+        Err(e) if e.kind() != io::ErrorKind::AlreadyExists => return Err(e),
+        _ => {}
+    }
+    let meta = fs::symlink_metadata(&dir)?;
+    if !meta.is_dir() || meta.uid() != uid || meta.permissions().mode() & 0o077 != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} is not a directory only this user can access", dir.display()),
+        ));
+    }
+    Ok(dir)
+}
+
+/// `<prefix>` in `parent`, which on Windows is the user's own temp directory.
+#[cfg(not(unix))]
+pub(crate) fn private_dir(parent: &Path, prefix: &str) -> io::Result<PathBuf> {
+    let dir = parent.join(prefix);
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// Hand `files` to a running instance, printing each one it opened. Returns `false` (and
+/// sends nothing) when no instance answers for the first file.
+pub fn forward_files(files: &[PathBuf]) -> bool {
+    let Some(first) = files.first().and_then(|p| send_to_running(p)) else { return false; };
+    let results = std::iter::once(Some(first)).chain(files[1..].iter().map(|p| send_to_running(p)));
+    for (p, res) in files.iter().zip(results) {
+        match res {
+            Some(Ok(())) => println!("Opened {} in the running kpad.", p.display()),
+            Some(Err(e)) => eprintln!("kpad: {}: {e}", p.display()),
+            None => eprintln!("kpad: {}: the running kpad did not answer", p.display()),
+        }
+    }
+    true
+}
+
+/// Try to hand `path` to a running instance. `None` if there is none (or it did not answer),
+/// else whether it opened the file.
+pub fn send_to_running(path: &Path) -> Option<Result<(), String>> {
+    let name = socket_name("kpad").ok()?;
+    let stream = Stream::connect(name).ok()?;
+    // Remote locations are forwarded verbatim; local paths are made absolute for the other cwd.
+    let abs = if path.to_str().is_some_and(is_remote) {
+        path.to_path_buf()
+    } else {
+        std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+    };
+
+    let mut conn = BufReader::new(stream);
+    writeln!(conn.get_mut(), "{}", abs.display()).ok()?;
+    let mut reply = String::new();
+    conn.read_line(&mut reply).ok()?;
+    match reply.trim() {
+        "ok" => Some(Ok(())),
+        reply => Some(Err(reply.strip_prefix("error: ")?.to_string())),
+    }
+}
+
+/// Become the primary instance: accept paths from other `kpad --reuse` invocations, post them
+/// to the bus as `Message::OpenFile` and pass on the editor's answer.
+pub fn spawn_listener(tx: Sender<Message>) -> Result<()> {
+    let listener = ListenerOptions::new()
+        .name(socket_name("kpad")?)
+        .try_overwrite(true)
+        .create_sync()?;
+
+    thread::spawn(move || {
+        for conn in listener.incoming().filter_map(std::result::Result::ok) {
+            let mut conn = BufReader::new(conn);
+            let mut line = String::new();
+            if conn.read_line(&mut line).is_err() || line.trim().is_empty() {
+                continue;
+            }
+            let (reply, opened) = mpsc::channel();
+            if tx.send(Message::OpenFile { path: PathBuf::from(line.trim()), reply }).is_err() {
+                break;
+            }
+            let answer = match opened.recv() {
+                Ok(Ok(())) => "ok".to_string(),
+                Ok(Err(e)) => format!("error: {}", e.replace('\n', " ")),
+                Err(_) => break,
+            };
+            let _ = writeln!(conn.get_mut(), "{answer}");
+        }
+    });
+    Ok(())
+}
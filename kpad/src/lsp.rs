@@ -0,0 +1,333 @@
+//! Optional Language Server Protocol client, used for diagnostics: `[lsp]` in kpad.toml names a
+//! server command per file extension. The editor starts a server the first time a file of its
+//! extension is active, opens each file with `textDocument/didOpen` and sends the whole text
+//! again with `textDocument/didChange` after edits (full sync). The server's
+//! `textDocument/publishDiagnostics` arrive on the bus as `Message::Diagnostics`.
+//!
+//! Messages are JSON-RPC with `Content-Length` headers over the server's stdin and stdout. A
+//! writer thread holds everything back until the server answered `initialize`; a reader thread
+//! forwards diagnostics and answers the server's own requests with `null`.
+
+use crate::bus::Message; // diagnostics for the editor
+use anyhow::{Context, Result}; // anyhow error handling
+use serde_json::{json, Value}; // JSON-RPC messages
+use std::fmt::Write as _; // percent-encoding URIs
+use std::io::{self, BufRead, BufReader, Write}; // message framing
+use std::path::Path; // document and root paths
+use std::process::{Child, ChildStdin, Command, Stdio}; // the server process
+use std::sync::mpsc::{self, Receiver, Sender}; // writer queue, bus
+use std::thread; // reader and writer threads
+
+/// Id of the `initialize` request; the only request kpad sends.
+const INITIALIZE_ID: u64 = 1;
+
+/// How serious a diagnostic is; errors sort first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+    Information,
+    Hint,
+}
+
+impl Severity {
+    /// From the LSP number; a missing one counts as an error.
+    fn from_lsp(n: Option<u64>) -> Self {
+        match n {
+            Some(2) => Self::Warning,
+            Some(3) => Self::Information,
+            Some(4) => Self::Hint,
+            _ => Self::Error,
+        }
+    }
+
+    /// Lowercase name for the diagnostics list.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Warning => "warning",
+            Self::Information => "info",
+            Self::Hint => "hint",
+        }
+    }
+}
+
+/// One diagnostic. Positions are as LSP sends them: 0-based lines and UTF-16 columns.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub start: (usize, usize),
+    pub end: (usize, usize),
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// What the writer thread sends.
+enum Outgoing {
+    /// A message, held back until the server is initialized.
+    Message(Value),
+    /// The server answered `initialize`.
+    Ready,
+}
+
+/// A running language server; dropping it stops the process.
+pub struct Server {
+    child: Child,
+    out: Sender<Outgoing>,
+}
+
+impl Server {
+    /// Start `command` (program and arguments) for the workspace `root`. `name` tags the
+    /// `Message::LspStopped` posted when the server exits.
+    pub fn start(command: &[String], root: &Path, name: &str, tx: Sender<Message>) -> Result<Self> {
+        let (program, args) = command.split_first().context("no server command")?;
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .with_context(|| format!("Failed to start {program}"))?;
+        let mut stdin = child.stdin.take().context("no server stdin")?;
+        let stdout = child.stdout.take().context("no server stdout")?;
+
+        write_message(&mut stdin, &initialize(root))?;
+        let (out, queue) = mpsc::channel();
+        thread::spawn(move || write_loop(stdin, &queue));
+        let replies = out.clone();
+        let name = name.to_string();
+        thread::spawn(move || {
+            read_loop(BufReader::new(stdout), &replies, &tx);
+            let _ = tx.send(Message::LspStopped(name));
+        });
+        Ok(Self { child, out })
+    }
+
+    /// Tell the server `uri` is open, with its whole text.
+    pub fn did_open(&self, uri: &str, language: &str, text: &str) {
+        self.notify("textDocument/didOpen", &json!({
+            "textDocument": { "uri": uri, "languageId": language, "version": 1, "text": text },
+        }));
+    }
+
+    /// Tell the server `uri` is no longer open.
+    pub fn did_close(&self, uri: &str) {
+        self.notify("textDocument/didClose", &json!({ "textDocument": { "uri": uri } }));
+    }
+
+    /// Send the whole new text of `uri`, as its version `version`.
+    pub fn did_change(&self, uri: &str, version: u64, text: &str) {
+        self.notify("textDocument/didChange", &json!({
+            "textDocument": { "uri": uri, "version": version },
+            "contentChanges": [{ "text": text }],
+        }));
+    }
+
+    fn notify(&self, method: &str, params: &Value) {
+        let _ = self.out.send(Outgoing::Message(json!({ "jsonrpc": "2.0", "method": method, "params": params })));
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// The `initialize` request: kpad only takes diagnostics, with full text sync.
+fn initialize(root: &Path) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": INITIALIZE_ID,
+        "method": "initialize",
+        "params": {
+            "processId": std::process::id(),
+            "rootUri": path_to_uri(root),
+            "capabilities": {
+                "textDocument": {
+                    "synchronization": { "didSave": false },
+                    "publishDiagnostics": { "relatedInformation": false },
+                },
+            },
+        },
+    })
+}
+
+/// Write queued messages to the server, holding them back until `Outgoing::Ready`.
+fn write_loop(mut stdin: ChildStdin, queue: &Receiver<Outgoing>) {
+    let mut held = Vec::new();
+    let mut ready = false;
+    for outgoing in queue {
+        let sent = match outgoing {
+            Outgoing::Message(msg) if !ready => {
+                held.push(msg);
+                Ok(())
+            }
+            Outgoing::Message(msg) => write_message(&mut stdin, &msg),
+            Outgoing::Ready => {
+                ready = true;
+                let initialized = json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} });
+                std::iter::once(initialized).chain(held.drain(..)).try_for_each(|msg| write_message(&mut stdin, &msg))
+            }
+        };
+        if sent.is_err() { return; }
+    }
+}
+
+/// Read server messages until it exits: post diagnostics to the bus and answer requests.
+fn read_loop(mut reader: impl BufRead, out: &Sender<Outgoing>, tx: &Sender<Message>) {
+    while let Ok(Some(msg)) = read_message(&mut reader) {
+        let method = msg.get("method").and_then(Value::as_str);
+        match (msg.get("id"), method) {
+            (Some(id), Some(method)) => {
+                // `workspace/configuration` wants one (here: empty) setting per item.
+                let result = if method == "workspace/configuration" {
+                    let items = msg["params"]["items"].as_array().map_or(0, Vec::len);
+                    Value::Array(vec![Value::Null; items])
+                } else {
+                    Value::Null
+                };
+                let _ = out.send(Outgoing::Message(json!({ "jsonrpc": "2.0", "id": id, "result": result })));
+            }
+            (Some(id), None) if id.as_u64() == Some(INITIALIZE_ID) => {
+                let _ = out.send(Outgoing::Ready);
+            }
+            (None, Some("textDocument/publishDiagnostics")) => {
+                if let Some((uri, diagnostics)) = parse_diagnostics(&msg["params"]) {
+                    if tx.send(Message::Diagnostics { uri, diagnostics }).is_err() { return; }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Write one message with its `Content-Length` header.
+fn write_message(w: &mut impl Write, msg: &Value) -> io::Result<()> {
+    let body = msg.to_string();
+    write!(w, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    w.flush()
+}
+
+/// Read one message; `None` at the end of the stream.
+fn read_message(r: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut len = None;
+    loop {
+        let mut header = String::new();
+        if r.read_line(&mut header)? == 0 { return Ok(None); }
+        let header = header.trim_end();
+        if header.is_empty() { break; }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                len = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let len = len.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "missing Content-Length"))?;
+    let mut body = vec![0; len];
+    r.read_exact(&mut body)?;
+    serde_json::from_slice(&body).map(Some).map_err(io::Error::from)
+}
+
+/// The URI and diagnostics of `publishDiagnostics` params.
+fn parse_diagnostics(params: &Value) -> Option<(String, Vec<Diagnostic>)> {
+    let uri = params["uri"].as_str()?.to_string();
+    let position = |p: &Value| Some((usize::try_from(p["line"].as_u64()?).ok()?, usize::try_from(p["character"].as_u64()?).ok()?));
+    let diagnostics = params["diagnostics"].as_array()?.iter().filter_map(|d| {
+        Some(Diagnostic {
+            start: position(&d["range"]["start"])?,
+            end: position(&d["range"]["end"])?,
+            severity: Severity::from_lsp(d["severity"].as_u64()),
+            message: d["message"].as_str()?.to_string(),
+        })
+    }).collect();
+    Some((uri, diagnostics))
+}
+
+/// `file://` URI of an absolute path (`file:///C:/dir` for a Windows drive path).
+pub fn path_to_uri(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    // `fs::canonicalize` gives Windows paths a `\\?\` prefix.
+    let path = path.strip_prefix("//?/").unwrap_or(&path);
+    let mut uri = String::from(if path.starts_with('/') { "file://" } else { "file:///" });
+    for b in path.bytes() {
+        if b.is_ascii_alphanumeric() || b"/-._~:".contains(&b) {
+            uri.push(char::from(b));
+        } else {
+            let _ = write!(uri, "%{b:02X}");
+        }
+    }
+    uri
+}
+
+/// Char index in `line` of UTF-16 column `col` (LSP positions); past the end is the line length.
+pub fn utf16_to_char(line: &str, col: usize) -> usize {
+    let mut units = 0;
+    for (i, ch) in line.chars().enumerate() {
+        if units >= col { return i; }
+        units += ch.len_utf16();
+    }
+    line.chars().count()
+}
+
+/// LSP language id for a file extension.
+pub fn language_id(ext: &str) -> &str {
+    match ext {
+        "rs" => "rust",
+        "py" => "python",
+        "js" | "mjs" | "cjs" => "javascript",
+        "ts" => "typescript",
+        "tsx" => "typescriptreact",
+        "jsx" => "javascriptreact",
+        "c" | "h" => "c",
+        "cc" | "cpp" | "cxx" | "hpp" => "cpp",
+        "cs" => "csharp",
+        "md" => "markdown",
+        "sh" => "shellscript",
+        "yml" => "yaml",
+        "ps1" => "powershell",
+        _ => ext,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_messages() {
+        let mut wire = Vec::new();
+        write_message(&mut wire, &json!({ "id": 1, "result": "é" })).unwrap();
+        assert!(wire.starts_with(b"Content-Length: 22\r\n\r\n"));
+        wire.extend_from_slice(b"content-length: 2\r\nContent-Type: x\r\n\r\n{}");
+        let mut r = &wire[..];
+        assert_eq!(read_message(&mut r).unwrap(), Some(json!({ "id": 1, "result": "é" })));
+        assert_eq!(read_message(&mut r).unwrap(), Some(json!({})));
+        assert_eq!(read_message(&mut r).unwrap(), None);
+    }
+
+    #[test]
+    fn parses_published_diagnostics() {
+        let params = json!({
+            "uri": "file:///src/main.rs",
+            "diagnostics": [
+                { "range": { "start": { "line": 2, "character": 4 }, "end": { "line": 2, "character": 9 } }, "severity": 2, "message": "unused" },
+                { "range": { "start": { "line": 0, "character": 0 }, "end": { "line": 1, "character": 0 } }, "message": "broken" },
+            ],
+        });
+        let (uri, diagnostics) = parse_diagnostics(&params).unwrap();
+        assert_eq!(uri, "file:///src/main.rs");
+        assert_eq!(diagnostics[0], Diagnostic { start: (2, 4), end: (2, 9), severity: Severity::Warning, message: "unused".to_string() });
+        assert_eq!(diagnostics[1].severity, Severity::Error);
+    }
+
+    #[test]
+    fn converts_paths_and_columns() {
+        assert_eq!(path_to_uri(Path::new("/home/me/my notes.rs")), "file:///home/me/my%20notes.rs");
+        assert_eq!(path_to_uri(Path::new("C:\\src\\a.rs")), "file:///C:/src/a.rs");
+        assert_eq!(path_to_uri(Path::new("\\\\?\\C:\\src\\a.rs")), "file:///C:/src/a.rs");
+        // "😀" is two UTF-16 units.
+        assert_eq!(utf16_to_char("a😀b", 3), 2);
+        assert_eq!(utf16_to_char("ab", 9), 2);
+    }
+}
@@ -11,18 +11,48 @@
 //! - **Plugins**: loaded from `./plugins/*/plugin.toml` + Rhai scripts; they register commands
 //!   and can modify editor state through **`plugins::PluginApi`**.
 
+// the bidi module maps logical text order to visual order for right-to-left scripts
+mod bidi;
+
+// the bus module contains the message bus the main loop waits on
+mod bus;
+
 // the buffer module contains the document model (a `Vec<String>` of lines) and the low-level editing
 mod buffer;
 
+// the collab module implements pair editing over a local socket (--host/--attach)
+mod collab;
+
+// the cli module defines the command line flags (clap) and shell completions
+mod cli;
+
 // the commands module contains the command registry and command handling
 mod commands;
 
+// the config module loads the user's kpad.toml
+mod config;
+
 // the editor module contains the application state + key handling + rendering + prompts + undo/redo
 mod editor;
 
+// the headless module runs commands without a terminal (--execute)
+mod headless;
+
+// the instance module implements single-instance mode (forwarding files to a running kpad)
+mod instance;
+
+// the lsp module talks to language servers for diagnostics
+mod lsp;
+
 // the plugins module contains the plugin manager and plugin api
 mod plugins;
 
+// the remote module opens and saves scp:// files
+mod remote;
+
+// the scaffold module writes the default config and new plugin skeletons
+mod scaffold;
+
 // the terminal module contains the terminal guard and terminal setup
 mod terminal;
 
@@ -33,10 +63,13 @@ mod types;
 mod utils;
 
 use anyhow::Result; // anyhow error handling
-use crossterm::event::{self, Event, KeyEventKind}; // terminal events
+use bus::{Bus, Message}; // main loop message bus
+use cli::{Cli, Mode}; // command line flags
+use crossterm::event::{Event, KeyEventKind}; // terminal events
 use editor::Editor; // main editor state and logic
 use std::io; // standard input/output
-use std::time::Duration; // timing for events and polling
+use std::rc::Rc; // bus shared with the editor
+use std::time::Duration; // status message timing
 use terminal::TerminalGuard; // raii terminal mode guard
 
 /// Program entry point.
@@ -55,63 +88,70 @@ fn main() {
 /// - initializes `Editor` state
 /// - loops: render → read input events → update state
 fn run() -> Result<()> {
-    let args: Vec<String> = std::env::args().collect();
-    
-    // Simple argument parsing
-    let mut file_to_open = None;
-    
-    if args.len() > 1 {
-        match args[1].as_str() {
-            "-h" | "--help" => {
-                println!("kpad — A simple TUI text editor");
-                println!();
-                println!("USAGE:");
-                println!("    kpad [FILE]          Open a file (creates if doesn't exist)");
-                println!("    kpad -h, --help      Show this help message");
-                println!("    kpad -v, --version   Show version information");
-                println!();
-                println!("KEYBINDINGS:");
-                println!("    Ctrl+P                 Command Palette (Discovery)");
-                println!("    Ctrl+S                 Save");
-                println!("    Ctrl+O                 Open file prompt");
-                println!("    Ctrl+Q                 Quit");
-                println!("    Alt+W                  Toggle Word Wrap");
-                println!("    Home / End             Top / Bottom of document");
-                return Ok(());
-            }
-            "-v" | "--version" => {
-                println!("kpad v0.1.0");
-                return Ok(());
-            }
-            path if path.starts_with('-') => {
-                eprintln!("Error: Unknown flag '{}'", path);
-                eprintln!("Try 'kpad --help' for more information.");
-                std::process::exit(1);
-            }
-            path => {
-                file_to_open = Some(std::path::PathBuf::from(path));
-            }
-        }
+    let cli = Cli::parse_args();
+    if let Some(shell) = cli.completions {
+        cli::print_completions(shell);
+        return Ok(());
+    }
+    // Everything that reads kpad.toml finds it through $KPAD_CONFIG (see `config_path`).
+    if let Some(path) = &cli.config {
+        std::env::set_var("KPAD_CONFIG", path);
+    }
+    // Likewise, safe mode is seen through $KPAD_SAFE (see `utils::safe_mode`).
+    if cli.safe {
+        std::env::set_var("KPAD_SAFE", "1");
+    }
+    if let Some(done) = run_without_ui(&cli) {
+        return done;
+    }
+
+    // Single-instance mode: hand the files to a running kpad instead of starting a second editor.
+    let mode = cli.mode;
+    if mode == Mode::Reuse && instance::forward_files(&cli.files) {
+        return Ok(());
     }
 
     let mut stdout = io::stdout();
     let _term = TerminalGuard::new(&mut stdout)?;
+    let mut editor = open_editor(cli)?;
 
-    let mut editor = Editor::new(file_to_open)?;
+    let bus = Rc::new(Bus::new());
+    bus.spawn_input_reader();
+    editor.set_bus(&bus);
+    if mode == Mode::Attach {
+        editor.attach_pair(bus.sender())?;
+    }
+    if mode == Mode::Reuse {
+        if let Err(e) = instance::spawn_listener(bus.sender()) {
+            editor.set_status(format!("Single-instance mode unavailable: {e}"), Duration::from_secs(4));
+        }
+    }
+    // Kept alive for the whole session; dropping it stops plugin auto-reload.
+    let _plugin_watcher = match plugins::watch_plugin_dirs(editor.plugin_dirs(), bus.sender()) {
+        Ok(w) => Some(w),
+        Err(e) => {
+            editor.set_status(format!("Plugin auto-reload unavailable: {e}"), Duration::from_secs(4));
+            None
+        }
+    };
 
     // Main UI loop:
     // - render the whole screen (simple + robust)
-    // - poll for input so we can also update time-based UI (status message expiration)
+    // - wait on the message bus; the timeout adapts to pending time-based UI (status message
+    //   expiration, animations) so an idle editor does not wake up needlessly
     loop {
+        editor.sync_pair();
+        editor.sync_lsp();
+        editor.track_cursor();
         editor.render(&mut stdout)?;
 
-        // Poll so we can expire transient status messages.
-        if event::poll(Duration::from_millis(50))? {
-            match event::read()? {
+        match bus.wait(editor.next_wakeup())? {
+            Some(Message::Input(ev)) => match ev {
                 Event::Key(key) => {
                     if key.kind != KeyEventKind::Press {
                         continue;
                     }
+                    editor.record_key(key);
                     let should_quit = editor.handle_key(key)?;
                     if should_quit {
                         break;
@@ -120,15 +160,78 @@ fn run() -> Result<()> {
                 Event::Mouse(mouse) => {
                     editor.handle_mouse(mouse)?;
                 }
+                Event::Paste(text) => {
+                    editor.handle_paste(&text)?;
+                }
                 Event::Resize(_, _) => {
                     editor.on_resize()?;
                 }
                 _ => {}
+            },
+            Some(Message::InputError(e)) => return Err(e.into()),
+            Some(Message::OpenFile { path, reply }) => {
+                let _ = reply.send(editor.open_from_instance(path));
             }
-        } else {
-            editor.tick();
+            Some(Message::PluginsChanged) => editor.schedule_plugin_reload(),
+            Some(Message::GrepHits { search, hits, done }) => editor.add_grep_hits(search, hits, done),
+            Some(Message::PairEdit { op, seen }) => editor.queue_pair_edit(op, seen),
+            Some(Message::PairClosed { out_of_step }) => editor.pair_closed(out_of_step),
+            Some(Message::Diagnostics { uri, diagnostics }) => editor.set_lsp_diagnostics(uri, diagnostics),
+            Some(Message::LspStopped(ext)) => editor.lsp_stopped(&ext),
+            Some(Message::BuildDone(report)) => editor.build_done(report),
+            None => editor.tick(),
         }
     }
 
     Ok(())
 }
+
+/// Run the modes that exit without starting the UI (`--init-config`, `--check`, `--execute`,
+/// `--script`, `--host`); `None` when the editor should start instead.
+fn run_without_ui(cli: &Cli) -> Option<Result<()>> {
+    match cli.mode {
+        Mode::InitConfig => {
+            return Some(scaffold::init_config().map(|path| println!("Wrote {}", path.display())));
+        }
+        Mode::Check => return Some(headless::check(&cli.files, cli.encoding)),
+        Mode::Host => return Some(collab::host(&cli.files[0])),
+        Mode::Edit | Mode::Reuse | Mode::Attach => {}
+    }
+    if let Some(script) = &cli.execute {
+        return Some(headless::execute(&cli.files, script, cli.encoding));
+    }
+    cli.script.as_ref().map(|script| headless::script(&cli.files, script, cli.encoding))
+}
+
+/// Create the editor with the files, piped input and start places from the command line.
+fn open_editor(mut cli: Cli) -> Result<Editor> {
+    let stdin = cli.take_stdin();
+    let mut rest = cli.files.into_iter().zip(cli.locations);
+    let (first, first_at) = rest.next().unzip();
+    let mut editor = Editor::new(first, cli.encoding)?;
+    if cli.ascii {
+        editor.use_ascii();
+    }
+    editor.doc.read_only |= cli.readonly;
+    for (p, at) in rest {
+        if editor.open_from_instance(p).is_err() {
+            continue;
+        }
+        editor.doc.read_only |= cli.readonly;
+        if let Some((n, col)) = at {
+            editor.goto_line(n, col)?;
+        }
+    }
+    if stdin {
+        editor.open_stdin()?;
+    }
+    // Files opened later in the session are UTF-8 again.
+    editor.open_encoding = None;
+    if editor.buffer_count() > 1 {
+        editor.switch_buffer(0)?;
+    }
+    if let Some((n, col)) = first_at.flatten().or(cli.line.map(|n| (n, None))) {
+        editor.goto_line(n, col)?;
+    }
+    Ok(editor)
+}
@@ -12,21 +12,66 @@
 
 use crate::buffer::Buffer; // document model
 use crate::editor::Editor; // editor state
-use crate::types::{HighlightColor, HighlightRule, Pos}; // core types
+use super::store::PluginStore; // persistent plugin values
+use crate::types::{HighlightColor, HighlightRule, Pos, PromptKind, SelectionKind}; // core types
 use crate::utils::clamp_usize_i64; // utility functions
+use rhai::{Array, Dynamic, EvalAltResult, Map}; // script values and errors
+use std::io::{self, Write}; // feeding a program's stdin
+use std::path::PathBuf; // file path handling
+use std::process::{Command, Output, Stdio}; // running programs for api.exec
+use std::thread; // stdin writer
 use std::time::Duration; // timing for status messages
 
+/// 1-based (`line`, `col`) as a buffer position, clamped into the buffer.
+fn position(ed: &Editor, line: i64, col: i64) -> Pos {
+    let y = clamp_usize_i64(line - 1, 0, ed.doc.buf.line_count().saturating_sub(1));
+    Pos { y, x: clamp_usize_i64(col - 1, 0, ed.doc.buf.line_len_chars(y)) }
+}
+
+/// The 1-based range from (`l1`, `c1`) to (`l2`, `c2`) as ordered buffer positions.
+fn range(ed: &Editor, l1: i64, c1: i64, l2: i64, c2: i64) -> (Pos, Pos) {
+    let (a, b) = (position(ed, l1, c1), position(ed, l2, c2));
+    (a.min(b), a.max(b))
+}
+
+/// Run `cmd` with `args` in the current directory and wait for it. `input` is written to its
+/// stdin from another thread, so a program that answers before reading everything cannot
+/// block on a full pipe.
+fn run_program(cmd: &str, args: &[String], input: &str) -> io::Result<Output> {
+    let mut child = Command::new(cmd)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let stdin = child.stdin.take();
+    let input = input.to_string();
+    // Programs that ignore their input close the pipe early; that is not an error.
+    let writer = thread::spawn(move || stdin.map(|mut s| s.write_all(input.as_bytes())));
+    let output = child.wait_with_output();
+    let _ = writer.join();
+    output
+}
+
+/// 0-based index of 1-based line `n`, which may be at most `last` (1-based).
+fn line_index(n: i64, last: usize) -> Result<usize, Box<EvalAltResult>> {
+    usize::try_from(n).ok().filter(|&n| (1..=last).contains(&n)).map(|n| n - 1)
+        .ok_or_else(|| format!("No line {n} (lines are 1 to {last})").into())
+}
+
 /// API wrapper passed to Rhai scripts.
 #[derive(Clone)]
 pub struct PluginApi {
     /// Raw pointer back to the `Editor`.
     ed: *mut Editor,
+    /// Id of the plugin this call belongs to.
+    plugin_id: String,
 }
 
 impl PluginApi {
-    /// Create a new API wrapper for this script call.
-    pub fn new(ed: &mut Editor) -> Self {
-        Self { ed }
+    /// Create a new API wrapper for a call into plugin `plugin_id`.
+    pub fn new(ed: &mut Editor, plugin_id: &str) -> Self {
+        Self { ed, plugin_id: plugin_id.to_string() }
     }
 
     /// Temporarily borrow the underlying editor mutably and run `f` against it.
@@ -36,18 +81,109 @@ impl PluginApi {
 
     /// Get the entire buffer contents as a single string.
     pub fn text(&mut self) -> String {
-        self.with_editor(|ed| ed.buf.to_string())
+        self.with_editor(|ed| ed.doc.buf.to_string())
     }
 
     /// Replace the entire buffer contents with `s`.
     pub fn set_text(&mut self, s: String) {
         self.with_editor(|ed| {
-            ed.buf = Buffer::from_string(&s);
-            ed.cursor = Pos { y: 0, x: 0 };
-            ed.anchor = None;
-            ed.scroll_y = 0;
-            ed.scroll_x = 0;
-            ed.dirty = true;
+            if !ed.ensure_writable() { return; }
+            ed.doc.buf = Buffer::from_string(&s);
+            ed.doc.cursor = Pos { y: 0, x: 0 };
+            ed.doc.anchor = None;
+            ed.doc.scroll_y = 0;
+            ed.doc.scroll_x = 0;
+            ed.doc.dirty = true;
+        })
+    }
+
+    /// Text from (`l1`, `c1`) up to, not including, (`l2`, `c2`), 1-based; ending at column 1
+    /// of the next line takes the line break.
+    pub fn get_range(&mut self, l1: i64, c1: i64, l2: i64, c2: i64) -> String {
+        self.with_editor(|ed| {
+            let (start, end) = range(ed, l1, c1, l2, c2);
+            ed.doc.buf.get_range(start, end)
+        })
+    }
+
+    /// Replace the text from (`l1`, `c1`) up to (`l2`, `c2`) with `s` as one undo step. The
+    /// cursor and selection stay on the text they were on.
+    pub fn replace_range(&mut self, l1: i64, c1: i64, l2: i64, c2: i64, s: &str) {
+        self.with_editor(|ed| {
+            if !ed.ensure_writable() { return; }
+            let (start, end) = range(ed, l1, c1, l2, c2);
+            ed.replace_range_in_place(start, end, s);
+        });
+    }
+
+    /// Insert `s` at (`line`, `col`) as one undo step, leaving the cursor on its text.
+    pub fn insert_at(&mut self, line: i64, col: i64, s: &str) {
+        self.replace_range(line, col, line, col, s);
+    }
+
+    /// Delete the text from (`l1`, `c1`) up to (`l2`, `c2`) as one undo step.
+    pub fn delete_range(&mut self, l1: i64, c1: i64, l2: i64, c2: i64) {
+        self.replace_range(l1, c1, l2, c2, "");
+    }
+
+    /// Number of lines in the buffer (a trailing line break starts an empty last line).
+    pub fn line_count(&mut self) -> i64 {
+        self.with_editor(|ed| i64::try_from(ed.doc.buf.line_count()).unwrap_or(i64::MAX))
+    }
+
+    /// Text of 1-based line `n`, without its line break.
+    pub fn get_line(&mut self, n: i64) -> Result<String, Box<EvalAltResult>> {
+        self.with_editor(|ed| Ok(ed.doc.buf.line(line_index(n, ed.doc.buf.line_count())?).to_string()))
+    }
+
+    /// Replace the text of line `n` (its line break stays) as one undo step. A cursor on the
+    /// line keeps its column as far as the new text reaches.
+    pub fn set_line(&mut self, n: i64, s: &str) -> Result<(), Box<EvalAltResult>> {
+        self.with_editor(|ed| {
+            let y = line_index(n, ed.doc.buf.line_count())?;
+            if !ed.ensure_writable() { return Ok(()); }
+            let (cursor, anchor) = (ed.doc.cursor, ed.doc.anchor);
+            ed.replace_range_in_place(Pos { y, x: 0 }, Pos { y, x: ed.doc.buf.line_len_chars(y) }, s);
+            if !s.contains('\n') {
+                if cursor.y == y { ed.doc.cursor = ed.doc.buf.clamp_pos(cursor); }
+                if let Some(a) = anchor.filter(|a| a.y == y) { ed.doc.anchor = Some(ed.doc.buf.clamp_pos(a)); }
+            }
+            Ok(())
+        })
+    }
+
+    /// Insert `s` as a new line `n`, moving that line and the ones after it down; `n` one past
+    /// the last line appends. One undo step.
+    pub fn insert_line(&mut self, n: i64, s: &str) -> Result<(), Box<EvalAltResult>> {
+        self.with_editor(|ed| {
+            let count = ed.doc.buf.line_count();
+            let y = line_index(n, count + 1)?;
+            if !ed.ensure_writable() { return Ok(()); }
+            if y < count {
+                ed.replace_range_in_place(Pos { y, x: 0 }, Pos { y, x: 0 }, &format!("{s}\n"));
+            } else {
+                let end = Pos { y: count - 1, x: ed.doc.buf.line_len_chars(count - 1) };
+                ed.replace_range_in_place(end, end, &format!("\n{s}"));
+            }
+            Ok(())
+        })
+    }
+
+    /// Delete line `n` with its line break as one undo step (the only line is emptied).
+    pub fn delete_line(&mut self, n: i64) -> Result<(), Box<EvalAltResult>> {
+        self.with_editor(|ed| {
+            let count = ed.doc.buf.line_count();
+            let y = line_index(n, count)?;
+            if !ed.ensure_writable() { return Ok(()); }
+            let (start, end) = if y + 1 < count {
+                (Pos { y, x: 0 }, Pos { y: y + 1, x: 0 })
+            } else if y > 0 {
+                (Pos { y: y - 1, x: ed.doc.buf.line_len_chars(y - 1) }, Pos { y, x: ed.doc.buf.line_len_chars(y) })
+            } else {
+                (Pos { y, x: 0 }, Pos { y, x: ed.doc.buf.line_len_chars(y) })
+            };
+            ed.replace_range_in_place(start, end, "");
+            Ok(())
         })
     }
 
@@ -64,6 +200,7 @@ impl PluginApi {
     /// Replace the selection with `s`.
     pub fn replace_selection(&mut self, s: String) {
         self.with_editor(|ed| {
+            if !ed.ensure_writable() { return; }
             ed.replace_selection_or_insert(&s);
         })
     }
@@ -71,56 +208,266 @@ impl PluginApi {
     /// Insert text at the cursor.
     pub fn insert(&mut self, s: String) {
         self.with_editor(|ed| {
+            if !ed.ensure_writable() { return; }
             ed.replace_selection_or_insert(&s);
         })
     }
 
     /// 1-based cursor line.
     pub fn cursor_line(&mut self) -> i64 {
-        self.with_editor(|ed| (ed.cursor.y as i64) + 1)
+        self.with_editor(|ed| (ed.doc.cursor.y as i64) + 1)
     }
 
     /// 1-based cursor column.
     pub fn cursor_col(&mut self) -> i64 {
-        self.with_editor(|ed| (ed.cursor.x as i64) + 1)
+        self.with_editor(|ed| (ed.doc.cursor.x as i64) + 1)
     }
 
     /// Set the cursor position using 1-based coordinates.
     pub fn set_cursor(&mut self, line: i64, col: i64) {
         self.with_editor(|ed| {
-            let y = clamp_usize_i64(line - 1, 0, ed.buf.line_count().saturating_sub(1));
-            let max_x = ed.buf.line_len_chars(y);
+            let y = clamp_usize_i64(line - 1, 0, ed.doc.buf.line_count().saturating_sub(1));
+            let max_x = ed.doc.buf.line_len_chars(y);
             let x = clamp_usize_i64(col - 1, 0, max_x);
-            ed.cursor = Pos { y, x };
-            ed.anchor = None;
+            ed.doc.cursor = Pos { y, x };
+            ed.doc.anchor = None;
         })
     }
 
+    /// Find the next regex match at or after 1-based (`from_line`, `from_col`).
+    /// Returns `[line, col]` (1-based) or `()` when there is no match; does not move the cursor.
+    pub fn find(&mut self, pattern: &str, from_line: i64, from_col: i64) -> Result<Dynamic, Box<EvalAltResult>> {
+        let re = regex::Regex::new(pattern).map_err(|e| format!("Invalid regex '{pattern}': {e}"))?;
+        Ok(self.with_editor(|ed| {
+            let y = clamp_usize_i64(from_line - 1, 0, ed.doc.buf.line_count().saturating_sub(1));
+            let x = clamp_usize_i64(from_col - 1, 0, ed.doc.buf.line_len_chars(y));
+            ed.find_regex(&re, Pos { y, x }).map_or(Dynamic::UNIT, |p| {
+                let one_based = |v: usize| Dynamic::from(i64::try_from(v + 1).unwrap_or(i64::MAX));
+                let pos: Array = vec![one_based(p.y), one_based(p.x)];
+                pos.into()
+            })
+        }))
+    }
+
+    /// Select from (`l1`, `c1`) to (`l2`, `c2`), 1-based; the cursor ends at the second position.
+    pub fn select(&mut self, l1: i64, c1: i64, l2: i64, c2: i64) {
+        self.with_editor(|ed| {
+            ed.doc.anchor = Some(position(ed, l1, c1));
+            ed.doc.cursor = position(ed, l2, c2);
+            ed.selection_kind = SelectionKind::Stream;
+            ed.mark_redraw();
+        });
+    }
+
+    /// Center the view on a 1-based line (the cursor moves there if it is on another line).
+    pub fn scroll_to(&mut self, line: i64) {
+        self.with_editor(|ed| {
+            let y = clamp_usize_i64(line - 1, 0, ed.doc.buf.line_count().saturating_sub(1));
+            // Only fails without a terminal; the cursor has still moved.
+            let _ = ed.center_on_line(y);
+        });
+    }
+
+    /// Terminal width in columns (80 when running headless).
+    pub fn term_width(&mut self) -> i64 {
+        self.with_editor(|ed| ed.screen_size().map_or(0, |(w, _)| i64::from(w)))
+    }
+
+    /// Terminal height in rows, status bar included (24 when running headless).
+    pub fn term_height(&mut self) -> i64 {
+        self.with_editor(|ed| ed.screen_size().map_or(0, |(_, h)| i64::from(h)))
+    }
+
+    /// 1-based first line shown in the scrolled text area.
+    pub fn first_visible_line(&mut self) -> i64 {
+        self.with_editor(|ed| i64::try_from(ed.visible_lines().0 + 1).unwrap_or(i64::MAX))
+    }
+
+    /// 1-based last line shown in the scrolled text area.
+    pub fn last_visible_line(&mut self) -> i64 {
+        self.with_editor(|ed| i64::try_from(ed.visible_lines().1 + 1).unwrap_or(i64::MAX))
+    }
+
+    /// Rows scrolled off the top (screen rows, so wrapped lines count once per row).
+    pub fn scroll_top(&mut self) -> i64 {
+        self.with_editor(|ed| i64::try_from(ed.doc.scroll_y).unwrap_or(i64::MAX))
+    }
+
+    /// Columns scrolled off the left (always 0 with word wrap).
+    pub fn scroll_left(&mut self) -> i64 {
+        self.with_editor(|ed| i64::try_from(ed.doc.scroll_x).unwrap_or(i64::MAX))
+    }
+
     /// Get the full text of the current line.
     pub fn current_line_text(&mut self) -> String {
-        self.with_editor(|ed| ed.buf.line(ed.cursor.y).to_string())
+        self.with_editor(|ed| ed.doc.buf.line(ed.doc.cursor.y).to_string())
     }
 
     /// Replace the current line with `s`.
     pub fn set_current_line_text(&mut self, s: String) {
         self.with_editor(|ed| {
-            if ed.cursor.y < ed.buf.line_count() {
-                ed.buf.set_line(ed.cursor.y, &s);
-                ed.cursor.x = ed.cursor.x.min(ed.buf.line_len_chars(ed.cursor.y));
-                ed.dirty = true;
+            if ed.doc.cursor.y < ed.doc.buf.line_count() && ed.ensure_writable() {
+                ed.doc.buf.set_line(ed.doc.cursor.y, &s);
+                ed.doc.cursor.x = ed.doc.cursor.x.min(ed.doc.buf.line_len_chars(ed.doc.cursor.y));
+                ed.doc.dirty = true;
+            }
+        })
+    }
+
+    /// Open a file (or `scp://` / `http(s)://` location) in its own buffer, like the Open prompt.
+    /// Returns `true` on success.
+    pub fn open_file(&mut self, path: String) -> bool {
+        self.with_editor(|ed| match ed.open_path(PathBuf::from(&path)) {
+            Ok(()) => true,
+            Err(e) => {
+                ed.set_status(format!("{e:#}"), Duration::from_secs(3));
+                false
+            }
+        })
+    }
+
+    /// Save like Ctrl+S (an unnamed buffer opens the Save As prompt).
+    /// Returns `true` if the buffer has no unsaved changes afterwards.
+    pub fn save(&mut self) -> bool {
+        self.with_editor(|ed| {
+            if let Err(e) = ed.cmd_save() {
+                ed.set_status(format!("{e:#}"), Duration::from_secs(3));
+            }
+            !ed.doc.dirty && ed.doc.file_path.is_some()
+        })
+    }
+
+    /// Run a command line (built-in or plugin, with arguments: `"goto_line 12"`) as if typed in
+    /// the command palette. `quit` and `save_and_quit` are refused. Returns `false` if the
+    /// command is unknown or failed.
+    pub fn run_command(&mut self, line: &str) -> bool {
+        self.with_editor(|ed| {
+            let expanded = ed.commands.expand_alias(line);
+            let name = expanded.split_whitespace().next().unwrap_or_default();
+            if name.eq_ignore_ascii_case("quit") || name.eq_ignore_ascii_case("save_and_quit") {
+                ed.set_status(format!("Plugins cannot run '{name}'."), Duration::from_secs(3));
+                return false;
+            }
+            if ed.commands.get(name).is_none() {
+                ed.set_status(format!("Unknown command: '{name}'"), Duration::from_secs(3));
+                return false;
+            }
+            match ed.run_command_by_name(line) {
+                Ok(_) => true,
+                Err(e) => {
+                    ed.set_status(format!("{name}: {e:#}"), Duration::from_secs(3));
+                    false
+                }
+            }
+        })
+    }
+
+    /// Names of all registered commands, sorted.
+    pub fn list_commands(&mut self) -> Array {
+        self.with_editor(|ed| ed.commands.list_names().into_iter().map(Dynamic::from).collect())
+    }
+
+    /// Call this plugin's function `func(api)` after `ms` milliseconds, on the editor's main loop.
+    pub fn defer(&mut self, ms: i64, func: &str) -> Result<(), Box<EvalAltResult>> {
+        let plugin_id = self.plugin_id.clone();
+        self.with_editor(|ed| {
+            if !ed.plugins.has_command_fn(&plugin_id, func) {
+                return Err(format!("defer: no function {func}(api) in plugin {plugin_id}").into());
             }
+            let delay = Duration::from_millis(u64::try_from(ms).unwrap_or(0));
+            ed.schedule_plugin_timer(plugin_id, func.to_string(), delay)
+                .map_err(|e| format!("defer: {e}").into())
         })
     }
 
+    /// The value this plugin stored under `key` in an earlier call or session, or `()`.
+    pub fn store_get(&mut self, key: &str) -> Result<Dynamic, Box<EvalAltResult>> {
+        let value = PluginStore::for_plugin(&self.plugin_id)
+            .and_then(|s| s.get(key))
+            .map_err(|e| format!("store_get: {e:#}"))?;
+        value.map_or(Ok(Dynamic::UNIT), rhai::serde::to_dynamic)
+    }
+
+    /// Persist `value` (anything JSON can hold) under `key`; `()` removes the key.
+    pub fn store_set(&mut self, key: &str, value: Dynamic) -> Result<(), Box<EvalAltResult>> {
+        let value = if value.is_unit() {
+            None
+        } else {
+            Some(serde_json::to_value(value).map_err(|e| format!("store_set: {e}"))?)
+        };
+        PluginStore::for_plugin(&self.plugin_id)
+            .and_then(|s| s.set(key, value))
+            .map_err(|e| format!("store_set: {e:#}").into())
+    }
+
+    /// Ask the user for text in the prompt line: `label` comes before the input, which starts
+    /// as `default`. Waits for Enter and returns the text; Esc gives `""`, as does asking
+    /// without a terminal (`--script`, `--execute`) or while another prompt is open.
+    pub fn prompt(&mut self, label: &str, default: &str) -> Result<String, Box<EvalAltResult>> {
+        self.ask(PromptKind::PluginInput, format!("{label}: "), default).map(Option::unwrap_or_default)
+    }
+
+    /// Ask a yes/no question and wait for the answer: `true` for `y`/`yes`. Esc, no terminal or
+    /// another open prompt give `false`.
+    pub fn confirm(&mut self, message: &str) -> Result<bool, Box<EvalAltResult>> {
+        let answer = self.ask(PromptKind::PluginConfirm, format!("{message} (y/N): "), "")?;
+        Ok(answer.is_some_and(|a| matches!(a.trim().to_lowercase().as_str(), "y" | "yes")))
+    }
+
+    /// Ask in a prompt of `kind`; `None` if it was cancelled or could not be asked.
+    fn ask(&mut self, kind: PromptKind, label: String, initial: &str) -> Result<Option<String>, Box<EvalAltResult>> {
+        self.with_editor(|ed| ed.ask_user(kind, label, initial)).map_err(|e| format!("{e:#}").into())
+    }
+
+    /// Run program `cmd` with `args` (found on `PATH` like a shell would, not through one),
+    /// give it `stdin_text` as input and wait for it to exit. Returns
+    /// `#{ stdout, stderr, code }`; `code` is -1 if a signal killed it. Only plugins with
+    /// `exec = true` under `[permissions]` in plugin.toml (and `--script` files) may call it.
+    pub fn exec(&mut self, cmd: &str, args: Array, stdin_text: &str) -> Result<Map, Box<EvalAltResult>> {
+        let plugin_id = self.plugin_id.clone();
+        if !self.with_editor(|ed| ed.plugins.may_exec(&plugin_id)) {
+            return Err(format!("exec: plugin {plugin_id} may not run programs (set exec = true under [permissions] in its plugin.toml)").into());
+        }
+        let args: Vec<String> = args.into_iter().map(|a| a.to_string()).collect();
+        let output = run_program(cmd, &args, stdin_text).map_err(|e| format!("exec: {cmd}: {e}"))?;
+        let mut result = Map::new();
+        result.insert("stdout".into(), String::from_utf8_lossy(&output.stdout).into_owned().into());
+        result.insert("stderr".into(), String::from_utf8_lossy(&output.stderr).into_owned().into());
+        result.insert("code".into(), i64::from(output.status.code().unwrap_or(-1)).into());
+        Ok(result)
+    }
+
     /// Show a short status message.
     pub fn status(&mut self, msg: String) {
         self.with_editor(|ed| ed.set_status(msg, Duration::from_secs(2)))
     }
 
+    /// Show `text` in this plugin's status bar segment until it is changed; `""` removes it.
+    pub fn set_status_segment(&mut self, text: String) {
+        let plugin_id = self.plugin_id.clone();
+        self.with_editor(|ed| ed.set_status_segment(&plugin_id, text));
+    }
+
+    /// Text of the named register `name` (`""` if it was never set).
+    pub fn register_get(&mut self, name: &str) -> String {
+        self.with_editor(|ed| ed.registers.get(name).cloned().unwrap_or_default())
+    }
+
+    /// Store `text` in the named register `name`, shared by all plugins for the session.
+    pub fn register_set(&mut self, name: &str, text: String) {
+        self.with_editor(|ed| ed.registers.insert(name.to_string(), text));
+    }
+
+    /// Report a problem found by a validator command: shown like `status`, and makes
+    /// `kpad --check` fail.
+    pub fn diagnostic(&mut self, msg: String) {
+        self.with_editor(|ed| ed.add_diagnostic(msg));
+    }
+
     /// Return the current file path as a string.
     pub fn file_path(&mut self) -> String {
         self.with_editor(|ed| {
-            ed.file_path
+            ed.doc.file_path
                 .as_ref()
                 .map(|p| p.display().to_string())
                 .unwrap_or_default()
@@ -130,7 +477,7 @@ impl PluginApi {
     /// Get the file extension of the current file (lowercase, without dot).
     pub fn file_extension(&mut self) -> String {
         self.with_editor(|ed| {
-            ed.file_path
+            ed.doc.file_path
                 .as_ref()
                 .and_then(|p| p.extension())
                 .and_then(|e| e.to_str())
@@ -207,6 +554,15 @@ pub fn register_api(engine: &mut rhai::Engine) {
     engine.register_type::<PluginApi>();
     engine.register_fn("text", PluginApi::text);
     engine.register_fn("set_text", PluginApi::set_text);
+    engine.register_fn("get_range", PluginApi::get_range);
+    engine.register_fn("replace_range", PluginApi::replace_range);
+    engine.register_fn("insert_at", PluginApi::insert_at);
+    engine.register_fn("delete_range", PluginApi::delete_range);
+    engine.register_fn("line_count", PluginApi::line_count);
+    engine.register_fn("get_line", PluginApi::get_line);
+    engine.register_fn("set_line", PluginApi::set_line);
+    engine.register_fn("insert_line", PluginApi::insert_line);
+    engine.register_fn("delete_line", PluginApi::delete_line);
     engine.register_fn("has_selection", PluginApi::has_selection);
     engine.register_fn("selection_text", PluginApi::selection_text);
     engine.register_fn("replace_selection", PluginApi::replace_selection);
@@ -214,10 +570,33 @@ pub fn register_api(engine: &mut rhai::Engine) {
     engine.register_fn("cursor_line", PluginApi::cursor_line);
     engine.register_fn("cursor_col", PluginApi::cursor_col);
     engine.register_fn("set_cursor", PluginApi::set_cursor);
+    engine.register_fn("find", PluginApi::find);
+    engine.register_fn("select", PluginApi::select);
+    engine.register_fn("scroll_to", PluginApi::scroll_to);
+    engine.register_fn("term_width", PluginApi::term_width);
+    engine.register_fn("term_height", PluginApi::term_height);
+    engine.register_fn("first_visible_line", PluginApi::first_visible_line);
+    engine.register_fn("last_visible_line", PluginApi::last_visible_line);
+    engine.register_fn("scroll_top", PluginApi::scroll_top);
+    engine.register_fn("scroll_left", PluginApi::scroll_left);
     engine.register_fn("current_line_text", PluginApi::current_line_text);
     engine.register_fn("set_current_line_text", PluginApi::set_current_line_text);
+    engine.register_fn("prompt", PluginApi::prompt);
+    engine.register_fn("confirm", PluginApi::confirm);
+    engine.register_fn("exec", PluginApi::exec);
     engine.register_fn("status", PluginApi::status);
+    engine.register_fn("set_status_segment", PluginApi::set_status_segment);
+    engine.register_fn("register_get", PluginApi::register_get);
+    engine.register_fn("register_set", PluginApi::register_set);
+    engine.register_fn("diagnostic", PluginApi::diagnostic);
     engine.register_fn("file_path", PluginApi::file_path);
+    engine.register_fn("open_file", PluginApi::open_file);
+    engine.register_fn("save", PluginApi::save);
+    engine.register_fn("run_command", PluginApi::run_command);
+    engine.register_fn("list_commands", PluginApi::list_commands);
+    engine.register_fn("defer", PluginApi::defer);
+    engine.register_fn("store_get", PluginApi::store_get);
+    engine.register_fn("store_set", PluginApi::store_set);
     engine.register_fn("file_extension", PluginApi::file_extension);
     // Highlighting API
     engine.register_fn("add_highlight", PluginApi::add_highlight);
@@ -1,22 +1,50 @@
 //! Plugin system: loads Rhai scripts from `plugins/*/plugin.toml` and provides a safe API.
 
 mod api; // plugin api for rhai scripts
+mod stdlib; // text, regex, json and path helper modules for scripts
+mod store; // persistent per-plugin key-value store
+mod watch; // plugin directory watcher for auto-reload
 
 pub use api::PluginApi; // expose the api type
+pub use store::PluginStore; // also kpad's own state files
+pub use watch::watch_plugin_dirs; // start the auto-reload watcher
 
-use crate::commands::{Command, CommandRegistry, CommandSource}; // command system
+use crate::commands::{normalize_key_string, Command, CommandRegistry, CommandSource}; // command system
 use crate::editor::Editor; // editor state
-use anyhow::{anyhow, Context, Result}; // anyhow error handling
+use crate::types::{HighlightColor, HighlightRule}; // highlight rule types
+use anyhow::{anyhow, bail, Context, Result}; // anyhow error handling
 use serde::Deserialize; // trait for deserializing toml
 use std::fs; // file system access
-use std::path::PathBuf; // file path handling
-use std::time::Duration; // timing for status messages
+use std::path::{Path, PathBuf}; // file path handling
+use std::time::{Duration, Instant}; // timing for status messages and timers
+
+/// Version of the scripting API this build provides. Bump when methods are added to `PluginApi`
+/// (plugins declare the version they need with `api_version` in `plugin.toml`).
+///
+/// - v2: `open_file`, `save`, `run_command`, `list_commands`, `defer`, `store_get`, `store_set`
+/// - v3: `diagnostic`
+/// - v4: `set_status_segment`, the `on_cursor_move` hook
+/// - v5: `term_width`, `term_height`, `first_visible_line`, `last_visible_line`, `scroll_top`, `scroll_left`
+/// - v6: `register_get`, `register_set`
+/// - v7: `get_range`, `replace_range`, `insert_at`, `delete_range`
+/// - v8: `line_count`, `get_line`, `set_line`, `insert_line`, `delete_line`
+/// - v9: `prompt`, `confirm`
+/// - v10: `exec`
+pub const PLUGIN_API_VERSION: u32 = 10;
+
+/// Plugin id `PluginApi` calls from `kpad --script` files are made under.
+const SCRIPT_ID: &str = "script";
 
 /// Optional lifecycle hooks that plugins may implement.
 #[derive(Debug, Clone, Copy)]
 pub enum Hook {
-    OnOpen,
-    OnSave,
+    /// A file was opened (`on_open`).
+    Open,
+    /// A file was saved (`on_save`).
+    Save,
+    /// The cursor settled somewhere new (`on_cursor_move`, debounced, see
+    /// `Editor::track_cursor`).
+    CursorMove,
 }
 
 /// Parsed representation of a plugin's `plugin.toml` manifest.
@@ -25,12 +53,66 @@ struct PluginManifest {
     id: String,
     name: Option<String>,
     script: String,
+    /// The plugin's own version, informational.
+    version: Option<String>,
+    /// Scripting API version the plugin needs.
+    api_version: Option<u32>,
 
     #[serde(default)]
     commands: Vec<PluginCommand>,
 
     #[serde(default)]
     hooks: PluginHooks,
+
+    #[serde(default)]
+    filetypes: Vec<FileTypeManifest>,
+
+    #[serde(default)]
+    permissions: PluginPermissions,
+}
+
+/// The `[permissions]` table: what a plugin may do beyond editing. Everything is off unless
+/// the manifest turns it on.
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+struct PluginPermissions {
+    /// Run programs with `api.exec`.
+    exec: bool,
+}
+
+/// A `[[filetypes]]` language bundle inside `plugin.toml`.
+#[derive(Debug, Deserialize)]
+struct FileTypeManifest {
+    extensions: Vec<String>,
+    comment: Option<String>,
+    build: Option<String>,
+    dictionary: Option<String>,
+    #[serde(default)]
+    highlights: Vec<HighlightManifest>,
+}
+
+/// A `[[filetypes.highlights]]` rule.
+#[derive(Debug, Deserialize)]
+struct HighlightManifest {
+    pattern: String,
+    color: String,
+    #[serde(default)]
+    priority: i32,
+    #[serde(default)]
+    group: usize,
+}
+
+/// File-type support contributed by a plugin, applied to files with matching extensions.
+pub struct FileType {
+    /// Lowercase extensions without the dot.
+    pub extensions: Vec<String>,
+    /// Line comment prefix, e.g. `//` or `#`.
+    pub comment: Option<String>,
+    /// Build command; `{file}` is replaced with the file path.
+    pub build: Option<String>,
+    /// Word list (one word per line) offered by `complete_word`.
+    pub dictionary: Option<PathBuf>,
+    pub highlights: Vec<HighlightRule>,
 }
 
 /// A command declaration inside `plugin.toml`.
@@ -40,21 +122,59 @@ struct PluginCommand {
     description: String,
     func: String,
     key: Option<String>,
+    /// Label of the command in the right-click context menu.
+    menu: Option<String>,
 }
 
 /// Optional plugin hook function names.
 #[derive(Debug, Deserialize, Default)]
 struct PluginHooks {
-    on_open: Option<String>,
-    on_save: Option<String>,
+    #[serde(rename = "on_open")]
+    open: Option<String>,
+    #[serde(rename = "on_save")]
+    save: Option<String>,
+    #[serde(rename = "on_cursor_move")]
+    cursor_move: Option<String>,
+}
+
+impl PluginHooks {
+    /// Function name for `hook`, if the plugin has one.
+    fn get(&self, hook: Hook) -> Option<&str> {
+        match hook {
+            Hook::Open => self.open.as_deref(),
+            Hook::Save => self.save.as_deref(),
+            Hook::CursorMove => self.cursor_move.as_deref(),
+        }
+    }
+}
+
+/// Metadata about a loaded plugin, shown in the plugins overlay.
+pub struct PluginInfo {
+    pub id: String,
+    pub name: String,
+    pub version: Option<String>,
+    pub api_version: Option<u32>,
+    /// Compatibility warning; the plugin is still loaded.
+    pub warning: Option<String>,
+    /// May run programs (`exec = true` under `[permissions]`).
+    pub exec: bool,
+}
+
+/// A plugin function scheduled with `api.defer(ms, func)`, run by the editor's tick loop.
+pub struct PluginTimer {
+    pub at: Instant,
+    pub plugin_id: String,
+    pub func: String,
 }
 
 /// A loaded plugin: compiled Rhai AST + metadata.
 struct Plugin {
-    id: String,
-    _name: String,
+    info: PluginInfo,
     ast: rhai::AST,
     hooks: PluginHooks,
+    filetypes: Vec<FileType>,
+    /// Context menu entries: label and command name.
+    menu_items: Vec<(String, String)>,
 }
 
 /// Loads plugins from disk and runs plugin commands/hooks.
@@ -62,18 +182,24 @@ struct Plugin {
 pub struct PluginManager {
     engine: rhai::Engine,
     plugins: Vec<Plugin>,
+    /// Plugins that failed to load, as `"<plugin dir>: <error>"`.
+    errors: Vec<String>,
 }
 
 impl PluginManager {
     /// Load all plugins from `search_dirs` and register their commands into `reg`.
-    pub fn load(search_dirs: Vec<PathBuf>, reg: &mut CommandRegistry) -> Result<Self> {
+    ///
+    /// A broken plugin does not stop the others from loading; its error is kept in `errors()`.
+    pub fn load(search_dirs: Vec<PathBuf>, reg: &mut CommandRegistry) -> Self {
         let mut engine = rhai::Engine::new();
         engine.set_max_operations(2_000_000);
 
         // Register PluginApi type and methods
         api::register_api(&mut engine);
+        stdlib::register_stdlib(&mut engine);
 
         let mut plugins = Vec::new();
+        let mut errors = Vec::new();
 
         for dir in search_dirs {
             if !dir.exists() {
@@ -90,77 +216,123 @@ impl PluginManager {
                 if !path.is_dir() {
                     continue;
                 }
-                let manifest_path = path.join("plugin.toml");
-                if !manifest_path.exists() {
+                if !path.join("plugin.toml").exists() {
                     continue;
                 }
 
-                let manifest_s = fs::read_to_string(&manifest_path)
-                    .with_context(|| format!("Reading {}", manifest_path.display()))?;
-                let manifest: PluginManifest = toml::from_str(&manifest_s)
-                    .with_context(|| format!("Parsing {}", manifest_path.display()))?;
-
-                let script_path = path.join(&manifest.script);
-                let ast = engine
-                    .compile_file(script_path.clone())
-                    .map_err(|e| anyhow!("Compiling {}: {}", script_path.display(), e))?;
-
-                let id = manifest.id.clone();
-                let name = manifest.name.clone().unwrap_or_else(|| id.clone());
-
-                // Register commands
-                for c in &manifest.commands {
-                    reg.register(Command {
-                        name: c.name.clone(),
-                        description: format!("{} (plugin: {})", c.description, name),
-                        key: c.key.as_ref().map(|k| normalize_key_string(k)),
-                        source: CommandSource::Plugin {
-                            plugin_id: id.clone(),
-                            func: c.func.clone(),
-                        },
-                    });
+                match load_plugin(&engine, &path, reg) {
+                    Ok(plugin) => plugins.push(plugin),
+                    Err(e) => {
+                        let dir_name = path.file_name().map_or_else(|| path.display().to_string(), |n| n.to_string_lossy().into_owned());
+                        errors.push(format!("{dir_name}: {e:#}"));
+                    }
                 }
-
-                plugins.push(Plugin {
-                    id,
-                    _name: name,
-                    ast,
-                    hooks: manifest.hooks,
-                });
             }
         }
 
-        Ok(Self { engine, plugins })
+        Self { engine, plugins, errors }
+    }
+
+    /// Number of successfully loaded plugins.
+    pub fn len(&self) -> usize {
+        self.plugins.len()
+    }
+
+    /// Metadata of the loaded plugins, in load order.
+    pub fn infos(&self) -> impl Iterator<Item = &PluginInfo> {
+        self.plugins.iter().map(|p| &p.info)
+    }
+
+    /// All file types declared by loaded plugins.
+    pub fn filetypes(&self) -> impl Iterator<Item = &FileType> {
+        self.plugins.iter().flat_map(|p| &p.filetypes)
+    }
+
+    /// The file type for `ext` (case-insensitive); the first plugin declaring it wins.
+    pub fn filetype_for(&self, ext: &str) -> Option<&FileType> {
+        let ext = ext.to_lowercase();
+        self.filetypes().find(|ft| ft.extensions.contains(&ext))
+    }
+
+    /// Compatibility warnings, as `"<plugin id>: <warning>"`.
+    pub fn warnings(&self) -> Vec<String> {
+        self.infos()
+            .filter_map(|i| i.warning.as_ref().map(|w| format!("{}: {w}", i.id)))
+            .collect()
+    }
+
+    /// Context menu entries of all loaded plugins (label, command name), in load order.
+    pub fn menu_items(&self) -> impl Iterator<Item = &(String, String)> {
+        self.plugins.iter().flat_map(|p| &p.menu_items)
+    }
+
+    /// Load errors, one entry per plugin that failed.
+    pub fn errors(&self) -> &[String] {
+        &self.errors
     }
 
     /// Find a loaded plugin by id.
     fn find(&self, id: &str) -> Option<&Plugin> {
-        self.plugins.iter().find(|p| p.id == id)
+        self.plugins.iter().find(|p| p.info.id == id)
+    }
+
+    /// Whether plugin `plugin_id` may run programs with `api.exec`. `--script` files may: the
+    /// user ran them directly.
+    pub fn may_exec(&self, plugin_id: &str) -> bool {
+        self.find(plugin_id).map_or(plugin_id == SCRIPT_ID, |p| p.info.exec)
     }
 
-    /// Run a plugin command function.
-    pub fn run_command(&mut self, ed: &mut Editor, plugin_id: &str, func: &str) -> Result<()> {
+    /// Whether plugin `plugin_id` defines `func` taking just the `api` argument.
+    pub fn has_command_fn(&self, plugin_id: &str, func: &str) -> bool {
+        self.find(plugin_id)
+            .is_some_and(|p| p.ast.iter_functions().any(|f| f.name == func && f.params.len() == 1))
+    }
+
+    /// Run a plugin command function: `func(api, args)` when the plugin defines it with two
+    /// parameters (`args` as an array of strings), else `func(api)`, which takes no arguments.
+    pub fn run_command(&self, ed: &mut Editor, plugin_id: &str, func: &str, args: &[String]) -> Result<()> {
         let plugin = self
             .find(plugin_id)
             .ok_or_else(|| anyhow!("Plugin not found: {}", plugin_id))?;
-        let api = PluginApi::new(ed);
+        let takes_args = plugin.ast.iter_functions().any(|f| f.name == func && f.params.len() == 2);
+        if !takes_args && !args.is_empty() {
+            bail!("{plugin_id}::{func} takes no arguments");
+        }
+        let api = PluginApi::new(ed, plugin_id);
         let mut scope = rhai::Scope::new();
-        let _ = self.engine
-            .call_fn::<rhai::Dynamic>(&mut scope, &plugin.ast, func, (api,))
-            .map_err(|e| anyhow!("Plugin command failed: {}::{}: {}", plugin_id, func, e))?;
+        let res = if takes_args {
+            let args: rhai::Array = args.iter().cloned().map(rhai::Dynamic::from).collect();
+            self.engine.call_fn::<rhai::Dynamic>(&mut scope, &plugin.ast, func, (api, args))
+        } else {
+            self.engine.call_fn::<rhai::Dynamic>(&mut scope, &plugin.ast, func, (api,))
+        };
+        let _ = res.map_err(|e| anyhow!("Plugin command failed: {}::{}: {}", plugin_id, func, e))?;
         Ok(())
     }
 
+    /// Run the Rhai file `path` as a script with the API in scope as `api` (`kpad --script`).
+    pub fn run_script(&self, ed: &mut Editor, path: &Path) -> Result<()> {
+        let ast = self.engine
+            .compile_file(path.to_path_buf())
+            .map_err(|e| anyhow!("{}: {e}", path.display()))?;
+        let mut scope = rhai::Scope::new();
+        scope.push("api", PluginApi::new(ed, SCRIPT_ID));
+        self.engine
+            .run_ast_with_scope(&mut scope, &ast)
+            .map_err(|e| anyhow!("{}: {e}", path.display()))
+    }
+
+    /// Whether any plugin has a function for `hook`.
+    pub fn has_hook(&self, hook: Hook) -> bool {
+        self.plugins.iter().any(|p| p.hooks.get(hook).is_some())
+    }
+
     /// Call a lifecycle hook on all plugins (best-effort).
-    pub fn call_hook(&mut self, ed: &mut Editor, hook: Hook, path: Option<&PathBuf>) -> Result<()> {
+    pub fn call_hook(&self, ed: &mut Editor, hook: Hook, path: Option<&PathBuf>) -> Result<()> {
         for p in &self.plugins {
-            let func = match hook {
-                Hook::OnOpen => p.hooks.on_open.as_deref(),
-                Hook::OnSave => p.hooks.on_save.as_deref(),
-            };
-            let Some(func) = func else { continue; };
+            let Some(func) = p.hooks.get(hook) else { continue; };
 
-            let api = PluginApi::new(ed);
+            let api = PluginApi::new(ed, &p.info.id);
             let mut scope = rhai::Scope::new();
             let res = if let Some(path) = path {
                 self.engine.call_fn::<rhai::Dynamic>(
@@ -175,7 +347,7 @@ impl PluginManager {
             };
             if let Err(e) = res {
                 ed.set_status(
-                    format!("Plugin hook error ({}): {}", p.id, e),
+                    format!("Plugin hook error ({}): {}", p.info.id, e),
                     Duration::from_secs(3),
                 );
             }
@@ -184,55 +356,74 @@ impl PluginManager {
     }
 }
 
-/// Normalize a user-provided keybinding string into canonical form.
-fn normalize_key_string(s: &str) -> String {
-    let mut ctrl = false;
-    let mut alt = false;
-    let mut shift = false;
-    let mut key = None::<String>;
-
-    for part in s.split('+').map(|p| p.trim()).filter(|p| !p.is_empty()) {
-        let p = part.to_lowercase();
-        match p.as_str() {
-            "ctrl" | "control" => ctrl = true,
-            "alt" => alt = true,
-            "shift" => shift = true,
-            _ => {
-                key = Some(match p.as_str() {
-                    "enter" => "Enter".to_string(),
-                    "esc" | "escape" => "Esc".to_string(),
-                    "backspace" => "Backspace".to_string(),
-                    "delete" | "del" => "Delete".to_string(),
-                    "tab" => "Tab".to_string(),
-                    "left" => "Left".to_string(),
-                    "right" => "Right".to_string(),
-                    "up" => "Up".to_string(),
-                    "down" => "Down".to_string(),
-                    "home" => "Home".to_string(),
-                    "end" => "End".to_string(),
-                    "pageup" => "PageUp".to_string(),
-                    "pagedown" => "PageDown".to_string(),
-                    other => {
-                        if other.len() == 1 {
-                            other.chars().next().unwrap().to_ascii_uppercase().to_string()
-                        } else if other.starts_with('f')
-                            && other[1..].chars().all(|c| c.is_ascii_digit())
-                        {
-                            format!("F{}", &other[1..])
-                        } else {
-                            part.to_string()
-                        }
-                    }
-                });
-            }
-        }
+/// Load one plugin directory: parse the manifest, compile the script, register its commands.
+fn load_plugin(engine: &rhai::Engine, path: &Path, reg: &mut CommandRegistry) -> Result<Plugin> {
+    let manifest_path = path.join("plugin.toml");
+    let manifest_s = fs::read_to_string(&manifest_path)
+        .with_context(|| format!("Reading {}", manifest_path.display()))?;
+    let manifest: PluginManifest = toml::from_str(&manifest_s)
+        .with_context(|| format!("Parsing {}", manifest_path.display()))?;
+
+    let filetypes = manifest.filetypes.into_iter().map(|ft| parse_filetype(ft, path)).collect::<Result<Vec<_>>>()?;
+
+    let script_path = path.join(&manifest.script);
+    let ast = engine
+        .compile_file(script_path.clone())
+        .map_err(|e| anyhow!("Compiling {}: {}", script_path.display(), e))?;
+
+    let id = manifest.id.clone();
+    let name = manifest.name.clone().unwrap_or_else(|| id.clone());
+
+    // Register commands
+    for c in &manifest.commands {
+        reg.register(Command {
+            name: c.name.clone(),
+            description: format!("{} (plugin: {})", c.description, name),
+            key: c.key.as_ref().map(|k| normalize_key_string(k)),
+            source: CommandSource::Plugin {
+                plugin_id: id.clone(),
+                func: c.func.clone(),
+            },
+        });
     }
 
-    let key = key.unwrap_or_else(|| "?".to_string());
-    let mut parts = Vec::new();
-    if ctrl { parts.push("Ctrl".to_string()); }
-    if alt { parts.push("Alt".to_string()); }
-    if shift { parts.push("Shift".to_string()); }
-    parts.push(key);
-    parts.join("+")
+    // Incompatible plugins still load: most scripts only use part of the API.
+    let warning = manifest.api_version
+        .filter(|&v| v > PLUGIN_API_VERSION)
+        .map(|v| format!("requires plugin API v{v}, kpad provides v{PLUGIN_API_VERSION}"));
+
+    Ok(Plugin {
+        info: PluginInfo {
+            id,
+            name,
+            version: manifest.version,
+            api_version: manifest.api_version,
+            warning,
+            exec: manifest.permissions.exec,
+        },
+        ast,
+        hooks: manifest.hooks,
+        filetypes,
+        menu_items: manifest.commands.iter()
+            .filter_map(|c| Some((c.menu.clone()?, c.name.clone())))
+            .collect(),
+    })
+}
+
+/// Validate a `[[filetypes]]` entry (unknown colors fail the plugin load). Paths are relative
+/// to the plugin directory `dir`.
+fn parse_filetype(ft: FileTypeManifest, dir: &Path) -> Result<FileType> {
+    let highlights = ft.highlights.into_iter().map(|h| {
+        let color = HighlightColor::from_str(&h.color)
+            .ok_or_else(|| anyhow!("Unknown highlight color: {}", h.color))?;
+        Ok(HighlightRule { pattern: h.pattern, color, priority: h.priority, group: h.group })
+    }).collect::<Result<Vec<_>>>()?;
+    Ok(FileType {
+        extensions: ft.extensions.iter().map(|e| e.trim_start_matches('.').to_lowercase()).collect(),
+        comment: ft.comment,
+        build: ft.build,
+        dictionary: ft.dictionary.map(|d| dir.join(d)),
+        highlights,
+    })
 }
+
@@ -0,0 +1,188 @@
+//! Helper modules registered into the plugin engine, on top of Rhai's core builtins.
+//!
+//! Scripts call them with a module prefix:
+//! - `text::`  string utilities (`lines`, `words`, `repeat`, `pad_left`, `pad_right`, `trim_start`,
+//!   `trim_end`, `title_case`)
+//! - `regex::` regular expressions (`is_match`, `find`, `find_all`, `captures`, `replace`,
+//!   `replace_first`, `split`)
+//! - `json::`  JSON (`parse`, `stringify`, `pretty`)
+//! - `path::`  path strings (`file_name`, `stem`, `extension`, `parent`, `join`, `exists`)
+
+use regex::Regex; // regular expressions
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Module}; // rhai scripting types
+use std::cell::RefCell; // compiled regex cache
+use std::collections::HashMap; // compiled regex cache
+use std::path::Path; // path helpers
+
+type FnResult<T> = Result<T, Box<EvalAltResult>>;
+
+/// Compiled patterns kept per thread; cleared when it grows past this many entries.
+const REGEX_CACHE_LIMIT: usize = 64;
+
+thread_local! {
+    static REGEX_CACHE: RefCell<HashMap<String, Regex>> = RefCell::new(HashMap::new());
+}
+
+/// Register the `text`, `regex`, `json` and `path` modules.
+pub fn register_stdlib(engine: &mut Engine) {
+    engine.register_static_module("text", text_module().into());
+    engine.register_static_module("regex", regex_module().into());
+    engine.register_static_module("json", json_module().into());
+    engine.register_static_module("path", path_module().into());
+}
+
+fn text_module() -> Module {
+    let mut m = Module::new();
+    m.set_native_fn("lines", |s: &str| Ok(s.lines().map(|l| Dynamic::from(l.to_string())).collect::<Array>()));
+    m.set_native_fn("words", |s: &str| Ok(s.split_whitespace().map(|w| Dynamic::from(w.to_string())).collect::<Array>()));
+    m.set_native_fn("repeat", |s: &str, n: i64| Ok(s.repeat(usize::try_from(n).unwrap_or(0))));
+    m.set_native_fn("pad_left", |s: &str, width: i64| Ok(pad(s, width, true)));
+    m.set_native_fn("pad_right", |s: &str, width: i64| Ok(pad(s, width, false)));
+    m.set_native_fn("trim_start", |s: &str| Ok(s.trim_start().to_string()));
+    m.set_native_fn("trim_end", |s: &str| Ok(s.trim_end().to_string()));
+    m.set_native_fn("title_case", |s: &str| Ok(title_case(s)));
+    m
+}
+
+fn regex_module() -> Module {
+    let mut m = Module::new();
+    m.set_native_fn("is_match", |pattern: &str, s: &str| with_regex(pattern, |re| re.is_match(s)));
+    // `()` when there is no match, so scripts can test `if m == () { ... }`.
+    m.set_native_fn("find", |pattern: &str, s: &str| {
+        with_regex(pattern, |re| re.find(s).map_or(Dynamic::UNIT, |m| m.as_str().into()))
+    });
+    m.set_native_fn("find_all", |pattern: &str, s: &str| {
+        with_regex(pattern, |re| re.find_iter(s).map(|m| Dynamic::from(m.as_str().to_string())).collect::<Array>())
+    });
+    // Capture groups of the first match (group 0 is the whole match, missing groups are `()`).
+    m.set_native_fn("captures", |pattern: &str, s: &str| {
+        with_regex(pattern, |re| {
+            re.captures(s).map_or_else(Array::new, |caps| {
+                caps.iter().map(|g| g.map_or(Dynamic::UNIT, |g| g.as_str().into())).collect()
+            })
+        })
+    });
+    m.set_native_fn("replace", |pattern: &str, s: &str, with: &str| {
+        with_regex(pattern, |re| re.replace_all(s, with).into_owned())
+    });
+    m.set_native_fn("replace_first", |pattern: &str, s: &str, with: &str| {
+        with_regex(pattern, |re| re.replace(s, with).into_owned())
+    });
+    m.set_native_fn("split", |pattern: &str, s: &str| {
+        with_regex(pattern, |re| re.split(s).map(|p| Dynamic::from(p.to_string())).collect::<Array>())
+    });
+    m
+}
+
+fn json_module() -> Module {
+    let mut m = Module::new();
+    m.set_native_fn("parse", |s: &str| {
+        let value: serde_json::Value = serde_json::from_str(s).map_err(|e| format!("json::parse: {e}"))?;
+        rhai::serde::to_dynamic(value)
+    });
+    m.set_native_fn("stringify", |value: Dynamic| to_json(&value, false));
+    m.set_native_fn("pretty", |value: Dynamic| to_json(&value, true));
+    m
+}
+
+fn path_module() -> Module {
+    let mut m = Module::new();
+    m.set_native_fn("file_name", |p: &str| Ok(os_str(Path::new(p).file_name())));
+    m.set_native_fn("stem", |p: &str| Ok(os_str(Path::new(p).file_stem())));
+    m.set_native_fn("extension", |p: &str| Ok(os_str(Path::new(p).extension())));
+    m.set_native_fn("parent", |p: &str| {
+        Ok(Path::new(p).parent().map(|d| d.display().to_string()).unwrap_or_default())
+    });
+    m.set_native_fn("join", |a: &str, b: &str| Ok(Path::new(a).join(b).display().to_string()));
+    m.set_native_fn("exists", |p: &str| Ok(Path::new(p).exists()));
+    m
+}
+
+/// Run `f` with the compiled `pattern`, reporting invalid patterns as script errors.
+fn with_regex<T>(pattern: &str, f: impl FnOnce(&Regex) -> T) -> FnResult<T> {
+    REGEX_CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        if !cache.contains_key(pattern) {
+            let re = Regex::new(pattern).map_err(|e| format!("Invalid regex '{pattern}': {e}"))?;
+            if cache.len() >= REGEX_CACHE_LIMIT {
+                cache.clear();
+            }
+            cache.insert(pattern.to_string(), re);
+        }
+        Ok(f(&cache[pattern]))
+    })
+}
+
+fn to_json(value: &Dynamic, pretty: bool) -> FnResult<String> {
+    let value: serde_json::Value = rhai::serde::from_dynamic(value)?;
+    let s = if pretty { serde_json::to_string_pretty(&value) } else { serde_json::to_string(&value) };
+    Ok(s.map_err(|e| format!("json::stringify: {e}"))?)
+}
+
+fn pad(s: &str, width: i64, left: bool) -> String {
+    let missing = usize::try_from(width).unwrap_or(0).saturating_sub(s.chars().count());
+    let fill = " ".repeat(missing);
+    if left { fill + s } else { s.to_string() + fill.as_str() }
+}
+
+fn title_case(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut at_word_start = true;
+    for ch in s.chars() {
+        if at_word_start && ch.is_alphabetic() {
+            out.extend(ch.to_uppercase());
+        } else {
+            out.extend(ch.to_lowercase());
+        }
+        at_word_start = !ch.is_alphanumeric();
+    }
+    out
+}
+
+fn os_str(s: Option<&std::ffi::OsStr>) -> String {
+    s.map(|s| s.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn engine() -> Engine {
+        let mut engine = Engine::new();
+        register_stdlib(&mut engine);
+        engine
+    }
+
+    #[test]
+    fn text_helpers() {
+        let e = engine();
+        assert_eq!(e.eval::<String>(r#"text::pad_left("7", 3)"#).unwrap(), "  7");
+        assert_eq!(e.eval::<String>(r#"text::title_case("hello wORLD")"#).unwrap(), "Hello World");
+        assert_eq!(e.eval::<i64>(r#"text::words(" a  b c ").len()"#).unwrap(), 3);
+    }
+
+    #[test]
+    fn regex_helpers() {
+        let e = engine();
+        assert!(e.eval::<bool>(r#"regex::is_match("^\\d+$", "123")"#).unwrap());
+        assert_eq!(e.eval::<String>(r#"regex::replace("\\s+", "a  b   c", " ")"#).unwrap(), "a b c");
+        assert_eq!(e.eval::<String>(r#"regex::captures("(\\w+)@(\\w+)", "me@host")[2]"#).unwrap(), "host");
+        assert!(e.eval::<Dynamic>(r#"regex::find("x", "abc")"#).unwrap().is_unit());
+        assert!(e.eval::<bool>(r#"regex::is_match("(", "x")"#).is_err());
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let e = engine();
+        let out = e.eval::<String>(r#"let v = json::parse("{\"a\":[1,true,null]}"); v.a[0] += 1; json::stringify(v)"#).unwrap();
+        assert_eq!(out, r#"{"a":[2,true,null]}"#);
+        assert!(e.eval::<Dynamic>(r#"json::parse("{")"#).is_err());
+    }
+
+    #[test]
+    fn path_helpers() {
+        let e = engine();
+        assert_eq!(e.eval::<String>(r#"path::extension("dir/file.tar.gz")"#).unwrap(), "gz");
+        assert_eq!(e.eval::<String>(r#"path::stem("dir/file.md")"#).unwrap(), "file");
+    }
+}
@@ -0,0 +1,87 @@
+//! Persistent per-plugin key-value store (`api.store_get` / `api.store_set`).
+//!
+//! Each plugin gets one JSON object in `<state dir>/plugins/<plugin id>.json`. The file is read
+//! on every get and rewritten on every set: stores are small and plugin calls are rare, and it
+//! means several kpad instances never overwrite each other's values with stale copies.
+
+use crate::utils::state_dir; // where stores live
+use anyhow::{Context, Result}; // anyhow error handling
+use serde_json::{Map, Value}; // stored values
+use std::fs; // reading and writing store files
+use std::path::PathBuf; // file path handling
+
+/// The store file of one plugin.
+pub struct PluginStore {
+    path: PathBuf,
+}
+
+impl PluginStore {
+    /// The store of plugin `plugin_id` in the state directory.
+    pub fn for_plugin(plugin_id: &str) -> Result<Self> {
+        // Ids come from manifests; keep them from escaping the directory.
+        let name: String = plugin_id
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') { c } else { '_' })
+            .collect();
+        let path = state_dir()?.join("plugins").join(format!("{}.json", name.trim_start_matches('.')));
+        Ok(Self { path })
+    }
+
+    /// A store of kpad's own, `<state dir>/<name>.json` (word goals, ...).
+    pub fn named(name: &str) -> Result<Self> {
+        Ok(Self { path: state_dir()?.join(format!("{name}.json")) })
+    }
+
+    /// The value stored under `key`, if any.
+    pub fn get(&self, key: &str) -> Result<Option<Value>> {
+        Ok(self.read()?.remove(key))
+    }
+
+    /// Store `value` under `key`; `None` removes the key.
+    pub fn set(&self, key: &str, value: Option<Value>) -> Result<()> {
+        let mut map = self.read()?;
+        match value {
+            Some(v) => map.insert(key.to_string(), v),
+            None => map.remove(key),
+        };
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("Creating {}", dir.display()))?;
+        }
+        // Write a sibling file and rename it over the store so a crash never leaves half a file.
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string_pretty(&map)?).with_context(|| format!("Writing {}", tmp.display()))?;
+        fs::rename(&tmp, &self.path).with_context(|| format!("Writing {}", self.path.display()))
+    }
+
+    /// Every stored key and value.
+    pub fn entries(&self) -> Result<Map<String, Value>> {
+        self.read()
+    }
+
+    fn read(&self) -> Result<Map<String, Value>> {
+        match fs::read_to_string(&self.path) {
+            Ok(s) => serde_json::from_str(&s).with_context(|| format!("Reading {}", self.path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Map::new()),
+            Err(e) => Err(e).with_context(|| format!("Reading {}", self.path.display())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_get_remove() {
+        let dir = std::env::temp_dir().join(format!("kpad-store-test-{}", std::process::id()));
+        let store = PluginStore { path: dir.join("p.json") };
+        assert_eq!(store.get("k").unwrap(), None);
+        store.set("k", Some(Value::from(vec![1, 2]))).unwrap();
+        store.set("other", Some(Value::from("x"))).unwrap();
+        assert_eq!(store.get("k").unwrap(), Some(Value::from(vec![1, 2])));
+        store.set("k", None).unwrap();
+        assert_eq!(store.get("k").unwrap(), None);
+        assert_eq!(store.get("other").unwrap(), Some(Value::from("x")));
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,26 @@
+//! Auto-reload: watch plugin directories and tell the main loop when scripts change.
+
+use crate::bus::Message; // message bus messages
+use anyhow::Result; // anyhow error handling
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher}; // file system notifications
+use std::path::PathBuf; // file path handling
+use std::sync::mpsc::Sender; // bus sender
+
+/// Watch `dirs` (recursively) and post `Message::PluginsChanged` on every change.
+///
+/// Directories that do not exist are skipped. The returned watcher stops when dropped, so the
+/// caller keeps it alive for as long as auto-reload should run. Editors usually save in several
+/// steps (write temp file, rename, ...), so the editor debounces the messages.
+pub fn watch_plugin_dirs(dirs: &[PathBuf], tx: Sender<Message>) -> Result<RecommendedWatcher> {
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        if let Ok(ev) = res {
+            if !matches!(ev.kind, EventKind::Access(_)) {
+                let _ = tx.send(Message::PluginsChanged);
+            }
+        }
+    })?;
+    for dir in dirs.iter().filter(|d| d.is_dir()) {
+        watcher.watch(dir, RecursiveMode::Recursive)?;
+    }
+    Ok(watcher)
+}
@@ -0,0 +1,256 @@
+//! Remote files: `scp://[user@]host[:port]/path` locations and `http(s)://` URLs.
+//!
+//! A remote file is downloaded into a local cache file which the editor opens like any other
+//! file; saving writes the cache file and uploads it back. Transfers use SFTP over libssh2
+//! (`ssh2`). The server's key must already be in `~/.ssh/known_hosts`, and authentication goes
+//! through the SSH agent or an unencrypted default key (`~/.ssh/id_ed25519`, `id_ecdsa`,
+//! `id_rsa`): a password prompt cannot be answered while the editor owns the terminal.
+//! `~/.ssh/config` is not read, so aliases and per-host settings do not apply.
+//!
+//! Cache files live in a directory only the user can enter (`kpad-remote-<uid>` in the temp
+//! directory), since remote files may hold secrets.
+//!
+//! Path convention (same as Vim's netrw): `scp://host/notes.txt` is relative to the remote home
+//! directory, `scp://host//etc/hosts` is absolute.
+//!
+//! HTTP(S) URLs are fetched once into a read-only buffer; there is nothing to upload back to.
+
+use crate::instance::private_dir; // per-user cache directory
+use anyhow::{anyhow, bail, Context, Result}; // anyhow error handling
+use sha2::{Digest, Sha256}; // cache file names
+use ssh2::{CheckResult, KnownHostFileKind, Session, Sftp}; // SSH connections
+use std::fs::File; // cache files
+use std::io::{self, Read}; // reading response bodies, copying transfers
+use std::net::TcpStream; // SSH transport
+use std::path::{Path, PathBuf}; // file path handling
+
+/// Largest response body we load into a buffer.
+const MAX_URL_BYTES: u64 = 32 * 1024 * 1024;
+
+/// How long an SSH connection may stall before the transfer fails, in milliseconds.
+const SSH_TIMEOUT_MS: u32 = 15_000;
+
+/// Private keys tried after the agent, in `~/.ssh`.
+const DEFAULT_KEYS: [&str; 3] = ["id_ed25519", "id_ecdsa", "id_rsa"];
+
+/// Whether `s` is something the editor opens remotely rather than from disk.
+pub fn is_remote(s: &str) -> bool {
+    is_http_url(s) || RemotePath::parse(s).is_some()
+}
+
+/// Whether `s` is an `http://` or `https://` URL.
+pub fn is_http_url(s: &str) -> bool {
+    s.starts_with("http://") || s.starts_with("https://")
+}
+
+/// Download the text at `url`.
+pub fn fetch_url(url: &str) -> Result<String> {
+    let mut resp = ureq::get(url).call().with_context(|| format!("Fetching {url}"))?;
+    let mut bytes = Vec::new();
+    resp.body_mut()
+        .as_reader()
+        .take(MAX_URL_BYTES + 1)
+        .read_to_end(&mut bytes)
+        .with_context(|| format!("Reading {url}"))?;
+    if bytes.len() as u64 > MAX_URL_BYTES {
+        bail!("{url} is larger than {} MB", MAX_URL_BYTES >> 20);
+    }
+    String::from_utf8(bytes).map_err(|_| anyhow!("{url} is not UTF-8 text"))
+}
+
+/// File name suggested when saving a buffer fetched from `url` (last path segment).
+pub fn url_file_name(url: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let after_scheme = path.split_once("://").map_or(path, |(_, rest)| rest);
+    match after_scheme.split_once('/') {
+        Some((_, p)) if !p.is_empty() && !p.ends_with('/') => p.rsplit('/').next().unwrap_or(p).to_string(),
+        _ => "download.txt".to_string(),
+    }
+}
+
+/// A parsed `scp://` location.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemotePath {
+    pub user: Option<String>,
+    pub host: String,
+    pub port: Option<u16>,
+    /// Remote path (relative to home unless it starts with `/`).
+    pub path: String,
+}
+
+impl RemotePath {
+    /// Parse an `scp://` URL. Returns `None` for anything else (including local paths).
+    pub fn parse(s: &str) -> Option<Self> {
+        let rest = s.strip_prefix("scp://")?;
+        let (authority, path) = rest.split_once('/')?;
+        if path.is_empty() {
+            return None;
+        }
+        let (user, host_port) = match authority.rsplit_once('@') {
+            Some((u, hp)) => (Some(u.to_string()), hp),
+            None => (None, authority),
+        };
+        let (host, port) = match host_port.rsplit_once(':') {
+            Some((h, p)) => (h, Some(p.parse().ok()?)),
+            None => (host_port, None),
+        };
+        // No host or user name starts with `-`; such a location is an ssh option in disguise.
+        if host.is_empty() || host.starts_with('-') || user.as_ref().is_some_and(|u| u.starts_with('-')) {
+            return None;
+        }
+        Some(Self { user, host: host.to_string(), port, path: path.to_string() })
+    }
+
+    /// The URL form shown to the user.
+    pub fn url(&self) -> String {
+        let user = self.user.as_ref().map(|u| format!("{u}@")).unwrap_or_default();
+        let port = self.port.map(|p| format!(":{p}")).unwrap_or_default();
+        format!("scp://{user}{}{port}/{}", self.host, self.path)
+    }
+
+    /// Local cache file for this location, in the private cache directory (created if
+    /// needed): a hash of the whole URL, so every location gets its own file, followed by the
+    /// file name so extension-based features (highlighting, plugins) still work.
+    pub fn cache_path(&self) -> Result<PathBuf> {
+        let dir = private_dir(&std::env::temp_dir(), "kpad-remote").context("Cannot create the remote file cache")?;
+        Ok(dir.join(self.cache_name()))
+    }
+
+    /// File name of the cache file within the cache directory.
+    fn cache_name(&self) -> String {
+        let hash = format!("{:x}", Sha256::digest(self.url()));
+        let name = self.path.rsplit('/').next().filter(|n| !matches!(*n, "" | "." | "..")).unwrap_or("file");
+        format!("{}-{}", &hash[..16], name.replace('\\', "_"))
+    }
+
+    /// Download the remote file into its cache path.
+    pub fn download(&self) -> Result<PathBuf> {
+        let local = self.cache_path()?;
+        let transfer = || -> Result<()> {
+            let mut remote = self.connect()?.open(Path::new(&self.path))?;
+            let mut file = File::create(&local).with_context(|| format!("Creating {}", local.display()))?;
+            io::copy(&mut remote, &mut file)?;
+            Ok(())
+        };
+        transfer().with_context(|| format!("Downloading {}", self.url()))?;
+        Ok(local)
+    }
+
+    /// Upload `local` to this location.
+    pub fn upload(&self, local: &Path) -> Result<()> {
+        let transfer = || -> Result<()> {
+            let mut file = File::open(local).with_context(|| format!("Reading {}", local.display()))?;
+            let mut remote = self.connect()?.create(Path::new(&self.path))?;
+            io::copy(&mut file, &mut remote)?;
+            Ok(())
+        };
+        transfer().with_context(|| format!("Uploading {}", self.url()))
+    }
+
+    /// Open an SFTP session: connect, check the host key against `~/.ssh/known_hosts` and
+    /// log in with the agent or a default key.
+    fn connect(&self) -> Result<Sftp> {
+        let port = self.port.unwrap_or(22);
+        let tcp = TcpStream::connect((self.host.as_str(), port))
+            .with_context(|| format!("Cannot connect to {}:{port}", self.host))?;
+        let mut session = Session::new()?;
+        session.set_timeout(SSH_TIMEOUT_MS);
+        session.set_tcp_stream(tcp);
+        session.handshake()?;
+        self.check_host_key(&session, port)?;
+
+        let user = match &self.user {
+            Some(u) => u.clone(),
+            None => std::env::var("USER").or_else(|_| std::env::var("USERNAME"))
+                .map_err(|_| anyhow!("No user name; write scp://user@{}/...", self.host))?,
+        };
+        if session.userauth_agent(&user).is_err() {
+            let ssh_dir = dirs::home_dir().unwrap_or_default().join(".ssh");
+            for key in DEFAULT_KEYS.iter().map(|k| ssh_dir.join(k)).filter(|k| k.exists()) {
+                if session.userauth_pubkey_file(&user, None, &key, None).is_ok() {
+                    break;
+                }
+            }
+        }
+        if !session.authenticated() {
+            bail!("Authentication as {user} failed (use the SSH agent or an unencrypted key in ~/.ssh)");
+        }
+        Ok(session.sftp()?)
+    }
+
+    /// Refuse hosts whose key is missing from `~/.ssh/known_hosts` or does not match it.
+    fn check_host_key(&self, session: &Session, port: u16) -> Result<()> {
+        let (key, _) = session.host_key().ok_or_else(|| anyhow!("{} sent no host key", self.host))?;
+        let mut known = session.known_hosts()?;
+        let file = dirs::home_dir().unwrap_or_default().join(".ssh").join("known_hosts");
+        // A missing file just leaves the list empty, which reports the host as unknown.
+        let _ = known.read_file(&file, KnownHostFileKind::OpenSSH);
+        match known.check_port(&self.host, port, key) {
+            CheckResult::Match => Ok(()),
+            CheckResult::Mismatch => bail!("Host key of {} does not match ~/.ssh/known_hosts", self.host),
+            CheckResult::NotFound => bail!("{} is not in ~/.ssh/known_hosts; connect once with ssh to add it", self.host),
+            CheckResult::Failure => bail!("Cannot check the host key of {}", self.host),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_full_url() {
+        let r = RemotePath::parse("scp://me@example.com:2222/notes/todo.md").unwrap();
+        assert_eq!(r.user.as_deref(), Some("me"));
+        assert_eq!(r.host, "example.com");
+        assert_eq!(r.port, Some(2222));
+        assert_eq!(r.path, "notes/todo.md");
+        assert_eq!(r.url(), "scp://me@example.com:2222/notes/todo.md");
+    }
+
+    #[test]
+    fn parse_absolute_path() {
+        let r = RemotePath::parse("scp://host//etc/hosts").unwrap();
+        assert_eq!(r.user, None);
+        assert_eq!(r.port, None);
+        assert_eq!(r.path, "/etc/hosts");
+    }
+
+    #[test]
+    fn parse_rejects_non_remote() {
+        assert_eq!(RemotePath::parse("notes.txt"), None);
+        assert_eq!(RemotePath::parse("scp://host"), None);
+        assert_eq!(RemotePath::parse("scp://host/"), None);
+        assert_eq!(RemotePath::parse("scp:///path"), None);
+        assert_eq!(RemotePath::parse("scp://host:abc/path"), None);
+        assert_eq!(RemotePath::parse("scp://-oProxyCommand=evil/path"), None);
+        assert_eq!(RemotePath::parse("scp://-F@host/path"), None);
+    }
+
+    #[test]
+    fn url_file_name_uses_last_segment() {
+        assert_eq!(url_file_name("https://example.com/a/b/config.toml?raw=1"), "config.toml");
+        assert_eq!(url_file_name("https://example.com/"), "download.txt");
+        assert_eq!(url_file_name("https://example.com"), "download.txt");
+        assert!(is_remote("http://example.com/x"));
+        assert!(!is_remote("notes.txt"));
+    }
+
+    #[test]
+    fn cache_path_keeps_file_name() {
+        let cache = |url| RemotePath::parse(url).unwrap().cache_path().unwrap();
+        let path = cache("scp://host/dir/file.rs");
+        assert!(path.to_string_lossy().ends_with("-file.rs"));
+        let dir = path.parent().unwrap();
+        assert_eq!(dir.parent(), Some(std::env::temp_dir().as_path()));
+        assert!(dir.file_name().unwrap().to_string_lossy().starts_with("kpad-remote"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(dir).unwrap().permissions().mode() & 0o777, 0o700);
+        }
+        assert_ne!(cache("scp://host/a/b_c"), cache("scp://host/a_b/c"));
+        assert_ne!(path, cache("scp://me@host:2222/dir/file.rs"));
+        assert_eq!(cache("scp://../x/..").parent(), path.parent());
+    }
+}
@@ -0,0 +1,229 @@
+//! Starting points for customization: `kpad --init-config` writes a commented default
+//! `kpad.toml`, and the `plugin_new` command writes a plugin skeleton into the user plugin
+//! directory (`plugins/` next to the config file).
+
+use crate::config::{config_path, user_plugin_dir}; // where the files go
+use anyhow::{bail, Context, Result}; // anyhow error handling
+use std::fs; // writing the files
+use std::path::{Path, PathBuf}; // file paths
+
+/// The file `--init-config` writes: every setting at its default, with the optional ones
+/// commented out.
+pub const DEFAULT_CONFIG: &str = r#"# kpad configuration. Every setting is optional; delete a line to use its default.
+
+# Spaces inserted by Tab (1-16).
+tab_width = 4
+
+# Start with word wrap on (Alt+W toggles it).
+word_wrap = false
+
+# Indent continuation rows of wrapped lines: "none", "same" as the line, or "indent" (one
+# tab_width deeper).
+wrap_indent = "none"
+
+# Draw a guide column right after this line length (0: none).
+ruler = 80
+
+# Lines of context kept above and below the cursor when scrolling (the scrolloff command
+# cycles 0/3/5/10).
+scrolloff = 0
+
+# Lines the view scrolls per mouse wheel step; the cursor stays where it is.
+scroll_lines = 3
+
+# Color theme: "dark" or "light".
+theme = "dark"
+
+# Files of this many MB and more open without syntax highlighting and word wrap (0: never).
+large_file_mb = 50
+
+# Undo history per buffer: at most this many steps and this many MB of text (0: no limit).
+undo_steps = 1000
+undo_mb = 64
+
+# How sort_lines compares lines: "natural" (numbers by value, file9 before file10) or "plain"
+# (char by char).
+sort = "natural"
+
+# Typing ( [ { " or ' also inserts the closing character.
+auto_close = true
+
+# Show a tab bar of the open buffers once there are two or more.
+tab_bar = true
+
+# On errors ("No matches", a failed save): "off", "visual" (flash the status bar),
+# "audible" (terminal bell) or "both".
+bell = "off"
+
+# Middle click pastes at the pointer: "selection" (the selected text, or the clipboard when
+# nothing is selected), "clipboard" or "off".
+middle_click = "selection"
+
+# "standard", or "vim" for modal editing (Normal/Insert/Visual modes; `toggle_modal` switches).
+keymap = "standard"
+
+# Low-power mode: an idle editor sleeps until a key or event arrives instead of waking every
+# idle_poll_ms (the low_power command toggles it). poll_ms is the wake-up interval while
+# scrolling animates.
+low_power = false
+idle_poll_ms = 1000
+poll_ms = 50
+
+# Extra plugin directories, searched after ./plugins, the executable's plugins/ and the
+# plugins/ folder next to this file. Relative paths are relative to this file.
+# plugin_dirs = ["~/kpad-plugins"]
+
+# Key chord = command name (see the `keys` command). A command bound here loses its default
+# key; "" unbinds a key.
+[keybindings]
+# "Alt+R" = "rename_word"
+# "Ctrl+N" = ""
+
+# Auto-closed pairs by file extension, as open/close characters in a row; "" turns
+# auto-closing off. Defaults: ()[]{}"" everywhere, plus '' outside .rs, .txt and .md.
+[auto_close_pairs]
+# md = "()[]**"
+
+# Keyboard macros as commands: typed text, with other keys in angle brackets (<Enter>,
+# <Ctrl+S>, <lt> for "<"). Bind them under [keybindings]; save_macro adds recorded ones.
+[macros]
+# comment_line = "<Home>// <Down>"
+
+# Command aliases for the command palette, --execute and [keybindings]: name = command line.
+# Built in: w, q, wq, x, e (open) and goto (goto_line).
+[alias]
+# fmt = "json_validate"
+# wrapon = "set wrap on"
+
+# Commands `kpad --check` runs, by file extension. JSON and TOML files get json_validate and
+# toml_validate unless listed here.
+[check]
+# json = ["json_validate"]
+
+# Language servers by file extension: program and arguments. Their diagnostics are underlined
+# in the text and listed by the `diagnostics` command.
+[lsp]
+# rs = ["rust-analyzer"]
+# py = ["pylsp"]
+"#;
+
+/// Write `DEFAULT_CONFIG` to the config path; an existing file is left alone.
+pub fn init_config() -> Result<PathBuf> {
+    let Some(path) = config_path() else { bail!("Cannot find the config directory (no home directory)"); };
+    if path.exists() {
+        bail!("{} already exists; remove it first to start over", path.display());
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    fs::write(&path, DEFAULT_CONFIG).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+/// `plugin.toml` of a new plugin: one command, `<name>_hello`, calling `hello` in main.rhai.
+fn plugin_manifest(name: &str) -> String {
+    format!(r#"# Unique identifier for this plugin folder.
+id = "{name}"
+
+# Human-friendly name (shown by the `plugins` command).
+name = "{name}"
+version = "0.1.0"
+
+# Plugin API version this plugin was written for.
+api_version = {api}
+
+# Which Rhai script file to load from this plugin folder.
+script = "main.rhai"
+
+# Functions in main.rhai to run when files are opened or saved, or when the cursor comes to
+# rest after moving (on_cursor_move gets just `api`).
+# [hooks]
+# on_open = "on_open"
+# on_cursor_move = "on_cursor_move"
+
+# Let main.rhai run programs with api.exec (off unless set).
+# [permissions]
+# exec = true
+
+[[commands]]
+# Command name that appears in the command palette (Ctrl+P).
+name = "{name}_hello"
+description = "Say hello from the {name} plugin"
+
+# Rhai function name to call inside `main.rhai`.
+func = "hello"
+
+# Optional keybinding, e.g. "Ctrl+Alt+H".
+# key = "Ctrl+Alt+H"
+
+# Optional label in the right-click context menu.
+menu = "Hello from {name}"
+"#, api = crate::plugins::PLUGIN_API_VERSION)
+}
+
+/// `main.rhai` of a new plugin.
+const PLUGIN_SCRIPT: &str = r#"// Commands get an `api` object for the editor: api.text(), api.insert(s),
+// api.selection_text(), api.status(msg), ... (see docs/PLUGIN_DEVELOPMENT.md).
+
+fn hello(api) {
+    if api.has_selection() {
+        api.status("Selected: " + api.selection_text());
+    } else {
+        api.status("Hello from line " + api.cursor_line() + "!");
+    }
+}
+
+// Hooks get the file path too; enable them under [hooks] in plugin.toml.
+// fn on_open(api, path) {
+//     api.add_highlight("txt", "TODO", "yellow", 10);
+// }
+"#;
+
+/// Create the plugin `name` in `dir`; returns the path of its script.
+pub fn new_plugin(dir: &Path, name: &str) -> Result<PathBuf> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+        bail!("Plugin names use letters, digits, '_' and '-' only");
+    }
+    let plugin_dir = dir.join(name);
+    if plugin_dir.exists() {
+        bail!("{} already exists", plugin_dir.display());
+    }
+    fs::create_dir_all(&plugin_dir).with_context(|| format!("Failed to create {}", plugin_dir.display()))?;
+    fs::write(plugin_dir.join("plugin.toml"), plugin_manifest(name))?;
+    let script = plugin_dir.join("main.rhai");
+    fs::write(&script, PLUGIN_SCRIPT)?;
+    Ok(script)
+}
+
+/// Create the plugin `name` in the user plugin directory.
+pub fn new_user_plugin(name: &str) -> Result<PathBuf> {
+    let Some(dir) = user_plugin_dir() else { bail!("Cannot find the config directory (no home directory)"); };
+    new_plugin(&dir, name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::CommandRegistry;
+    use crate::config::Config;
+    use crate::plugins::PluginManager;
+
+    #[test]
+    fn scaffolds_load() {
+        let config: Config = toml::from_str(DEFAULT_CONFIG).unwrap();
+        assert_eq!(config.tab_width, 4);
+
+        let dir = std::env::temp_dir().join(format!("kpad-scaffold-{}", std::process::id()));
+        let script = new_plugin(&dir, "my_tool").unwrap();
+        assert!(script.ends_with("my_tool/main.rhai"));
+        assert!(new_plugin(&dir, "my_tool").is_err());
+        assert!(new_plugin(&dir, "../x").is_err());
+
+        let mut commands = CommandRegistry::new();
+        let plugins = PluginManager::load(vec![dir.clone()], &mut commands);
+        assert!(plugins.errors().is_empty(), "{:?}", plugins.errors());
+        assert!(commands.get("my_tool_hello").is_some());
+        assert!(plugins.menu_items().any(|(label, command)| label == "Hello from my_tool" && command == "my_tool_hello"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,10 +1,13 @@
-//! Terminal setup and teardown.
+//! Terminal setup and teardown, and what the terminal can draw (`TermCaps`).
 
 use anyhow::{Context, Result}; // anyhow error handling
 use crossterm::{ // terminal manipulation
     cursor,
-    event::{EnableMouseCapture, DisableMouseCapture},
-    style,
+    event::{
+        DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+        KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
+    style::{self, Color},
     terminal::{self, ClearType},
     ExecutableCommand,
 };
@@ -14,18 +17,31 @@ use std::io::{self, Stdout, Write}; // standard io traits and types
 ///
 /// In Rust, "RAII" means you acquire a resource in `new()` and release it in `Drop`.
 /// That guarantees cleanup even if the function returns early.
-pub struct TerminalGuard;
+pub struct TerminalGuard {
+    /// Whether keyboard enhancement flags were pushed (and must be popped).
+    keyboard_enhanced: bool,
+}
 
 impl TerminalGuard {
-    /// Enable raw mode, alternate screen, and mouse capture.
+    /// Enable raw mode, alternate screen, mouse capture, bracketed paste and enhanced key reporting.
     pub fn new(stdout: &mut Stdout) -> Result<Self> {
         terminal::enable_raw_mode().context("enable_raw_mode failed")?;
         stdout.execute(terminal::EnterAlternateScreen)?;
         stdout.execute(EnableMouseCapture)?;
+        // Pastes arrive as one `Event::Paste` instead of a key per character. The Windows
+        // console does not support it and keeps sending keys.
+        let _ = stdout.execute(EnableBracketedPaste);
+        // Kitty keyboard protocol: Ctrl+Shift+letter, Ctrl+Enter, Ctrl+Tab, ... arrive as their
+        // own chords instead of collapsing into Ctrl+letter, Enter and Tab. Terminals without it
+        // (and the Windows console, which reports these chords already) keep legacy input.
+        let keyboard_enhanced = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        if keyboard_enhanced {
+            stdout.execute(PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES))?;
+        }
         stdout.execute(cursor::Hide)?;
         stdout.execute(terminal::Clear(ClearType::All))?;
         stdout.flush()?;
-        Ok(Self)
+        Ok(Self { keyboard_enhanced })
     }
 }
 
@@ -34,7 +50,11 @@ impl Drop for TerminalGuard {
     fn drop(&mut self) {
         let mut stdout = io::stdout();
         let _ = stdout.execute(style::ResetColor);
+        if self.keyboard_enhanced {
+            let _ = stdout.execute(PopKeyboardEnhancementFlags);
+        }
         let _ = stdout.execute(cursor::Show);
+        let _ = stdout.execute(DisableBracketedPaste);
         let _ = stdout.execute(DisableMouseCapture);
         let _ = stdout.execute(terminal::LeaveAlternateScreen);
         let _ = terminal::disable_raw_mode();
@@ -42,4 +62,110 @@ impl Drop for TerminalGuard {
     }
 }
 
+/// What the terminal can draw. Legacy consoles (old cmd.exe, `TERM=dumb`, serial terminals)
+/// get ASCII separators and the 16 basic colors instead of box drawing and the 256-color palette.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TermCaps {
+    /// Box-drawing and block characters.
+    pub unicode: bool,
+    /// The 256-color palette and RGB colors.
+    pub colors_256: bool,
+}
+
+/// The 16 basic colors and their usual RGB values, for `TermCaps::color`.
+const BASIC_COLORS: [(Color, [u8; 3]); 16] = [
+    (Color::Black, [0, 0, 0]),
+    (Color::DarkRed, [128, 0, 0]),
+    (Color::DarkGreen, [0, 128, 0]),
+    (Color::DarkYellow, [128, 128, 0]),
+    (Color::DarkBlue, [0, 0, 128]),
+    (Color::DarkMagenta, [128, 0, 128]),
+    (Color::DarkCyan, [0, 128, 128]),
+    (Color::Grey, [192, 192, 192]),
+    (Color::DarkGrey, [128, 128, 128]),
+    (Color::Red, [255, 0, 0]),
+    (Color::Green, [0, 255, 0]),
+    (Color::Yellow, [255, 255, 0]),
+    (Color::Blue, [0, 0, 255]),
+    (Color::Magenta, [255, 0, 255]),
+    (Color::Cyan, [0, 255, 255]),
+    (Color::White, [255, 255, 255]),
+];
+
+/// RGB value of a 256-color palette entry.
+fn ansi_rgb(n: u8) -> [u8; 3] {
+    match n {
+        0..=15 => BASIC_COLORS[usize::from(n)].1,
+        16..=231 => {
+            let level = |i: u8| if i == 0 { 0 } else { 55 + 40 * i };
+            let i = n - 16;
+            [level(i / 36), level(i / 6 % 6), level(i % 6)]
+        }
+        _ => [8 + 10 * (n - 232); 3],
+    }
+}
+
+impl TermCaps {
+    /// Everything available (modern terminals).
+    pub const FULL: Self = Self { unicode: true, colors_256: true };
+    /// ASCII and 16 colors only (`--ascii`).
+    pub const ASCII: Self = Self { unicode: false, colors_256: false };
+
+    /// Guess the capabilities from environment variables; `var` reads one.
+    pub fn detect(var: impl Fn(&str) -> Option<String>) -> Self {
+        let set = |name: &str| var(name).is_some_and(|v| !v.is_empty());
+        // Windows Terminal, ConEmu, VS Code and friends announce themselves; the legacy console does not.
+        let modern_host = set("WT_SESSION") || set("TERM_PROGRAM") || set("ConEmuANSI") || set("COLORTERM");
+        let term = var("TERM").unwrap_or_default();
+        if term.is_empty() {
+            return if cfg!(windows) && !modern_host { Self::ASCII } else { Self::FULL };
+        }
+        let serial = term == "dumb" || term == "ansi" || term.starts_with("vt");
+        Self {
+            unicode: !serial,
+            colors_256: modern_host || !(serial || term == "linux" || term == "cons25"),
+        }
+    }
+
+    /// `unicode` if the terminal draws it, else `ascii`.
+    pub fn glyph<'a>(self, unicode: &'a str, ascii: &'a str) -> &'a str {
+        if self.unicode { unicode } else { ascii }
+    }
+
+    /// `color`, or the nearest basic color when only 16 are available.
+    pub fn color(self, color: Color) -> Color {
+        let rgb = match color {
+            _ if self.colors_256 => return color,
+            Color::AnsiValue(n) => ansi_rgb(n),
+            Color::Rgb { r, g, b } => [r, g, b],
+            _ => return color,
+        };
+        let distance = |c: &[u8; 3]| c.iter().zip(rgb).map(|(&a, b)| (i32::from(a) - i32::from(b)).pow(2)).sum::<i32>();
+        BASIC_COLORS.iter().min_by_key(|(_, c)| distance(c)).map_or(color, |&(basic, _)| basic)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn falls_back_on_legacy_terminals() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| vars.iter().find(|(k, _)| *k == name).map(|(_, v)| (*v).to_string())
+        };
+        assert_eq!(TermCaps::detect(env(&[("TERM", "xterm-256color")])), TermCaps::FULL);
+        assert_eq!(TermCaps::detect(env(&[("TERM", "dumb")])), TermCaps::ASCII);
+        assert_eq!(TermCaps::detect(env(&[("TERM", "linux")])), TermCaps { unicode: true, colors_256: false });
+        assert!(!TermCaps::detect(env(&[("TERM", "vt100"), ("COLORTERM", "truecolor")])).unicode);
 
+        let caps = TermCaps::ASCII;
+        assert_eq!(caps.color(Color::AnsiValue(235)), Color::Black);
+        assert_eq!(caps.color(Color::AnsiValue(245)), Color::DarkGrey);
+        assert_eq!(caps.color(Color::AnsiValue(196)), Color::Red);
+        assert_eq!(caps.color(Color::Rgb { r: 250, g: 240, b: 10 }), Color::Yellow);
+        assert_eq!(caps.color(Color::DarkBlue), Color::DarkBlue);
+        assert_eq!(TermCaps::FULL.color(Color::AnsiValue(235)), Color::AnsiValue(235));
+        assert_eq!(caps.glyph("│", "|"), "|");
+    }
+}
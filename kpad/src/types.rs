@@ -24,6 +24,17 @@ impl PartialOrd for Pos {
     }
 }
 
+/// How the text between the selection anchor and the cursor is selected.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SelectionKind {
+    /// Everything from the anchor to the cursor.
+    #[default]
+    Stream,
+    /// A rectangle (Alt+Shift+Arrow): the same columns on every line between the anchor and the
+    /// cursor. `col` is the cursor's column, which may lie past the end of the cursor's line.
+    Block { col: usize },
+}
+
 /// An atomic edit operation in the document.
 #[derive(Clone, Debug)]
 pub enum EditOperation {
@@ -32,6 +43,8 @@ pub enum EditOperation {
     /// A range of text was deleted.
     /// We store the `deleted_text` so we can restore it during undo.
     Delete { start: Pos, _end: Pos, deleted_text: String },
+    /// Text starting at `start` was replaced (`old` -> `new`) in one step.
+    Replace { start: Pos, old: String, new: String },
 }
 
 /// A single entry in the undo/redo stack.
@@ -45,6 +58,18 @@ pub struct UndoEntry {
     pub anchor_before: Option<Pos>,
 }
 
+impl UndoEntry {
+    /// Approximate memory held by the entry: the struct plus the text it keeps.
+    pub fn size(&self) -> usize {
+        let text = match &self.op {
+            EditOperation::Insert { text, .. } => text.len(),
+            EditOperation::Delete { deleted_text, .. } => deleted_text.len(),
+            EditOperation::Replace { old, new, .. } => old.len() + new.len(),
+        };
+        std::mem::size_of::<Self>() + text
+    }
+}
+
 /// The different prompt modes shown in the bottom line (open/save/find/command/goto).
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PromptKind {
@@ -53,6 +78,21 @@ pub enum PromptKind {
     Find,
     Command,
     GotoLine,
+    PinLines,
+    WordGoal,
+    Pomodoro,
+    Grep,
+    Rename,
+    DeleteFile,
+    MakeReadOnly,
+    MakeWritable,
+    SaveReadOnly,
+    PluginName,
+    MacroName,
+    /// Text asked for by a plugin (`api.prompt`); see `Editor::ask_user`.
+    PluginInput,
+    /// A yes/no question from a plugin (`api.confirm`).
+    PluginConfirm,
 }
 
 /// Prompt state (what the user is typing at the bottom).
@@ -67,6 +107,10 @@ pub struct Prompt {
     pub completion_index: Option<usize>,
     /// The input value when Tab was first pressed (to detect changes).
     pub completion_base: String,
+    /// The history entry Up/Down shows, and what was typed before browsing.
+    pub history: Option<(usize, String)>,
+    /// The highlighted command palette suggestion, and the input it was picked for.
+    pub selected: Option<(usize, String)>,
 }
 
 impl Prompt {
@@ -81,8 +125,20 @@ impl Prompt {
             completions: Vec::new(),
             completion_index: None,
             completion_base: String::new(),
+            history: None,
+            selected: None,
         }
     }
+
+    /// Whether the input is the candidate Tab cycling last picked (not edited since).
+    pub fn is_cycling(&self) -> bool {
+        self.completion_index.is_some_and(|i| self.completions.get(i) == Some(&self.input))
+    }
+
+    /// Whether the input comes from Tab completion rather than typing.
+    pub fn is_completed(&self) -> bool {
+        self.is_cycling() || (!self.completions.is_empty() && self.completion_base == self.input)
+    }
 }
 
 /// Short-lived status message shown in the status bar.
@@ -90,6 +146,8 @@ impl Prompt {
 pub struct StatusMsg {
     pub text: String,
     pub until: Instant,
+    /// Drawn highlighted to catch the eye (`set_alert`).
+    pub alert: bool,
 }
 
 /// The character sequence used to separate lines in the file.
@@ -1,12 +1,26 @@
 //! Utility functions.
 
 use std::cmp::min; // comparison helpers
+use unicode_width::UnicodeWidthChar; // character width handling
 
 /// Number of decimal digits in `n` (used to size the line-number gutter).
 pub fn digits(n: usize) -> usize {
     n.to_string().len()
 }
 
+/// Display width of `ch` in terminal cells.
+///
+/// With `ambiguous_wide`, East Asian ambiguous-width chars (e.g. `±`, `○`, Greek/Cyrillic in
+/// some fonts) count as two cells, matching how CJK terminals draw them.
+pub fn char_width(ch: char, ambiguous_wide: bool) -> usize {
+    let w = if ambiguous_wide {
+        UnicodeWidthChar::width_cjk(ch)
+    } else {
+        UnicodeWidthChar::width(ch)
+    };
+    w.unwrap_or(1)
+}
+
 /// Clamp an `isize` (which may be negative) into a `[lo, hi]` range and return `usize`.
 pub fn clamp_usize(v: isize, lo: usize, hi: usize) -> usize {
     if v < lo as isize {
@@ -29,13 +43,53 @@ pub fn clamp_usize_i64(v: i64, lo: usize, hi: usize) -> usize {
     }
 }
 
+/// Compute a vertical scroll offset that keeps `row` visible with `margin` rows of context.
+///
+/// - `scroll`: current first visible row
+/// - `total_rows`: number of rows in the document (the margin never scrolls past the end)
+/// - `view_h`: number of visible rows
+///
+/// The margin is capped at half the view so the cursor can always be placed.
+pub fn scroll_with_margin(scroll: usize, row: usize, total_rows: usize, view_h: usize, margin: usize) -> usize {
+    if view_h == 0 {
+        return row;
+    }
+    let margin = margin.min(view_h.saturating_sub(1) / 2);
+    let max_scroll = total_rows.saturating_sub(view_h).max(scroll);
+    if row < scroll + margin {
+        row.saturating_sub(margin)
+    } else if row + margin >= scroll + view_h {
+        (row + margin + 1).saturating_sub(view_h).min(max_scroll)
+    } else {
+        scroll
+    }
+}
+
+/// Where a view of `view_h` rows starting at row `top` is in a document of `total_rows`:
+/// `All` when everything fits, `Top`, `Bot`, or how far it has scrolled as `NN%`.
+pub fn scroll_position(top: usize, view_h: usize, total_rows: usize) -> String {
+    let at_end = top + view_h >= total_rows;
+    match (top == 0, at_end) {
+        (true, true) => "All".to_string(),
+        (true, false) => "Top".to_string(),
+        (false, true) => "Bot".to_string(),
+        (false, false) => format!("{}%", top * 100 / (total_rows - view_h)),
+    }
+}
+
 /// Get the default plugin search directories.
 ///
 /// Returns:
 /// - `./plugins` relative to the current working directory
 /// - `plugins/` next to the executable (useful for distributing a folder)
+/// - `plugins/` next to the user's kpad.toml
+///
+/// None in safe mode (`--safe`).
 pub fn default_plugin_dirs() -> anyhow::Result<Vec<std::path::PathBuf>> {
     let mut dirs = Vec::new();
+    if safe_mode() {
+        return Ok(dirs);
+    }
 
     // 1) ./plugins relative to current working directory
     if let Ok(cwd) = std::env::current_dir() {
@@ -49,9 +103,45 @@ pub fn default_plugin_dirs() -> anyhow::Result<Vec<std::path::PathBuf>> {
         }
     }
 
+    // 3) the user's plugins, next to kpad.toml
+    dirs.extend(crate::config::user_plugin_dir());
+
     Ok(dirs)
 }
 
+/// Whether kpad runs in safe mode (`--safe`, which sets `$KPAD_SAFE`): no plugins, no user
+/// config and no saved state.
+pub fn safe_mode() -> bool {
+    std::env::var_os("KPAD_SAFE").is_some()
+}
+
+/// Directory for state kpad keeps between sessions (plugin stores, ...).
+///
+/// An error in safe mode. `$KPAD_STATE_DIR` if set, otherwise the platform state/local data directory plus `kpad`
+/// (`~/.local/state/kpad`, `~/Library/Application Support/kpad`, `%LOCALAPPDATA%\kpad`).
+pub fn state_dir() -> anyhow::Result<std::path::PathBuf> {
+    if safe_mode() {
+        anyhow::bail!("Saved state is off in safe mode");
+    }
+    if let Some(dir) = std::env::var_os("KPAD_STATE_DIR") {
+        return Ok(dir.into());
+    }
+    dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .map(|d| d.join("kpad"))
+        .ok_or_else(|| anyhow::anyhow!("No state directory on this system"))
+}
+
+/// A 1-based line and optional 1-based column written as `LINE` or `LINE:COL` (`goto_line`,
+/// `kpad FILE:LINE:COL`). Negative lines are kept; `goto_line` clamps them.
+pub fn parse_location(text: &str) -> Option<(isize, Option<usize>)> {
+    let (line, col) = match text.split_once(':') {
+        Some((line, col)) => (line, Some(col.trim().parse().ok()?)),
+        None => (text, None),
+    };
+    Some((line.trim().parse().ok()?, col))
+}
+
 /// Calculate the Levenshtein distance between two strings.
 /// This is used for "did you mean?" suggestions for unknown commands.
 pub fn levenshtein_distance(s1: &str, s2: &str) -> usize {
@@ -98,6 +188,15 @@ mod tests {
         assert_eq!(digits(1000), 4);
     }
 
+    #[test]
+    fn test_char_width() {
+        assert_eq!(char_width('a', false), 1);
+        assert_eq!(char_width('日', false), 2);
+        assert_eq!(char_width('±', false), 1);
+        assert_eq!(char_width('±', true), 2);
+        assert_eq!(char_width('a', true), 1);
+    }
+
     #[test]
     fn test_clamp_usize() {
         assert_eq!(clamp_usize(-5, 0, 10), 0);
@@ -105,6 +204,38 @@ mod tests {
         assert_eq!(clamp_usize(15, 0, 10), 10);
     }
 
+    #[test]
+    fn test_scroll_with_margin() {
+        // No margin: only scroll when the row leaves the view.
+        assert_eq!(scroll_with_margin(0, 5, 100, 10, 0), 0);
+        assert_eq!(scroll_with_margin(0, 10, 100, 10, 0), 1);
+        assert_eq!(scroll_with_margin(20, 15, 100, 10, 0), 15);
+        // Margin keeps context above and below.
+        assert_eq!(scroll_with_margin(0, 7, 100, 10, 3), 1);
+        assert_eq!(scroll_with_margin(20, 22, 100, 10, 3), 19);
+        // Never scroll past the end of the document because of the margin.
+        assert_eq!(scroll_with_margin(90, 99, 100, 10, 3), 90);
+        // Margin larger than half the view is capped.
+        assert_eq!(scroll_with_margin(0, 4, 100, 10, 50), 0);
+    }
+
+    #[test]
+    fn test_scroll_position() {
+        assert_eq!(scroll_position(0, 20, 10), "All");
+        assert_eq!(scroll_position(0, 20, 100), "Top");
+        assert_eq!(scroll_position(40, 20, 100), "50%");
+        assert_eq!(scroll_position(1, 20, 100), "1%");
+        assert_eq!(scroll_position(80, 20, 100), "Bot");
+    }
+
+    #[test]
+    fn test_parse_location() {
+        assert_eq!(parse_location("12"), Some((12, None)));
+        assert_eq!(parse_location(" 12:5 "), Some((12, Some(5))));
+        assert_eq!(parse_location("12:"), None);
+        assert_eq!(parse_location("x"), None);
+    }
+
     #[test]
     fn test_levenshtein() {
         assert_eq!(levenshtein_distance("", ""), 0);